@@ -1,5 +1,6 @@
 use clap::CommandFactory;
 use clap::Parser;
+use clap_complete::CompleteEnv;
 use clap_complete::Shell;
 use clap_complete::generate;
 use codex_arg0::arg0_dispatch_or_else;
@@ -124,7 +125,9 @@ enum Subcommand {
 
 #[derive(Debug, Parser)]
 struct CompletionCommand {
-    /// Shell to generate completions for
+    /// Shell to generate completions for. These completions are static; for
+    /// ones that also complete values such as ticket ids from a workflow
+    /// manifest, source the output of `CODEX_COMPLETE=<shell> codex` instead.
     #[clap(value_enum, default_value_t = Shell::Bash)]
     shell: Shell,
 }
@@ -356,6 +359,11 @@ fn pre_main_hardening() {
 }
 
 fn main() -> anyhow::Result<()> {
+    // Answers completion requests from the script `CODEX_COMPLETE=<shell> codex`
+    // prints, which can complete values such as ticket ids; exits if it did.
+    CompleteEnv::with_factory(MultitoolCli::command)
+        .var("CODEX_COMPLETE")
+        .complete();
     arg0_dispatch_or_else(|codex_linux_sandbox_exe| async move {
         cli_main(codex_linux_sandbox_exe).await?;
         Ok(())
@@ -1,8 +1,81 @@
+use anyhow::Context;
 use anyhow::Result;
-use clap::{Args, Subcommand};
+use anyhow::bail;
+use chrono::DateTime;
+use chrono::Utc;
+use clap::Args;
+use clap::Subcommand;
+use clap_complete::engine::ArgValueCompleter;
+use clap_complete::engine::CompletionCandidate;
 use codex_common::CliConfigOverrides;
-use codex_workflow::{load_status, run_workflow, WorkflowRunOptions, WorkflowStatusReport};
+use codex_core::config::find_codex_home;
+use codex_core::config_loader::load_config_as_toml;
+use codex_workflow::ARTIFACTS_ROOT_ENV_VAR;
+use codex_workflow::ArchiveFormat;
+use codex_workflow::ArchiveOptions;
+use codex_workflow::ArtifactSizes;
+use codex_workflow::CancelOptions;
+use codex_workflow::CleanOptions;
+use codex_workflow::CompleteOptions;
+use codex_workflow::DEFAULT_LIST_GLOB;
+use codex_workflow::GITHUB_TOKEN_ENV_VARS;
+use codex_workflow::GithubImportOptions;
+use codex_workflow::GraphFormat;
+use codex_workflow::InitOptions;
+use codex_workflow::ListOptions;
+use codex_workflow::LockInfo;
+use codex_workflow::LogFormat;
+use codex_workflow::ManifestFormat;
+use codex_workflow::PrintCommand;
+use codex_workflow::ReportFormat;
+use codex_workflow::SessionLogQuery;
+use codex_workflow::SessionPhase;
+use codex_workflow::TicketConfirmer;
+use codex_workflow::TicketDecision;
+use codex_workflow::TicketPlan;
+use codex_workflow::TicketRunState;
+use codex_workflow::TicketStatus;
+use codex_workflow::WorkflowEvent;
+use codex_workflow::WorkflowManifest;
+use codex_workflow::WorkflowRunOptions;
+use codex_workflow::WorkflowStatusReport;
+use codex_workflow::archive_workflow;
+use codex_workflow::cancel_workflow;
+use codex_workflow::clean_workflow;
+use codex_workflow::complete_ticket;
+use codex_workflow::find_session_log;
+use codex_workflow::format_duration;
+use codex_workflow::import_github_issues;
+use codex_workflow::init_manifest;
+use codex_workflow::is_live_session_log;
+use codex_workflow::list_workflows;
+use codex_workflow::load_status;
+use codex_workflow::preview_prompt;
+use codex_workflow::read_events;
+use codex_workflow::render_graph;
+use codex_workflow::run_workflow;
+use crossterm::cursor::MoveTo;
+use crossterm::terminal::Clear;
+use crossterm::terminal::ClearType;
+use owo_colors::OwoColorize;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::io::BufRead;
+use std::io::IsTerminal;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use supports_color::Stream;
+use tokio::sync::mpsc;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::prelude::*;
 
 use crate::prepend_config_flags;
 
@@ -13,11 +86,55 @@ pub struct WorkflowCli {
 }
 
 #[derive(Debug, Subcommand)]
+#[allow(clippy::large_enum_variant)]
 pub enum WorkflowSubcommand {
     /// Run an orchestrated workflow based on a manifest file.
+    ///
+    /// Every session gets the manifest's `config_overrides` first, then the
+    /// `-c` overrides given here, so `-c` wins where they set the same key.
+    /// It runs with a ticket's `profile`, or else `--profile`, or else the
+    /// manifest's `config_profile`.
     Run(WorkflowRunArgs),
     /// Display the current status of a workflow.
     Status(WorkflowStatusArgs),
+    /// Delete workflow artifacts: logs, finished tickets, old runs, or all.
+    Clean(WorkflowCleanArgs),
+    /// Package the manifest, state, logs, prompts, and patches into a
+    /// `.tar.gz` or `.zip` with a generated SUMMARY.md.
+    #[command(visible_alias = "export")]
+    Archive(WorkflowArchiveArgs),
+    /// Check a manifest for problems without running it.
+    Validate(WorkflowValidateArgs),
+    /// Write a commented example manifest to start from.
+    Init(WorkflowInitArgs),
+    /// Write a manifest with one ticket per open GitHub issue with a label,
+    /// or add tickets for new issues to one. The token is read from
+    /// `GITHUB_TOKEN` or `GH_TOKEN`.
+    ImportGithub(WorkflowImportGithubArgs),
+    /// Print, or follow, a ticket's latest session log.
+    Logs(WorkflowLogsArgs),
+    /// Print the ticket dependency graph as Graphviz DOT or Mermaid.
+    Graph(WorkflowGraphArgs),
+    /// Print the prompt and codex command a ticket's next session would use,
+    /// and the prompt's estimated size in tokens, without running it.
+    Prompt(WorkflowPromptArgs),
+    /// Write a Markdown or HTML report of the last run: a status table, each
+    /// ticket's review summary with links to its logs and diff, and a rollup.
+    /// `--format junit` writes JUnit XML with a test case per ticket for CI.
+    Report(WorkflowReportArgs),
+    /// Find the workflows under a directory and print one line for each:
+    /// name, manifest, ticket counts by status, and last activity.
+    List(WorkflowListArgs),
+    /// Ask a running workflow to stop, even one started in another terminal
+    /// or on another host sharing the artifacts directory.
+    Cancel(WorkflowCancelArgs),
+    /// Mark a ticket Complete by hand, for work done outside the workflow, so
+    /// its dependents run on the next `--resume`.
+    Complete(WorkflowCompleteArgs),
+    /// Print the JSON Schema of workflow manifests, for editor completion
+    /// and CI validation.
+    #[command(hide = true)]
+    Schema,
 }
 
 #[derive(Debug, Args)]
@@ -27,17 +144,96 @@ pub struct WorkflowRunArgs {
     pub manifest: PathBuf,
 
     /// Directory to store workflow artifacts (logs, patches, state.json).
+    /// Takes precedence over `CODEX_WORKFLOW_ARTIFACTS_DIR` and the
+    /// `workflow.artifacts_root` config key.
     #[arg(long = "artifacts-dir", value_name = "DIR")]
     pub artifacts_dir: Option<PathBuf>,
 
+    /// Resolve relative ticket `working_dir`s and the default artifacts
+    /// directory against DIR instead of the manifest's directory.
+    #[arg(long = "base-dir", visible_alias = "manifest-dir", value_name = "DIR")]
+    pub base_dir: Option<PathBuf>,
+
     /// Resume from a previously saved workflow state if available.
     #[arg(long)]
     pub resume: bool,
 
+    /// Start at this ticket, marking every ticket before it (by dependencies,
+    /// then manifest order) complete without running it. The ticket itself
+    /// runs even if it had finished; with --resume, later tickets keep their
+    /// saved status.
+    #[arg(
+        long = "resume-from",
+        value_name = "ID",
+        add = ArgValueCompleter::new(complete_ticket_ids)
+    )]
+    pub resume_from: Option<String>,
+
+    /// Start a fresh run but keep tickets that completed last time and whose
+    /// spec and `inputs` files are unchanged since, as long as everything
+    /// they depend on is kept as well.
+    #[arg(
+        long = "skip-unchanged",
+        conflicts_with = "resume",
+        default_value_t = false
+    )]
+    pub skip_unchanged: bool,
+
+    /// Run only this ticket; a glob such as `backend-*` selects every
+    /// matching ticket. Repeatable. Other tickets are left untouched.
+    #[arg(
+        long = "ticket",
+        value_name = "ID|GLOB",
+        add = ArgValueCompleter::new(complete_ticket_ids)
+    )]
+    pub tickets: Vec<String>,
+
+    /// Move a `Blocked` ticket back to `Pending` so this run retries it.
+    /// Blocked tickets never reached a verdict (unlike `Failed` ones), so they
+    /// can be retried once the underlying problem is fixed. Repeatable.
+    #[arg(
+        long = "unblock",
+        value_name = "ID",
+        requires = "resume",
+        add = ArgValueCompleter::new(complete_ticket_ids)
+    )]
+    pub unblock: Vec<String>,
+
+    /// Move every `Blocked` ticket back to `Pending`.
+    #[arg(long = "unblock-all", requires = "resume", default_value_t = false)]
+    pub unblock_all: bool,
+
+    /// Retry only the tickets that ended `Failed` or `Blocked`, moving them
+    /// back to `Pending` first; completed and not yet started tickets are
+    /// left as they are.
+    #[arg(
+        long = "only-failed",
+        requires = "resume",
+        conflicts_with_all = ["unblock", "unblock_all"],
+        default_value_t = false
+    )]
+    pub only_failed: bool,
+
     /// Override the Codex binary path (defaults to the current executable).
     #[arg(long = "codex-bin", value_name = "PATH")]
     pub codex_bin: Option<PathBuf>,
 
+    /// Run review sessions with this binary instead, e.g. a wrapper script
+    /// or another codex version. A ticket's own `reviewer_bin` takes
+    /// precedence.
+    #[arg(long = "reviewer-bin", value_name = "PATH")]
+    pub reviewer_bin: Option<PathBuf>,
+
+    /// Arguments to start every codex invocation with instead of `exec`,
+    /// e.g. `--codex-subcommand "agent exec"` for a codex-compatible
+    /// front-end. Split like a shell command line; pass "" for none.
+    #[arg(
+        long = "codex-subcommand",
+        value_name = "ARGS",
+        allow_hyphen_values = true
+    )]
+    pub codex_subcommand: Option<String>,
+
     /// Optional worker model override passed to codex exec.
     #[arg(long = "worker-model", value_name = "MODEL")]
     pub worker_model: Option<String>,
@@ -46,6 +242,308 @@ pub struct WorkflowRunArgs {
     #[arg(long = "reviewer-model", value_name = "MODEL")]
     pub reviewer_model: Option<String>,
 
+    /// codex config profile for every session, passed as `codex exec
+    /// --profile`. A ticket's own `profile` takes precedence.
+    #[arg(long = "profile", value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Pass prompts to codex exec as a command-line argument instead of over
+    /// stdin. Only needed for older codex binaries.
+    #[arg(long = "prompt-argv", default_value_t = false)]
+    pub prompt_argv: bool,
+
+    /// Build each worker and review prompt by running CMD with the ticket
+    /// as JSON on stdin and using its stdout, instead of the built-in
+    /// prompts. A ticket's own `prompt`/`review_prompt` still wins. If CMD
+    /// fails, prints nothing, or runs over two minutes, only that ticket
+    /// fails.
+    #[arg(long = "prompt-command", value_name = "CMD")]
+    pub prompt_command: Option<String>,
+
+    /// Print each codex invocation to stderr just before it starts: the
+    /// binary and every argument, with the prompt elided (the default) or,
+    /// with `--print-command=full`, printed after it. Secret values are
+    /// redacted either way.
+    #[arg(
+        long = "print-command",
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "redacted"
+    )]
+    pub print_command: Option<PrintCommand>,
+
+    /// Dotenv-style file (`KEY=VALUE` per line) whose entries are exported to
+    /// every session. Values are redacted from session logs.
+    #[arg(long = "secrets-file", value_name = "PATH")]
+    pub secrets_file: Option<PathBuf>,
+
+    /// Keep only the most recent N attempt logs per ticket and phase
+    /// (`worker.<n>.log`, `review.<n>.log`). By default all attempts are kept.
+    #[arg(long = "keep-log-attempts", value_name = "N", value_parser = clap::value_parser!(i32).range(1..))]
+    pub keep_log_attempts: Option<i32>,
+
+    /// Run reviews by resuming the worker's codex session so the reviewer has
+    /// the full conversation. Falls back to a fresh session if resuming fails.
+    #[arg(long = "review-resumes-worker-session", default_value_t = false)]
+    pub review_resumes_worker_session: bool,
+
+    /// Rerun a ticket's worker (after a failure or on --resume) by resuming
+    /// its previous codex session, so it keeps the earlier context.
+    #[arg(long = "worker-resumes-session", default_value_t = false)]
+    pub worker_resumes_session: bool,
+
+    /// Capture everything the worker changed since the ticket's starting
+    /// commit, commits included, and have the reviewer focus on that diff
+    /// instead of the whole working tree.
+    #[arg(long = "review-against-diff", default_value_t = false)]
+    pub review_against_diff: bool,
+
+    /// Launch sessions without `--skip-git-repo-check`, so codex refuses
+    /// working directories outside a git repository. Overrides the
+    /// manifest's `skip_git_repo_check`.
+    #[arg(long = "require-git-repo", default_value_t = false)]
+    pub require_git_repo: bool,
+
+    /// Warn when a session's prompt is estimated at more than N tokens (about
+    /// four characters each), since it will likely overflow the model's
+    /// context. Overrides the manifest's `prompt_token_budget`.
+    #[arg(long = "prompt-token-budget", value_name = "N", value_parser = clap::value_parser!(i64).range(0..))]
+    pub prompt_token_budget: Option<i64>,
+
+    /// Fail a ticket whose prompt is over the token budget instead of
+    /// launching its session anyway.
+    #[arg(long = "strict-context", default_value_t = false)]
+    pub strict_context: bool,
+
+    /// Leave completed tickets' changes uncommitted, overriding the
+    /// manifest's `git.autocommit` for this run.
+    #[arg(long = "no-autocommit", default_value_t = false)]
+    pub no_autocommit: bool,
+
+    /// Maximum number of tickets to run concurrently (default: 1).
+    ///
+    /// Launch order is deterministic: whenever a slot is free it goes to the
+    /// first ticket in manifest order whose dependencies are done. A ticket
+    /// keeps its slot through its retries and reworks, and while launches
+    /// are paused for low memory or disk space no later ticket jumps the
+    /// queue. Nothing in scheduling is random, so two runs of a manifest
+    /// whose sessions end the same way launch them in the same order; only
+    /// how long sessions take can change it.
+    #[arg(long = "max-parallel", value_name = "N")]
+    pub max_parallel: Option<usize>,
+
+    /// When --max-parallel is not given, read the parallelism from
+    /// CODEX_WORKFLOW_PARALLEL or fall back to the number of available cores.
+    #[arg(long = "concurrency-from-env", default_value_t = false)]
+    pub concurrency_from_env: bool,
+
+    /// Append this run as a new timestamped section of `summary.md` instead of
+    /// overwriting it, keeping a history across runs.
+    #[arg(long = "append-summary", default_value_t = false)]
+    pub append_summary: bool,
+
+    /// Extra argument to pass through to every codex exec invocation, e.g.
+    /// `--codex-arg=--include-plan-tool`. Repeatable; no shell interpretation.
+    #[arg(long = "codex-arg", value_name = "ARG", allow_hyphen_values = true)]
+    pub codex_args: Vec<String>,
+
+    /// Session log format: `markdown` (default), `plain` or `json`. Also sets
+    /// the log file extension (`worker.1.md` / `worker.1.log` /
+    /// `worker.1.json`). Logs used to be `.log` files; `plain` keeps them
+    /// that way for scripts that read them. While a session runs its log is
+    /// plain text with the output so far, whatever the format.
+    #[arg(long = "log-format", value_name = "FORMAT")]
+    pub log_format: Option<LogFormat>,
+
+    /// Keep ANSI escape sequences in session logs. By default they are stripped
+    /// so logs read cleanly in editors and pagers.
+    #[arg(long = "log-ansi", default_value_t = false)]
+    pub log_ansi: bool,
+
+    /// Do not prefix logged session output lines with the elapsed time.
+    #[arg(long = "no-log-timestamps", default_value_t = false)]
+    pub no_log_timestamps: bool,
+
+    /// Cap each session log at this many bytes (default 64 MiB). Past the cap
+    /// the middle of the output is dropped and the final output is kept.
+    #[arg(long = "max-log-bytes", value_name = "BYTES", value_parser = clap::value_parser!(i64).range(0..))]
+    pub max_log_bytes: Option<i64>,
+
+    /// Pause launching new sessions while the host has less than this much
+    /// available memory, e.g. `2G` or `512M`. A session still starts when
+    /// nothing else is running.
+    #[arg(long = "min-free-memory", value_name = "SIZE", value_parser = parse_byte_size)]
+    pub min_free_memory: Option<i64>,
+
+    /// Fail before running anything if the artifacts directory's volume has
+    /// less than this much free space, e.g. `5G`. While the run is going, new
+    /// sessions wait for space to free up.
+    #[arg(long = "min-free-disk", value_name = "SIZE", value_parser = parse_byte_size)]
+    pub min_free_disk: Option<i64>,
+
+    /// Before the first ticket, GET URL (e.g. the model provider's API) and
+    /// wait while it fails to answer or answers with a 5xx status, probing
+    /// again with growing pauses of up to 5 minutes. Each probe is recorded
+    /// in `events.jsonl`.
+    #[arg(long = "preflight-url", value_name = "URL")]
+    pub preflight_url: Option<String>,
+
+    /// Like `--preflight-url`, but the probe is COMMAND succeeding, e.g.
+    /// `--preflight-command 'curl -sf https://status.example.com'`.
+    #[arg(long = "preflight-command", value_name = "COMMAND")]
+    pub preflight_command: Option<String>,
+
+    /// Run the preflight probes before every worker and review session, not
+    /// only before the first ticket.
+    #[arg(long = "preflight-each-session")]
+    pub preflight_each_session: bool,
+
+    /// Rerun a failed worker session up to this many times. Overrides the
+    /// manifest's `max_retries`.
+    #[arg(long = "max-retries", value_name = "N", value_parser = clap::value_parser!(i32).range(0..))]
+    pub max_retries: Option<i32>,
+
+    /// Send a ticket back to the worker up to this many times when its
+    /// reviewer requests changes. Overrides the manifest's `max_reworks`.
+    #[arg(long = "max-reworks", value_name = "N", value_parser = clap::value_parser!(i32).range(0..))]
+    pub max_reworks: Option<i32>,
+
+    /// Only retry worker sessions that exit with this code. Repeatable;
+    /// overrides the manifest's `retry_on_exit_codes`.
+    #[arg(long = "retry-on-exit-code", value_name = "CODE")]
+    pub retry_on_exit_codes: Vec<i32>,
+
+    /// Terminate a worker or review session, including every process it
+    /// started, once it has run for this many seconds. Overrides the
+    /// manifest's `worker_timeout` and `review_timeout`.
+    #[arg(long = "session-timeout", value_name = "SECS")]
+    pub session_timeout: Option<u64>,
+
+    /// Time limit of worker sessions in seconds, overriding --session-timeout.
+    #[arg(long = "worker-timeout", value_name = "SECS")]
+    pub worker_timeout: Option<u64>,
+
+    /// Time limit of review sessions in seconds, overriding --session-timeout.
+    #[arg(long = "review-timeout", value_name = "SECS")]
+    pub review_timeout: Option<u64>,
+
+    /// Multiply the session timeout by this factor on each worker retry, e.g.
+    /// 2 for 10m, 20m, 40m. Overrides the manifest's `timeout_backoff`.
+    #[arg(long = "timeout-backoff", value_name = "FACTOR", value_parser = parse_timeout_backoff)]
+    pub timeout_backoff: Option<f64>,
+
+    /// With --resume, remove state entries for tickets that are no longer in
+    /// the manifest instead of only warning about them.
+    #[arg(long = "prune-orphans", default_value_t = false, requires = "resume")]
+    pub prune_orphans: bool,
+
+    /// With --prune-orphans, also delete the orphaned tickets' artifact
+    /// directories.
+    #[arg(
+        long = "delete-orphan-artifacts",
+        default_value_t = false,
+        requires = "prune_orphans"
+    )]
+    pub delete_orphan_artifacts: bool,
+
+    /// With --resume, fail if the state has entries for tickets that are no
+    /// longer in the manifest.
+    #[arg(
+        long = "fail-on-orphans",
+        default_value_t = false,
+        requires = "resume",
+        conflicts_with = "prune_orphans"
+    )]
+    pub fail_on_orphans: bool,
+
+    /// Abort the run with an error as soon as a ticket is blocked by a failed
+    /// dependency. By default blocked tickets are skipped and the rest continue.
+    #[arg(long = "strict-deps", default_value_t = false)]
+    pub strict_deps: bool,
+
+    /// Write ticket artifacts directly into the artifacts directory,
+    /// overwriting the previous run's, instead of a new `runs/<timestamp>`
+    /// directory.
+    #[arg(long = "reuse-run-dir", default_value_t = false)]
+    pub reuse_run_dir: bool,
+
+    /// With --resume, keep writing into this existing run directory (a name
+    /// under `runs/`, or `latest`). --resume alone continues in `latest`.
+    #[arg(
+        long = "resume-run",
+        value_name = "RUN",
+        requires = "resume",
+        conflicts_with = "reuse_run_dir"
+    )]
+    pub resume_run: Option<String>,
+
+    /// Show each ticket before it launches and ask whether to run it (y),
+    /// leave it pending (n), mark it skipped (s), or save and stop (q).
+    /// Tickets run one at a time.
+    #[arg(long = "interactive", default_value_t = false)]
+    pub interactive: bool,
+
+    /// Exit successfully even when tickets end `Failed` or `Blocked`. By
+    /// default such tickets (other than those with `allow_failure: true`)
+    /// make the command exit non-zero.
+    #[arg(long = "exit-zero-on-failure", default_value_t = false)]
+    pub exit_zero_on_failure: bool,
+
+    /// Print ticket notes in full instead of cutting them to the terminal
+    /// width.
+    #[arg(long = "full-notes", default_value_t = false)]
+    pub full_notes: bool,
+
+    /// Stream every ticket status change as a JSON line to a listening Unix
+    /// socket at PATH or TCP endpoint at HOST:PORT, e.g. for a dashboard. A
+    /// consumer that falls behind or disconnects misses events but does not
+    /// slow the run.
+    #[arg(long = "events-socket", value_name = "PATH|ADDR")]
+    pub events_socket: Option<String>,
+
+    /// POST each ticket's final status and the end of the run to URL, e.g. a
+    /// Slack or Teams incoming webhook. Overrides the manifest's
+    /// `notifications.webhook_url`; failed deliveries are recorded in
+    /// `events.jsonl` and never fail the run.
+    #[arg(long = "webhook-url", value_name = "URL")]
+    pub webhook_url: Option<String>,
+
+    /// Keep a Prometheus textfile at PATH, e.g. in node_exporter's textfile
+    /// collector directory, with ticket counts by status, session and failure
+    /// counters, the start time and per-ticket session durations. It is
+    /// rewritten atomically whenever the run's state changes.
+    #[arg(long = "metrics-file", value_name = "PATH")]
+    pub metrics_file: Option<PathBuf>,
+
+    /// Rewrite `report.json` in the artifacts directory every SECS seconds
+    /// with the run's status report, in the `status --json` format, for
+    /// monitoring tools to poll during long runs.
+    #[arg(long = "report-interval", value_name = "SECS")]
+    pub report_interval: Option<u64>,
+
+    /// Print a plain `[done/total] TICKET: STATUS` line to stderr on every
+    /// ticket status change, for CI logs that cannot show a live display.
+    #[arg(long = "progress", default_value_t = false)]
+    pub progress: bool,
+
+    /// Print only the final report instead of a timestamped line as each
+    /// ticket's worker and review start and finish.
+    #[arg(
+        long = "quiet",
+        short = 'q',
+        default_value_t = false,
+        conflicts_with = "verbose"
+    )]
+    pub quiet: bool,
+
+    /// Also echo session output to stderr as it arrives, each line labelled
+    /// with its ticket and session, and log the run's tracing spans and
+    /// events (state transitions, session exits, resume decisions) at debug
+    /// level.
+    #[arg(long = "verbose", short = 'v', default_value_t = false)]
+    pub verbose: bool,
+
     #[clap(flatten)]
     pub config_overrides: CliConfigOverrides,
 }
@@ -57,71 +555,1992 @@ pub struct WorkflowStatusArgs {
     pub manifest: PathBuf,
 
     /// Directory that stores workflow artifacts. If omitted, defaults to
+    /// `<root>/<workflow-name>` for a root from `CODEX_WORKFLOW_ARTIFACTS_DIR`
+    /// or the `workflow.artifacts_root` config key, else to
     /// `.codex/workflows/<workflow-name>` next to the manifest.
     #[arg(long = "artifacts-dir", value_name = "DIR")]
     pub artifacts_dir: Option<PathBuf>,
+
+    /// Resolve relative ticket `working_dir`s and the default artifacts
+    /// directory against DIR instead of the manifest's directory.
+    #[arg(long = "base-dir", visible_alias = "manifest-dir", value_name = "DIR")]
+    pub base_dir: Option<PathBuf>,
+
+    /// Print the status as JSON.
+    #[arg(long = "json", default_value_t = false)]
+    pub json: bool,
+
+    /// Include each ticket's final worker/review prompts and codex command
+    /// lines in the JSON output, for auditing.
+    #[arg(long = "include-prompts", default_value_t = false, requires = "json")]
+    pub include_prompts: bool,
+
+    /// Dotenv-style file whose values are redacted from the prompts
+    /// `--include-prompts` adds, usually the run's `--secrets-file`.
+    #[arg(
+        long = "secrets-file",
+        value_name = "PATH",
+        requires = "include_prompts"
+    )]
+    pub secrets_file: Option<PathBuf>,
+
+    /// Only show tickets in STATUS: pending, running_worker, needs_review,
+    /// running_review, complete, failed, blocked or skipped. Repeat to show
+    /// several, e.g. `--status failed --status blocked`. `--check` still
+    /// considers every ticket.
+    #[arg(long = "status", value_name = "STATUS")]
+    pub statuses: Vec<TicketStatus>,
+
+    /// Show how much disk each ticket's logs, patches, and other artifacts
+    /// take, and the total for the artifacts directory.
+    #[arg(long = "du", default_value_t = false)]
+    pub du: bool,
+
+    /// Exit non-zero unless every ticket is complete, skipped, or an
+    /// allowed failure. Useful as a CI gate.
+    #[arg(long = "check", default_value_t = false)]
+    pub check: bool,
+
+    /// As for `workflow run`.
+    #[arg(long = "full-notes", default_value_t = false)]
+    pub full_notes: bool,
+
+    /// After the status, print the last N entries (20 by default) of the
+    /// workflow's `events.jsonl` event log.
+    #[arg(
+        long = "events",
+        value_name = "N",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "20",
+        conflicts_with_all = ["json", "watch"]
+    )]
+    pub events: Option<usize>,
+
+    /// Keep refreshing the status, marking tickets whose status changed,
+    /// until no ticket is pending or running (or Ctrl-C). With --json, print
+    /// one JSON snapshot per line whenever the status changes.
+    #[arg(long = "watch", conflicts_with = "check", default_value_t = false)]
+    pub watch: bool,
+
+    /// Seconds between refreshes with --watch.
+    #[arg(
+        long = "interval",
+        value_name = "SECONDS",
+        default_value_t = 2.0,
+        requires = "watch"
+    )]
+    pub interval: f64,
+}
+
+#[derive(Debug, Args)]
+#[command(group(
+    clap::ArgGroup::new("selection")
+        .required(true)
+        .multiple(true)
+        .args(["logs_only", "completed", "all", "keep_last"])
+))]
+pub struct WorkflowCleanArgs {
+    /// Path to the workflow manifest (YAML or TOML).
+    #[arg(value_name = "MANIFEST")]
+    pub manifest: PathBuf,
+
+    /// Directory that stores workflow artifacts. If omitted, defaults to
+    /// `<root>/<workflow-name>` for a root from `CODEX_WORKFLOW_ARTIFACTS_DIR`
+    /// or the `workflow.artifacts_root` config key, else to
+    /// `.codex/workflows/<workflow-name>` next to the manifest.
+    #[arg(long = "artifacts-dir", value_name = "DIR")]
+    pub artifacts_dir: Option<PathBuf>,
+
+    /// Resolve the default artifacts directory against DIR instead of the
+    /// manifest's directory.
+    #[arg(long = "base-dir", visible_alias = "manifest-dir", value_name = "DIR")]
+    pub base_dir: Option<PathBuf>,
+
+    /// Remove the session logs of every ticket but keep state and outputs.
+    #[arg(long = "logs-only", default_value_t = false)]
+    pub logs_only: bool,
+
+    /// Remove the artifacts of tickets whose status is Complete.
+    #[arg(long = "completed", default_value_t = false)]
+    pub completed: bool,
+
+    /// Remove the whole artifacts directory, including the state.
+    #[arg(
+        long = "all",
+        default_value_t = false,
+        conflicts_with_all = ["logs_only", "completed", "keep_last"]
+    )]
+    pub all: bool,
+
+    /// Remove all but the newest N run directories.
+    #[arg(long = "keep-last", value_name = "N", value_parser = clap::value_parser!(i64).range(1..))]
+    pub keep_last: Option<i64>,
+
+    /// Do not ask for confirmation before --all.
+    #[arg(long = "yes", short = 'y', default_value_t = false)]
+    pub yes: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct WorkflowArchiveArgs {
+    /// Path to the workflow manifest (YAML or TOML).
+    #[arg(value_name = "MANIFEST")]
+    pub manifest: PathBuf,
+
+    /// Directory that stores workflow artifacts. If omitted, defaults to
+    /// `<root>/<workflow-name>` for a root from `CODEX_WORKFLOW_ARTIFACTS_DIR`
+    /// or the `workflow.artifacts_root` config key, else to
+    /// `.codex/workflows/<workflow-name>` next to the manifest.
+    #[arg(long = "artifacts-dir", value_name = "DIR")]
+    pub artifacts_dir: Option<PathBuf>,
+
+    /// Resolve the default artifacts directory against DIR instead of the
+    /// manifest's directory.
+    #[arg(long = "base-dir", visible_alias = "manifest-dir", value_name = "DIR")]
+    pub base_dir: Option<PathBuf>,
+
+    /// Archive to write (default: `<workflow-name>-<timestamp>.tar.gz`).
+    #[arg(long = "output", short = 'o', value_name = "FILE")]
+    pub output: Option<PathBuf>,
+
+    /// Archive format: `tar.gz` or `zip`. Defaults to zip for an output
+    /// ending in `.zip`, else tar.gz.
+    #[arg(long = "format", value_name = "FORMAT")]
+    pub format: Option<ArchiveFormat>,
+
+    /// Leave out the artifacts of tickets that are not Complete.
+    #[arg(long = "completed-only", default_value_t = false)]
+    pub completed_only: bool,
+
+    /// Only include the artifacts of this ticket; a glob such as `backend-*`
+    /// selects every matching ticket. Repeatable.
+    #[arg(
+        long = "ticket",
+        value_name = "ID|GLOB",
+        add = ArgValueCompleter::new(complete_ticket_ids)
+    )]
+    pub tickets: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct WorkflowValidateArgs {
+    /// Path to the workflow manifest (YAML or TOML).
+    #[arg(value_name = "MANIFEST")]
+    pub manifest: PathBuf,
+
+    /// Resolve relative ticket `working_dir`s against DIR instead of the
+    /// manifest's directory.
+    #[arg(long = "base-dir", visible_alias = "manifest-dir", value_name = "DIR")]
+    pub base_dir: Option<PathBuf>,
+
+    /// Fail on warnings (unknown fields, tickets that can never run) too.
+    #[arg(long = "strict", default_value_t = false)]
+    pub strict: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct WorkflowInitArgs {
+    /// Manifest to create (default: `workflow.yaml`, or `workflow.toml` with
+    /// `--format toml`).
+    #[arg(value_name = "PATH")]
+    pub path: Option<PathBuf>,
+
+    /// Manifest syntax: `yaml` or `toml`. Defaults to the one PATH's
+    /// extension implies, else YAML.
+    #[arg(long = "format", value_name = "FORMAT")]
+    pub format: Option<ManifestFormat>,
+
+    /// Overwrite PATH if it already exists.
+    #[arg(long = "force", default_value_t = false)]
+    pub force: bool,
+
+    /// Create one stub ticket per subdirectory of DIR, with the subdirectory
+    /// as its `working_dir`, instead of the example tickets.
+    #[arg(long = "from-dir", value_name = "DIR")]
+    pub from_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct WorkflowImportGithubArgs {
+    /// Repository whose issues to import, as OWNER/NAME.
+    #[arg(long = "repo", value_name = "OWNER/NAME")]
+    pub repo: String,
+
+    /// Import the open issues with this label.
+    #[arg(long = "label", value_name = "LABEL", default_value = "codex-workflow")]
+    pub label: String,
+
+    /// Manifest to write; a `.toml` extension writes TOML, anything else YAML.
+    #[arg(short = 'o', long = "output", value_name = "PATH")]
+    pub output: PathBuf,
+
+    /// Add tickets for issues that have none yet to an existing manifest,
+    /// leaving its tickets and other settings as they are.
+    #[arg(long = "update", default_value_t = false)]
+    pub update: bool,
+
+    /// GitHub API to use, e.g. `https://<host>/api/v3` for GitHub Enterprise.
+    #[arg(long = "api-url", value_name = "URL")]
+    pub api_url: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct WorkflowLogsArgs {
+    /// Path to the workflow manifest (YAML or TOML).
+    #[arg(value_name = "MANIFEST")]
+    pub manifest: PathBuf,
+
+    /// Ticket whose log to show; any case or an unambiguous prefix will do.
+    #[arg(value_name = "TICKET", add = ArgValueCompleter::new(complete_ticket_ids))]
+    pub ticket: String,
+
+    /// Show the review session's log.
+    #[arg(long = "review", default_value_t = false, conflicts_with = "worker")]
+    pub review: bool,
+
+    /// Show the worker session's log (the default).
+    #[arg(long = "worker", default_value_t = false)]
+    pub worker: bool,
+
+    /// Attempt whose log to show (default: the latest).
+    #[arg(long = "attempt", value_name = "N", value_parser = clap::value_parser!(i32).range(1..))]
+    pub attempt: Option<i32>,
+
+    /// Print only the last N lines.
+    #[arg(long = "tail", short = 'n', value_name = "N")]
+    pub tail: Option<usize>,
+
+    /// Keep printing as the log grows, including the output of a session
+    /// that is still running, and move on to newer attempts unless
+    /// `--attempt` is given. Stops once the ticket reaches a final status
+    /// or no run is active.
+    #[arg(long = "follow", short = 'f', default_value_t = false)]
+    pub follow: bool,
+
+    /// Directory that stores workflow artifacts. If omitted, it is resolved
+    /// as for `workflow status`.
+    #[arg(long = "artifacts-dir", value_name = "DIR")]
+    pub artifacts_dir: Option<PathBuf>,
+
+    /// Resolve the default artifacts directory against DIR instead of the
+    /// manifest's directory.
+    #[arg(long = "base-dir", visible_alias = "manifest-dir", value_name = "DIR")]
+    pub base_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct WorkflowGraphArgs {
+    /// Path to the workflow manifest (YAML or TOML).
+    #[arg(value_name = "MANIFEST")]
+    pub manifest: PathBuf,
+
+    /// Output format: `dot` (default) or `mermaid`.
+    #[arg(long = "format", value_name = "FORMAT", default_value = "dot")]
+    pub format: GraphFormat,
+
+    /// Color tickets by their status in the saved workflow state.
+    #[arg(long = "with-status", default_value_t = false)]
+    pub with_status: bool,
+
+    /// Directory that stores workflow artifacts, for `--with-status`. If
+    /// omitted, it is resolved as for `workflow status`.
+    #[arg(long = "artifacts-dir", value_name = "DIR")]
+    pub artifacts_dir: Option<PathBuf>,
+
+    /// Resolve the default artifacts directory against DIR instead of the
+    /// manifest's directory.
+    #[arg(long = "base-dir", visible_alias = "manifest-dir", value_name = "DIR")]
+    pub base_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct WorkflowReportArgs {
+    /// Path to the workflow manifest (YAML or TOML).
+    #[arg(value_name = "MANIFEST")]
+    pub manifest: PathBuf,
+
+    /// Write the report to PATH instead of stdout. Links are relative to its
+    /// directory.
+    #[arg(short = 'o', long = "output", value_name = "PATH")]
+    pub output: Option<PathBuf>,
+
+    /// `markdown`, `html`, `junit` or `csv`; defaults to the format the
+    /// output's extension implies (`.xml` for JUnit), else Markdown.
+    #[arg(long = "format", value_name = "FORMAT")]
+    pub format: Option<ReportFormat>,
+
+    /// As for `workflow status`.
+    #[arg(long = "artifacts-dir", value_name = "DIR")]
+    pub artifacts_dir: Option<PathBuf>,
+
+    /// As for `workflow status`.
+    #[arg(long = "base-dir", visible_alias = "manifest-dir", value_name = "DIR")]
+    pub base_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct WorkflowCancelArgs {
+    /// Path to the workflow manifest (YAML or TOML).
+    #[arg(value_name = "MANIFEST")]
+    pub manifest: PathBuf,
+
+    /// Kill the running sessions instead of letting their tickets finish.
+    /// Killed tickets go back to where `--resume` picks them up.
+    #[arg(long = "hard", default_value_t = false)]
+    pub hard: bool,
+
+    /// As for `workflow status`.
+    #[arg(long = "artifacts-dir", value_name = "DIR")]
+    pub artifacts_dir: Option<PathBuf>,
+
+    /// As for `workflow status`.
+    #[arg(long = "base-dir", visible_alias = "manifest-dir", value_name = "DIR")]
+    pub base_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct WorkflowCompleteArgs {
+    /// Path to the workflow manifest (YAML or TOML).
+    #[arg(value_name = "MANIFEST")]
+    pub manifest: PathBuf,
+
+    /// Ticket to mark complete; any case or an unambiguous prefix will do.
+    #[arg(value_name = "TICKET", add = ArgValueCompleter::new(complete_ticket_ids))]
+    pub ticket: String,
+
+    /// Note to record on the ticket instead of "Marked complete by hand".
+    #[arg(long = "note", value_name = "TEXT")]
+    pub note: Option<String>,
+
+    /// Complete the ticket even if some of its dependencies are not.
+    #[arg(long = "force", default_value_t = false)]
+    pub force: bool,
+
+    /// As for `workflow status`.
+    #[arg(long = "artifacts-dir", value_name = "DIR")]
+    pub artifacts_dir: Option<PathBuf>,
+
+    /// As for `workflow status`.
+    #[arg(long = "base-dir", visible_alias = "manifest-dir", value_name = "DIR")]
+    pub base_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct WorkflowListArgs {
+    /// Directory to scan; defaults to the current directory. Gitignored and
+    /// hidden directories are skipped.
+    #[arg(value_name = "ROOT")]
+    pub root: Option<PathBuf>,
+
+    /// Glob, relative to ROOT, that manifest files match. Workflows with
+    /// state under a `.codex/workflows` directory are listed regardless.
+    #[arg(long = "glob", value_name = "GLOB", default_value = DEFAULT_LIST_GLOB)]
+    pub glob: String,
+
+    /// Print the workflows as JSON.
+    #[arg(long = "json", default_value_t = false)]
+    pub json: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct WorkflowPromptArgs {
+    /// Path to the workflow manifest (YAML or TOML).
+    #[arg(value_name = "MANIFEST")]
+    pub manifest: PathBuf,
+
+    /// Ticket whose prompt to build; any case or an unambiguous prefix will
+    /// do.
+    #[arg(value_name = "TICKET", add = ArgValueCompleter::new(complete_ticket_ids))]
+    pub ticket: String,
+
+    /// Build the review prompt instead of the worker prompt.
+    #[arg(long = "review", default_value_t = false)]
+    pub review: bool,
+
+    /// Directory that stores workflow artifacts, whose state the prompt is
+    /// built from. If omitted, it is resolved as for `workflow run`.
+    #[arg(long = "artifacts-dir", value_name = "DIR")]
+    pub artifacts_dir: Option<PathBuf>,
+
+    /// Resolve relative ticket `working_dir`s and the default artifacts
+    /// directory against DIR instead of the manifest's directory.
+    #[arg(long = "base-dir", visible_alias = "manifest-dir", value_name = "DIR")]
+    pub base_dir: Option<PathBuf>,
+
+    /// As for `workflow run`.
+    #[arg(long = "codex-bin", value_name = "PATH")]
+    pub codex_bin: Option<PathBuf>,
+
+    /// As for `workflow run`.
+    #[arg(long = "reviewer-bin", value_name = "PATH")]
+    pub reviewer_bin: Option<PathBuf>,
+
+    /// As for `workflow run`.
+    #[arg(
+        long = "codex-subcommand",
+        value_name = "ARGS",
+        allow_hyphen_values = true
+    )]
+    pub codex_subcommand: Option<String>,
+
+    /// As for `workflow run`.
+    #[arg(long = "worker-model", value_name = "MODEL")]
+    pub worker_model: Option<String>,
+
+    /// As for `workflow run`.
+    #[arg(long = "reviewer-model", value_name = "MODEL")]
+    pub reviewer_model: Option<String>,
+
+    /// As for `workflow run`.
+    #[arg(long = "profile", value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// As for `workflow run`.
+    #[arg(long = "prompt-command", value_name = "CMD")]
+    pub prompt_command: Option<String>,
+
+    /// As for `workflow run`.
+    #[arg(long = "codex-arg", value_name = "ARG", allow_hyphen_values = true)]
+    pub codex_args: Vec<String>,
+
+    /// As for `workflow run`.
+    #[arg(long = "require-git-repo", default_value_t = false)]
+    pub require_git_repo: bool,
+
+    /// As for `workflow run`.
+    #[arg(long = "prompt-token-budget", value_name = "N", value_parser = clap::value_parser!(i64).range(0..))]
+    pub prompt_token_budget: Option<i64>,
 }
 
 pub async fn execute(cli: WorkflowCli, root_overrides: CliConfigOverrides) -> Result<()> {
+    let overrides = &root_overrides;
     match cli.action {
         WorkflowSubcommand::Run(mut run_args) => {
             prepend_config_flags(&mut run_args.config_overrides, root_overrides);
-            run(run_args).await
+            let workflow = workflow_config(&run_args.config_overrides).await?;
+            let artifacts_root =
+                env_artifacts_root().or_else(|| artifacts_root_setting(workflow.as_ref()));
+            let notify_command = notify_command_setting(workflow.as_ref())?;
+            run(run_args, artifacts_root, notify_command).await
+        }
+        WorkflowSubcommand::Status(status_args) => {
+            let artifacts_root =
+                lenient_artifacts_root(status_args.artifacts_dir.as_deref(), overrides).await;
+            if status_args.watch {
+                watch_status(status_args, artifacts_root).await
+            } else {
+                status(status_args, artifacts_root)
+            }
+        }
+        WorkflowSubcommand::Clean(clean_args) => {
+            let artifacts_root =
+                artifacts_root(clean_args.artifacts_dir.as_deref(), overrides).await?;
+            clean(clean_args, artifacts_root)
+        }
+        WorkflowSubcommand::Archive(archive_args) => {
+            let artifacts_root =
+                artifacts_root(archive_args.artifacts_dir.as_deref(), overrides).await?;
+            archive(archive_args, artifacts_root)
         }
-        WorkflowSubcommand::Status(status_args) => status(status_args),
+        WorkflowSubcommand::Validate(validate_args) => validate(validate_args),
+        WorkflowSubcommand::Init(init_args) => init(init_args),
+        WorkflowSubcommand::ImportGithub(import_args) => import_github(import_args).await,
+        WorkflowSubcommand::Graph(graph_args) => {
+            let artifacts_root =
+                artifacts_root(graph_args.artifacts_dir.as_deref(), overrides).await?;
+            graph(graph_args, artifacts_root)
+        }
+        WorkflowSubcommand::Report(report_args) => {
+            let artifacts_root =
+                artifacts_root(report_args.artifacts_dir.as_deref(), overrides).await?;
+            report(report_args, artifacts_root)
+        }
+        WorkflowSubcommand::List(list_args) => {
+            list(list_args, artifacts_root(None, overrides).await?)
+        }
+        WorkflowSubcommand::Cancel(cancel_args) => {
+            let artifacts_root =
+                lenient_artifacts_root(cancel_args.artifacts_dir.as_deref(), overrides).await;
+            cancel(cancel_args, artifacts_root)
+        }
+        WorkflowSubcommand::Complete(complete_args) => {
+            let artifacts_root =
+                artifacts_root(complete_args.artifacts_dir.as_deref(), overrides).await?;
+            complete(complete_args, artifacts_root)
+        }
+        WorkflowSubcommand::Schema => {
+            let schema = WorkflowManifest::json_schema();
+            println!("{}", serde_json::to_string_pretty(&schema)?);
+            Ok(())
+        }
+        WorkflowSubcommand::Prompt(prompt_args) => {
+            let artifacts_root =
+                artifacts_root(prompt_args.artifacts_dir.as_deref(), overrides).await?;
+            prompt(prompt_args, root_overrides, artifacts_root).await
+        }
+        WorkflowSubcommand::Logs(logs_args) => {
+            let artifacts_root =
+                artifacts_root(logs_args.artifacts_dir.as_deref(), overrides).await?;
+            logs(logs_args, artifacts_root).await
+        }
+    }
+}
+
+/// The manifest's id for a ticket typed on the command line: the id itself,
+/// in any case, or an unambiguous prefix of it.
+fn resolve_ticket_arg(manifest: &Path, input: &str) -> Result<String> {
+    let manifest = WorkflowManifest::load(manifest)?;
+    Ok(manifest.resolve_ticket(input)?.id.clone())
+}
+
+/// [`resolve_ticket_arg`] for each of `inputs` that is not a glob; globs are
+/// matched against the ids by the run itself.
+fn resolve_ticket_args(manifest: &Path, inputs: Vec<String>) -> Result<Vec<String>> {
+    let is_glob = |input: &str| input.contains(['*', '?', '[']);
+    if inputs.iter().all(|input| is_glob(input)) {
+        return Ok(inputs);
     }
+    let manifest = WorkflowManifest::load(manifest)?;
+    inputs
+        .into_iter()
+        .map(|input| {
+            if is_glob(&input) {
+                Ok(input)
+            } else {
+                Ok(manifest.resolve_ticket(&input)?.id.clone())
+            }
+        })
+        .collect()
 }
 
-async fn run(args: WorkflowRunArgs) -> Result<()> {
+/// Completes ticket ids from the first manifest named on the command line
+/// being completed, when one can be loaded.
+fn complete_ticket_ids(current: &OsStr) -> Vec<CompletionCandidate> {
+    let current = current.to_string_lossy().to_lowercase();
+    let manifest = std::env::args_os()
+        .map(PathBuf::from)
+        .filter(|path| {
+            matches!(
+                path.extension().and_then(OsStr::to_str),
+                Some("yaml" | "yml" | "toml" | "tml")
+            )
+        })
+        .find_map(|path| WorkflowManifest::load(&path).ok());
+    let Some(manifest) = manifest else {
+        return Vec::new();
+    };
+    manifest
+        .tickets
+        .iter()
+        .filter(|ticket| ticket.id.to_lowercase().starts_with(&current))
+        .map(|ticket| {
+            let summary = ticket.summary.lines().next().unwrap_or_default();
+            CompletionCandidate::new(&ticket.id).help(Some(summary.to_string().into()))
+        })
+        .collect()
+}
+
+/// The codex config's `[workflow]` table, with `-c` overrides applied.
+async fn workflow_config(overrides: &CliConfigOverrides) -> Result<Option<toml::Value>> {
+    let codex_home = find_codex_home().context("failed to resolve CODEX_HOME")?;
+    let mut config = load_config_as_toml(&codex_home)
+        .await
+        .with_context(|| format!("failed to load config from {}", codex_home.display()))?;
+    overrides
+        .apply_on_value(&mut config)
+        .map_err(anyhow::Error::msg)?;
+    Ok(config.get("workflow").cloned())
+}
+
+/// The root of the artifacts directories when `artifacts_dir` is not given:
+/// `CODEX_WORKFLOW_ARTIFACTS_DIR`, else the `workflow.artifacts_root` config
+/// key. `config.toml` is only loaded when neither settles it.
+async fn artifacts_root(
+    artifacts_dir: Option<&Path>,
+    overrides: &CliConfigOverrides,
+) -> Result<Option<PathBuf>> {
+    if artifacts_dir.is_some() {
+        return Ok(None);
+    }
+    if let Some(root) = env_artifacts_root() {
+        return Ok(Some(root));
+    }
+    Ok(artifacts_root_setting(
+        workflow_config(overrides).await?.as_ref(),
+    ))
+}
+
+/// [`artifacts_root`] for commands that must keep working with a broken
+/// `config.toml`, such as stopping a run: a config that fails to load is
+/// reported and ignored.
+async fn lenient_artifacts_root(
+    artifacts_dir: Option<&Path>,
+    overrides: &CliConfigOverrides,
+) -> Option<PathBuf> {
+    artifacts_root(artifacts_dir, overrides)
+        .await
+        .unwrap_or_else(|err| {
+            eprintln!("Warning: ignoring workflow.artifacts_root: {err:#}");
+            None
+        })
+}
+
+/// `CODEX_WORKFLOW_ARTIFACTS_DIR`, unless unset or empty.
+fn env_artifacts_root() -> Option<PathBuf> {
+    std::env::var_os(ARTIFACTS_ROOT_ENV_VAR)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// The `artifacts_root` key of the `[workflow]` table.
+fn artifacts_root_setting(workflow: Option<&toml::Value>) -> Option<PathBuf> {
+    workflow
+        .and_then(|workflow| workflow.get("artifacts_root"))
+        .and_then(|root| root.as_str().map(PathBuf::from))
+}
+
+/// The `notify_command` key of the `[workflow]` table: a program and its
+/// arguments.
+fn notify_command_setting(workflow: Option<&toml::Value>) -> Result<Option<Vec<String>>> {
+    let Some(command) = workflow.and_then(|workflow| workflow.get("notify_command")) else {
+        return Ok(None);
+    };
+    command
+        .as_array()
+        .and_then(|args| {
+            args.iter()
+                .map(|arg| arg.as_str().map(str::to_string))
+                .collect::<Option<Vec<_>>>()
+        })
+        .map(Some)
+        .context("workflow.notify_command must be a list of strings")
+}
+
+async fn run(
+    args: WorkflowRunArgs,
+    artifacts_root: Option<PathBuf>,
+    notify_command: Option<Vec<String>>,
+) -> Result<()> {
+    let exit_zero_on_failure = args.exit_zero_on_failure;
+    let full_notes = args.full_notes;
+    if args.verbose {
+        init_debug_tracing();
+    }
+    let tickets = resolve_ticket_args(&args.manifest, args.tickets)?;
+    let unblock = resolve_ticket_args(&args.manifest, args.unblock)?;
+    let resume_from = args
+        .resume_from
+        .as_deref()
+        .map(|ticket| resolve_ticket_arg(&args.manifest, ticket))
+        .transpose()?;
+    let (events, printer) = if args.quiet && !args.progress {
+        (None, None)
+    } else {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let summaries = (!args.quiet).then(|| ticket_summaries(&args.manifest));
+        (
+            Some(sender),
+            Some(tokio::spawn(print_events(
+                receiver,
+                summaries,
+                args.progress,
+            ))),
+        )
+    };
     let options = WorkflowRunOptions {
         manifest_path: args.manifest,
+        base_dir: args.base_dir,
         artifacts_dir: args.artifacts_dir,
+        artifacts_root,
         resume: args.resume,
+        resume_from,
+        skip_unchanged: args.skip_unchanged,
+        tickets,
         codex_bin: args.codex_bin,
+        reviewer_bin: args.reviewer_bin,
+        codex_subcommand: args.codex_subcommand,
         config_overrides: args.config_overrides,
         worker_model: args.worker_model,
         reviewer_model: args.reviewer_model,
+        profile: args.profile,
+        prompt_via_argv: args.prompt_argv,
+        prompt_command: args.prompt_command,
+        print_command: args.print_command,
+        secrets_file: args.secrets_file,
+        unblock,
+        unblock_all: args.unblock_all,
+        only_failed: args.only_failed,
+        keep_log_attempts: args.keep_log_attempts,
+        review_resumes_worker_session: args.review_resumes_worker_session,
+        worker_resumes_session: args.worker_resumes_session,
+        review_against_diff: args.review_against_diff,
+        skip_git_repo_check: args.require_git_repo.then_some(false),
+        prompt_token_budget: args.prompt_token_budget,
+        strict_context: args.strict_context,
+        no_autocommit: args.no_autocommit,
+        github_token: GITHUB_TOKEN_ENV_VARS
+            .iter()
+            .find_map(|name| std::env::var(name).ok().filter(|token| !token.is_empty())),
+        max_parallel: args.max_parallel,
+        concurrency_from_env: args.concurrency_from_env,
+        append_summary: args.append_summary,
+        extra_args: args.codex_args,
+        log_format: args.log_format,
+        log_ansi: args.log_ansi,
+        omit_log_timestamps: args.no_log_timestamps,
+        max_log_bytes: args.max_log_bytes,
+        min_free_memory: args.min_free_memory,
+        min_free_disk: args.min_free_disk,
+        preflight_url: args.preflight_url,
+        preflight_command: args.preflight_command,
+        preflight_each_session: args.preflight_each_session,
+        max_retries: args.max_retries,
+        max_reworks: args.max_reworks,
+        retry_on_exit_codes: args.retry_on_exit_codes,
+        session_timeout: args.session_timeout.map(Duration::from_secs),
+        worker_timeout: args.worker_timeout.map(Duration::from_secs),
+        review_timeout: args.review_timeout.map(Duration::from_secs),
+        timeout_backoff: args.timeout_backoff,
+        prune_orphans: args.prune_orphans,
+        delete_orphan_artifacts: args.delete_orphan_artifacts,
+        fail_on_orphans: args.fail_on_orphans,
+        strict_deps: args.strict_deps,
+        reuse_run_dir: args.reuse_run_dir,
+        resume_run: args.resume_run,
+        confirmer: args
+            .interactive
+            .then(|| Arc::new(TerminalConfirmer) as Arc<dyn TicketConfirmer>),
+        events,
+        events_socket: args.events_socket,
+        echo_session_output: args.verbose,
+        webhook_url: args.webhook_url,
+        notify_command,
+        metrics_file: args.metrics_file,
+        report_interval: args.report_interval.map(Duration::from_secs),
+    };
+    let report = run_workflow(options).await;
+    // The run dropped its event sender, so the printer drains and stops.
+    if let Some(printer) = printer {
+        let _ = printer.await;
+    }
+    let report = match report {
+        Ok(report) => report,
+        Err(err) => {
+            // Show how far the run got before the error.
+            if let Some(report) = err.partial_report() {
+                print_report(report, full_notes, &HashSet::new());
+            }
+            return Err(err.into());
+        }
     };
-    let report = run_workflow(options).await?;
-    print_report(&report);
+    print_report(&report, full_notes, &HashSet::new());
+    let failed = report.failed_tickets();
+    if !failed.is_empty() && !exit_zero_on_failure {
+        anyhow::bail!(
+            "{} ticket(s) failed or blocked: {}",
+            failed.len(),
+            ticket_ids(&failed)
+        );
+    }
     Ok(())
 }
 
-fn status(args: WorkflowStatusArgs) -> Result<()> {
-    match load_status(&args.manifest, args.artifacts_dir) {
-        Ok(Some(report)) => {
-            print_report(&report);
-            Ok(())
+/// Logs the workflow crate's spans and events to stderr at debug level,
+/// unless a subscriber is already installed.
+fn init_debug_tracing() {
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_span_events(FmtSpan::NEW | FmtSpan::CLOSE)
+        .with_filter(Targets::new().with_target("codex_workflow", LevelFilter::DEBUG));
+    let _ = tracing_subscriber::registry().with(layer).try_init();
+}
+
+/// First summary line of each ticket, for the progress lines. Empty when the
+/// manifest does not load; the run reports that error itself.
+fn ticket_summaries(manifest: &Path) -> BTreeMap<String, String> {
+    WorkflowManifest::load(manifest)
+        .map(|manifest| manifest.summaries())
+        .unwrap_or_default()
+}
+
+/// Prints a line per run event until the run drops its sender: to stdout
+/// unless `summaries` is `None` (`--quiet`), and with `progress` also the
+/// `--progress` line to stderr.
+async fn print_events(
+    mut events: mpsc::UnboundedReceiver<WorkflowEvent>,
+    summaries: Option<BTreeMap<String, String>>,
+    progress: bool,
+) {
+    while let Some(event) = events.recv().await {
+        if progress && let Some(line) = progress_event_line(&event) {
+            eprintln!("{line}");
         }
-        Ok(None) => {
-            println!(
-                "No workflow state found for manifest {}",
-                args.manifest.display()
+        // Status changes already say when sessions start and finish.
+        if matches!(
+            event,
+            WorkflowEvent::SessionStarted { .. } | WorkflowEvent::SessionFinished { .. }
+        ) {
+            continue;
+        }
+        if let Some(summaries) = &summaries {
+            println!("{}", event_line(&event, summaries));
+        }
+    }
+}
+
+/// The `--progress` line for a status change, e.g. `[3/8] T4: Running
+/// worker`, where 3 of the 8 tickets are in a final status.
+fn progress_event_line(event: &WorkflowEvent) -> Option<String> {
+    let WorkflowEvent::TicketStatusChanged {
+        ticket_id,
+        to,
+        done,
+        total,
+        ..
+    } = event
+    else {
+        return None;
+    };
+    Some(format!("[{done}/{total}] {ticket_id}: {}", to.label()))
+}
+
+/// One plain, timestamped line per event so the output stays readable and
+/// greppable when stdout is a file or CI log, e.g.
+/// `2026-01-01T12:00:00Z [2/9] T4: Migrate schema — worker starting`.
+fn event_line(event: &WorkflowEvent, summaries: &BTreeMap<String, String>) -> String {
+    match event {
+        WorkflowEvent::RunStarted {
+            workflow_name, at, ..
+        } => format!("{} Workflow {workflow_name} started", event_time(at)),
+        WorkflowEvent::TicketStatusChanged {
+            ticket_id,
+            from,
+            to,
+            note,
+            done,
+            total,
+            at,
+            ..
+        } => {
+            let mut line = format!("{} [{done}/{total}] {ticket_id}", event_time(at));
+            if let Some(summary) = summaries.get(ticket_id).filter(|s| !s.is_empty()) {
+                line.push_str(&format!(": {summary}"));
+            }
+            line.push_str(&format!(" — {}", transition_label(from, to)));
+            if matches!(to, TicketStatus::Failed | TicketStatus::Blocked)
+                && let Some(note) = note.as_deref().and_then(|note| note.lines().next())
+            {
+                line.push_str(&format!(" ({note})"));
+            }
+            line
+        }
+        WorkflowEvent::SessionStarted {
+            ticket_id,
+            phase,
+            attempt,
+            log_path,
+            at,
+        } => format!(
+            "{} {ticket_id}: {} attempt {attempt} started ({})",
+            event_time(at),
+            phase.as_str(),
+            log_path.display()
+        ),
+        WorkflowEvent::SessionFinished {
+            ticket_id,
+            phase,
+            attempt,
+            success,
+            timed_out,
+            exit_code,
+            at,
+        } => {
+            let outcome = match (success, timed_out, exit_code) {
+                (true, _, _) => "succeeded".to_string(),
+                (false, true, _) => "timed out".to_string(),
+                (false, false, Some(code)) => format!("failed with exit code {code}"),
+                (false, false, None) => "failed".to_string(),
+            };
+            format!(
+                "{} {ticket_id}: {} attempt {attempt} {outcome}",
+                event_time(at),
+                phase.as_str()
+            )
+        }
+        WorkflowEvent::SessionRetrying {
+            ticket_id,
+            phase,
+            retry,
+            max_retries,
+            at,
+        } => format!(
+            "{} {ticket_id}: retrying {} ({retry} of {max_retries})",
+            event_time(at),
+            phase.as_str()
+        ),
+        WorkflowEvent::PreflightChecked {
+            ticket_id,
+            passed,
+            error,
+            retry_in_secs,
+            at,
+        } => {
+            let subject = ticket_id.as_deref().unwrap_or("Workflow");
+            if *passed {
+                return format!("{} {subject}: preflight check passed", event_time(at));
+            }
+            let mut line = format!("{} {subject}: preflight check failed", event_time(at));
+            if let Some(error) = error {
+                line.push_str(&format!(": {error}"));
+            }
+            if let Some(secs) = retry_in_secs {
+                line.push_str(&format!("; retrying in {secs}s"));
+            }
+            line
+        }
+        WorkflowEvent::PromptOverBudget {
+            ticket_id,
+            phase,
+            estimated_tokens,
+            budget,
+            strict,
+            at,
+        } => {
+            let outcome = if *strict {
+                "failing the ticket"
+            } else {
+                "it will likely overflow the model's context"
+            };
+            format!(
+                "{} {ticket_id}: {} prompt is ~{estimated_tokens} tokens, over the budget of \
+                {budget}; {outcome}",
+                event_time(at),
+                phase.as_str()
+            )
+        }
+        WorkflowEvent::RunInterrupted { reason, at } => {
+            format!("{} Workflow interrupted: {reason}", event_time(at))
+        }
+        WorkflowEvent::RunFinished {
+            workflow_name,
+            failed,
+            error,
+            at,
+        } => {
+            let mut line = format!("{} Workflow {workflow_name} finished", event_time(at));
+            if !failed.is_empty() {
+                line.push_str(&format!(", failed: {}", failed.join(", ")));
+            }
+            if let Some(error) = error {
+                line.push_str(&format!(", stopped: {error}"));
+            }
+            line
+        }
+        WorkflowEvent::NotifyCommandFailed {
+            event_type,
+            ticket_id,
+            error,
+            at,
+        } => {
+            let subject = ticket_id.as_deref().unwrap_or("Workflow");
+            format!(
+                "{} {subject}: notify command ({event_type}) failed: {error}",
+                event_time(at)
+            )
+        }
+        WorkflowEvent::WebhookFailed {
+            event_type,
+            ticket_id,
+            error,
+            at,
+        } => {
+            let subject = ticket_id.as_deref().unwrap_or("Workflow");
+            format!(
+                "{} {subject}: webhook notification ({event_type}) failed: {error}",
+                event_time(at)
+            )
+        }
+    }
+}
+
+/// `workflow status --events`: the tail of the event log, one line per event.
+fn print_event_log(path: &Path, limit: usize, manifest: &Path) -> Result<()> {
+    println!();
+    if !path.exists() {
+        println!("No event log at {}", path.display());
+        return Ok(());
+    }
+    println!("Events ({}):", path.display());
+    let summaries = ticket_summaries(manifest);
+    for event in read_events(path, limit)? {
+        println!("  {}", event_line(&event, &summaries));
+    }
+    Ok(())
+}
+
+fn event_time(at: &DateTime<Utc>) -> String {
+    at.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+fn transition_label(from: &TicketStatus, to: &TicketStatus) -> String {
+    let outcome = to.label().to_lowercase();
+    match (from, to) {
+        (_, TicketStatus::RunningWorker) => "worker starting".to_string(),
+        (_, TicketStatus::RunningReview) => "review starting".to_string(),
+        (TicketStatus::RunningWorker, _) => format!("worker finished, {outcome}"),
+        (TicketStatus::RunningReview, _) => format!("review finished, {outcome}"),
+        _ => outcome,
+    }
+}
+
+fn ticket_ids(tickets: &[&TicketRunState]) -> String {
+    tickets
+        .iter()
+        .map(|ticket| ticket.ticket_id.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Prompts on the terminal before each ticket of an `--interactive` run.
+struct TerminalConfirmer;
+
+impl TicketConfirmer for TerminalConfirmer {
+    fn confirm(&self, plan: &TicketPlan) -> Result<TicketDecision> {
+        println!();
+        println!("Next ticket: {} - {}", plan.ticket_id, plan.summary);
+        if plan.status == TicketStatus::NeedsReview {
+            println!("  Resumes at: review");
+        }
+        println!("  Working dir: {}", plan.working_dir.display());
+        if !plan.depends_on.is_empty() {
+            println!("  Depends on: {}", plan.depends_on.join(", "));
+        }
+        for requirement in &plan.requirements {
+            println!("  - {requirement}");
+        }
+        let stdin = std::io::stdin();
+        loop {
+            print!("Run this ticket? [y]es / [n]o, leave pending / [s]kip / [q]uit: ");
+            std::io::stdout().flush()?;
+            let mut answer = String::new();
+            if stdin.lock().read_line(&mut answer)? == 0 {
+                // End of input: stop rather than guess.
+                println!();
+                return Ok(TicketDecision::Quit);
+            }
+            match answer.trim().to_ascii_lowercase().as_str() {
+                "y" | "yes" => return Ok(TicketDecision::Run),
+                "n" | "no" => return Ok(TicketDecision::Defer),
+                "s" | "skip" => return Ok(TicketDecision::Skip),
+                "q" | "quit" => return Ok(TicketDecision::Quit),
+                _ => println!("Please answer y, n, s, or q."),
+            }
+        }
+    }
+}
+
+/// Parses a `--timeout-backoff` factor, which must be at least 1.
+fn parse_timeout_backoff(value: &str) -> Result<f64, String> {
+    match value.trim().parse::<f64>() {
+        Ok(factor) if factor >= 1.0 && factor.is_finite() => Ok(factor),
+        _ => Err(format!(
+            "invalid factor `{value}` (expected a number of at least 1)"
+        )),
+    }
+}
+
+/// Parses a byte count with an optional binary `K`, `M`, `G`, or `T` suffix.
+fn parse_byte_size(value: &str) -> Result<i64, String> {
+    let value = value.trim();
+    let digits_end = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (digits, suffix) = value.split_at(digits_end);
+    let number: i64 = digits
+        .parse()
+        .map_err(|_| format!("invalid size `{value}`"))?;
+    let shift = match suffix
+        .trim()
+        .to_ascii_uppercase()
+        .trim_end_matches("IB")
+        .trim_end_matches('B')
+    {
+        "" => 0,
+        "K" => 10,
+        "M" => 20,
+        "G" => 30,
+        "T" => 40,
+        _ => return Err(format!("invalid size suffix in `{value}`")),
+    };
+    number
+        .checked_mul(1 << shift)
+        .ok_or_else(|| format!("size `{value}` is too large"))
+}
+
+fn status(args: WorkflowStatusArgs, artifacts_root: Option<PathBuf>) -> Result<()> {
+    let mut report = load_status(
+        &args.manifest,
+        args.artifacts_dir,
+        artifacts_root,
+        args.base_dir,
+    )?;
+    if args.du {
+        report = report.map(WorkflowStatusReport::with_disk_usage);
+    }
+    if let Some(path) = &args.secrets_file {
+        report = report
+            .map(|report| report.with_secrets_file(path))
+            .transpose()?;
+    }
+    let unfinished = report
+        .as_ref()
+        .map(|report| ticket_ids(&report.unfinished_tickets()));
+    if let Some(report) = &mut report {
+        retain_statuses(report, &args.statuses);
+    }
+    match &report {
+        Some(report) if args.json => {
+            let json = report.to_json(args.include_prompts)?;
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        }
+        Some(report) => print_report(report, args.full_notes, &HashSet::new()),
+        None => println!(
+            "No workflow state found for manifest {}",
+            args.manifest.display()
+        ),
+    }
+    if let (Some(limit), Some(report)) = (args.events, &report) {
+        print_event_log(&report.events_path(), limit, &args.manifest)?;
+    }
+    if args.check {
+        let Some(unfinished) = unfinished else {
+            anyhow::bail!("workflow has not run yet");
+        };
+        if !unfinished.is_empty() {
+            anyhow::bail!("workflow is not complete; unfinished tickets: {unfinished}");
+        }
+    }
+    Ok(())
+}
+
+/// `--status`: keeps only the tickets in one of `statuses`, unless it is
+/// empty.
+fn retain_statuses(report: &mut WorkflowStatusReport, statuses: &[TicketStatus]) {
+    if !statuses.is_empty() {
+        report
+            .tickets
+            .retain(|ticket| statuses.contains(&ticket.status));
+    }
+}
+
+/// `workflow status --watch`: redraws the status every `--interval` until the
+/// workflow has nothing left to run.
+async fn watch_status(args: WorkflowStatusArgs, artifacts_root: Option<PathBuf>) -> Result<()> {
+    let interval = Duration::from_secs_f64(args.interval.max(0.1));
+    let redraw = std::io::stdout().is_terminal();
+    let mut previous: Option<BTreeMap<String, TicketStatus>> = None;
+    let mut last_json: Option<serde_json::Value> = None;
+    let mut waiting_shown = false;
+    loop {
+        let loaded = load_status(
+            &args.manifest,
+            args.artifacts_dir.clone(),
+            artifacts_root.clone(),
+            args.base_dir.clone(),
+        );
+        let report = match loaded {
+            Ok(report) => report,
+            // A run can be writing the state file just as it is read; keep
+            // showing the last status until the next refresh.
+            Err(_) if previous.is_some() => None,
+            Err(err) => return Err(err.into()),
+        };
+        match report {
+            Some(mut report) => {
+                if args.du {
+                    report = report.with_disk_usage();
+                }
+                let finished = report.is_finished();
+                retain_statuses(&mut report, &args.statuses);
+                let statuses: BTreeMap<String, TicketStatus> = report
+                    .tickets
+                    .iter()
+                    .map(|ticket| (ticket.ticket_id.clone(), ticket.status.clone()))
+                    .collect();
+                if args.json {
+                    if let Some(path) = &args.secrets_file {
+                        report = report.with_secrets_file(path)?;
+                    }
+                    let json = report.to_json(args.include_prompts)?;
+                    if last_json.as_ref() != Some(&json) {
+                        println!("{}", serde_json::to_string(&json)?);
+                        last_json = Some(json);
+                    }
+                } else {
+                    let changed: HashSet<String> = match &previous {
+                        Some(previous) => statuses
+                            .iter()
+                            .filter(|(id, status)| previous.get(*id) != Some(*status))
+                            .map(|(id, _)| id.clone())
+                            .collect(),
+                        None => HashSet::new(),
+                    };
+                    // Piped output only gets a new snapshot when something changed.
+                    if redraw {
+                        crossterm::execute!(
+                            std::io::stdout(),
+                            Clear(ClearType::All),
+                            MoveTo(0, 0)
+                        )?;
+                        print_report(&report, args.full_notes, &changed);
+                        println!();
+                        println!("Refreshing every {}s; Ctrl-C to stop.", args.interval);
+                    } else if previous.is_none() || !changed.is_empty() {
+                        print_report(&report, args.full_notes, &changed);
+                        println!();
+                    }
+                }
+                previous = Some(statuses);
+                if finished {
+                    return Ok(());
+                }
+            }
+            None if previous.is_none() && !waiting_shown && !args.json => {
+                println!(
+                    "Waiting for workflow state of {}...",
+                    args.manifest.display()
+                );
+                waiting_shown = true;
+            }
+            None => {}
+        }
+        std::io::stdout().flush()?;
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
+
+fn clean(args: WorkflowCleanArgs, artifacts_root: Option<PathBuf>) -> Result<()> {
+    if args.all && !args.yes {
+        print!(
+            "Delete all artifacts and state of workflow {}? [y/N]: ",
+            args.manifest.display()
+        );
+        std::io::stdout().flush()?;
+        let mut answer = String::new();
+        std::io::stdin().lock().read_line(&mut answer)?;
+        if !matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes") {
+            println!("Nothing deleted.");
+            return Ok(());
+        }
+    }
+    let report = clean_workflow(&CleanOptions {
+        manifest_path: args.manifest,
+        base_dir: args.base_dir,
+        artifacts_dir: args.artifacts_dir,
+        artifacts_root,
+        logs_only: args.logs_only,
+        completed: args.completed,
+        all: args.all,
+        keep_last: args.keep_last,
+    })?;
+    for path in &report.removed {
+        println!("Removed {}", path.display());
+    }
+    println!(
+        "Deleted {} item(s), {} bytes, from {}",
+        report.removed.len(),
+        report.removed_bytes,
+        report.root.display()
+    );
+    Ok(())
+}
+
+fn archive(args: WorkflowArchiveArgs, artifacts_root: Option<PathBuf>) -> Result<()> {
+    let tickets = resolve_ticket_args(&args.manifest, args.tickets)?;
+    let report = archive_workflow(&ArchiveOptions {
+        manifest_path: args.manifest,
+        base_dir: args.base_dir,
+        artifacts_dir: args.artifacts_dir,
+        artifacts_root,
+        output: args.output,
+        format: args.format,
+        completed_only: args.completed_only,
+        tickets,
+    })?;
+    println!("Wrote {} ({} files)", report.output.display(), report.files);
+    Ok(())
+}
+
+fn validate(args: WorkflowValidateArgs) -> Result<()> {
+    let mut manifest = WorkflowManifest::parse(&args.manifest)?;
+    manifest.base_dir = args.base_dir;
+    let issues = manifest.validate_full();
+    for issue in &issues {
+        println!("{issue}");
+    }
+    let errors = issues.iter().filter(|issue| issue.is_error()).count();
+    let warnings = issues.len() - errors;
+    if errors > 0 || (args.strict && warnings > 0) {
+        bail!(
+            "{} is invalid: {errors} error(s), {warnings} warning(s)",
+            args.manifest.display()
+        );
+    }
+    println!(
+        "{} is valid: {} ticket(s), {warnings} warning(s)",
+        args.manifest.display(),
+        manifest.tickets.len()
+    );
+    Ok(())
+}
+
+fn init(args: WorkflowInitArgs) -> Result<()> {
+    let report = init_manifest(&InitOptions {
+        path: args.path,
+        format: args.format,
+        force: args.force,
+        from_dir: args.from_dir,
+    })?;
+    println!(
+        "Wrote {} with {} ticket(s); check it with `codex workflow validate {}`",
+        report.path.display(),
+        report.tickets,
+        report.path.display()
+    );
+    Ok(())
+}
+
+async fn import_github(args: WorkflowImportGithubArgs) -> Result<()> {
+    let token = GITHUB_TOKEN_ENV_VARS
+        .iter()
+        .find_map(|name| std::env::var(name).ok().filter(|token| !token.is_empty()));
+    let report = import_github_issues(&GithubImportOptions {
+        repo: args.repo,
+        label: args.label,
+        output: args.output,
+        update: args.update,
+        token,
+        api_url: args.api_url,
+    })
+    .await?;
+    let path = report.path.display();
+    if args.update {
+        println!(
+            "Added {} ticket(s) to {path}; {} issue(s) already had one",
+            report.added.len(),
+            report.existing
+        );
+    } else {
+        println!(
+            "Wrote {path} with {} ticket(s); check it with `codex workflow validate {path}`",
+            report.added.len()
+        );
+    }
+    Ok(())
+}
+
+fn graph(args: WorkflowGraphArgs, artifacts_root: Option<PathBuf>) -> Result<()> {
+    let manifest = WorkflowManifest::load(&args.manifest)?;
+    let statuses: Option<BTreeMap<String, TicketStatus>> = if args.with_status {
+        let report = load_status(
+            &args.manifest,
+            args.artifacts_dir,
+            artifacts_root,
+            args.base_dir,
+        )?;
+        match report {
+            Some(report) => Some(
+                report
+                    .tickets
+                    .into_iter()
+                    .map(|ticket| (ticket.ticket_id, ticket.status))
+                    .collect(),
+            ),
+            None => {
+                eprintln!("No workflow state found; every ticket is shown as pending.");
+                Some(BTreeMap::new())
+            }
+        }
+    } else {
+        None
+    };
+    print!(
+        "{}",
+        render_graph(&manifest, args.format, statuses.as_ref())
+    );
+    Ok(())
+}
+
+fn report(args: WorkflowReportArgs, artifacts_root: Option<PathBuf>) -> Result<()> {
+    let Some(report) = load_status(
+        &args.manifest,
+        args.artifacts_dir,
+        artifacts_root,
+        args.base_dir,
+    )?
+    else {
+        bail!(
+            "no workflow state found for manifest {}; run it first",
+            args.manifest.display()
+        );
+    };
+    let format = args
+        .format
+        .or_else(|| args.output.as_deref().and_then(ReportFormat::from_path))
+        .unwrap_or_default();
+    let link_base = args
+        .output
+        .as_deref()
+        .and_then(Path::parent)
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let rendered = report.render(format, link_base);
+    match &args.output {
+        Some(path) => {
+            std::fs::write(path, rendered)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            println!("Wrote {}", path.display());
+        }
+        None => print!("{rendered}"),
+    }
+    Ok(())
+}
+
+fn cancel(args: WorkflowCancelArgs, artifacts_root: Option<PathBuf>) -> Result<()> {
+    let report = cancel_workflow(&CancelOptions {
+        manifest_path: args.manifest.clone(),
+        base_dir: args.base_dir,
+        artifacts_dir: args.artifacts_dir,
+        artifacts_root,
+        hard: args.hard,
+    })?;
+    let Some(run) = report.run else {
+        println!(
+            "No running workflow found for manifest {} (artifacts in {})",
+            args.manifest.display(),
+            report.root.display()
+        );
+        return Ok(());
+    };
+    let host = run
+        .hostname
+        .map(|host| format!(" on {host}"))
+        .unwrap_or_default();
+    let outcome = if args.hard {
+        "kill its running sessions and stop"
+    } else {
+        "stop once its running tickets finish"
+    };
+    println!(
+        "Asked the run in process {}{host}, started {}, to {outcome}",
+        run.pid,
+        run.acquired_at.format("%Y-%m-%d %H:%M:%S UTC")
+    );
+    Ok(())
+}
+
+fn complete(args: WorkflowCompleteArgs, artifacts_root: Option<PathBuf>) -> Result<()> {
+    let ticket_id = resolve_ticket_arg(&args.manifest, &args.ticket)?;
+    let ticket = complete_ticket(&CompleteOptions {
+        manifest_path: args.manifest,
+        base_dir: args.base_dir,
+        artifacts_dir: args.artifacts_dir,
+        artifacts_root,
+        ticket_id,
+        note: args.note,
+        force: args.force,
+    })?;
+    println!(
+        "Marked ticket {} complete: {}",
+        ticket.ticket_id,
+        ticket.note.as_deref().unwrap_or_default()
+    );
+    Ok(())
+}
+
+fn list(args: WorkflowListArgs, artifacts_root: Option<PathBuf>) -> Result<()> {
+    let root = args.root.unwrap_or_else(|| PathBuf::from("."));
+    let report = list_workflows(&ListOptions {
+        root: root.clone(),
+        glob: Some(args.glob),
+        artifacts_root,
+    })?;
+    for warning in &report.warnings {
+        eprintln!("Warning: {warning}");
+    }
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report.workflows)?);
+        return Ok(());
+    }
+    if report.workflows.is_empty() {
+        println!("No workflows found under {}", root.display());
+        return Ok(());
+    }
+    let rows: Vec<[String; 4]> = report
+        .workflows
+        .iter()
+        .map(|workflow| {
+            let manifest = match &workflow.manifest_path {
+                Some(path) => path
+                    .strip_prefix(&root)
+                    .unwrap_or(path)
+                    .display()
+                    .to_string(),
+                None => "(no manifest)".to_string(),
+            };
+            let counts = workflow
+                .counts
+                .iter()
+                .map(|count| format!("{} {}", count.count, count.status.label().to_lowercase()))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let last_activity = match workflow.last_activity {
+                Some(at) => at.format("%Y-%m-%d %H:%M UTC").to_string(),
+                None => "never run".to_string(),
+            };
+            [workflow.name.clone(), manifest, counts, last_activity]
+        })
+        .collect();
+    let width = |column: usize| rows.iter().map(|row| row[column].len()).max().unwrap_or(0);
+    let (name_width, manifest_width, counts_width) = (width(0), width(1), width(2));
+    for [name, manifest, counts, last_activity] in &rows {
+        println!(
+            "{name:<name_width$}  {manifest:<manifest_width$}  {counts:<counts_width$}  \
+            {last_activity}"
+        );
+    }
+    Ok(())
+}
+
+async fn prompt(
+    args: WorkflowPromptArgs,
+    config_overrides: CliConfigOverrides,
+    artifacts_root: Option<PathBuf>,
+) -> Result<()> {
+    let ticket_id = resolve_ticket_arg(&args.manifest, &args.ticket)?;
+    let options = WorkflowRunOptions {
+        manifest_path: args.manifest,
+        base_dir: args.base_dir,
+        artifacts_dir: args.artifacts_dir,
+        artifacts_root,
+        codex_bin: args.codex_bin,
+        reviewer_bin: args.reviewer_bin,
+        codex_subcommand: args.codex_subcommand,
+        config_overrides,
+        worker_model: args.worker_model,
+        reviewer_model: args.reviewer_model,
+        profile: args.profile,
+        prompt_command: args.prompt_command,
+        extra_args: args.codex_args,
+        skip_git_repo_check: args.require_git_repo.then_some(false),
+        prompt_token_budget: args.prompt_token_budget,
+        ..Default::default()
+    };
+    let phase = if args.review {
+        SessionPhase::Review
+    } else {
+        SessionPhase::Worker
+    };
+    let preview = preview_prompt(&options, &ticket_id, phase).await?;
+    println!(
+        "Model: {}",
+        preview.model.as_deref().unwrap_or("(codex default)")
+    );
+    println!("Working directory: {}", preview.working_dir.display());
+    println!("Command: {}", preview.command_line);
+    match preview.token_budget {
+        Some(budget) if preview.estimated_tokens > budget => println!(
+            "Estimated prompt size: ~{} tokens, over the budget of {budget}",
+            preview.estimated_tokens
+        ),
+        Some(budget) => println!(
+            "Estimated prompt size: ~{} tokens (budget {budget})",
+            preview.estimated_tokens
+        ),
+        None => println!(
+            "Estimated prompt size: ~{} tokens",
+            preview.estimated_tokens
+        ),
+    }
+    println!();
+    print!("{}", preview.prompt);
+    if !preview.prompt.ends_with('\n') {
+        println!();
+    }
+    Ok(())
+}
+
+async fn logs(args: WorkflowLogsArgs, artifacts_root: Option<PathBuf>) -> Result<()> {
+    let ticket_id = resolve_ticket_arg(&args.manifest, &args.ticket)?;
+    let query = SessionLogQuery {
+        manifest_path: args.manifest,
+        base_dir: args.base_dir,
+        artifacts_dir: args.artifacts_dir,
+        artifacts_root,
+        ticket_id,
+        phase: if args.review {
+            SessionPhase::Review
+        } else {
+            SessionPhase::Worker
+        },
+        attempt: args.attempt,
+    };
+    let mut path = find_session_log(&query)?;
+    let contents =
+        std::fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+    let start = match args.tail {
+        Some(lines) => tail_start(&contents, lines),
+        None => 0,
+    };
+    let mut stdout = std::io::stdout();
+    stdout.write_all(&contents[start..])?;
+    stdout.flush()?;
+    let mut live = is_live_session_log(contents.as_slice())?;
+    if !args.follow {
+        if live {
+            eprintln!(
+                "The session is still running; this is its output so far. \
+                 Pass --follow to keep printing it."
             );
-            Ok(())
         }
-        Err(err) => Err(err),
+        return Ok(());
+    }
+    let mut offset = contents.len() as u64;
+    loop {
+        // Checked before reading, so output written before the ticket
+        // settled is still printed.
+        let settled = ticket_settled(&query)?;
+        if query.attempt.is_none()
+            && let Ok(latest) = find_session_log(&query)
+            && latest != path
+        {
+            eprintln!("==> {} <==", latest.display());
+            path = latest;
+            offset = 0;
+            live = false;
+        }
+        let mut file = std::fs::File::open(&path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        let was_live = std::mem::replace(&mut live, is_live_session_log(&mut file)?);
+        let len = file.metadata()?.len();
+        if was_live && !live {
+            // The finished log replaced the one that was being written.
+            eprintln!(
+                "The session finished; its complete log is {}",
+                path.display()
+            );
+            offset = len;
+        }
+        if len < offset {
+            // Truncated or replaced; start over.
+            offset = 0;
+        }
+        if len > offset {
+            file.seek(SeekFrom::Start(offset))?;
+            offset += std::io::copy(&mut file, &mut stdout)?;
+            stdout.flush()?;
+        }
+        if settled {
+            return Ok(());
+        }
+        tokio::time::sleep(LOG_FOLLOW_INTERVAL).await;
+    }
+}
+
+/// Whether nothing more will be written to a ticket's logs for now: it
+/// reached a final status, or no run is active.
+fn ticket_settled(query: &SessionLogQuery) -> Result<bool> {
+    let Some(report) = load_status(
+        &query.manifest_path,
+        query.artifacts_dir.clone(),
+        query.artifacts_root.clone(),
+        query.base_dir.clone(),
+    )?
+    else {
+        return Ok(true);
+    };
+    let running = report.run.as_ref().is_some_and(LockInfo::holder_is_alive);
+    let is_final = report
+        .tickets
+        .iter()
+        .find(|ticket| ticket.ticket_id == query.ticket_id)
+        .is_some_and(|ticket| ticket.status.is_final());
+    Ok(is_final || !running)
+}
+
+const LOG_FOLLOW_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Byte offset of the start of the last `lines` lines of `contents`.
+fn tail_start(contents: &[u8], lines: usize) -> usize {
+    if lines == 0 {
+        return contents.len();
     }
+    // A trailing newline ends the last line rather than starting a new one.
+    let body = contents.strip_suffix(b"\n").unwrap_or(contents);
+    body.iter()
+        .enumerate()
+        .rev()
+        .filter(|(_, byte)| **byte == b'\n')
+        .nth(lines - 1)
+        .map_or(0, |(index, _)| index + 1)
 }
 
-fn print_report(report: &WorkflowStatusReport) {
+/// "actively running (PID 1234, last heartbeat 8s ago)", or "stale" when the
+/// lock's holder is gone.
+fn run_liveness(run: &LockInfo) -> String {
+    let age = format_duration(Utc::now() - run.last_heartbeat());
+    if run.holder_is_alive() {
+        let host = run
+            .hostname
+            .as_ref()
+            .map(|host| format!(" on {host}"))
+            .unwrap_or_default();
+        format!(
+            "actively running (PID {}{host}, last heartbeat {age} ago)",
+            run.pid
+        )
+    } else {
+        format!("stale — last heartbeat {age} ago (PID {})", run.pid)
+    }
+}
+
+/// Prints the status table; tickets in `changed` are marked with `*`.
+fn print_report(report: &WorkflowStatusReport, full_notes: bool, changed: &HashSet<String>) {
     println!("Workflow: {}", report.workflow_name);
     println!("State file: {}", report.state_path.display());
+    if let Some(run_dir) = &report.run_dir {
+        println!("Run directory: {}", run_dir.display());
+    }
+    if let Some(run) = &report.run {
+        println!("Run: {}", run_liveness(run));
+    }
+    for warning in &report.warnings {
+        println!("Warning: {warning}");
+    }
+    if let Some(usage) = &report.disk_usage {
+        println!("Disk usage: {}", format_sizes(&usage.total));
+    }
+    // `supports_color` is also off when stdout is not a terminal or NO_COLOR
+    // is set.
+    let color = supports_color::on(Stream::Stdout).is_some();
+    // Notes are cut to the terminal's width only when printing to one.
+    let columns = (!full_notes && std::io::stdout().is_terminal())
+        .then(|| crossterm::terminal::size().ok())
+        .flatten()
+        .map(|(columns, _)| usize::from(columns));
+    println!("{}", progress_line(&report.tickets));
+    let id_width = report
+        .tickets
+        .iter()
+        .map(|ticket| ticket.ticket_id.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max("TICKET".len());
+    let note_column = 2 + id_width + 2 + STATUS_WIDTH + 2 + DURATION_WIDTH + 2;
+    println!(
+        "  {:<id_width$}  {:<STATUS_WIDTH$}  {:>DURATION_WIDTH$}  NOTE",
+        "TICKET", "STATUS", "DURATION"
+    );
+    let now = Utc::now();
     for ticket in &report.tickets {
+        let duration = ticket
+            .elapsed(now)
+            .map(format_duration)
+            .unwrap_or_else(|| "-".to_string());
+        let note = ticket
+            .note
+            .as_deref()
+            .unwrap_or("No status note recorded yet.");
+        let note = match columns {
+            Some(columns) => truncate_note(note, columns.saturating_sub(note_column)),
+            None => note.replace('\n', &format!("\n{}", " ".repeat(note_column))),
+        };
+        let marker = if changed.contains(&ticket.ticket_id) {
+            "*"
+        } else {
+            " "
+        };
+        let id = format!("{:<id_width$}", ticket.ticket_id);
+        let id = if color && marker == "*" {
+            id.bold().to_string()
+        } else {
+            id
+        };
         println!(
-            "- {:<12} {:<15} {}",
-            ticket.ticket_id,
-            format!("{:?}", ticket.status),
-            ticket
-                .note
-                .as_deref()
-                .unwrap_or("No status note recorded yet.")
+            "{marker} {id}  {}  {duration:>DURATION_WIDTH$}  {note}",
+            status_cell(&ticket.status, color),
         );
+        if !ticket.requirement_results.is_empty() {
+            let results = ticket
+                .requirement_results
+                .iter()
+                .map(|(id, outcome)| format!("{id} {outcome:?}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            println!("    requirements: {results}");
+        }
+        if let Some(pull_request) = &ticket.pull_request {
+            println!("    pull request: {pull_request}");
+        }
         if let Some(worker_log) = &ticket.worker_log {
             println!("    worker log: {}", worker_log.display());
         }
         if let Some(review_log) = &ticket.review_log {
             println!("    review log: {}", review_log.display());
         }
+        let kept_logs = ticket.attempts.iter().filter(|a| !a.log_pruned).count();
+        let earlier_attempts = kept_logs.saturating_sub(
+            usize::from(ticket.worker_log.is_some()) + usize::from(ticket.review_log.is_some()),
+        );
+        if earlier_attempts > 0 {
+            println!("    earlier attempt logs: {earlier_attempts}");
+        }
+        for attempt in ticket
+            .attempts
+            .iter()
+            .filter(|a| a.truncated_bytes > 0 && !a.log_pruned)
+        {
+            println!(
+                "    truncated log: {} ({} bytes dropped)",
+                attempt.log_path.display(),
+                attempt.truncated_bytes
+            );
+        }
+        if let (Some(diff), Some(changed_files)) = (&ticket.worker_diff, ticket.changed_files) {
+            println!(
+                "    worker diff: {} ({changed_files} file(s) changed)",
+                diff.display()
+            );
+        }
+        if let Some(report) = &ticket.review_report {
+            println!("    review verdict: {:?}", report.verdict);
+            for issue in &report.blocking_issues {
+                println!("      blocking: {issue}");
+            }
+        }
+        if let Some(worker_output) = &ticket.worker_output {
+            println!("    worker output: {}", worker_output.display());
+        }
+        if let Some(review_output) = &ticket.review_output {
+            println!("    review output: {}", review_output.display());
+        }
+        if let Some(sizes) = report
+            .disk_usage
+            .as_ref()
+            .and_then(|usage| usage.tickets.get(&ticket.ticket_id))
+        {
+            println!("    disk usage: {}", format_sizes(sizes));
+        }
+    }
+}
+
+/// Width of the status column: the longest status label.
+const STATUS_WIDTH: usize = 14;
+
+/// Width of the duration column, enough for `99h59m`.
+const DURATION_WIDTH: usize = 8;
+
+/// Cells in the progress bar.
+const PROGRESS_BAR_WIDTH: usize = 20;
+
+/// Notes are never cut shorter than this, however narrow the terminal.
+const MIN_NOTE_WIDTH: usize = 20;
+
+/// The ticket's status padded to a fixed width, colored by outcome when
+/// `color` is set.
+fn status_cell(status: &TicketStatus, color: bool) -> String {
+    let cell = format!("{:<STATUS_WIDTH$}", status.label());
+    if !color {
+        return cell;
+    }
+    match status {
+        TicketStatus::Complete => cell.green().to_string(),
+        TicketStatus::Failed => cell.red().to_string(),
+        TicketStatus::RunningWorker | TicketStatus::RunningReview | TicketStatus::NeedsReview => {
+            cell.yellow().to_string()
+        }
+        TicketStatus::Pending | TicketStatus::Skipped => cell.dimmed().to_string(),
+        TicketStatus::Blocked => cell,
+    }
+}
+
+/// A bar of the completed share of tickets, e.g.
+/// `[##########..........] 7/14 complete, 1 failed`.
+fn progress_line(tickets: &[TicketRunState]) -> String {
+    let count = |matches: fn(&TicketStatus) -> bool| {
+        tickets
+            .iter()
+            .filter(|ticket| matches(&ticket.status))
+            .count()
+    };
+    let complete = count(|status| *status == TicketStatus::Complete);
+    let filled = (complete * PROGRESS_BAR_WIDTH)
+        .checked_div(tickets.len())
+        .unwrap_or(0);
+    let mut line = format!(
+        "[{}{}] {complete}/{} complete",
+        "#".repeat(filled),
+        ".".repeat(PROGRESS_BAR_WIDTH - filled),
+        tickets.len()
+    );
+    let others = [
+        (
+            count(|status| {
+                matches!(
+                    status,
+                    TicketStatus::RunningWorker | TicketStatus::RunningReview
+                )
+            }),
+            "running",
+        ),
+        (count(|status| *status == TicketStatus::Failed), "failed"),
+        (count(|status| *status == TicketStatus::Blocked), "blocked"),
+        (count(|status| *status == TicketStatus::Skipped), "skipped"),
+    ];
+    for (count, label) in others {
+        if count > 0 {
+            line.push_str(&format!(", {count} {label}"));
+        }
+    }
+    line
+}
+
+/// The note's first line, cut to `width` characters with an ellipsis.
+fn truncate_note(note: &str, width: usize) -> String {
+    let width = width.max(MIN_NOTE_WIDTH);
+    let line = note.lines().next().unwrap_or("");
+    let cut = line.chars().count() > width || note.trim_end().contains('\n');
+    if !cut {
+        return line.to_string();
+    }
+    let kept: String = line.chars().take(width - 1).collect();
+    format!("{}…", kept.trim_end())
+}
+
+fn format_sizes(sizes: &ArtifactSizes) -> String {
+    format!(
+        "{} (logs {}, patches {}, other {})",
+        format_bytes(sizes.total()),
+        format_bytes(sizes.logs),
+        format_bytes(sizes.patches),
+        format_bytes(sizes.other)
+    )
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
     }
+    format!("{value:.1} {}", UNITS[unit])
 }
@@ -0,0 +1,63 @@
+#![cfg(unix)]
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use tempfile::TempDir;
+
+fn codex_command(codex_home: &Path) -> Result<assert_cmd::Command> {
+    let mut cmd = assert_cmd::Command::cargo_bin("codex")?;
+    cmd.env("CODEX_HOME", codex_home);
+    cmd.env_remove("RUST_LOG");
+    Ok(cmd)
+}
+
+/// A stand-in codex binary whose sessions all fail.
+fn failing_codex(dir: &Path) -> Result<PathBuf> {
+    let path = dir.join("fake-codex");
+    fs::write(&path, "#!/bin/sh\ncat >/dev/null\nexit 1\n")?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+    Ok(path)
+}
+
+fn run_stderr(dir: &TempDir, verbose: bool) -> Result<String> {
+    let manifest = dir.path().join("demo.workflow.yaml");
+    fs::write(
+        &manifest,
+        "name: demo\ntickets:\n  - id: T1\n    summary: First ticket\n",
+    )?;
+    let codex_bin = failing_codex(dir.path())?;
+    let codex_home = dir.path().join("home");
+    fs::create_dir_all(&codex_home)?;
+
+    let mut cmd = codex_command(&codex_home)?;
+    cmd.args(["workflow", "run"])
+        .arg(&manifest)
+        .arg("--codex-bin")
+        .arg(&codex_bin)
+        .arg("--exit-zero-on-failure");
+    if verbose {
+        cmd.arg("-v");
+    }
+    let output = cmd.output()?;
+    assert!(output.status.success(), "{output:?}");
+    Ok(String::from_utf8(output.stderr)?)
+}
+
+#[test]
+fn verbose_logs_tracing_spans_and_events_to_stderr() -> Result<()> {
+    let stderr = run_stderr(&TempDir::new()?, true)?;
+    assert!(stderr.contains("workflow_run"), "{stderr}");
+    assert!(stderr.contains("starting ticket"), "{stderr}");
+    Ok(())
+}
+
+#[test]
+fn runs_without_verbose_log_no_tracing() -> Result<()> {
+    let stderr = run_stderr(&TempDir::new()?, false)?;
+    assert!(!stderr.contains("starting ticket"), "{stderr}");
+    Ok(())
+}
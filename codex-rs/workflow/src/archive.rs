@@ -0,0 +1,435 @@
+//! Packaging of a workflow's artifacts into a single tarball or zip file.
+
+use crate::layout::WORKTREE_DIR;
+use crate::layout::WorkflowLayout;
+use crate::layout::ticket_dir_name;
+use crate::manifest::WorkflowManifest;
+use crate::orchestrator::select_tickets;
+use crate::state::TicketRunState;
+use crate::state::TicketStatus;
+use crate::state::WorkflowState;
+use crate::summary::render_archive_summary;
+use anyhow::Context;
+use anyhow::Result;
+use chrono::Utc;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+use zip::ZipWriter;
+use zip::write::SimpleFileOptions;
+
+/// Container format of an archive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    #[default]
+    TarGz,
+    Zip,
+}
+
+impl ArchiveFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ArchiveFormat::TarGz => "tar.gz",
+            ArchiveFormat::Zip => "zip",
+        }
+    }
+
+    /// `Zip` for a `.zip` path, otherwise `TarGz`.
+    fn for_path(path: &Path) -> Self {
+        let is_zip = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"));
+        if is_zip {
+            ArchiveFormat::Zip
+        } else {
+            ArchiveFormat::TarGz
+        }
+    }
+}
+
+impl FromStr for ArchiveFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "tar.gz" | "tgz" | "tar" => Ok(ArchiveFormat::TarGz),
+            "zip" => Ok(ArchiveFormat::Zip),
+            other => Err(format!(
+                "unknown archive format `{other}` (expected tar.gz or zip)"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveOptions {
+    pub manifest_path: PathBuf,
+    pub base_dir: Option<PathBuf>,
+    pub artifacts_dir: Option<PathBuf>,
+    /// Root of the artifacts directories; see [`WorkflowLayout::resolve`].
+    pub artifacts_root: Option<PathBuf>,
+    /// Where to write the archive; defaults to
+    /// `<workflow-name>-<timestamp>.<ext>` in the current directory.
+    pub output: Option<PathBuf>,
+    /// Defaults to `Zip` for an `output` ending in `.zip`, else `TarGz`.
+    pub format: Option<ArchiveFormat>,
+    /// Leave out the ticket directories of tickets that are not `Complete`.
+    pub completed_only: bool,
+    /// Only include the ticket directories of tickets matching these ids or
+    /// globs; empty means every ticket.
+    pub tickets: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ArchiveReport {
+    pub output: PathBuf,
+    /// Number of files written to the archive.
+    pub files: usize,
+}
+
+/// Writes a gzipped tarball or zip file with a single top-level
+/// `<workflow-name>/` directory holding a copy of the manifest, a generated
+/// `SUMMARY.md`, and the artifacts directory under `artifacts/`. Entry paths
+/// are relative.
+pub fn archive_workflow(opts: &ArchiveOptions) -> Result<ArchiveReport> {
+    let mut manifest = WorkflowManifest::load(&opts.manifest_path)?;
+    manifest.base_dir = opts.base_dir.clone();
+    let workflow_name = manifest.workflow_name();
+    let layout = WorkflowLayout::resolve(
+        &manifest,
+        opts.artifacts_dir.as_deref(),
+        opts.artifacts_root.as_deref(),
+    );
+    let state_path = layout.state_file();
+    if !state_path.exists() {
+        anyhow::bail!(
+            "no workflow state found at {}; nothing to archive",
+            state_path.display()
+        );
+    }
+    let state = WorkflowState::load(&state_path)?;
+    let selected = select_tickets(&manifest, &opts.tickets)?;
+    let mut tickets: Vec<TicketRunState> = state.tickets.values().cloned().collect();
+    if !opts.tickets.is_empty() {
+        tickets.retain(|ticket| selected.contains(ticket.ticket_id.as_str()));
+    }
+    let excluded_dirs: HashSet<String> = state
+        .tickets
+        .values()
+        .filter(|ticket| {
+            !tickets
+                .iter()
+                .any(|kept| kept.ticket_id == ticket.ticket_id)
+                || (opts.completed_only && ticket.status != TicketStatus::Complete)
+        })
+        .map(|ticket| ticket_dir_name(&ticket.ticket_id))
+        .collect();
+
+    let format = opts
+        .format
+        .or_else(|| opts.output.as_deref().map(ArchiveFormat::for_path))
+        .unwrap_or_default();
+    let output = opts.output.clone().unwrap_or_else(|| {
+        PathBuf::from(format!(
+            "{workflow_name}-{}.{}",
+            Utc::now().format("%Y%m%dT%H%M%SZ"),
+            format.extension()
+        ))
+    });
+    let file = fs::File::create(&output)
+        .with_context(|| format!("failed to create {}", output.display()))?;
+    let mut writer = ArchiveWriter::new(format, file);
+    let top = PathBuf::from(&workflow_name);
+
+    let manifest_name = opts
+        .manifest_path
+        .file_name()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("workflow.yaml"));
+    writer.add_file(&opts.manifest_path, &top.join(manifest_name))?;
+    let summary = render_archive_summary(&workflow_name, &tickets);
+    writer
+        .add_data(&top.join("SUMMARY.md"), summary.as_bytes())
+        .context("failed to archive SUMMARY.md")?;
+    let mut files = 2;
+
+    let skip: HashSet<PathBuf> = [layout.lock_file(), absolute(&output)]
+        .into_iter()
+        .collect();
+    // Directories to archive, each with its path in the archive. Tickets
+    // with a `log_dir` keep theirs outside the root; they are archived
+    // where they would be without one.
+    let artifacts = top.join("artifacts");
+    let mut pending = vec![(layout.root().to_path_buf(), artifacts.clone())];
+    let base_dir = manifest.base_dir();
+    for ticket in &manifest.tickets {
+        let Some(log_dir) = ticket.resolved_log_dir(&base_dir) else {
+            continue;
+        };
+        if excluded_dirs.contains(&ticket_dir_name(&ticket.id)) {
+            continue;
+        }
+        for dir in layout.all_ticket_dirs(&ticket.id) {
+            if dir.starts_with(layout.root()) {
+                continue;
+            }
+            if let Ok(relative) = dir.strip_prefix(&log_dir) {
+                pending.push((dir.clone(), artifacts.join(relative)));
+            }
+        }
+    }
+    let mut archived = HashSet::new();
+    while let Some((dir, archive_dir)) = pending.pop() {
+        let mut entries: Vec<PathBuf> = fs::read_dir(&dir)
+            .with_context(|| format!("failed to read {}", dir.display()))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .collect();
+        entries.sort();
+        for path in entries {
+            if skip.contains(&absolute(&path)) {
+                continue;
+            }
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let file_type = fs::symlink_metadata(&path)
+                .with_context(|| format!("failed to inspect {}", path.display()))?
+                .file_type();
+            if file_type.is_dir() {
+                // Kept worktrees are checkouts of the repository, not artifacts.
+                if !excluded_dirs.contains(name.as_ref()) && name != WORKTREE_DIR {
+                    let archive_path = archive_dir.join(name.as_ref());
+                    pending.push((path, archive_path));
+                }
+                continue;
+            }
+            if file_type.is_symlink() && writer.format() == ArchiveFormat::Zip {
+                // Zip has no portable symlink entry; `latest` is derivable.
+                continue;
+            }
+            let archive_path = archive_dir.join(name.as_ref());
+            if archived.insert(archive_path.clone()) {
+                writer.add_file(&path, &archive_path)?;
+                files += 1;
+            }
+        }
+    }
+    writer
+        .finish()
+        .with_context(|| format!("failed to write {}", output.display()))?;
+    Ok(ArchiveReport { output, files })
+}
+
+enum ArchiveWriter {
+    TarGz(tar::Builder<GzEncoder<fs::File>>),
+    Zip(ZipWriter<fs::File>),
+}
+
+impl ArchiveWriter {
+    fn new(format: ArchiveFormat, file: fs::File) -> Self {
+        match format {
+            ArchiveFormat::TarGz => {
+                let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+                builder.follow_symlinks(false);
+                ArchiveWriter::TarGz(builder)
+            }
+            ArchiveFormat::Zip => ArchiveWriter::Zip(ZipWriter::new(file)),
+        }
+    }
+
+    fn format(&self) -> ArchiveFormat {
+        match self {
+            ArchiveWriter::TarGz(_) => ArchiveFormat::TarGz,
+            ArchiveWriter::Zip(_) => ArchiveFormat::Zip,
+        }
+    }
+
+    /// Adds the file at `path` as `name`.
+    fn add_file(&mut self, path: &Path, name: &Path) -> Result<()> {
+        let result = match self {
+            ArchiveWriter::TarGz(builder) => builder
+                .append_path_with_name(path, name)
+                .map_err(anyhow::Error::from),
+            ArchiveWriter::Zip(_) => fs::read(path)
+                .map_err(anyhow::Error::from)
+                .and_then(|contents| self.add_data(name, &contents)),
+        };
+        result.with_context(|| format!("failed to archive {}", path.display()))
+    }
+
+    fn add_data(&mut self, name: &Path, contents: &[u8]) -> Result<()> {
+        match self {
+            ArchiveWriter::TarGz(builder) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(contents.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(Utc::now().timestamp().max(0) as u64);
+                header.set_cksum();
+                builder.append_data(&mut header, name, contents)?;
+            }
+            ArchiveWriter::Zip(zip) => {
+                zip.start_file(zip_entry_name(name), SimpleFileOptions::default())?;
+                zip.write_all(contents)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        match self {
+            ArchiveWriter::TarGz(builder) => {
+                builder.into_inner()?.finish()?;
+            }
+            ArchiveWriter::Zip(zip) => {
+                zip.finish()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `name` with `/` separators, as zip requires on every platform.
+fn zip_entry_name(name: &Path) -> String {
+    name.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::GzDecoder;
+
+    #[test]
+    fn archives_manifest_summary_and_relative_artifacts() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest_path = dir.path().join("workflow.yaml");
+        fs::write(
+            &manifest_path,
+            "name: demo\ntickets:\n  - id: T1\n    summary: One\n  - id: T2\n    summary: Two\n",
+        )
+        .expect("write manifest");
+        let manifest = WorkflowManifest::load(&manifest_path).expect("load manifest");
+        let layout = WorkflowLayout::new(dir.path().join("artifacts"));
+        layout.ensure_root(&manifest).expect("ensure root");
+        let mut state = WorkflowState::initialize(&manifest);
+        state
+            .ticket_mut("T1")
+            .expect("ticket T1")
+            .mark_finished(TicketStatus::Complete, Some("Review passed".into()));
+        state.save(&layout.state_file()).expect("save state");
+        for ticket in ["T1", "T2"] {
+            layout.ensure_ticket_dir(ticket).expect("ticket dir");
+            fs::write(layout.worker_log_path(ticket, 1), "log").expect("write log");
+        }
+
+        let output = dir.path().join("out.tar.gz");
+        let report = archive_workflow(&ArchiveOptions {
+            manifest_path,
+            artifacts_dir: Some(layout.root().to_path_buf()),
+            output: Some(output.clone()),
+            completed_only: true,
+            ..Default::default()
+        })
+        .expect("archive");
+        assert_eq!(report.files, 6);
+
+        let mut archive = tar::Archive::new(GzDecoder::new(
+            fs::File::open(&output).expect("open archive"),
+        ));
+        let mut names: Vec<String> = archive
+            .entries()
+            .expect("entries")
+            .map(|entry| {
+                entry
+                    .expect("entry")
+                    .path()
+                    .expect("path")
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "demo/SUMMARY.md",
+                "demo/artifacts/.gitignore",
+                "demo/artifacts/state.json",
+                "demo/artifacts/ticket-T1/worker.1.md",
+                "demo/artifacts/workflow.meta.json",
+                "demo/workflow.yaml",
+            ]
+        );
+
+        let output = dir.path().join("out.zip");
+        let report = archive_workflow(&ArchiveOptions {
+            manifest_path: dir.path().join("workflow.yaml"),
+            artifacts_dir: Some(layout.root().to_path_buf()),
+            output: Some(output.clone()),
+            tickets: vec!["T2".to_string()],
+            ..Default::default()
+        })
+        .expect("export zip");
+        assert_eq!(report.files, 6);
+        let mut zip =
+            zip::ZipArchive::new(fs::File::open(&output).expect("open zip")).expect("read zip");
+        let mut names: Vec<String> = zip.file_names().map(str::to_string).collect();
+        names.sort();
+        assert!(names.contains(&"demo/artifacts/ticket-T2/worker.1.md".to_string()));
+        assert!(!names.iter().any(|name| name.contains("ticket-T1")));
+        let mut summary = String::new();
+        std::io::Read::read_to_string(
+            &mut zip.by_name("demo/SUMMARY.md").expect("summary"),
+            &mut summary,
+        )
+        .expect("read summary");
+        assert!(summary.contains("| T2 |"));
+        assert!(!summary.contains("| T1 |"), "{summary}");
+    }
+
+    #[test]
+    fn archives_ticket_log_dirs_as_artifacts() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest_path = dir.path().join("workflow.yaml");
+        fs::write(
+            &manifest_path,
+            "name: demo\ntickets:\n  - id: T1\n    summary: One\n    log_dir: logs\n",
+        )
+        .expect("write manifest");
+        let manifest = WorkflowManifest::load(&manifest_path).expect("load manifest");
+        let layout =
+            WorkflowLayout::new(dir.path().join("artifacts")).with_ticket_log_dirs(&manifest);
+        layout.ensure_root(&manifest).expect("ensure root");
+        WorkflowState::initialize(&manifest)
+            .save(&layout.state_file())
+            .expect("save state");
+        layout.ensure_ticket_dir("T1").expect("ticket dir");
+        fs::write(layout.worker_log_path("T1", 1), "log").expect("write log");
+
+        let output = dir.path().join("out.zip");
+        archive_workflow(&ArchiveOptions {
+            manifest_path,
+            artifacts_dir: Some(layout.root().to_path_buf()),
+            output: Some(output.clone()),
+            ..Default::default()
+        })
+        .expect("archive");
+        let zip =
+            zip::ZipArchive::new(fs::File::open(&output).expect("open zip")).expect("read zip");
+        let names: Vec<&str> = zip.file_names().collect();
+        assert!(
+            names.contains(&"demo/artifacts/ticket-T1/worker.1.md"),
+            "{names:?}"
+        );
+    }
+}
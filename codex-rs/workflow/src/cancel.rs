@@ -0,0 +1,94 @@
+//! Asking a running workflow to stop, for `workflow cancel`.
+
+use crate::layout::WorkflowLayout;
+use crate::lock::LockInfo;
+use crate::lock::read_lock;
+use crate::manifest::WorkflowManifest;
+use anyhow::Context;
+use anyhow::Result;
+use chrono::DateTime;
+use chrono::Utc;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How often a run looks for a cancellation request while sessions run.
+pub(crate) const CANCEL_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Contents of the cancellation marker.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct CancelRequest {
+    /// Kill the running sessions instead of letting their tickets finish.
+    pub hard: bool,
+    pub requested_at: DateTime<Utc>,
+}
+
+impl CancelRequest {
+    /// The pending request in `layout`'s root, if any.
+    pub(crate) fn read(layout: &WorkflowLayout) -> Option<Self> {
+        let contents = fs::read(layout.cancel_file()).ok()?;
+        serde_json::from_slice(&contents).ok()
+    }
+
+    /// Removes a request left behind by an earlier run.
+    pub(crate) fn clear(layout: &WorkflowLayout) -> Result<()> {
+        let path = layout.cancel_file();
+        match fs::remove_file(&path) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err).with_context(|| format!("failed to remove {}", path.display())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CancelOptions {
+    pub manifest_path: PathBuf,
+    pub base_dir: Option<PathBuf>,
+    pub artifacts_dir: Option<PathBuf>,
+    /// Root of the artifacts directories; see [`WorkflowLayout::resolve`].
+    pub artifacts_root: Option<PathBuf>,
+    /// Kill the running sessions rather than letting their tickets finish.
+    pub hard: bool,
+}
+
+#[derive(Debug)]
+pub struct CancelReport {
+    pub root: PathBuf,
+    /// The run asked to stop, from its lock file; `None` when no live run
+    /// holds the lock, in which case nothing was written.
+    pub run: Option<LockInfo>,
+}
+
+/// Asks the run holding the workflow's lock to stop: it launches no further
+/// tickets and, with `hard`, kills its running sessions. The run may be on
+/// another host sharing the artifacts directory; it notices the request
+/// within a few seconds.
+pub fn cancel_workflow(opts: &CancelOptions) -> Result<CancelReport> {
+    let mut manifest = WorkflowManifest::load(&opts.manifest_path)?;
+    manifest.base_dir = opts.base_dir.clone();
+    let layout = WorkflowLayout::resolve(
+        &manifest,
+        opts.artifacts_dir.as_deref(),
+        opts.artifacts_root.as_deref(),
+    );
+    let run = read_lock(&layout.lock_file()).filter(LockInfo::holder_is_alive);
+    if run.is_some() {
+        let request = CancelRequest {
+            hard: opts.hard,
+            requested_at: Utc::now(),
+        };
+        let path = layout.cancel_file();
+        // Write then rename, so the run never reads a partial request.
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(&request)?)
+            .with_context(|| format!("failed to write {}", tmp.display()))?;
+        fs::rename(&tmp, &path).with_context(|| format!("failed to write {}", path.display()))?;
+    }
+    Ok(CancelReport {
+        root: layout.root().to_path_buf(),
+        run,
+    })
+}
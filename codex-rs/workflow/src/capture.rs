@@ -0,0 +1,363 @@
+//! Size-bounded capture of a child's output streams, and the log they are
+//! written to while the session runs.
+
+use crate::log_format::format_elapsed;
+use crate::secrets::redact;
+use anyhow::Context;
+use std::collections::VecDeque;
+use std::fs;
+use std::fs::File;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::time::Duration;
+
+/// Default cap for a whole session log; each output stream gets half.
+pub const DEFAULT_MAX_LOG_BYTES: i64 = 64 * 1024 * 1024;
+
+/// Collects a stream while holding at most `limit` bytes. Once the limit is
+/// reached the middle of the output is dropped: the first quarter is kept as
+/// it arrived and the remainder always holds the most recent output, since
+/// that is where errors usually are. Only the tail is held in memory; the
+/// head is handed back by `push` for the caller to store, e.g. in a
+/// [`LiveLog`], and passed in again to `finish`.
+#[derive(Debug)]
+pub(crate) struct BoundedCapture {
+    head_len: usize,
+    head_times: Vec<Duration>,
+    head_limit: usize,
+    /// Set once output went past the head; later output only reaches the tail.
+    head_done: bool,
+    tail: VecDeque<u8>,
+    /// Arrival time of each newline currently in `tail`.
+    tail_times: VecDeque<Duration>,
+    tail_limit: usize,
+    dropped: u64,
+    truncated_at: Option<Duration>,
+}
+
+/// A finished capture. `line_times[i]` is when line `i` of `bytes` arrived.
+#[derive(Debug)]
+pub(crate) struct CapturedOutput {
+    pub bytes: Vec<u8>,
+    pub line_times: Vec<Duration>,
+    pub truncated_bytes: u64,
+}
+
+impl BoundedCapture {
+    pub(crate) fn new(limit: usize) -> Self {
+        let head_limit = limit / 4;
+        Self {
+            head_len: 0,
+            head_times: Vec::new(),
+            head_limit,
+            head_done: false,
+            tail: VecDeque::new(),
+            tail_times: VecDeque::new(),
+            tail_limit: limit - head_limit,
+            dropped: 0,
+            truncated_at: None,
+        }
+    }
+
+    /// Adds the next chunk of the stream and returns the part of it that
+    /// belongs to the head.
+    pub(crate) fn push<'a>(&mut self, mut chunk: &'a [u8], elapsed: Duration) -> &'a [u8] {
+        let mut head: &[u8] = &[];
+        if !self.head_done {
+            let take = chunk.len().min(self.head_limit - self.head_len);
+            (head, chunk) = chunk.split_at(take);
+            self.head_len += head.len();
+            self.head_times
+                .extend(std::iter::repeat_n(elapsed, count_newlines(head)));
+            self.head_done = !chunk.is_empty();
+        }
+        if chunk.len() > self.tail_limit {
+            let (skipped, rest) = chunk.split_at(chunk.len() - self.tail_limit);
+            self.drop_bytes(skipped.len(), elapsed);
+            chunk = rest;
+        }
+        let overflow = (self.tail.len() + chunk.len()).saturating_sub(self.tail_limit);
+        if overflow > 0 {
+            let evicted_newlines = self.tail.drain(..overflow).filter(|b| *b == b'\n').count();
+            self.tail_times.drain(..evicted_newlines);
+            self.drop_bytes(overflow, elapsed);
+        }
+        self.tail.extend(chunk);
+        self.tail_times
+            .extend(std::iter::repeat_n(elapsed, count_newlines(chunk)));
+        head
+    }
+
+    /// Whether the head is complete, so `push` returns nothing more.
+    pub(crate) fn head_done(&self) -> bool {
+        self.head_done
+    }
+
+    fn drop_bytes(&mut self, len: usize, elapsed: Duration) {
+        self.dropped += len as u64;
+        self.truncated_at.get_or_insert(elapsed);
+    }
+
+    /// Joins `head`, the bytes `push` returned, with the marker for dropped
+    /// output and the tail.
+    pub(crate) fn finish(self, head: Vec<u8>, elapsed: Duration) -> CapturedOutput {
+        let mut bytes = head;
+        let mut line_times = self.head_times;
+        if let Some(truncated_at) = self.truncated_at {
+            let mut marker = format!("[... {} bytes truncated ...]\n", self.dropped);
+            if !bytes.is_empty() && !bytes.ends_with(b"\n") {
+                marker.insert(0, '\n');
+            }
+            line_times.extend(std::iter::repeat_n(
+                truncated_at,
+                count_newlines(marker.as_bytes()),
+            ));
+            bytes.extend_from_slice(marker.as_bytes());
+        }
+        bytes.extend(self.tail);
+        line_times.extend(self.tail_times);
+        if !bytes.is_empty() && !bytes.ends_with(b"\n") {
+            line_times.push(elapsed);
+        }
+        CapturedOutput {
+            bytes,
+            line_times,
+            truncated_bytes: self.dropped,
+        }
+    }
+}
+
+/// One of a session's output streams, as labelled in a [`LiveLog`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+impl LogStream {
+    fn label(self) -> &'static str {
+        match self {
+            LogStream::Stdout => "stdout",
+            LogStream::Stderr => "stderr",
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            LogStream::Stdout => 0,
+            LogStream::Stderr => 1,
+        }
+    }
+}
+
+/// The session log while the session runs: a preamble followed by the head
+/// of both streams, one `[00:00:12] stdout | line` record per line as it
+/// arrives, so the log can be followed and survives a killed orchestrator.
+/// A line cut off by the end of the head or the stream is recorded as
+/// `stdout + line`. The finished log replaces it.
+pub(crate) struct LiveLog {
+    path: PathBuf,
+    /// Where the records start, after the preamble.
+    output_start: u64,
+    secret_values: Vec<String>,
+    writer: Mutex<LiveLogWriter>,
+}
+
+struct LiveLogWriter {
+    file: File,
+    /// The incomplete last line of each stream.
+    pending: [Vec<u8>; 2],
+}
+
+impl LiveLog {
+    pub(crate) fn create(
+        path: &Path,
+        preamble: &str,
+        secret_values: Vec<String>,
+    ) -> anyhow::Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        let write = || -> std::io::Result<(File, u64)> {
+            let mut file = File::create(path)?;
+            file.write_all(preamble.as_bytes())?;
+            let output_start = file.stream_position()?;
+            Ok((file, output_start))
+        };
+        let (file, output_start) =
+            write().with_context(|| format!("failed to write {}", path.display()))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            output_start,
+            secret_values,
+            writer: Mutex::new(LiveLogWriter {
+                file,
+                pending: Default::default(),
+            }),
+        })
+    }
+
+    /// Records the complete lines `head`, a part of `stream`'s head, ends.
+    pub(crate) fn push(
+        &self,
+        stream: LogStream,
+        head: &[u8],
+        elapsed: Duration,
+    ) -> std::io::Result<()> {
+        if head.is_empty() {
+            return Ok(());
+        }
+        let mut writer = self.lock_writer();
+        let pending = &mut writer.pending[stream.index()];
+        pending.extend_from_slice(head);
+        let Some(end) = pending.iter().rposition(|byte| *byte == b'\n') else {
+            return Ok(());
+        };
+        let lines: Vec<u8> = pending.drain(..=end).collect();
+        let records: String = String::from_utf8_lossy(&lines)
+            .split_terminator('\n')
+            .map(|line| self.record(stream, '|', line, elapsed))
+            .collect();
+        writer.file.write_all(records.as_bytes())
+    }
+
+    /// Records what is left of `stream`'s head once it is complete.
+    pub(crate) fn close(&self, stream: LogStream, elapsed: Duration) -> std::io::Result<()> {
+        let mut writer = self.lock_writer();
+        let rest = std::mem::take(&mut writer.pending[stream.index()]);
+        if rest.is_empty() {
+            return Ok(());
+        }
+        let record = self.record(stream, '+', &String::from_utf8_lossy(&rest), elapsed);
+        writer.file.write_all(record.as_bytes())
+    }
+
+    /// The head of `stream` read back from the log, with secrets redacted.
+    pub(crate) fn head(&self, stream: LogStream) -> std::io::Result<Vec<u8>> {
+        let mut file = File::open(&self.path)?;
+        file.seek(SeekFrom::Start(self.output_start))?;
+        let mut records = Vec::new();
+        file.read_to_end(&mut records)?;
+        let mut head = String::new();
+        for record in String::from_utf8_lossy(&records).split_terminator('\n') {
+            let Some(rest) = record
+                .split_once("] ")
+                .and_then(|(_, rest)| rest.strip_prefix(stream.label()))
+            else {
+                continue;
+            };
+            if let Some(line) = rest.strip_prefix(" | ") {
+                head.push_str(line);
+                head.push('\n');
+            } else if let Some(line) = rest.strip_prefix(" + ") {
+                head.push_str(line);
+            }
+        }
+        Ok(head.into_bytes())
+    }
+
+    fn record(&self, stream: LogStream, separator: char, line: &str, elapsed: Duration) -> String {
+        format!(
+            "{} {} {separator} {}\n",
+            format_elapsed(elapsed),
+            stream.label(),
+            redact(line, &self.secret_values)
+        )
+    }
+
+    fn lock_writer(&self) -> MutexGuard<'_, LiveLogWriter> {
+        self.writer
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+fn count_newlines(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|byte| **byte == b'\n').count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_everything_under_the_limit() {
+        let mut capture = BoundedCapture::new(64);
+        let mut head = capture.push(b"one\ntwo\n", Duration::from_secs(1)).to_vec();
+        head.extend_from_slice(capture.push(b"three", Duration::from_secs(2)));
+        let output = capture.finish(head, Duration::from_secs(3));
+        assert_eq!(output.bytes, b"one\ntwo\nthree");
+        assert_eq!(output.truncated_bytes, 0);
+        assert_eq!(
+            output.line_times,
+            vec![
+                Duration::from_secs(1),
+                Duration::from_secs(1),
+                Duration::from_secs(3)
+            ]
+        );
+    }
+
+    #[test]
+    fn drops_the_middle_and_keeps_the_tail() {
+        let mut capture = BoundedCapture::new(16);
+        let mut head = Vec::new();
+        for line in 0..10 {
+            head.extend_from_slice(capture.push(
+                format!("line {line}\n").as_bytes(),
+                Duration::from_secs(line),
+            ));
+        }
+        assert!(capture.head_done());
+        head.extend_from_slice(capture.push(b"error!\n", Duration::from_secs(99)));
+        let output = capture.finish(head, Duration::from_secs(100));
+        let text = String::from_utf8(output.bytes).expect("utf8");
+        assert_eq!(text, "line\n[... 61 bytes truncated ...]\nne 9\nerror!\n");
+        assert_eq!(output.truncated_bytes, 61);
+        assert_eq!(output.line_times.len(), text.lines().count());
+        assert_eq!(output.line_times.last(), Some(&Duration::from_secs(99)));
+    }
+
+    #[test]
+    fn live_log_records_heads_as_lines_arrive() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("ticket-T1/worker.1.md");
+        let log = LiveLog::create(
+            &path,
+            "Codex session (running)\n",
+            vec!["sk-test".to_string()],
+        )
+        .expect("create live log");
+        let second = Duration::from_secs(1);
+        log.push(LogStream::Stdout, b"one\ntw", second)
+            .expect("push");
+        log.push(LogStream::Stderr, b"key sk-test\n", second)
+            .expect("push");
+        log.push(LogStream::Stdout, b"o\nthr", second)
+            .expect("push");
+        log.close(LogStream::Stdout, second).expect("close");
+        log.close(LogStream::Stderr, second).expect("close");
+
+        let text = fs::read_to_string(&path).expect("read live log");
+        assert_eq!(
+            text,
+            "Codex session (running)\n\
+             [00:00:01] stdout | one\n\
+             [00:00:01] stderr | key [REDACTED]\n\
+             [00:00:01] stdout | two\n\
+             [00:00:01] stdout + thr\n"
+        );
+        assert_eq!(log.head(LogStream::Stdout).expect("head"), b"one\ntwo\nthr");
+        assert_eq!(
+            log.head(LogStream::Stderr).expect("head"),
+            b"key [REDACTED]\n"
+        );
+    }
+}
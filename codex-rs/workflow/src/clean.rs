@@ -0,0 +1,270 @@
+//! Removal of old workflow artifacts.
+
+use crate::layout::WorkflowLayout;
+use crate::lock::RunLock;
+use crate::manifest::WorkflowManifest;
+use crate::state::TicketStatus;
+use crate::state::WorkflowState;
+use crate::usage::disk_usage;
+use anyhow::Context;
+use anyhow::Result;
+use anyhow::bail;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// What [`clean_workflow`] removes. The selections can be combined, except
+/// for `all`, which removes everything.
+#[derive(Debug, Clone, Default)]
+pub struct CleanOptions {
+    pub manifest_path: PathBuf,
+    pub base_dir: Option<PathBuf>,
+    pub artifacts_dir: Option<PathBuf>,
+    /// Root of the artifacts directories; see [`WorkflowLayout::resolve`].
+    pub artifacts_root: Option<PathBuf>,
+    /// Remove session logs from every ticket directory, keeping the state.
+    pub logs_only: bool,
+    /// Remove the ticket directories of `Complete` tickets.
+    pub completed: bool,
+    /// Remove the whole artifacts directory, state included.
+    pub all: bool,
+    /// Remove all but the newest this many run directories; at least 1.
+    pub keep_last: Option<i64>,
+}
+
+#[derive(Debug, Default)]
+pub struct CleanReport {
+    pub root: PathBuf,
+    /// Files and directories removed, in removal order.
+    pub removed: Vec<PathBuf>,
+    /// Total size of the removed files.
+    pub removed_bytes: u64,
+}
+
+/// Removes artifacts according to `opts`. Fails without removing anything
+/// while a run holds the workflow's lock.
+pub fn clean_workflow(opts: &CleanOptions) -> Result<CleanReport> {
+    if !(opts.logs_only || opts.completed || opts.all || opts.keep_last.is_some()) {
+        bail!("nothing to clean; pass --logs-only, --completed, --keep-last, or --all");
+    }
+    if opts.all && (opts.logs_only || opts.completed || opts.keep_last.is_some()) {
+        bail!("--all cannot be combined with other clean options");
+    }
+    if opts.keep_last.is_some_and(|keep| keep < 1) {
+        bail!("--keep-last must be at least 1");
+    }
+    let mut manifest = WorkflowManifest::load(&opts.manifest_path)?;
+    manifest.base_dir = opts.base_dir.clone();
+    let layout = WorkflowLayout::resolve(
+        &manifest,
+        opts.artifacts_dir.as_deref(),
+        opts.artifacts_root.as_deref(),
+    );
+    let mut report = CleanReport {
+        root: layout.root().to_path_buf(),
+        ..Default::default()
+    };
+    if !layout.root().exists() {
+        return Ok(report);
+    }
+    let lock = RunLock::acquire(&layout.lock_file())
+        .context("refusing to clean while the workflow is running")?;
+
+    if opts.all {
+        // Empty the root while holding the lock, so a run cannot start on
+        // what is left of it; the lock file then goes with the empty root.
+        let root = layout.root();
+        let lock_file = layout.lock_file();
+        let bytes = disk_usage(root);
+        let entries =
+            fs::read_dir(root).with_context(|| format!("failed to read {}", root.display()))?;
+        for entry in entries {
+            let path = entry
+                .with_context(|| format!("failed to read {}", root.display()))?
+                .path();
+            if path != lock_file {
+                remove_path(&path)?;
+            }
+        }
+        drop(lock);
+        fs::remove_dir(root).with_context(|| format!("failed to remove {}", root.display()))?;
+        report.removed_bytes += bytes;
+        report.removed.push(root.to_path_buf());
+        return Ok(report);
+    }
+    if let Some(keep) = opts.keep_last {
+        let runs = layout.run_dirs();
+        let keep = usize::try_from(keep).unwrap_or(usize::MAX);
+        let excess = runs.len().saturating_sub(keep);
+        for run in &runs[..excess] {
+            report.remove(run)?;
+        }
+    }
+    if opts.completed {
+        let state_path = layout.state_file();
+        if state_path.exists() {
+            let state = WorkflowState::load(&state_path)?;
+            for ticket in state.tickets.values() {
+                if ticket.status == TicketStatus::Complete {
+                    for dir in layout.all_ticket_dirs(&ticket.ticket_id) {
+                        report.remove(&dir)?;
+                    }
+                }
+            }
+        }
+    }
+    if opts.logs_only {
+        // Tickets with a `log_dir` keep their directories outside the root.
+        let mut dirs = layout.existing_ticket_dirs();
+        for ticket in &manifest.tickets {
+            for dir in layout.all_ticket_dirs(&ticket.id) {
+                if !dirs.contains(&dir) {
+                    dirs.push(dir);
+                }
+            }
+        }
+        for dir in dirs {
+            for log in WorkflowLayout::session_log_files(&dir) {
+                report.remove(&log)?;
+            }
+        }
+    }
+    drop(lock);
+    Ok(report)
+}
+
+impl CleanReport {
+    fn remove(&mut self, path: &Path) -> Result<()> {
+        let bytes = disk_usage(path);
+        remove_path(path)?;
+        self.removed_bytes += bytes;
+        self.removed.push(path.to_path_buf());
+        Ok(())
+    }
+}
+
+fn remove_path(path: &Path) -> Result<()> {
+    let metadata = fs::symlink_metadata(path)
+        .with_context(|| format!("failed to inspect {}", path.display()))?;
+    if metadata.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+    .with_context(|| format!("failed to remove {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup(dir: &Path) -> (CleanOptions, WorkflowLayout) {
+        let manifest_path = dir.join("workflow.yaml");
+        fs::write(
+            &manifest_path,
+            "tickets:\n  - id: T1\n    summary: One\n  - id: T2\n    summary: Two\n",
+        )
+        .expect("write manifest");
+        let manifest = WorkflowManifest::load(&manifest_path).expect("load manifest");
+        let layout = WorkflowLayout::new(dir.join("artifacts"));
+        layout.ensure_root(&manifest).expect("ensure root");
+        let mut state = WorkflowState::initialize(&manifest);
+        state
+            .ticket_mut("T1")
+            .expect("ticket T1")
+            .mark_finished(TicketStatus::Complete, None);
+        state.save(&layout.state_file()).expect("save state");
+        for run in ["20260101T000000Z", "20260102T000000Z", "20260103T000000Z"] {
+            let run = layout.clone().start_run(run).expect("start run");
+            for ticket in ["T1", "T2"] {
+                run.ensure_ticket_dir(ticket).expect("ticket dir");
+                fs::write(run.worker_log_path(ticket, 1), "12345").expect("write log");
+                fs::write(run.worker_output_path(ticket), "out").expect("write output");
+            }
+        }
+        let opts = CleanOptions {
+            manifest_path,
+            artifacts_dir: Some(dir.join("artifacts")),
+            ..Default::default()
+        };
+        (opts, layout)
+    }
+
+    #[test]
+    fn removes_old_runs_completed_tickets_and_logs() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let (opts, layout) = setup(dir.path());
+        let report = clean_workflow(&CleanOptions {
+            keep_last: Some(2),
+            ..opts.clone()
+        })
+        .expect("clean");
+        assert_eq!(
+            report.removed,
+            vec![layout.runs_dir().join("20260101T000000Z")]
+        );
+        assert_eq!(report.removed_bytes, 2 * (5 + 3));
+        assert_eq!(layout.run_dirs().len(), 2);
+
+        let report = clean_workflow(&CleanOptions {
+            completed: true,
+            logs_only: true,
+            ..opts
+        })
+        .expect("clean");
+        assert!(layout.state_file().exists());
+        for run in layout.run_dirs() {
+            assert!(!run.join("ticket-T1").exists());
+            assert!(!run.join("ticket-T2/worker.1.md").exists());
+            assert!(run.join("ticket-T2/worker.output.md").exists());
+        }
+        assert!(report.removed_bytes > 0);
+    }
+
+    #[test]
+    fn refuses_while_locked_and_removes_everything_with_all() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let (opts, layout) = setup(dir.path());
+        let lock = RunLock::acquire(&layout.lock_file()).expect("lock");
+        let err = clean_workflow(&CleanOptions {
+            all: true,
+            ..opts.clone()
+        })
+        .expect_err("locked");
+        assert!(format!("{err:#}").contains("while the workflow is running"));
+        drop(lock);
+
+        let report = clean_workflow(&CleanOptions { all: true, ..opts }).expect("clean");
+        assert_eq!(report.removed, vec![layout.root().to_path_buf()]);
+        assert!(!layout.root().exists());
+    }
+
+    #[test]
+    fn removes_logs_from_ticket_log_dirs() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest_path = dir.path().join("workflow.yaml");
+        fs::write(
+            &manifest_path,
+            "tickets:\n  - id: T1\n    summary: One\n    log_dir: logs\n",
+        )
+        .expect("write manifest");
+        let manifest = WorkflowManifest::load(&manifest_path).expect("load manifest");
+        let layout =
+            WorkflowLayout::new(dir.path().join("artifacts")).with_ticket_log_dirs(&manifest);
+        layout.ensure_root(&manifest).expect("ensure root");
+        layout.ensure_ticket_dir("T1").expect("ticket dir");
+        let log = layout.worker_log_path("T1", 1);
+        fs::write(&log, "12345").expect("write log");
+        assert!(log.starts_with(dir.path().join("logs")));
+
+        let report = clean_workflow(&CleanOptions {
+            manifest_path,
+            artifacts_dir: Some(dir.path().join("artifacts")),
+            logs_only: true,
+            ..Default::default()
+        })
+        .expect("clean");
+        assert_eq!(report.removed, vec![log.clone()]);
+        assert!(!log.exists());
+    }
+}
@@ -0,0 +1,121 @@
+//! Marking tickets complete by hand for `workflow complete`.
+
+use crate::layout::WorkflowLayout;
+use crate::lock::RunLock;
+use crate::manifest::WorkflowManifest;
+use crate::state::TicketRunState;
+use crate::state::TicketStatus;
+use crate::state::WorkflowState;
+use anyhow::Context;
+use anyhow::Result;
+use anyhow::bail;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Default)]
+pub struct CompleteOptions {
+    pub manifest_path: PathBuf,
+    pub base_dir: Option<PathBuf>,
+    pub artifacts_dir: Option<PathBuf>,
+    /// Root of the artifacts directories; see [`WorkflowLayout::resolve`].
+    pub artifacts_root: Option<PathBuf>,
+    pub ticket_id: String,
+    /// Recorded as the ticket's note instead of the default one.
+    pub note: Option<String>,
+    /// Complete the ticket even though some of its dependencies are not.
+    pub force: bool,
+}
+
+/// Marks a ticket `Complete` in the saved state, creating the state if the
+/// workflow has not run yet, so its dependents run on the next `--resume`.
+/// Fails while a run holds the workflow's lock, and unless `force` is set,
+/// while any of the ticket's dependencies is neither complete nor skipped.
+pub fn complete_ticket(opts: &CompleteOptions) -> Result<TicketRunState> {
+    let mut manifest = WorkflowManifest::load(&opts.manifest_path)?;
+    manifest.base_dir = opts.base_dir.clone();
+    let ticket = manifest.ticket(&opts.ticket_id)?;
+    let layout = WorkflowLayout::resolve(
+        &manifest,
+        opts.artifacts_dir.as_deref(),
+        opts.artifacts_root.as_deref(),
+    );
+    layout.ensure_root(&manifest)?;
+    let _lock = RunLock::acquire(&layout.lock_file())
+        .context("refusing to change the state while the workflow is running")?;
+    let state_path = layout.state_file();
+    let mut state = if state_path.exists() {
+        WorkflowState::load(&state_path)?
+    } else {
+        WorkflowState::initialize(&manifest)
+    };
+    state.sync_with_manifest(&manifest);
+
+    let unfinished: Vec<String> = ticket
+        .depends_on
+        .iter()
+        .filter_map(|dependency| {
+            let status = state.ticket(dependency)?.status.clone();
+            match status {
+                TicketStatus::Complete | TicketStatus::Skipped => None,
+                status => Some(format!("{dependency} ({})", status.label())),
+            }
+        })
+        .collect();
+    if !unfinished.is_empty() && !opts.force {
+        bail!(
+            "ticket {} depends on tickets that are not complete: {}; pass --force to \
+            complete it anyway",
+            ticket.id,
+            unfinished.join(", ")
+        );
+    }
+    state.complete_manually(&ticket.id, opts.note.clone())?;
+    state.save(&state_path)?;
+    state
+        .ticket(&ticket.id)
+        .cloned()
+        .context("completed ticket is missing from the state")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn completes_only_tickets_whose_dependencies_are_done_unless_forced() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest_path = dir.path().join("workflow.yaml");
+        fs::write(
+            &manifest_path,
+            "tickets:\n  - id: T1\n    summary: One\n  - id: T2\n    summary: Two\n    \
+            depends_on: [T1]\n",
+        )
+        .expect("write manifest");
+        let opts = |ticket_id: &str, force: bool| CompleteOptions {
+            manifest_path: manifest_path.clone(),
+            artifacts_dir: Some(dir.path().join("artifacts")),
+            ticket_id: ticket_id.to_string(),
+            force,
+            ..Default::default()
+        };
+
+        let err = complete_ticket(&opts("T2", false)).expect_err("T1 is pending");
+        assert!(err.to_string().contains("T1 (Pending)"));
+        let forced = complete_ticket(&opts("T2", true)).expect("forced");
+        assert_eq!(forced.status, TicketStatus::Complete);
+        assert!(forced.started_at.is_some() && forced.finished_at.is_some());
+
+        let t1 = complete_ticket(&CompleteOptions {
+            note: Some("Fixed by hand".to_string()),
+            ..opts("T1", false)
+        })
+        .expect("complete T1");
+        assert_eq!(t1.note.as_deref(), Some("Fixed by hand"));
+        let state = WorkflowState::load(&dir.path().join("artifacts/state.json")).expect("state");
+        let statuses: Vec<&TicketStatus> = state.tickets.values().map(|t| &t.status).collect();
+        assert_eq!(
+            statuses,
+            vec![&TicketStatus::Complete, &TicketStatus::Complete]
+        );
+    }
+}
@@ -0,0 +1,57 @@
+//! Step-through confirmation of tickets before they launch.
+
+use crate::manifest::Requirement;
+use crate::manifest::TicketSpec;
+use crate::state::TicketStatus;
+use std::path::PathBuf;
+
+/// What to do with a ticket that is about to launch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TicketDecision {
+    Run,
+    /// Leave the ticket `Pending` for a later run. Tickets depending on it
+    /// are not started in this run either.
+    Defer,
+    /// Mark the ticket `Skipped`; tickets depending on it run as if it had
+    /// completed.
+    Skip,
+    /// Launch nothing more, save the state and end the run.
+    Quit,
+}
+
+/// The ticket shown to a [`TicketConfirmer`].
+#[derive(Debug, Clone)]
+pub struct TicketPlan {
+    pub ticket_id: String,
+    pub summary: String,
+    pub requirements: Vec<String>,
+    pub working_dir: PathBuf,
+    pub depends_on: Vec<String>,
+    /// Status the ticket resumes from; `NeedsReview` means only the review
+    /// will run.
+    pub status: TicketStatus,
+}
+
+impl TicketPlan {
+    pub(crate) fn new(ticket: &TicketSpec, working_dir: PathBuf, status: TicketStatus) -> Self {
+        Self {
+            ticket_id: ticket.id.clone(),
+            summary: ticket.summary.clone(),
+            requirements: ticket
+                .requirements
+                .iter()
+                .map(Requirement::render)
+                .collect(),
+            working_dir,
+            depends_on: ticket.depends_on.clone(),
+            status,
+        }
+    }
+}
+
+/// Asked before each ticket launches. The orchestrator runs tickets one at a
+/// time while a confirmer is set and asks it on a blocking thread, so
+/// implementations may block, e.g. on terminal input.
+pub trait TicketConfirmer: Send + Sync {
+    fn confirm(&self, plan: &TicketPlan) -> anyhow::Result<TicketDecision>;
+}
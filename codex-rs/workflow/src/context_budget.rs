@@ -0,0 +1,26 @@
+//! Rough prompt sizes for `prompt_token_budget`, so a prompt that will
+//! likely overflow the model's context is caught before a session is spent
+//! on it.
+
+/// Characters per token assumed by [`estimate_tokens`]; near enough for
+/// English prose and code with the usual tokenizers.
+const CHARS_PER_TOKEN: i64 = 4;
+
+/// Estimated tokens in `text`, rounded up.
+pub(crate) fn estimate_tokens(text: &str) -> i64 {
+    let chars = i64::try_from(text.chars().count()).unwrap_or(i64::MAX);
+    chars / CHARS_PER_TOKEN + i64::from(chars % CHARS_PER_TOKEN != 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_characters_not_bytes() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        assert_eq!(estimate_tokens("héllo wörld"), 3);
+    }
+}
@@ -0,0 +1,100 @@
+//! Checks free disk space under the artifacts directory, so a full disk is
+//! reported before a run instead of as a failed write halfway through it.
+
+use anyhow::Context;
+use anyhow::Result;
+use anyhow::bail;
+use std::path::Path;
+use std::path::PathBuf;
+use sysinfo::Disk;
+use sysinfo::DiskRefreshKind;
+use sysinfo::Disks;
+
+pub(crate) struct DiskGuard {
+    root: PathBuf,
+    min_free: Option<i64>,
+}
+
+impl DiskGuard {
+    pub(crate) fn new(root: &Path, min_free: Option<i64>) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            min_free,
+        }
+    }
+
+    /// Fails if the volume holding the artifacts directory has less than the
+    /// required space available.
+    pub(crate) fn check(&self) -> Result<()> {
+        let Some(min_free) = self.min_free else {
+            return Ok(());
+        };
+        let available = available_space(&self.root).with_context(|| {
+            format!("failed to read free disk space for {}", self.root.display())
+        })?;
+        if available < min_free {
+            bail!(
+                "only {available} bytes are free for {}, below --min-free-disk {min_free}; \
+                free up space or pass --artifacts-dir to use another volume",
+                self.root.display()
+            );
+        }
+        Ok(())
+    }
+
+    /// Whether another session may start now. Like the memory check, a
+    /// session always starts when nothing else is running, so a run that
+    /// passed [`DiskGuard::check`] keeps making progress.
+    pub(crate) fn allows_launch(&self, in_flight: usize) -> bool {
+        let Some(min_free) = self.min_free else {
+            return true;
+        };
+        if in_flight == 0 {
+            return true;
+        }
+        match available_space(&self.root) {
+            Ok(available) => available >= min_free,
+            // An unreadable volume was already reported by `check`; do not stall.
+            Err(_) => true,
+        }
+    }
+}
+
+/// Space available on the volume holding `path`: that of the disk mounted
+/// deepest among its ancestors.
+fn available_space(path: &Path) -> Result<i64> {
+    let path = std::fs::canonicalize(path)?;
+    let disks = Disks::new_with_refreshed_list_specifics(DiskRefreshKind::nothing().with_storage());
+    disks
+        .list()
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().components().count())
+        .map(Disk::available_space)
+        .map(|available| i64::try_from(available).unwrap_or(i64::MAX))
+        .with_context(|| format!("no mounted volume holds {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fails_early_below_threshold() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        DiskGuard::new(dir.path(), None)
+            .check()
+            .expect("no threshold");
+        DiskGuard::new(dir.path(), Some(1))
+            .check()
+            .expect("one byte free");
+        let err = DiskGuard::new(dir.path(), Some(i64::MAX))
+            .check()
+            .expect_err("not that much space");
+        assert!(err.to_string().contains("--min-free-disk"));
+
+        let guard = DiskGuard::new(dir.path(), Some(i64::MAX));
+        assert!(guard.allows_launch(0));
+        assert!(!guard.allows_launch(1));
+    }
+}
@@ -0,0 +1,155 @@
+//! The error type of the crate's public entry points.
+
+use crate::orchestrator::WorkflowStatusReport;
+use crate::validate::ManifestIssue;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Why loading a manifest, reading a workflow's state, or running a workflow
+/// failed. Failures without a variant of their own are kept as `Other`.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum WorkflowError {
+    /// The manifest file could not be read.
+    #[error("failed to read workflow manifest {}", path.display())]
+    ManifestRead {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    /// The manifest is neither valid YAML nor valid TOML, or does not have
+    /// the manifest's shape.
+    #[error("failed to parse workflow manifest {}", path.display())]
+    ManifestParse {
+        path: PathBuf,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// The manifest parsed but has errors; only error-severity issues are
+    /// listed.
+    #[error("invalid workflow manifest:\n{}", render_issues(issues))]
+    Validation { issues: Vec<ManifestIssue> },
+    /// A ticket's working directory is missing when its session is due.
+    #[error(
+        "working directory {} does not exist for ticket {ticket_id}",
+        path.display()
+    )]
+    WorkingDirMissing { ticket_id: String, path: PathBuf },
+    /// The directory a ticket's review is to run in is missing when the
+    /// review is due.
+    #[error(
+        "review working directory {} does not exist for ticket {ticket_id}",
+        path.display()
+    )]
+    ReviewWorkingDirMissing { ticket_id: String, path: PathBuf },
+    /// The codex binary could not be started, so no session ran. Also
+    /// raised before a run when a binary it needs cannot be found.
+    #[error("failed to start codex binary {} ({})", program.display(), source.kind())]
+    LauncherSpawn {
+        program: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    /// The workflow state file could not be read or parsed.
+    #[error("failed to read workflow state {}", path.display())]
+    StateLoad {
+        path: PathBuf,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    /// The workflow state file could not be written.
+    #[error("failed to persist workflow state {}", path.display())]
+    StatePersist {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    /// The run stopped on an error after it had started on its tickets;
+    /// reads as that error.
+    #[error(transparent)]
+    RunStopped(Box<StoppedRun>),
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl WorkflowError {
+    /// What the run had done before it stopped, for errors that carry it.
+    pub fn partial_report(&self) -> Option<&WorkflowStatusReport> {
+        match self {
+            WorkflowError::RunStopped(run) => Some(&run.report),
+            _ => None,
+        }
+    }
+
+    /// The error that stopped the run for [`WorkflowError::RunStopped`],
+    /// otherwise `self`, so callers can match on what went wrong.
+    pub fn stopped_by(&self) -> &WorkflowError {
+        match self {
+            WorkflowError::RunStopped(run) => &run.error,
+            err => err,
+        }
+    }
+}
+
+/// Internal code works in `anyhow`; a typed error raised inside it comes back
+/// out as itself rather than as `Other`.
+impl From<anyhow::Error> for WorkflowError {
+    fn from(err: anyhow::Error) -> Self {
+        match err.downcast::<WorkflowError>() {
+            Ok(err) => err,
+            Err(err) => WorkflowError::Other(err),
+        }
+    }
+}
+
+/// The error that stopped a run, and the state the run left behind, which is
+/// also written to `result.json`.
+#[derive(Debug)]
+pub struct StoppedRun {
+    pub error: WorkflowError,
+    pub report: WorkflowStatusReport,
+}
+
+impl fmt::Display for StoppedRun {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl std::error::Error for StoppedRun {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+fn render_issues(issues: &[ManifestIssue]) -> String {
+    issues
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typed_errors_survive_a_trip_through_anyhow() {
+        let err = anyhow::Error::from(WorkflowError::WorkingDirMissing {
+            ticket_id: "T1".to_string(),
+            path: PathBuf::from("/missing"),
+        });
+        let err = WorkflowError::from(err.context("while running T1"));
+        assert!(matches!(
+            &err,
+            WorkflowError::WorkingDirMissing { ticket_id, .. } if ticket_id == "T1"
+        ));
+
+        let err = WorkflowError::from(anyhow::anyhow!("something else"));
+        assert!(matches!(err.stopped_by(), WorkflowError::Other(_)));
+        assert!(matches!(err, WorkflowError::Other(_)));
+        assert_eq!(err.to_string(), "something else");
+    }
+}
@@ -0,0 +1,450 @@
+//! Run events for embedders, `--events-socket` consumers, and the
+//! `events.jsonl` log in the artifacts directory.
+//!
+//! On a socket and in the log, each event is one JSON object per line with
+//! its fields, a `type` naming the variant in snake case (`run_started`,
+//! `ticket_status_changed`, ...), and an `event_version`. The version is
+//! bumped whenever a field is removed or changes meaning; new fields and
+//! event types may appear without a bump.
+
+use crate::notify_command::NotifyCommand;
+use crate::state::SessionPhase;
+use crate::state::TicketStatus;
+use crate::webhook::Webhook;
+use anyhow::Context;
+use anyhow::Result;
+use chrono::DateTime;
+use chrono::Utc;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fs;
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Events queued for the socket before new ones are dropped, so a consumer
+/// that stops reading cannot hold up the run.
+const SOCKET_BUFFER: usize = 1024;
+
+/// How long the end of a run waits for queued events to reach the socket.
+const SOCKET_FLUSH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Schema version written as `event_version` with every serialized event.
+pub const EVENT_VERSION: u32 = 1;
+
+/// A change during a run.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WorkflowEvent {
+    /// The run's state is set up and no session has started yet.
+    RunStarted {
+        workflow_name: String,
+        run_dir: Option<PathBuf>,
+        settings: RunSettings,
+        at: DateTime<Utc>,
+    },
+    /// A ticket moved to a new status; written after `state.json` is saved.
+    TicketStatusChanged {
+        workflow_name: String,
+        ticket_id: String,
+        from: TicketStatus,
+        to: TicketStatus,
+        note: Option<String>,
+        worker_log: Option<PathBuf>,
+        review_log: Option<PathBuf>,
+        /// Tickets in a final status after this change.
+        done: usize,
+        /// Tickets in the run.
+        total: usize,
+        at: DateTime<Utc>,
+    },
+    /// A worker or review session was launched. `attempt` numbers the
+    /// ticket's sessions of that phase from 1.
+    SessionStarted {
+        ticket_id: String,
+        phase: SessionPhase,
+        attempt: i32,
+        log_path: PathBuf,
+        at: DateTime<Utc>,
+    },
+    /// A session exited, or was terminated after its timeout.
+    SessionFinished {
+        ticket_id: String,
+        phase: SessionPhase,
+        attempt: i32,
+        success: bool,
+        timed_out: bool,
+        exit_code: Option<i32>,
+        at: DateTime<Utc>,
+    },
+    /// A failed worker session is about to be rerun; `retry` counts from 1
+    /// up to `max_retries`.
+    SessionRetrying {
+        ticket_id: String,
+        phase: SessionPhase,
+        retry: i32,
+        max_retries: i32,
+        at: DateTime<Utc>,
+    },
+    /// A `--preflight-url` or `--preflight-command` probe ran, before the
+    /// run's first ticket (`ticket_id` is `None`) or before one of a
+    /// ticket's sessions. A failed probe is repeated after `retry_in_secs`.
+    PreflightChecked {
+        ticket_id: Option<String>,
+        passed: bool,
+        error: Option<String>,
+        retry_in_secs: Option<u64>,
+        at: DateTime<Utc>,
+    },
+    /// A session's prompt is estimated at more tokens than the
+    /// `prompt_token_budget`. The session launches anyway unless `strict`
+    /// (`--strict-context`) is set, in which case the ticket fails instead.
+    PromptOverBudget {
+        ticket_id: String,
+        phase: SessionPhase,
+        estimated_tokens: i64,
+        budget: i64,
+        strict: bool,
+        at: DateTime<Utc>,
+    },
+    /// The run stopped launching tickets before it ran out of work, by
+    /// `workflow cancel` or on request during an interactive run.
+    RunInterrupted { reason: String, at: DateTime<Utc> },
+    /// The run ended, with `summary.md` written unless it stopped on an
+    /// error.
+    RunFinished {
+        workflow_name: String,
+        /// Tickets that failed or are blocked, allowed failures excluded.
+        failed: Vec<String>,
+        /// The error that stopped the run, including `--strict-deps` aborts.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+        at: DateTime<Utc>,
+    },
+    /// The `notifications.notify_command` failed for an event. `event_type`
+    /// is the `type` of that event.
+    NotifyCommandFailed {
+        event_type: String,
+        ticket_id: Option<String>,
+        error: String,
+        at: DateTime<Utc>,
+    },
+    /// A notification could not be delivered to the webhook. `event_type`
+    /// is the `type` of the event it carried.
+    WebhookFailed {
+        event_type: String,
+        ticket_id: Option<String>,
+        error: String,
+        at: DateTime<Utc>,
+    },
+}
+
+/// The options a run started with that shape how it goes, as recorded by
+/// [`WorkflowEvent::RunStarted`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RunSettings {
+    pub resume: bool,
+    /// `--resume-from` ticket; the tickets ordered before it were marked
+    /// complete.
+    pub resume_from: Option<String>,
+    /// `--ticket` patterns; empty when every ticket was selected.
+    pub tickets: Vec<String>,
+    pub max_parallel: usize,
+    pub max_retries: i32,
+    pub max_reworks: i32,
+    pub session_timeout_secs: Option<u64>,
+    /// The time limits worker and review sessions got, from whichever
+    /// option or manifest key set them.
+    pub worker_timeout_secs: Option<u64>,
+    pub review_timeout_secs: Option<u64>,
+    pub worker_model: Option<String>,
+    pub reviewer_model: Option<String>,
+    pub strict_deps: bool,
+}
+
+/// The serialized form of an event.
+#[derive(Serialize, Deserialize)]
+struct VersionedEvent<T> {
+    event_version: u32,
+    #[serde(flatten)]
+    event: T,
+}
+
+fn json_line(event: &WorkflowEvent) -> Option<Vec<u8>> {
+    let mut line = serde_json::to_vec(&VersionedEvent {
+        event_version: EVENT_VERSION,
+        event,
+    })
+    .ok()?;
+    line.push(b'\n');
+    Some(line)
+}
+
+/// The last `limit` events of an `events.jsonl` log, oldest first. Lines
+/// that do not parse, such as events from a newer version, are skipped.
+pub fn read_events(path: &Path, limit: usize) -> Result<Vec<WorkflowEvent>> {
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let mut events = VecDeque::with_capacity(limit.min(1024));
+    for line in BufReader::new(file).lines() {
+        let line = line.with_context(|| format!("failed to read {}", path.display()))?;
+        let Ok(versioned) = serde_json::from_str::<VersionedEvent<WorkflowEvent>>(&line) else {
+            continue;
+        };
+        if events.len() == limit {
+            events.pop_front();
+        }
+        if limit > 0 {
+            events.push_back(versioned.event);
+        }
+    }
+    Ok(events.into())
+}
+
+/// Fans events out to the embedder's channel, the events socket, the event
+/// log, the webhook, and the notify command.
+#[derive(Default)]
+pub(crate) struct EventSink {
+    outputs: Outputs,
+    writer: Option<JoinHandle<()>>,
+    webhook: Option<Webhook>,
+    notify_command: Option<NotifyCommand>,
+}
+
+/// The destinations every event is written to. Cloned into webhook
+/// deliveries and notify commands so they can report their failures.
+#[derive(Clone, Default)]
+struct Outputs {
+    channel: Option<mpsc::UnboundedSender<WorkflowEvent>>,
+    socket: Option<mpsc::Sender<WorkflowEvent>>,
+    log: Option<Arc<File>>,
+}
+
+impl EventSink {
+    /// Connects to `socket`: a `HOST:PORT` TCP address, otherwise the path
+    /// of a Unix domain socket. The consumer must already be listening.
+    /// Events are appended to the `log` file, which is created if needed.
+    /// An empty `notify_command` runs nothing.
+    pub(crate) async fn connect(
+        channel: Option<mpsc::UnboundedSender<WorkflowEvent>>,
+        socket: Option<&str>,
+        log: Option<&Path>,
+        webhook_url: Option<&str>,
+        notify_command: &[String],
+    ) -> Result<Self> {
+        let mut sink = EventSink {
+            outputs: Outputs {
+                channel,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        if let Some(url) = webhook_url {
+            sink.webhook = Some(Webhook::new(url)?);
+        }
+        if !notify_command.is_empty() {
+            sink.notify_command = Some(NotifyCommand::new(notify_command)?);
+        }
+        if let Some(path) = log {
+            let file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("failed to open event log {}", path.display()))?;
+            sink.outputs.log = Some(Arc::new(file));
+        }
+        if let Some(target) = socket {
+            let stream = connect_socket(target)
+                .await
+                .with_context(|| format!("failed to connect to events socket {target}"))?;
+            let (sender, receiver) = mpsc::channel(SOCKET_BUFFER);
+            sink.outputs.socket = Some(sender);
+            sink.writer = Some(tokio::spawn(write_events(stream, receiver)));
+        }
+        Ok(sink)
+    }
+
+    /// Never waits: a closed channel or a full socket queue drops the event,
+    /// and webhook deliveries and notify commands happen in the background.
+    pub(crate) fn emit(&self, event: WorkflowEvent) {
+        if is_notification(&event)
+            && let Some(body) = json_line(&event)
+        {
+            let event_type = event_type(&event);
+            let ticket_id = match &event {
+                WorkflowEvent::TicketStatusChanged { ticket_id, .. } => Some(ticket_id.clone()),
+                _ => None,
+            };
+            if let Some(command) = &self.notify_command {
+                let outputs = self.outputs.clone();
+                let event_type = event_type.clone();
+                let ticket_id = ticket_id.clone();
+                command.send(&event, body.clone(), move |error| {
+                    outputs.send(WorkflowEvent::NotifyCommandFailed {
+                        event_type,
+                        ticket_id,
+                        error,
+                        at: Utc::now(),
+                    });
+                });
+            }
+            if let Some(webhook) = &self.webhook {
+                let outputs = self.outputs.clone();
+                webhook.send(body, move |error| {
+                    outputs.send(WorkflowEvent::WebhookFailed {
+                        event_type,
+                        ticket_id,
+                        error,
+                        at: Utc::now(),
+                    });
+                });
+            }
+        }
+        self.outputs.send(event);
+    }
+
+    /// Gives queued events a bounded time to reach the socket, the webhook
+    /// and the notify command.
+    pub(crate) async fn close(self) {
+        let EventSink {
+            outputs,
+            writer,
+            webhook,
+            notify_command,
+        } = self;
+        // Failed deliveries still write to the outputs, so the socket is
+        // only closed after the webhook and the notify command are done.
+        if let Some(webhook) = webhook {
+            webhook.close().await;
+        }
+        if let Some(command) = notify_command {
+            command.close().await;
+        }
+        drop(outputs);
+        if let Some(writer) = writer {
+            let _ = tokio::time::timeout(SOCKET_FLUSH_TIMEOUT, writer).await;
+        }
+    }
+}
+
+impl Outputs {
+    /// Each event reaches the log with its own write, so the log of a run
+    /// that crashes ends at its last event.
+    fn send(&self, event: WorkflowEvent) {
+        if let Some(mut log) = self.log.as_deref()
+            && let Some(line) = json_line(&event)
+        {
+            // A full disk should not stop the run; the state file is what
+            // resuming relies on.
+            let _ = log.write_all(&line);
+        }
+        if let Some(channel) = &self.channel {
+            let _ = channel.send(event.clone());
+        }
+        if let Some(socket) = &self.socket {
+            let _ = socket.try_send(event);
+        }
+    }
+}
+
+/// Whether the webhook and the notify command hear about `event`: tickets
+/// reaching a final status and the end of the run.
+fn is_notification(event: &WorkflowEvent) -> bool {
+    match event {
+        WorkflowEvent::TicketStatusChanged { to, .. } => to.is_final(),
+        WorkflowEvent::RunFinished { .. } => true,
+        _ => false,
+    }
+}
+
+/// The `type` an event is serialized with.
+fn event_type(event: &WorkflowEvent) -> String {
+    serde_json::to_value(event)
+        .ok()
+        .and_then(|value| value["type"].as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+type EventStream = Box<dyn AsyncWrite + Send + Unpin>;
+
+async fn connect_socket(target: &str) -> std::io::Result<EventStream> {
+    let is_tcp = !target.contains('/')
+        && target
+            .rsplit_once(':')
+            .is_some_and(|(_, port)| port.parse::<u16>().is_ok());
+    if is_tcp {
+        return Ok(Box::new(tokio::net::TcpStream::connect(target).await?));
+    }
+    #[cfg(unix)]
+    {
+        Ok(Box::new(tokio::net::UnixStream::connect(target).await?))
+    }
+    #[cfg(not(unix))]
+    {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Unix domain sockets are not supported here; use HOST:PORT",
+        ))
+    }
+}
+
+async fn write_events(mut stream: EventStream, mut events: mpsc::Receiver<WorkflowEvent>) {
+    while let Some(event) = events.recv().await {
+        let Some(line) = json_line(&event) else {
+            continue;
+        };
+        // A consumer that went away ends the stream, not the run.
+        if stream.write_all(&line).await.is_err() {
+            return;
+        }
+    }
+    let _ = stream.flush().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn appends_versioned_events_to_the_log() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("events.jsonl");
+        let event = |reason: &str| WorkflowEvent::RunInterrupted {
+            reason: reason.to_string(),
+            at: Utc::now(),
+        };
+        for reason in ["first", "second"] {
+            let sink = EventSink::connect(None, None, Some(&path), None, &[])
+                .await
+                .expect("open log");
+            sink.emit(event(reason));
+            sink.close().await;
+        }
+        let mut contents = fs::read_to_string(&path).expect("read log");
+        let first: serde_json::Value =
+            serde_json::from_str(contents.lines().next().expect("line")).expect("json");
+        assert_eq!(first["event_version"], EVENT_VERSION);
+        assert_eq!(first["type"], "run_interrupted");
+
+        contents.push_str("{\"event_version\":99,\"type\":\"from_the_future\"}\n");
+        fs::write(&path, contents).expect("write log");
+        let reasons: Vec<String> = read_events(&path, 1)
+            .expect("read events")
+            .into_iter()
+            .map(|event| match event {
+                WorkflowEvent::RunInterrupted { reason, .. } => reason,
+                other => panic!("unexpected event {other:?}"),
+            })
+            .collect();
+        assert_eq!(reasons, vec!["second".to_string()]);
+    }
+}
@@ -0,0 +1,131 @@
+//! Content hashes of tickets for `--skip-unchanged`.
+
+use crate::manifest::Requirement;
+use crate::manifest::ReviewFailurePolicy;
+use crate::manifest::TicketSpec;
+use anyhow::Context;
+use anyhow::Result;
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// The parts of a ticket that decide what its sessions do. Fields that only
+/// affect where artifacts go or how the run treats the outcome, such as
+/// `log_dir` or `allow_failure`, are left out.
+#[derive(Serialize)]
+struct FingerprintSpec<'a> {
+    id: &'a str,
+    summary: &'a str,
+    description: Option<&'a str>,
+    requirements: &'a [Requirement],
+    working_dir: &'a Path,
+    review_working_dir: Option<&'a Path>,
+    profile: Option<&'a str>,
+    reviewer_bin: Option<&'a Path>,
+    prompt: Option<&'a str>,
+    review_prompt: Option<&'a str>,
+    env: &'a BTreeMap<String, String>,
+    extra_args: &'a [String],
+    depends_on: &'a [String],
+    expected_artifacts: &'a [PathBuf],
+    inputs: &'a [PathBuf],
+    on_review_failure: ReviewFailurePolicy,
+}
+
+/// SHA-256 over the ticket's spec and the files its `inputs` globs match
+/// under `working_dir`, as hex. `working_dir` is the ticket's resolved
+/// working directory, not a worktree's, so a ticket run in a worktree keeps
+/// its fingerprint. Directories matched by a glob are not descended into;
+/// match their files with `dir/**/*` instead.
+pub(crate) fn ticket_fingerprint(ticket: &TicketSpec, working_dir: &Path) -> Result<String> {
+    let spec = FingerprintSpec {
+        id: &ticket.id,
+        summary: &ticket.summary,
+        description: ticket.description.as_deref(),
+        requirements: &ticket.requirements,
+        working_dir,
+        review_working_dir: ticket.review_working_dir.as_deref(),
+        profile: ticket.profile.as_deref(),
+        reviewer_bin: ticket.reviewer_bin.as_deref(),
+        prompt: ticket.prompt.as_deref(),
+        review_prompt: ticket.review_prompt.as_deref(),
+        env: &ticket.env,
+        extra_args: &ticket.extra_args,
+        depends_on: &ticket.depends_on,
+        expected_artifacts: &ticket.expected_artifacts,
+        inputs: &ticket.inputs,
+        on_review_failure: ticket.on_review_failure,
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(serde_json::to_vec(&spec)?);
+    let root = glob::Pattern::escape(&working_dir.to_string_lossy());
+    let mut files = Vec::new();
+    for input in &ticket.inputs {
+        let pattern = format!("{root}/{}", input.to_string_lossy());
+        let matches =
+            glob::glob(&pattern).with_context(|| format!("invalid input glob {pattern}"))?;
+        files.extend(
+            matches
+                .filter_map(|path| path.ok())
+                .filter(|path| path.is_file()),
+        );
+    }
+    files.sort();
+    files.dedup();
+    for path in files {
+        let contents =
+            fs::read(&path).with_context(|| format!("failed to read {}", path.display()))?;
+        let relative = path.strip_prefix(working_dir).unwrap_or(&path);
+        // Length-prefix every part so no two file sets hash the same.
+        let name = relative.to_string_lossy();
+        hasher.update((name.len() as u64).to_le_bytes());
+        hasher.update(name.as_bytes());
+        hasher.update((contents.len() as u64).to_le_bytes());
+        hasher.update(&contents);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn changes_with_the_spec_and_matched_files_only() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        fs::create_dir_all(dir.path().join("src")).expect("mkdir");
+        fs::write(dir.path().join("src/lib.rs"), "fn a() {}").expect("write");
+        fs::write(dir.path().join("notes.txt"), "draft").expect("write");
+        let ticket = TicketSpec {
+            id: "T1".to_string(),
+            summary: "One".to_string(),
+            inputs: vec![PathBuf::from("src/**/*.rs")],
+            ..Default::default()
+        };
+        let fingerprint =
+            |ticket: &TicketSpec| ticket_fingerprint(ticket, dir.path()).expect("fingerprint");
+        let original = fingerprint(&ticket);
+
+        fs::write(dir.path().join("notes.txt"), "final").expect("write");
+        assert_eq!(fingerprint(&ticket), original);
+        fs::write(dir.path().join("src/lib.rs"), "fn b() {}").expect("write");
+        let edited = fingerprint(&ticket);
+        assert_ne!(edited, original);
+        let renamed = TicketSpec {
+            summary: "Renamed".to_string(),
+            ..ticket.clone()
+        };
+        assert_ne!(fingerprint(&renamed), edited);
+        let relocated_logs = TicketSpec {
+            log_dir: Some(PathBuf::from("/shared/logs")),
+            allow_failure: true,
+            ..ticket.clone()
+        };
+        assert_eq!(fingerprint(&relocated_logs), edited);
+    }
+}
@@ -0,0 +1,455 @@
+//! The branches, commits, worktrees and pull requests a run makes for the
+//! manifest's `git` settings.
+
+use crate::git_diff::git;
+use crate::git_diff::git_text;
+use crate::git_diff::head_commit;
+use crate::manifest::GitSettings;
+use crate::manifest::TicketSpec;
+use crate::state::TicketRunState;
+use crate::template;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Branch name used when a manifest sets no `git.branch_template`.
+const DEFAULT_BRANCH_TEMPLATE: &str = "workflow/{{ workflow.name }}/{{ ticket.id }}";
+
+/// Commit message used when a manifest sets no `git.commit_message_template`.
+const DEFAULT_COMMIT_MESSAGE_TEMPLATE: &str =
+    "{{ ticket.id }}: {{ ticket.summary }}\n\nGenerated by codex workflow {{ workflow.name }}";
+
+/// Pull request title used when a manifest sets no `git.pr_title_template`.
+const DEFAULT_PR_TITLE_TEMPLATE: &str = "{{ ticket.id }}: {{ ticket.summary }}";
+
+/// Remote ticket branches are pushed to when a manifest sets no
+/// `git.remote`.
+pub(crate) const DEFAULT_REMOTE: &str = "origin";
+
+/// The ticket's branch name from the manifest's template, made valid for git.
+pub(crate) fn ticket_branch_name(
+    settings: &GitSettings,
+    workflow_name: &str,
+    ticket_id: &str,
+) -> String {
+    let vars = BTreeMap::from([
+        ("workflow.name".to_string(), workflow_name.to_string()),
+        ("ticket.id".to_string(), ticket_id.to_string()),
+    ]);
+    let template = settings
+        .branch_template
+        .as_deref()
+        .unwrap_or(DEFAULT_BRANCH_TEMPLATE);
+    sanitize_branch_name(&template::render(template, &vars))
+}
+
+/// The message `git.autocommit` commits a completed ticket's changes with.
+pub(crate) fn ticket_commit_message(
+    settings: &GitSettings,
+    workflow_name: &str,
+    ticket: &TicketSpec,
+) -> String {
+    let vars = BTreeMap::from([
+        ("workflow.name".to_string(), workflow_name.to_string()),
+        ("ticket.id".to_string(), ticket.id.clone()),
+        ("ticket.summary".to_string(), ticket.summary.clone()),
+    ]);
+    let template = settings
+        .commit_message_template
+        .as_deref()
+        .unwrap_or(DEFAULT_COMMIT_MESSAGE_TEMPLATE);
+    template::render(template, &vars)
+}
+
+/// The title and body of the pull request `git.open_pr` opens for a
+/// completed ticket.
+pub(crate) fn ticket_pull_request(
+    settings: &GitSettings,
+    workflow_name: &str,
+    ticket: &TicketSpec,
+    state: &TicketRunState,
+) -> (String, String) {
+    let requirements: Vec<String> = ticket
+        .requirements
+        .iter()
+        .map(|requirement| match requirement.id() {
+            Some(id) => format!("- {id}: {}", requirement.text()),
+            None => format!("- {}", requirement.text()),
+        })
+        .collect();
+    let artifacts: Vec<String> = [
+        ("Worker log", &state.worker_log),
+        ("Worker output", &state.worker_output),
+        ("Review log", &state.review_log),
+        ("Review output", &state.review_output),
+        ("Diff", &state.worker_diff),
+    ]
+    .into_iter()
+    .filter_map(|(label, path)| Some(format!("- {label}: `{}`", path.as_ref()?.display())))
+    .collect();
+    let review_summary = state.review_summary.clone().unwrap_or_default();
+    let vars = BTreeMap::from([
+        ("workflow.name".to_string(), workflow_name.to_string()),
+        ("ticket.id".to_string(), ticket.id.clone()),
+        ("ticket.summary".to_string(), ticket.summary.clone()),
+        ("ticket.requirements".to_string(), requirements.join("\n")),
+        ("review.summary".to_string(), review_summary.clone()),
+        ("workflow.artifacts".to_string(), artifacts.join("\n")),
+    ]);
+    let title = template::render(
+        settings
+            .pr_title_template
+            .as_deref()
+            .unwrap_or(DEFAULT_PR_TITLE_TEMPLATE),
+        &vars,
+    );
+    let body = match &settings.pr_body_template {
+        Some(body) => template::render(body, &vars),
+        None => {
+            let mut sections = vec![ticket.summary.trim().to_string()];
+            if let Some(description) = &ticket.description {
+                sections.push(description.trim().to_string());
+            }
+            if !requirements.is_empty() {
+                sections.push(format!("## Requirements\n\n{}", requirements.join("\n")));
+            }
+            if !review_summary.trim().is_empty() {
+                sections.push(format!("## Review\n\n{}", review_summary.trim()));
+            }
+            if !artifacts.is_empty() {
+                sections.push(format!("## Workflow artifacts\n\n{}", artifacts.join("\n")));
+            }
+            sections.push(format!("Generated by codex workflow {workflow_name}"));
+            sections.join("\n\n")
+        }
+    };
+    (title, body)
+}
+
+/// Replaces what `git check-ref-format --branch` rejects: spaces, control
+/// characters, `~^:?*[\`, `..` and `@{` become `-`, and empty path
+/// components, leading dots and trailing `.` or `.lock` are dropped.
+pub(crate) fn sanitize_branch_name(name: &str) -> String {
+    let replaced: String = name
+        .chars()
+        .map(|c| {
+            if c.is_whitespace() || c.is_control() || "~^:?*[\\".contains(c) {
+                '-'
+            } else {
+                c
+            }
+        })
+        .collect();
+    let components: Vec<String> = replaced
+        .replace("..", "-")
+        .replace("@{", "-")
+        .split('/')
+        .filter_map(|component| {
+            let mut component = component.trim_start_matches('.');
+            loop {
+                let trimmed = component.trim_end_matches('.');
+                let trimmed = trimmed.strip_suffix(".lock").unwrap_or(trimmed);
+                if trimmed.len() == component.len() {
+                    break;
+                }
+                component = trimmed;
+            }
+            (!component.is_empty()).then(|| component.to_string())
+        })
+        .collect();
+    let name = components.join("/");
+    let name = name.trim_start_matches('-');
+    if name.is_empty() || name == "@" {
+        "workflow-ticket".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// Checks out `branch` in `working_dir`, creating it from `base` when it
+/// does not exist yet. Uncommitted changes are carried over, as with
+/// `git checkout`. Errors are git's stderr.
+pub(crate) async fn checkout_ticket_branch(
+    working_dir: &Path,
+    branch: &str,
+    base: &str,
+) -> Result<(), String> {
+    if branch_exists(working_dir, branch).await {
+        git_text(working_dir, &["checkout", "--quiet", branch]).await?;
+    } else {
+        git_text(working_dir, &["checkout", "--quiet", "-b", branch, base]).await?;
+    }
+    Ok(())
+}
+
+/// Whether the local branch `branch` exists.
+async fn branch_exists(working_dir: &Path, branch: &str) -> bool {
+    git_text(
+        working_dir,
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("refs/heads/{branch}"),
+        ],
+    )
+    .await
+    .is_ok()
+}
+
+/// Checks out `base` again, e.g. once the ticket completed.
+pub(crate) async fn checkout_base(working_dir: &Path, base: &str) -> Result<(), String> {
+    git_text(working_dir, &["checkout", "--quiet", base])
+        .await
+        .map(drop)
+}
+
+/// Paths, relative to the repository root, with staged or unstaged changes
+/// in the repository `working_dir` is in, untracked files included. A rename
+/// lists both of its paths.
+pub(crate) async fn changed_paths(working_dir: &Path) -> Result<Vec<String>, String> {
+    let status = git(
+        working_dir,
+        &["status", "--porcelain", "-z", "--untracked-files=all"],
+    )
+    .await?;
+    let status = String::from_utf8_lossy(&status);
+    let mut paths = Vec::new();
+    let mut entries = status.split('\0').filter(|entry| !entry.is_empty());
+    while let Some(entry) = entries.next() {
+        let (code, path) = entry.split_at(entry.len().min(3));
+        paths.push(path.to_string());
+        // Renames and copies are followed by their source path.
+        if code.contains('R') || code.contains('C') {
+            paths.extend(entries.next().map(str::to_string));
+        }
+    }
+    Ok(paths)
+}
+
+/// Stages and commits the changes in the repository `working_dir` is in,
+/// untracked files included, except those to `excluded` paths (see
+/// [`changed_paths`]), which other staged changes do not pull in either.
+/// Returns the new commit, or `None` when there was nothing to commit, and
+/// the excluded paths that have changes.
+pub(crate) async fn commit_changes(
+    working_dir: &Path,
+    message: &str,
+    excluded: &[String],
+) -> Result<(Option<String>, Vec<String>), String> {
+    let (left_out, paths): (Vec<String>, Vec<String>) = changed_paths(working_dir)
+        .await?
+        .into_iter()
+        .partition(|path| excluded.contains(path));
+    if paths.is_empty() {
+        return Ok((None, left_out));
+    }
+    let pathspecs: Vec<String> = paths
+        .iter()
+        .map(|path| format!(":(top,literal){path}"))
+        .collect();
+    let pathspecs = pathspecs.iter().map(String::as_str);
+    let add: Vec<&str> = ["add", "--all", "--"]
+        .into_iter()
+        .chain(pathspecs.clone())
+        .collect();
+    git_text(working_dir, &add).await?;
+    let commit: Vec<&str> = ["commit", "--quiet", "--only", "-m", message, "--"]
+        .into_iter()
+        .chain(pathspecs)
+        .collect();
+    git_text(working_dir, &commit).await?;
+    let commit = git_text(working_dir, &["rev-parse", "HEAD"]).await?;
+    Ok((Some(commit), left_out))
+}
+
+/// Pushes `branch` to `remote`, setting it as the branch's upstream.
+pub(crate) async fn push_branch(
+    working_dir: &Path,
+    remote: &str,
+    branch: &str,
+) -> Result<(), String> {
+    git_text(
+        working_dir,
+        &["push", "--quiet", "--set-upstream", remote, branch],
+    )
+    .await
+    .map(drop)
+}
+
+/// The URL of `remote`.
+pub(crate) async fn remote_url(working_dir: &Path, remote: &str) -> Result<String, String> {
+    git_text(working_dir, &["remote", "get-url", remote]).await
+}
+
+/// Adds a worktree at `path` of the repository `working_dir` is in, with a
+/// detached `HEAD` at `base` (default: the current `HEAD`), or with `branch`
+/// checked out, creating it at `base` on first use. Returns the directory in
+/// the worktree that corresponds to `working_dir`.
+pub(crate) async fn add_worktree(
+    working_dir: &Path,
+    path: &Path,
+    base: Option<&str>,
+    branch: Option<&str>,
+) -> Result<PathBuf, String> {
+    let toplevel = git_text(working_dir, &["rev-parse", "--show-toplevel"]).await?;
+    // Forget worktrees whose directories were deleted, e.g. by `workflow
+    // clean`, so their paths can be used again.
+    git_text(working_dir, &["worktree", "prune"]).await?;
+    let path_arg = path.to_string_lossy();
+    let base = base.unwrap_or("HEAD");
+    let mut args = vec!["worktree", "add", "--quiet"];
+    match branch {
+        Some(branch) if branch_exists(working_dir, branch).await => {
+            args.extend([path_arg.as_ref(), branch]);
+        }
+        Some(branch) => args.extend(["-b", branch, path_arg.as_ref(), base]),
+        None => args.extend(["--detach", path_arg.as_ref(), base]),
+    }
+    git_text(working_dir, &args).await?;
+    let relative = std::fs::canonicalize(working_dir)
+        .ok()
+        .and_then(|dir| dir.strip_prefix(&toplevel).ok().map(Path::to_path_buf))
+        .filter(|relative| !relative.as_os_str().is_empty());
+    Ok(match relative {
+        Some(relative) => path.join(relative),
+        None => path.to_path_buf(),
+    })
+}
+
+/// Removes the worktree at `path`. Git refuses while it has uncommitted
+/// changes, which then stay where they are.
+pub(crate) async fn remove_worktree(working_dir: &Path, path: &Path) -> Result<(), String> {
+    let path_arg = path.to_string_lossy();
+    git_text(working_dir, &["worktree", "remove", &path_arg])
+        .await
+        .map(drop)
+}
+
+/// The branch checked out in `working_dir`, or the commit when `HEAD` is
+/// detached.
+pub(crate) async fn current_head(working_dir: &Path) -> Result<String, String> {
+    match git_text(working_dir, &["symbolic-ref", "--quiet", "--short", "HEAD"]).await {
+        Ok(branch) => Ok(branch),
+        Err(_) => head_commit(working_dir)
+            .await
+            .ok_or_else(|| format!("no commit is checked out in {}", working_dir.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitizes_branch_names() {
+        assert_eq!(
+            sanitize_branch_name("workflow/Nightly build/T1: fix [db]"),
+            "workflow/Nightly-build/T1--fix--db]"
+        );
+        assert_eq!(
+            sanitize_branch_name("a..b//.hidden/c.lock."),
+            "a-b/hidden/c"
+        );
+        assert_eq!(sanitize_branch_name("--x@{1}~"), "x-1}-");
+        assert_eq!(sanitize_branch_name("/./"), "workflow-ticket");
+    }
+
+    #[test]
+    fn renders_the_branch_template() {
+        let mut settings = GitSettings::default();
+        assert_eq!(
+            ticket_branch_name(&settings, "demo", "T1"),
+            "workflow/demo/T1"
+        );
+        settings.branch_template = Some("agents/{{ticket.id}}".to_string());
+        assert_eq!(ticket_branch_name(&settings, "demo", "T 2"), "agents/T-2");
+    }
+
+    #[tokio::test]
+    async fn creates_branches_and_commits() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let repo = dir.path();
+        if git_text(repo, &["init", "--quiet", "-b", "main"])
+            .await
+            .is_err()
+        {
+            // git is not installed.
+            return;
+        }
+        for args in [
+            ["config", "user.name", "test"].as_slice(),
+            &["config", "user.email", "test@example.com"],
+            &["commit", "--quiet", "--allow-empty", "-m", "init"],
+        ] {
+            git_text(repo, args).await.expect("set up repo");
+        }
+
+        assert_eq!(current_head(repo).await.as_deref(), Ok("main"));
+        checkout_ticket_branch(repo, "workflow/demo/T1", "main")
+            .await
+            .expect("create branch");
+        assert_eq!(current_head(repo).await.as_deref(), Ok("workflow/demo/T1"));
+        checkout_base(repo, "main").await.expect("return to base");
+        checkout_ticket_branch(repo, "workflow/demo/T1", "main")
+            .await
+            .expect("reuse branch");
+        assert_eq!(current_head(repo).await.as_deref(), Ok("workflow/demo/T1"));
+
+        let err = checkout_ticket_branch(repo, "workflow/demo/T2", "no-such-base")
+            .await
+            .expect_err("unknown base");
+        assert!(err.contains("no-such-base"), "{err}");
+
+        let worktree = dir.path().join("worktree");
+        std::fs::create_dir(repo.join("sub")).expect("sub dir");
+        std::fs::write(repo.join("sub/file.txt"), "tracked\n").expect("write file");
+        commit_changes(repo, "add sub", &[])
+            .await
+            .expect("commit sub");
+        let working_dir = add_worktree(&repo.join("sub"), &worktree, None, None)
+            .await
+            .expect("add worktree");
+        assert_eq!(working_dir, worktree.join("sub"));
+        assert!(working_dir.join("file.txt").exists());
+        remove_worktree(repo, &worktree)
+            .await
+            .expect("remove worktree");
+        assert!(!worktree.exists());
+        for _ in 0..2 {
+            add_worktree(repo, &worktree, Some("main"), Some("workflow/demo/T3"))
+                .await
+                .expect("add worktree on branch");
+            assert_eq!(
+                current_head(&worktree).await.as_deref(),
+                Ok("workflow/demo/T3")
+            );
+            remove_worktree(repo, &worktree)
+                .await
+                .expect("remove worktree");
+        }
+
+        std::fs::write(repo.join("sub/file.txt"), "changed before\n").expect("write file");
+        std::fs::write(repo.join("staged.txt"), "staged before\n").expect("write file");
+        git_text(repo, &["add", "staged.txt"])
+            .await
+            .expect("git add");
+        let excluded = changed_paths(repo).await.expect("changed paths");
+        assert_eq!(excluded, ["staged.txt", "sub/file.txt"]);
+        std::fs::write(repo.join("change.txt"), "work\n").expect("write file");
+        std::fs::write(repo.join("sub/new file.txt"), "work\n").expect("write file");
+        let (commit, left_out) = commit_changes(&repo.join("sub"), "T1: work", &excluded)
+            .await
+            .expect("commit");
+        assert_eq!(commit, git_text(repo, &["rev-parse", "HEAD"]).await.ok());
+        assert_eq!(left_out, excluded);
+        let committed = git_text(repo, &["show", "--name-only", "--format=", "HEAD"])
+            .await
+            .expect("git show");
+        assert_eq!(committed, "change.txt\nsub/new file.txt");
+        assert_eq!(
+            commit_changes(repo, "T1: again", &excluded).await,
+            Ok((None, excluded.clone()))
+        );
+    }
+}
@@ -0,0 +1,212 @@
+//! Records what a worker changed in a git working tree, so there is a patch
+//! even when the agent did not save one itself.
+
+use anyhow::Context;
+use anyhow::Result;
+use std::path::Path;
+use std::path::PathBuf;
+use tokio::process::Command;
+
+/// Cap on `worker.diff`; the rest of a larger diff is replaced by a marker.
+pub(crate) const MAX_DIFF_BYTES: usize = 8 * 1024 * 1024;
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct CapturedDiff {
+    pub diff_path: PathBuf,
+    /// Entries of `git status --porcelain`, untracked files included.
+    pub changed_files: usize,
+}
+
+/// Writes the `git diff` of `working_dir` to `patch_dir/worker.diff` and
+/// the changed files to `patch_dir/files.txt`. Without a `base` commit these
+/// are the staged and unstaged changes and `git status --porcelain`; with
+/// one, everything since `base`, committed or not, with untracked files
+/// listed as `??`. Returns `None` when `working_dir` is not in a git work
+/// tree or git cannot be run.
+pub(crate) async fn capture_git_diff(
+    working_dir: &Path,
+    patch_dir: &Path,
+    base: Option<&str>,
+) -> Result<Option<CapturedDiff>> {
+    if git(working_dir, &["rev-parse", "--is-inside-work-tree"])
+        .await
+        .is_err()
+    {
+        return Ok(None);
+    }
+    let captured = match base {
+        Some(base) => diff_since(working_dir, base).await,
+        None => uncommitted_diff(working_dir).await,
+    };
+    let Some((mut diff, status)) = captured else {
+        return Ok(None);
+    };
+    if diff.len() > MAX_DIFF_BYTES {
+        let omitted = diff.len() - MAX_DIFF_BYTES;
+        diff.truncate(MAX_DIFF_BYTES);
+        diff.extend_from_slice(format!("\n[diff truncated: {omitted} bytes omitted]\n").as_bytes());
+    }
+    let diff_path = patch_dir.join("worker.diff");
+    std::fs::write(&diff_path, &diff)
+        .with_context(|| format!("failed to write {}", diff_path.display()))?;
+    let files_path = patch_dir.join("files.txt");
+    std::fs::write(&files_path, &status)
+        .with_context(|| format!("failed to write {}", files_path.display()))?;
+    let changed_files = String::from_utf8_lossy(&status)
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .count();
+    Ok(Some(CapturedDiff {
+        diff_path,
+        changed_files,
+    }))
+}
+
+/// Staged and unstaged changes, and `git status --porcelain`.
+async fn uncommitted_diff(working_dir: &Path) -> Option<(Vec<u8>, Vec<u8>)> {
+    let mut diff = git(working_dir, &["diff", "--staged"]).await.ok()?;
+    diff.extend_from_slice(&git(working_dir, &["diff"]).await.ok()?);
+    let status = git(working_dir, &["status", "--porcelain"]).await.ok()?;
+    Some((diff, status))
+}
+
+/// Changes to tracked files since `base`, and their names with untracked
+/// files added as `?? <path>`.
+async fn diff_since(working_dir: &Path, base: &str) -> Option<(Vec<u8>, Vec<u8>)> {
+    let diff = git(working_dir, &["diff", base]).await.ok()?;
+    let mut status = git(working_dir, &["diff", "--name-status", base])
+        .await
+        .ok()?;
+    let untracked = git(working_dir, &["ls-files", "--others", "--exclude-standard"])
+        .await
+        .ok()?;
+    for path in String::from_utf8_lossy(&untracked).lines() {
+        status.extend_from_slice(format!("?? {path}\n").as_bytes());
+    }
+    Some((diff, status))
+}
+
+/// The commit checked out in `working_dir`, if it is a git work tree with
+/// at least one commit.
+pub(crate) async fn head_commit(working_dir: &Path) -> Option<String> {
+    git_text(working_dir, &["rev-parse", "HEAD"]).await.ok()
+}
+
+/// Stdout of a successful git command, otherwise its stderr.
+pub(crate) async fn git(working_dir: &Path, args: &[&str]) -> Result<Vec<u8>, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(working_dir)
+        .output()
+        .await
+        .map_err(|err| format!("failed to run git: {err}"))?;
+    if output.status.success() {
+        Ok(output.stdout)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Err(if stderr.is_empty() {
+            format!("git {} exited with {}", args.join(" "), output.status)
+        } else {
+            stderr
+        })
+    }
+}
+
+/// [`git`] with its stdout as trimmed text.
+pub(crate) async fn git_text(working_dir: &Path, args: &[&str]) -> Result<String, String> {
+    git(working_dir, args)
+        .await
+        .map(|stdout| String::from_utf8_lossy(&stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn captures_staged_and_untracked_changes() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let repo = dir.path().join("repo");
+        let patches = dir.path().join("patches");
+        std::fs::create_dir_all(&repo).expect("repo dir");
+        std::fs::create_dir_all(&patches).expect("patch dir");
+        if git(&repo, &["init", "--quiet"]).await.is_err() {
+            // git is not installed.
+            return;
+        }
+        std::fs::write(repo.join("staged.txt"), "hello\n").expect("write file");
+        std::fs::write(repo.join("untracked.txt"), "new\n").expect("write file");
+        git(&repo, &["add", "staged.txt"]).await.expect("git add");
+
+        let captured = capture_git_diff(&repo, &patches, None)
+            .await
+            .expect("capture")
+            .expect("git repo");
+        assert_eq!(captured.changed_files, 2);
+        let diff = std::fs::read_to_string(&captured.diff_path).expect("read diff");
+        assert!(diff.contains("+hello"));
+        let files = std::fs::read_to_string(patches.join("files.txt")).expect("read files");
+        assert!(files.contains("untracked.txt"));
+
+        let plain = dir.path().join("plain");
+        std::fs::create_dir_all(&plain).expect("plain dir");
+        assert_eq!(
+            capture_git_diff(&plain, &patches, None)
+                .await
+                .expect("capture"),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn captures_commits_since_the_base() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let repo = dir.path().join("repo");
+        let patches = dir.path().join("patches");
+        std::fs::create_dir_all(&repo).expect("repo dir");
+        std::fs::create_dir_all(&patches).expect("patch dir");
+        if git(&repo, &["init", "--quiet"]).await.is_err() {
+            // git is not installed.
+            return;
+        }
+        let commit = |message: &'static str| {
+            let repo = repo.clone();
+            async move {
+                git(
+                    &repo,
+                    &[
+                        "-c",
+                        "user.name=test",
+                        "-c",
+                        "user.email=test@example.com",
+                        "commit",
+                        "--quiet",
+                        "--allow-empty",
+                        "-m",
+                        message,
+                    ],
+                )
+                .await
+                .expect("git commit");
+            }
+        };
+        commit("base").await;
+        let base = head_commit(&repo).await.expect("base commit");
+        std::fs::write(repo.join("committed.txt"), "from the worker\n").expect("write file");
+        git(&repo, &["add", "committed.txt"])
+            .await
+            .expect("git add");
+        commit("worker").await;
+        std::fs::write(repo.join("untracked.txt"), "new\n").expect("write file");
+
+        let captured = capture_git_diff(&repo, &patches, Some(&base))
+            .await
+            .expect("capture")
+            .expect("git repo");
+        assert_eq!(captured.changed_files, 2);
+        let diff = std::fs::read_to_string(&captured.diff_path).expect("read diff");
+        assert!(diff.contains("+from the worker"));
+        let files = std::fs::read_to_string(patches.join("files.txt")).expect("read files");
+        assert_eq!(files, "A\tcommitted.txt\n?? untracked.txt\n");
+    }
+}
@@ -0,0 +1,635 @@
+//! Manifests from GitHub issues for `workflow import-github`.
+//!
+//! Each open issue with the label becomes a ticket: `issue-<number>` as its
+//! id, the title as its summary, task-list items (`- [ ] ...`) of the body
+//! as its requirements and the rest of the body as its description.
+
+use crate::init::ManifestFormat;
+use crate::manifest::WorkflowManifest;
+use anyhow::Context;
+use anyhow::Result;
+use anyhow::bail;
+use chrono::DateTime;
+use chrono::Utc;
+use reqwest::StatusCode;
+use reqwest::Url;
+use reqwest::header::ACCEPT;
+use reqwest::header::AUTHORIZATION;
+use reqwest::header::HeaderMap;
+use reqwest::header::RETRY_AFTER;
+use reqwest::header::USER_AGENT;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::warn;
+
+/// Environment variables the token is read from, in order.
+pub const GITHUB_TOKEN_ENV_VARS: [&str; 2] = ["GITHUB_TOKEN", "GH_TOKEN"];
+
+pub(crate) const GITHUB_API_URL: &str = "https://api.github.com";
+
+/// The most GitHub returns per page.
+const ISSUES_PER_PAGE: usize = 100;
+
+/// Limit on each request, connecting included.
+pub(crate) const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Attempts per page while GitHub reports its rate limit exceeded.
+const RATE_LIMIT_ATTEMPTS: u32 = 5;
+
+/// Longest wait for the rate limit to reset before giving up instead.
+const RATE_LIMIT_MAX_WAIT: Duration = Duration::from_secs(15 * 60);
+
+/// Wait when GitHub names no reset time, as its docs advise for secondary
+/// rate limits.
+const RATE_LIMIT_DEFAULT_WAIT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Default)]
+pub struct GithubImportOptions {
+    /// Repository as `owner/name`.
+    pub repo: String,
+    /// Only open issues with this label are imported.
+    pub label: String,
+    /// Manifest to write; its extension picks YAML or TOML.
+    pub output: PathBuf,
+    /// Add tickets for issues that have none yet to the existing `output`,
+    /// leaving its other contents as they are.
+    pub update: bool,
+    /// Sent as a bearer token; see [`GITHUB_TOKEN_ENV_VARS`].
+    pub token: Option<String>,
+    /// Defaults to `https://api.github.com`; GitHub Enterprise serves the API
+    /// under `https://<host>/api/v3`.
+    pub api_url: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct GithubImportReport {
+    pub path: PathBuf,
+    /// Ids of the tickets written, in issue number order.
+    pub added: Vec<String>,
+    /// Matching issues skipped by `update` because their ticket exists.
+    pub existing: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct Issue {
+    number: u64,
+    title: String,
+    #[serde(default)]
+    body: Option<String>,
+    /// Set for pull requests, which the issues API lists as well.
+    #[serde(default)]
+    pull_request: Option<serde_json::Value>,
+}
+
+/// A ticket imported from an issue.
+#[derive(Debug, PartialEq, Eq)]
+struct ImportedTicket {
+    id: String,
+    summary: String,
+    description: Option<String>,
+    requirements: Vec<String>,
+}
+
+/// Fetches the repository's open issues with the label and writes them to
+/// `output` as tickets, or with `update`, adds the new ones to it.
+pub async fn import_github_issues(opts: &GithubImportOptions) -> Result<GithubImportReport> {
+    let (owner, name) = match opts.repo.split_once('/') {
+        Some((owner, name)) if !owner.is_empty() && !name.is_empty() && !name.contains('/') => {
+            (owner, name)
+        }
+        _ => bail!("--repo must be owner/name, not {:?}", opts.repo),
+    };
+    let path = &opts.output;
+    let existing = match (path.exists(), opts.update) {
+        (true, false) => bail!(
+            "{} already exists; pass --update to add new issues to it",
+            path.display()
+        ),
+        (true, true) => Some(WorkflowManifest::parse(path)?),
+        (false, _) => None,
+    };
+
+    let issues = fetch_issues(opts, owner, name).await?;
+    let known: HashSet<&str> = existing
+        .iter()
+        .flat_map(|manifest| &manifest.tickets)
+        .map(|ticket| ticket.id.as_str())
+        .collect();
+    let tickets: Vec<ImportedTicket> = issues.iter().map(ticket_from_issue).collect();
+    let (present, tickets): (Vec<ImportedTicket>, Vec<ImportedTicket>) = tickets
+        .into_iter()
+        .partition(|ticket| known.contains(ticket.id.as_str()));
+    let report = GithubImportReport {
+        path: path.clone(),
+        added: tickets.iter().map(|ticket| ticket.id.clone()).collect(),
+        existing: present.len(),
+    };
+    let format = ManifestFormat::from_path(path).unwrap_or_default();
+
+    let Some(manifest) = existing else {
+        // A manifest needs at least one ticket to load.
+        if tickets.is_empty() {
+            bail!(
+                "no open issues labeled {} in {}; nothing to import",
+                opts.label,
+                opts.repo
+            );
+        }
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        let contents = render_manifest(format, &opts.repo, &opts.label, name, &tickets);
+        fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))?;
+        return Ok(report);
+    };
+    if tickets.is_empty() {
+        return Ok(report);
+    }
+    let original =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let mut contents = original.clone();
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    match format {
+        ManifestFormat::Yaml => {
+            let indent = yaml_ticket_indent(&original).unwrap_or("  ");
+            for ticket in &tickets {
+                contents.push_str(&render_yaml_ticket(ticket, indent));
+            }
+        }
+        ManifestFormat::Toml => {
+            for ticket in &tickets {
+                contents.push('\n');
+                contents.push_str(&render_toml_ticket(ticket));
+            }
+        }
+    }
+    fs::write(path, &contents).with_context(|| format!("failed to write {}", path.display()))?;
+    // Appending only works while `tickets` is the manifest's last key.
+    let appended = WorkflowManifest::parse(path)
+        .is_ok_and(|updated| updated.tickets.len() == manifest.tickets.len() + tickets.len());
+    if !appended {
+        fs::write(path, original)
+            .with_context(|| format!("failed to restore {}", path.display()))?;
+        bail!(
+            "could not append tickets to {}; make `tickets` the last entry of the manifest \
+            and try again",
+            path.display()
+        );
+    }
+    Ok(report)
+}
+
+async fn fetch_issues(opts: &GithubImportOptions, owner: &str, name: &str) -> Result<Vec<Issue>> {
+    let api_url = opts.api_url.as_deref().unwrap_or(GITHUB_API_URL);
+    let url = format!(
+        "{}/repos/{owner}/{name}/issues",
+        api_url.trim_end_matches('/')
+    );
+    let url = Url::parse(&url).with_context(|| format!("invalid GitHub API URL {api_url}"))?;
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .context("failed to set up the GitHub client")?;
+    let mut issues = Vec::new();
+    for page in 1.. {
+        let mut page_url = url.clone();
+        page_url
+            .query_pairs_mut()
+            .append_pair("state", "open")
+            .append_pair("labels", &opts.label)
+            .append_pair("per_page", &ISSUES_PER_PAGE.to_string())
+            .append_pair("page", &page.to_string());
+        let body = fetch_page(&client, page_url, opts, api_url).await?;
+        let page_issues: Vec<Issue> =
+            serde_json::from_slice(&body).context("unexpected response from GitHub")?;
+        let last = page_issues.len() < ISSUES_PER_PAGE;
+        issues.extend(
+            page_issues
+                .into_iter()
+                .filter(|issue| issue.pull_request.is_none()),
+        );
+        if last {
+            break;
+        }
+    }
+    issues.sort_by_key(|issue| issue.number);
+    Ok(issues)
+}
+
+/// One page of issues, waiting out the rate limit when GitHub reports it.
+async fn fetch_page(
+    client: &reqwest::Client,
+    url: Url,
+    opts: &GithubImportOptions,
+    api_url: &str,
+) -> Result<Vec<u8>> {
+    let mut attempt = 1;
+    loop {
+        let mut request = client
+            .get(url.clone())
+            .header(ACCEPT, "application/vnd.github+json")
+            .header(USER_AGENT, "codex-workflow")
+            .header("X-GitHub-Api-Version", "2022-11-28");
+        if let Some(token) = &opts.token {
+            request = request.header(AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let response = request.send().await.with_context(|| {
+            format!("failed to reach GitHub at {api_url}; check the network connection")
+        })?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response
+            .bytes()
+            .await
+            .context("failed to read GitHub's response")?;
+        if status.is_success() {
+            return Ok(body.to_vec());
+        }
+        let Some(wait) = rate_limit_wait(status, &headers) else {
+            return Err(status_error(status, &body, opts));
+        };
+        if attempt == RATE_LIMIT_ATTEMPTS || wait > RATE_LIMIT_MAX_WAIT {
+            let reset = (Utc::now() + wait).format("%H:%M UTC");
+            let hint = if opts.token.is_none() {
+                "; set GITHUB_TOKEN for a higher limit"
+            } else {
+                ""
+            };
+            bail!("GitHub's rate limit is exceeded until about {reset}{hint}");
+        }
+        warn!(
+            wait_secs = wait.as_secs(),
+            attempt, "GitHub rate limit exceeded; retrying"
+        );
+        tokio::time::sleep(wait).await;
+        attempt += 1;
+    }
+}
+
+/// How long to wait when the response reports the rate limit exceeded:
+/// `Retry-After`, else until `X-RateLimit-Reset`.
+fn rate_limit_wait(status: StatusCode, headers: &HeaderMap) -> Option<Duration> {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    let retry_after = header(RETRY_AFTER.as_str()).and_then(|value| value.parse().ok());
+    let exhausted = header("x-ratelimit-remaining") == Some("0");
+    let limited = status == StatusCode::TOO_MANY_REQUESTS
+        || (status == StatusCode::FORBIDDEN && (exhausted || retry_after.is_some()));
+    if !limited {
+        return None;
+    }
+    if let Some(seconds) = retry_after {
+        return Some(Duration::from_secs(seconds));
+    }
+    let reset = header("x-ratelimit-reset")
+        .and_then(|value| value.parse().ok())
+        .and_then(|seconds| DateTime::<Utc>::from_timestamp(seconds, 0));
+    Some(match reset {
+        Some(reset) => (reset - Utc::now()).to_std().unwrap_or_default(),
+        None => RATE_LIMIT_DEFAULT_WAIT,
+    })
+}
+
+fn status_error(status: StatusCode, body: &[u8], opts: &GithubImportOptions) -> anyhow::Error {
+    let message = serde_json::from_slice::<serde_json::Value>(body)
+        .ok()
+        .and_then(|body| body["message"].as_str().map(str::to_string))
+        .unwrap_or_default();
+    let repo = &opts.repo;
+    match status {
+        StatusCode::UNAUTHORIZED => anyhow::anyhow!(
+            "GitHub rejected the token ({message}); check that GITHUB_TOKEN holds a valid, \
+            unexpired token"
+        ),
+        StatusCode::NOT_FOUND if opts.token.is_none() => anyhow::anyhow!(
+            "GitHub repository {repo} was not found; check --repo, or if it is private, set \
+            GITHUB_TOKEN to a token that can read its issues"
+        ),
+        StatusCode::NOT_FOUND => anyhow::anyhow!(
+            "GitHub repository {repo} was not found or the token cannot read it; check --repo \
+            and the token's repository access"
+        ),
+        StatusCode::FORBIDDEN => anyhow::anyhow!(
+            "GitHub denied access to the issues of {repo} ({message}); check the token's \
+            permissions"
+        ),
+        StatusCode::GONE => anyhow::anyhow!("{repo} has issues disabled"),
+        _ => anyhow::anyhow!("GitHub responded with {status}: {message}"),
+    }
+}
+
+fn ticket_from_issue(issue: &Issue) -> ImportedTicket {
+    let mut requirements = Vec::new();
+    let mut description = Vec::new();
+    let mut in_code_block = false;
+    for line in issue.body.as_deref().unwrap_or_default().lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code_block = !in_code_block;
+        }
+        match task_item(trimmed).filter(|_| !in_code_block) {
+            Some(task) => requirements.push(task.to_string()),
+            None => description.push(line.trim_end()),
+        }
+    }
+    let description = description.join("\n").trim().to_string();
+    ImportedTicket {
+        id: format!("issue-{}", issue.number),
+        summary: issue.title.trim().to_string(),
+        description: (!description.is_empty()).then_some(description),
+        requirements,
+    }
+}
+
+/// The text of a Markdown task-list item such as `- [ ] Add tests`, checked
+/// or not.
+fn task_item(line: &str) -> Option<&str> {
+    let rest = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))
+        .or_else(|| line.strip_prefix("+ "))?;
+    let text = ["[ ]", "[x]", "[X]"]
+        .iter()
+        .find_map(|checkbox| rest.strip_prefix(checkbox))?;
+    let text = text.trim();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Indentation of the items of a YAML manifest's `tickets` list.
+fn yaml_ticket_indent(contents: &str) -> Option<&str> {
+    contents
+        .lines()
+        .skip_while(|line| !line.starts_with("tickets:"))
+        .skip(1)
+        .find_map(|line| {
+            let trimmed = line.trim_start();
+            trimmed
+                .starts_with("- ")
+                .then(|| &line[..line.len() - trimmed.len()])
+        })
+}
+
+// JSON string literals are valid YAML and TOML strings.
+fn quote(text: &str) -> String {
+    serde_json::to_string(text).unwrap_or_default()
+}
+
+fn render_manifest(
+    format: ManifestFormat,
+    repo: &str,
+    label: &str,
+    name: &str,
+    tickets: &[ImportedTicket],
+) -> String {
+    let mut out = format!(
+        "# Codex workflow manifest imported from the open issues of {repo}\n\
+        # labeled {label}. Add issues labeled since with\n\
+        # `codex workflow import-github --repo {repo} --label {label} -o <this file> --update`.\n\n"
+    );
+    match format {
+        ManifestFormat::Yaml => {
+            out.push_str(&format!("name: {}\ntickets:\n", quote(name)));
+            for ticket in tickets {
+                out.push_str(&render_yaml_ticket(ticket, "  "));
+            }
+        }
+        ManifestFormat::Toml => {
+            out.push_str(&format!("name = {}\n", quote(name)));
+            for ticket in tickets {
+                out.push('\n');
+                out.push_str(&render_toml_ticket(ticket));
+            }
+        }
+    }
+    out
+}
+
+fn render_yaml_ticket(ticket: &ImportedTicket, indent: &str) -> String {
+    let mut out = format!(
+        "{indent}- id: {}\n{indent}  summary: {}\n",
+        quote(&ticket.id),
+        quote(&ticket.summary)
+    );
+    if let Some(description) = &ticket.description {
+        out.push_str(&format!("{indent}  description: {}\n", quote(description)));
+    }
+    if ticket.requirements.is_empty() {
+        out.push_str(&format!("{indent}  requirements: []\n"));
+    } else {
+        out.push_str(&format!("{indent}  requirements:\n"));
+        for requirement in &ticket.requirements {
+            out.push_str(&format!("{indent}    - {}\n", quote(requirement)));
+        }
+    }
+    out
+}
+
+fn render_toml_ticket(ticket: &ImportedTicket) -> String {
+    let mut out = format!(
+        "[[tickets]]\nid = {}\nsummary = {}\n",
+        quote(&ticket.id),
+        quote(&ticket.summary)
+    );
+    if let Some(description) = &ticket.description {
+        out.push_str(&format!("description = {}\n", quote(description)));
+    }
+    let requirements: Vec<String> = ticket
+        .requirements
+        .iter()
+        .map(|requirement| quote(requirement))
+        .collect();
+    out.push_str(&format!("requirements = [{}]\n", requirements.join(", ")));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers::header;
+    use wiremock::matchers::method;
+    use wiremock::matchers::path;
+    use wiremock::matchers::query_param;
+
+    fn options(server: &MockServer, output: PathBuf) -> GithubImportOptions {
+        GithubImportOptions {
+            repo: "acme/app".to_string(),
+            label: "codex-workflow".to_string(),
+            output,
+            token: Some("secret".to_string()),
+            api_url: Some(server.uri()),
+            ..Default::default()
+        }
+    }
+
+    async fn mount_issues(server: &MockServer, issues: serde_json::Value) {
+        Mock::given(method("GET"))
+            .and(path("/repos/acme/app/issues"))
+            .and(query_param("labels", "codex-workflow"))
+            .and(query_param("state", "open"))
+            .and(header("authorization", "Bearer secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(issues))
+            .mount(server)
+            .await;
+    }
+
+    #[test]
+    fn maps_task_lists_to_requirements() {
+        let issue = Issue {
+            number: 7,
+            title: " Add caching ".to_string(),
+            body: Some(
+                "Cache lookups.\r\n\r\n- [ ] Add a cache\n* [x] Measure\n```\n- [ ] not a task\n```\n"
+                    .to_string(),
+            ),
+            pull_request: None,
+        };
+        assert_eq!(
+            ticket_from_issue(&issue),
+            ImportedTicket {
+                id: "issue-7".to_string(),
+                summary: "Add caching".to_string(),
+                description: Some("Cache lookups.\n\n```\n- [ ] not a task\n```".to_string()),
+                requirements: vec!["Add a cache".to_string(), "Measure".to_string()],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn writes_a_valid_manifest_and_adds_new_issues_on_update() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        for extension in ["yaml", "toml"] {
+            let output = dir.path().join(format!("plan.{extension}"));
+            let server = MockServer::start().await;
+            mount_issues(
+                &server,
+                json!([
+                    {"number": 2, "title": "Second", "body": "- [ ] Do it"},
+                    {"number": 1, "title": "First \"quoted\"", "body": null},
+                    {"number": 3, "title": "A pull request", "pull_request": {}},
+                ]),
+            )
+            .await;
+            let report = import_github_issues(&options(&server, output.clone()))
+                .await
+                .expect("import");
+            assert_eq!(report.added, ["issue-1", "issue-2"]);
+
+            let manifest = WorkflowManifest::parse(&output).expect("parse");
+            assert_eq!(manifest.validate_full(), Vec::new());
+            assert_eq!(manifest.workflow_name(), "app");
+            assert_eq!(manifest.tickets[0].summary, "First \"quoted\"");
+            assert_eq!(manifest.tickets[1].requirements[0].text(), "Do it");
+
+            let err = import_github_issues(&options(&server, output.clone()))
+                .await
+                .expect_err("exists");
+            assert!(err.to_string().contains("--update"), "{err}");
+
+            let edited = fs::read_to_string(&output)
+                .expect("read")
+                .replace("First \\\"quoted\\\"", "Edited");
+            fs::write(&output, edited).expect("write");
+            let server = MockServer::start().await;
+            mount_issues(
+                &server,
+                json!([
+                    {"number": 1, "title": "First", "body": ""},
+                    {"number": 4, "title": "Fourth", "body": "Details"},
+                ]),
+            )
+            .await;
+            let report = import_github_issues(&GithubImportOptions {
+                update: true,
+                ..options(&server, output.clone())
+            })
+            .await
+            .expect("update");
+            assert_eq!(report.added, ["issue-4"]);
+            assert_eq!(report.existing, 1);
+            let manifest = WorkflowManifest::parse(&output).expect("parse");
+            assert_eq!(manifest.validate_full(), Vec::new());
+            let summaries: Vec<&str> = manifest
+                .tickets
+                .iter()
+                .map(|ticket| ticket.summary.as_str())
+                .collect();
+            assert_eq!(summaries, ["Edited", "Second", "Fourth"]);
+            assert_eq!(manifest.tickets[2].description.as_deref(), Some("Details"));
+        }
+    }
+
+    #[tokio::test]
+    async fn waits_out_the_rate_limit() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(403)
+                    .insert_header("x-ratelimit-remaining", "0")
+                    .insert_header("retry-after", "0"),
+            )
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        mount_issues(&server, json!([{"number": 1, "title": "First"}])).await;
+
+        let report = import_github_issues(&options(&server, dir.path().join("plan.yaml")))
+            .await
+            .expect("import");
+        assert_eq!(report.added, ["issue-1"]);
+    }
+
+    #[tokio::test]
+    async fn refuses_to_write_a_manifest_without_issues() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let output = dir.path().join("plan.yaml");
+        let server = MockServer::start().await;
+        mount_issues(
+            &server,
+            json!([{"number": 3, "title": "A pull request", "pull_request": {}}]),
+        )
+        .await;
+
+        let err = import_github_issues(&options(&server, output.clone()))
+            .await
+            .expect_err("no issues");
+        assert!(
+            err.to_string()
+                .contains("no open issues labeled codex-workflow in acme/app"),
+            "{err}"
+        );
+        assert!(!output.exists());
+    }
+
+    #[tokio::test]
+    async fn explains_missing_repositories() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({"message": "Not Found"})))
+            .mount(&server)
+            .await;
+
+        let err = import_github_issues(&GithubImportOptions {
+            token: None,
+            ..options(&server, dir.path().join("plan.yaml"))
+        })
+        .await
+        .expect_err("missing repository");
+        assert!(err.to_string().contains("GITHUB_TOKEN"), "{err}");
+        assert!(!dir.path().join("plan.yaml").exists());
+    }
+}
@@ -0,0 +1,229 @@
+//! GitHub pull requests for completed tickets with `git.open_pr`.
+
+use crate::github_import::GITHUB_API_URL;
+use crate::github_import::REQUEST_TIMEOUT;
+use reqwest::header::ACCEPT;
+use reqwest::header::AUTHORIZATION;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::header::USER_AGENT;
+use serde::Deserialize;
+use serde_json::json;
+
+/// A pull request to open, or to update if one is already open for `head`.
+pub(crate) struct PullRequest<'a> {
+    /// `owner/name`.
+    pub(crate) repo: &'a str,
+    pub(crate) head: &'a str,
+    pub(crate) base: &'a str,
+    pub(crate) title: &'a str,
+    pub(crate) body: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct PullRequestResponse {
+    number: u64,
+    html_url: String,
+}
+
+/// `owner/name` of the GitHub repository a remote URL points to, e.g.
+/// `git@github.com:owner/name.git` or `https://github.com/owner/name`.
+/// `None` for URLs without a host, such as local paths.
+pub(crate) fn repo_from_remote_url(url: &str) -> Option<String> {
+    let path = match url.split_once("://") {
+        Some((_, rest)) => rest.split_once('/')?.1,
+        None => url.split_once(':')?.1,
+    };
+    let path = path.trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let mut segments = path.rsplit('/');
+    let name = segments.next().filter(|name| !name.is_empty())?;
+    let owner = segments.next().filter(|owner| !owner.is_empty())?;
+    Some(format!("{owner}/{name}"))
+}
+
+/// Opens the pull request, or updates the title and body of the one open
+/// from the same branch. Returns its URL, or why it failed.
+pub(crate) async fn open_pull_request(
+    api_url: Option<&str>,
+    token: &str,
+    pr: &PullRequest<'_>,
+) -> Result<String, String> {
+    let owner = match pr.repo.split_once('/') {
+        Some((owner, name)) if !owner.is_empty() && !name.is_empty() => owner,
+        _ => {
+            return Err(format!(
+                "GitHub repository must be owner/name, not {:?}",
+                pr.repo
+            ));
+        }
+    };
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|err| format!("failed to set up the GitHub client: {err}"))?;
+    let pulls = format!(
+        "{}/repos/{}/pulls",
+        api_url.unwrap_or(GITHUB_API_URL).trim_end_matches('/'),
+        pr.repo
+    );
+    let send = |request: reqwest::RequestBuilder| async move {
+        let response = request
+            .header(ACCEPT, "application/vnd.github+json")
+            .header(USER_AGENT, "codex-workflow")
+            .header("X-GitHub-Api-Version", "2022-11-28")
+            .header(AUTHORIZATION, format!("Bearer {token}"))
+            .send()
+            .await
+            .map_err(|err| format!("failed to reach GitHub: {err}"))?;
+        let status = response.status();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|err| format!("failed to read GitHub's response: {err}"))?;
+        if !status.is_success() {
+            let message = serde_json::from_slice::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|body| body["message"].as_str().map(str::to_string))
+                .unwrap_or_default();
+            return Err(format!("GitHub responded with {status}: {message}"));
+        }
+        Ok(body)
+    };
+
+    let open = send(client.get(&pulls).query(&[
+        ("head", format!("{owner}:{}", pr.head)),
+        ("state", "open".to_string()),
+    ]))
+    .await?;
+    let open: Vec<PullRequestResponse> = serde_json::from_slice(&open)
+        .map_err(|err| format!("unexpected response from GitHub: {err}"))?;
+    let response = match open.first() {
+        Some(existing) => {
+            send(
+                client
+                    .patch(format!("{pulls}/{}", existing.number))
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(json!({ "title": pr.title, "body": pr.body }).to_string()),
+            )
+            .await?
+        }
+        None => {
+            let body = json!({
+                "title": pr.title,
+                "body": pr.body,
+                "head": pr.head,
+                "base": pr.base,
+            });
+            send(
+                client
+                    .post(&pulls)
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(body.to_string()),
+            )
+            .await?
+        }
+    };
+    serde_json::from_slice::<PullRequestResponse>(&response)
+        .map(|pr| pr.html_url)
+        .map_err(|err| format!("unexpected response from GitHub: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers::body_partial_json;
+    use wiremock::matchers::header;
+    use wiremock::matchers::method;
+    use wiremock::matchers::path;
+    use wiremock::matchers::query_param;
+
+    #[test]
+    fn reads_the_repository_from_remote_urls() {
+        for url in [
+            "git@github.com:openai/codex.git",
+            "https://github.com/openai/codex",
+            "https://github.com/openai/codex.git/",
+            "ssh://git@github.example.com:2222/openai/codex.git",
+        ] {
+            assert_eq!(
+                repo_from_remote_url(url).as_deref(),
+                Some("openai/codex"),
+                "{url}"
+            );
+        }
+        assert_eq!(repo_from_remote_url("/srv/git/codex.git"), None);
+        assert_eq!(repo_from_remote_url("https://github.com/codex"), None);
+    }
+
+    #[tokio::test]
+    async fn opens_a_pull_request_or_updates_the_open_one() {
+        let server = MockServer::start().await;
+        let pr = PullRequest {
+            repo: "acme/app",
+            head: "workflow/demo/T1",
+            base: "main",
+            title: "T1: First ticket",
+            body: "Body",
+        };
+        let listed = Mock::given(method("GET"))
+            .and(path("/repos/acme/app/pulls"))
+            .and(query_param("head", "acme:workflow/demo/T1"))
+            .and(header("authorization", "Bearer secret"));
+        let _none = listed
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .up_to_n_times(1)
+            .mount_as_scoped(&server)
+            .await;
+        let _created = Mock::given(method("POST"))
+            .and(path("/repos/acme/app/pulls"))
+            .and(body_partial_json(
+                json!({ "head": "workflow/demo/T1", "base": "main", "title": "T1: First ticket" }),
+            ))
+            .respond_with(ResponseTemplate::new(201).set_body_json(
+                json!({ "number": 7, "html_url": "https://github.com/acme/app/pull/7" }),
+            ))
+            .expect(1)
+            .mount_as_scoped(&server)
+            .await;
+        let url = open_pull_request(Some(&server.uri()), "secret", &pr)
+            .await
+            .expect("open pull request");
+        assert_eq!(url, "https://github.com/acme/app/pull/7");
+        drop(_none);
+        drop(_created);
+
+        let open = json!([{ "number": 7, "html_url": "https://github.com/acme/app/pull/7" }]);
+        Mock::given(method("GET"))
+            .and(path("/repos/acme/app/pulls"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(open))
+            .mount(&server)
+            .await;
+        Mock::given(method("PATCH"))
+            .and(path("/repos/acme/app/pulls/7"))
+            .and(body_partial_json(
+                json!({ "title": "T1: First ticket", "body": "Body" }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                json!({ "number": 7, "html_url": "https://github.com/acme/app/pull/7" }),
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let url = open_pull_request(Some(&server.uri()), "secret", &pr)
+            .await
+            .expect("update pull request");
+        assert_eq!(url, "https://github.com/acme/app/pull/7");
+
+        let err = open_pull_request(
+            Some(&server.uri()),
+            "secret",
+            &PullRequest { repo: "app", ..pr },
+        )
+        .await
+        .expect_err("invalid repo");
+        assert!(err.contains("owner/name"), "{err}");
+    }
+}
@@ -0,0 +1,221 @@
+//! Dependency graphs of a manifest for `workflow graph`.
+
+use crate::manifest::WorkflowManifest;
+use crate::state::TicketStatus;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// Longest summary shown in a node label, in characters.
+const MAX_SUMMARY_CHARS: usize = 40;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum GraphFormat {
+    /// Graphviz, for `dot -Tsvg`.
+    #[default]
+    Dot,
+    /// A Mermaid flowchart, for Markdown.
+    Mermaid,
+}
+
+impl FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "dot" | "graphviz" => Ok(GraphFormat::Dot),
+            "mermaid" => Ok(GraphFormat::Mermaid),
+            other => Err(format!(
+                "unknown graph format `{other}` (expected dot or mermaid)"
+            )),
+        }
+    }
+}
+
+/// Renders the tickets as nodes and `depends_on` as edges from each
+/// dependency to its dependent. With `statuses`, nodes are filled by status;
+/// tickets missing from it are drawn as pending.
+pub fn render_graph(
+    manifest: &WorkflowManifest,
+    format: GraphFormat,
+    statuses: Option<&BTreeMap<String, TicketStatus>>,
+) -> String {
+    let status_of = |id: &str| {
+        statuses.map(|statuses| statuses.get(id).cloned().unwrap_or(TicketStatus::Pending))
+    };
+    match format {
+        GraphFormat::Dot => render_dot(manifest, status_of),
+        GraphFormat::Mermaid => render_mermaid(manifest, status_of),
+    }
+}
+
+fn render_dot(
+    manifest: &WorkflowManifest,
+    status_of: impl Fn(&str) -> Option<TicketStatus>,
+) -> String {
+    let mut out = String::from("digraph workflow {\n    rankdir=LR;\n");
+    out.push_str("    node [shape=box, style=\"rounded,filled\", fillcolor=\"white\"];\n");
+    for ticket in &manifest.tickets {
+        let label = format!(
+            "{}\\n{}",
+            dot_escape(&ticket.id),
+            dot_escape(&truncate(&ticket.summary))
+        );
+        out.push_str(&format!(
+            "    \"{}\" [label=\"{label}\"",
+            dot_escape(&ticket.id)
+        ));
+        if let Some(status) = status_of(&ticket.id) {
+            out.push_str(&format!(", fillcolor=\"{}\"", status_color(&status)));
+        }
+        out.push_str("];\n");
+    }
+    for ticket in &manifest.tickets {
+        for dependency in &ticket.depends_on {
+            out.push_str(&format!(
+                "    \"{}\" -> \"{}\";\n",
+                dot_escape(dependency),
+                dot_escape(&ticket.id)
+            ));
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+fn render_mermaid(
+    manifest: &WorkflowManifest,
+    status_of: impl Fn(&str) -> Option<TicketStatus>,
+) -> String {
+    // Mermaid node ids allow few characters, so nodes get positional ids and
+    // the ticket id goes in the label.
+    let node_ids: BTreeMap<&str, String> = manifest
+        .tickets
+        .iter()
+        .enumerate()
+        .map(|(index, ticket)| (ticket.id.as_str(), format!("t{index}")))
+        .collect();
+    let mut out = String::from("flowchart LR\n");
+    // Status class -> (fill color, nodes).
+    let mut classes: BTreeMap<&'static str, (&'static str, Vec<&str>)> = BTreeMap::new();
+    for ticket in &manifest.tickets {
+        let node = &node_ids[ticket.id.as_str()];
+        out.push_str(&format!(
+            "    {node}[\"{}<br/>{}\"]\n",
+            mermaid_escape(&ticket.id),
+            mermaid_escape(&truncate(&ticket.summary))
+        ));
+        if let Some(status) = status_of(&ticket.id) {
+            classes
+                .entry(status_class(&status))
+                .or_insert_with(|| (status_color(&status), Vec::new()))
+                .1
+                .push(node);
+        }
+    }
+    for ticket in &manifest.tickets {
+        for dependency in &ticket.depends_on {
+            if let Some(from) = node_ids.get(dependency.as_str()) {
+                out.push_str(&format!(
+                    "    {from} --> {}\n",
+                    node_ids[ticket.id.as_str()]
+                ));
+            }
+        }
+    }
+    for (class, (color, nodes)) in &classes {
+        out.push_str(&format!("    classDef {class} fill:{color}\n"));
+        out.push_str(&format!("    class {} {class}\n", nodes.join(",")));
+    }
+    out
+}
+
+fn status_class(status: &TicketStatus) -> &'static str {
+    match status {
+        TicketStatus::Complete => "complete",
+        TicketStatus::Failed => "failed",
+        TicketStatus::RunningWorker | TicketStatus::RunningReview => "running",
+        TicketStatus::NeedsReview => "needs_review",
+        TicketStatus::Blocked => "blocked",
+        TicketStatus::Pending | TicketStatus::Skipped => "pending",
+    }
+}
+
+fn status_color(status: &TicketStatus) -> &'static str {
+    match status {
+        TicketStatus::Complete => "#c8e6c9",
+        TicketStatus::Failed => "#ffcdd2",
+        TicketStatus::RunningWorker | TicketStatus::RunningReview => "#fff59d",
+        TicketStatus::NeedsReview => "#bbdefb",
+        TicketStatus::Blocked => "#ffe0b2",
+        TicketStatus::Pending | TicketStatus::Skipped => "#eeeeee",
+    }
+}
+
+/// The first line of `summary`, cut to [`MAX_SUMMARY_CHARS`].
+fn truncate(summary: &str) -> String {
+    let line = summary.lines().next().unwrap_or("").trim();
+    if line.chars().count() <= MAX_SUMMARY_CHARS {
+        return line.to_string();
+    }
+    let cut: String = line.chars().take(MAX_SUMMARY_CHARS - 1).collect();
+    format!("{}…", cut.trim_end())
+}
+
+/// Escapes text for a double-quoted DOT string.
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Escapes text for a double-quoted Mermaid label.
+fn mermaid_escape(text: &str) -> String {
+    text.replace('#', "#35;")
+        .replace('"', "#quot;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::TicketSpec;
+
+    fn manifest() -> WorkflowManifest {
+        WorkflowManifest {
+            tickets: vec![
+                TicketSpec {
+                    id: "api/v1".to_string(),
+                    summary: "Say \"hi\"".to_string(),
+                    ..Default::default()
+                },
+                TicketSpec {
+                    id: "docs".to_string(),
+                    summary: "Document every endpoint of the public API in the README".to_string(),
+                    depends_on: vec!["api/v1".to_string()],
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn renders_dot_with_quoted_ids() {
+        let statuses = BTreeMap::from([("api/v1".to_string(), TicketStatus::Complete)]);
+        assert_eq!(
+            render_graph(&manifest(), GraphFormat::Dot, Some(&statuses)),
+            "digraph workflow {\n    rankdir=LR;\n    node [shape=box, style=\"rounded,filled\", \
+            fillcolor=\"white\"];\n    \"api/v1\" [label=\"api/v1\\nSay \\\"hi\\\"\", \
+            fillcolor=\"#c8e6c9\"];\n    \"docs\" [label=\"docs\\nDocument every endpoint of \
+            the public A…\", fillcolor=\"#eeeeee\"];\n    \"api/v1\" -> \"docs\";\n}\n"
+        );
+    }
+
+    #[test]
+    fn renders_mermaid_with_positional_ids() {
+        assert_eq!(
+            render_graph(&manifest(), GraphFormat::Mermaid, None),
+            "flowchart LR\n    t0[\"api/v1<br/>Say #quot;hi#quot;\"]\n    t1[\"docs<br/>Document \
+            every endpoint of the public A…\"]\n    t0 --> t1\n"
+        );
+    }
+}
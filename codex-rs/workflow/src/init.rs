@@ -0,0 +1,320 @@
+//! Starter manifests for `workflow init`.
+
+use anyhow::Context;
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Syntax of a generated manifest.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ManifestFormat {
+    #[default]
+    Yaml,
+    Toml,
+}
+
+impl ManifestFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ManifestFormat::Yaml => "yaml",
+            ManifestFormat::Toml => "toml",
+        }
+    }
+
+    /// The format a manifest path's extension implies, if any.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()?.to_str()?.parse().ok()
+    }
+}
+
+impl FromStr for ManifestFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "yaml" | "yml" => Ok(ManifestFormat::Yaml),
+            "toml" | "tml" => Ok(ManifestFormat::Toml),
+            other => Err(format!(
+                "unknown manifest format `{other}` (expected yaml or toml)"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct InitOptions {
+    /// Manifest to write; defaults to `workflow.<ext>` in the current
+    /// directory.
+    pub path: Option<PathBuf>,
+    /// Defaults to the format `path`'s extension implies, else YAML.
+    pub format: Option<ManifestFormat>,
+    /// Replace `path` if it already exists.
+    pub force: bool,
+    /// Write one stub ticket per subdirectory of this directory instead of
+    /// the example tickets.
+    pub from_dir: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct InitReport {
+    pub path: PathBuf,
+    pub tickets: usize,
+}
+
+/// Writes a commented starter manifest that passes `validate_full` as is.
+pub fn init_manifest(opts: &InitOptions) -> Result<InitReport> {
+    let format = opts
+        .format
+        .or_else(|| opts.path.as_deref().and_then(ManifestFormat::from_path))
+        .unwrap_or_default();
+    let path = opts
+        .path
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(format!("workflow.{}", format.extension())));
+    if path.exists() && !opts.force {
+        anyhow::bail!(
+            "{} already exists; pass --force to overwrite it",
+            path.display()
+        );
+    }
+    let (contents, tickets) = match &opts.from_dir {
+        Some(dir) => {
+            let manifest_dir = path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            let ticket_dirs = subdirectory_tickets(dir, manifest_dir)?;
+            let tickets = ticket_dirs.len();
+            (stub_manifest(format, &ticket_dirs), tickets)
+        }
+        None => (example_manifest(format).to_string(), 2),
+    };
+    if let Some(parent) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    fs::write(&path, contents).with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(InitReport { path, tickets })
+}
+
+/// The example manifest: two tickets showing requirements, `working_dir`,
+/// `depends_on`, and prompt overrides.
+pub fn example_manifest(format: ManifestFormat) -> &'static str {
+    match format {
+        ManifestFormat::Yaml => EXAMPLE_YAML,
+        ManifestFormat::Toml => EXAMPLE_TOML,
+    }
+}
+
+const EXAMPLE_YAML: &str = r#"# Codex workflow manifest. Run it with `codex workflow run workflow.yaml`
+# and check it with `codex workflow validate workflow.yaml`.
+
+# Used for the artifacts directory (.codex/workflows/<name>) and reports.
+name: example
+# Shared context included in every worker and review prompt.
+overview: Add a health check endpoint to the service and document it.
+
+# Times a failed worker session is rerun before its ticket fails.
+max_retries: 1
+
+tickets:
+  - id: health-endpoint
+    summary: Add a GET /health endpoint that returns 200 when the service is up.
+    # Plain strings, or `{ id, text }` so the reviewer reports on each by id.
+    requirements:
+      - id: R1
+        text: The endpoint responds with 200 and a JSON body.
+      - Add a test for the endpoint.
+    # Where the sessions run, relative to this file.
+    working_dir: .
+
+  - id: health-docs
+    summary: Document the health check endpoint.
+    requirements:
+      - Describe the endpoint and its response in the README.
+    # Starts only after these tickets are complete; `health-endpoint:worker`
+    # would only wait for its worker, not its review.
+    depends_on: [health-endpoint]
+    # Replace the generated worker and review prompts.
+    prompt: |
+      Update the README with a short section on GET /health. Do not change
+      any code.
+    review_prompt: |
+      Check that the README section matches the endpoint's behaviour.
+"#;
+
+const EXAMPLE_TOML: &str = r#"# Codex workflow manifest. Run it with `codex workflow run workflow.toml`
+# and check it with `codex workflow validate workflow.toml`.
+
+# Used for the artifacts directory (.codex/workflows/<name>) and reports.
+name = "example"
+# Shared context included in every worker and review prompt.
+overview = "Add a health check endpoint to the service and document it."
+
+# Times a failed worker session is rerun before its ticket fails.
+max_retries = 1
+
+[[tickets]]
+id = "health-endpoint"
+summary = "Add a GET /health endpoint that returns 200 when the service is up."
+# Plain strings, or `{ id, text }` so the reviewer reports on each by id.
+requirements = [
+    { id = "R1", text = "The endpoint responds with 200 and a JSON body." },
+    "Add a test for the endpoint.",
+]
+# Where the sessions run, relative to this file.
+working_dir = "."
+
+[[tickets]]
+id = "health-docs"
+summary = "Document the health check endpoint."
+requirements = ["Describe the endpoint and its response in the README."]
+# Starts only after these tickets are complete; `health-endpoint:worker`
+# would only wait for its worker, not its review.
+depends_on = ["health-endpoint"]
+# Replace the generated worker and review prompts.
+prompt = """
+Update the README with a short section on GET /health. Do not change
+any code.
+"""
+review_prompt = """
+Check that the README section matches the endpoint's behaviour.
+"""
+"#;
+
+/// Non-hidden subdirectories of `dir`, sorted, as (ticket id, working
+/// directory relative to `manifest_dir` where possible).
+fn subdirectory_tickets(dir: &Path, manifest_dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let entries = fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))?;
+    let mut tickets: Vec<(String, PathBuf)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            (!name.starts_with('.')).then(|| (name, entry.path()))
+        })
+        .collect();
+    if tickets.is_empty() {
+        anyhow::bail!(
+            "{} has no subdirectories to create tickets for",
+            dir.display()
+        );
+    }
+    tickets.sort();
+    let manifest_dir = absolute(manifest_dir);
+    for (_, path) in &mut tickets {
+        let full = absolute(path);
+        *path = match full.strip_prefix(&manifest_dir) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => full,
+        };
+    }
+    Ok(tickets)
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// A manifest with one stub ticket per `(id, working_dir)`.
+fn stub_manifest(format: ManifestFormat, tickets: &[(String, PathBuf)]) -> String {
+    // JSON string literals are valid YAML and TOML strings.
+    let quote = |text: &str| serde_json::to_string(text).unwrap_or_default();
+    let mut out = String::from(
+        "# Codex workflow manifest with one ticket per directory. Fill in each\n\
+        # ticket's summary and requirements, then check it with\n\
+        # `codex workflow validate`.\n\n",
+    );
+    match format {
+        ManifestFormat::Yaml => out.push_str("tickets:\n"),
+        ManifestFormat::Toml => {}
+    }
+    for (id, dir) in tickets {
+        let summary = quote(&format!("TODO: describe the change to {id}."));
+        let dir = quote(&dir.to_string_lossy());
+        let id = quote(id);
+        match format {
+            ManifestFormat::Yaml => out.push_str(&format!(
+                "  - id: {id}\n    summary: {summary}\n    requirements: []\n    \
+                working_dir: {dir}\n"
+            )),
+            ManifestFormat::Toml => out.push_str(&format!(
+                "[[tickets]]\nid = {id}\nsummary = {summary}\nrequirements = []\n\
+                working_dir = {dir}\n\n"
+            )),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::WorkflowManifest;
+
+    fn assert_valid(path: &Path, tickets: usize) {
+        let manifest = WorkflowManifest::parse(path).expect("parse");
+        assert_eq!(manifest.validate_full(), Vec::new());
+        assert_eq!(manifest.tickets.len(), tickets);
+    }
+
+    #[test]
+    fn examples_pass_validation() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        for format in [ManifestFormat::Yaml, ManifestFormat::Toml] {
+            let path = dir.path().join(format!("workflow.{}", format.extension()));
+            let report = init_manifest(&InitOptions {
+                path: Some(path.clone()),
+                ..Default::default()
+            })
+            .expect("init");
+            assert_eq!(report.tickets, 2);
+            assert_valid(&path, 2);
+            let manifest = WorkflowManifest::load(&path).expect("load");
+            assert_eq!(manifest.tickets[1].depends_on, vec!["health-endpoint"]);
+
+            let err = init_manifest(&InitOptions {
+                path: Some(path.clone()),
+                ..Default::default()
+            })
+            .expect_err("exists");
+            assert!(err.to_string().contains("--force"));
+            init_manifest(&InitOptions {
+                path: Some(path),
+                force: true,
+                ..Default::default()
+            })
+            .expect("force");
+        }
+    }
+
+    #[test]
+    fn from_dir_creates_a_ticket_per_subdirectory() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        for name in ["api", "web \"ui\"", ".git"] {
+            fs::create_dir_all(dir.path().join("services").join(name)).expect("mkdir");
+        }
+        fs::write(dir.path().join("services/README.md"), "").expect("write");
+        for format in [ManifestFormat::Yaml, ManifestFormat::Toml] {
+            let path = dir.path().join(format!("services.{}", format.extension()));
+            init_manifest(&InitOptions {
+                path: Some(path.clone()),
+                from_dir: Some(dir.path().join("services")),
+                ..Default::default()
+            })
+            .expect("init");
+            assert_valid(&path, 2);
+            let manifest = WorkflowManifest::load(&path).expect("load");
+            assert_eq!(manifest.tickets[1].id, "web \"ui\"");
+            assert_eq!(
+                manifest.tickets[0].working_dir,
+                Some(PathBuf::from("services/api"))
+            );
+        }
+    }
+}
@@ -1,52 +1,540 @@
+use crate::log_format::LogFormat;
+use crate::manifest::WorkflowManifest;
+use crate::state::SessionPhase;
 use anyhow::Context;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
 
+/// Paths of a workflow's artifacts. State and summary always live at the
+/// root; ticket directories live either directly under the root (the flat
+/// layout) or under `runs/<run-id>` once [`WorkflowLayout::start_run`] is used.
+/// Tickets with a `log_dir` keep their directories under that directory
+/// instead, mirroring the same flat and `runs/<run-id>` structure.
 #[derive(Debug, Clone)]
 pub struct WorkflowLayout {
     root: PathBuf,
+    run_dir: Option<PathBuf>,
+    log_format: LogFormat,
+    /// Ticket id -> the ticket's resolved `log_dir`.
+    ticket_log_dirs: BTreeMap<String, PathBuf>,
 }
 
+/// Contents of `workflow.meta.json`, written when the artifacts root is
+/// created, identifying the workflow the directory belongs to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkflowMeta {
+    pub manifest_path: PathBuf,
+    pub workflow_name: String,
+    /// Version of the crate that created the directory.
+    pub created_by: String,
+}
+
+impl WorkflowMeta {
+    pub fn for_manifest(manifest: &WorkflowManifest) -> Self {
+        Self {
+            manifest_path: std::path::absolute(&manifest.source_path)
+                .unwrap_or_else(|_| manifest.source_path.clone()),
+            workflow_name: manifest.workflow_name(),
+            created_by: format!("codex-workflow {}", env!("CARGO_PKG_VERSION")),
+        }
+    }
+}
+
+/// Environment variable naming a directory that holds the artifacts of every
+/// workflow without an explicit `--artifacts-dir`. The CLI reads it and
+/// passes it on as `artifacts_root`.
+pub const ARTIFACTS_ROOT_ENV_VAR: &str = "CODEX_WORKFLOW_ARTIFACTS_DIR";
+
+/// Extensions of session logs written by any log format, including the
+/// plain `.log` files of older versions.
+const LOG_EXTENSIONS: [&str; 3] = ["md", "json", "log"];
+
+/// Name of the event log in the artifacts root, next to `state.json`.
+pub(crate) const EVENTS_FILE: &str = "events.jsonl";
+
+/// Name of a ticket directory's `git.use_worktrees` checkout.
+pub(crate) const WORKTREE_DIR: &str = "worktree";
+
 impl WorkflowLayout {
     pub fn new(root: PathBuf) -> Self {
-        Self { root }
+        Self {
+            root,
+            run_dir: None,
+            log_format: LogFormat::default(),
+            ticket_log_dirs: BTreeMap::new(),
+        }
+    }
+
+    /// The layout of `manifest`'s artifacts when nothing says otherwise:
+    /// `.codex/workflows/<workflow-name>` under the manifest's base directory.
+    pub fn default_for(manifest: &WorkflowManifest) -> Self {
+        let root = manifest
+            .base_dir()
+            .join(".codex")
+            .join("workflows")
+            .join(manifest.workflow_name());
+        Self::new(root).with_ticket_log_dirs(manifest)
+    }
+
+    /// The layout of `manifest`'s artifacts as a run resolves it: an
+    /// explicit `artifacts_dir` wins; then `<root>/<workflow-name>` for an
+    /// `artifacts_root`, which callers take from [`ARTIFACTS_ROOT_ENV_VAR`]
+    /// or their configuration; otherwise [`WorkflowLayout::default_for`].
+    pub fn resolve(
+        manifest: &WorkflowManifest,
+        artifacts_dir: Option<&Path>,
+        artifacts_root: Option<&Path>,
+    ) -> Self {
+        if let Some(dir) = artifacts_dir {
+            return Self::new(dir.to_path_buf()).with_ticket_log_dirs(manifest);
+        }
+        match artifacts_root {
+            Some(root) => {
+                Self::new(root.join(manifest.workflow_name())).with_ticket_log_dirs(manifest)
+            }
+            None => Self::default_for(manifest),
+        }
+    }
+
+    /// Places the directories of tickets with a `log_dir` under that
+    /// directory rather than the artifacts root.
+    pub fn with_ticket_log_dirs(mut self, manifest: &WorkflowManifest) -> Self {
+        let base_dir = manifest.base_dir();
+        self.ticket_log_dirs = manifest
+            .tickets
+            .iter()
+            .filter_map(|ticket| Some((ticket.id.clone(), ticket.resolved_log_dir(&base_dir)?)))
+            .collect();
+        self
+    }
+
+    /// Sets the format, and with it the extension, of new session logs.
+    pub fn with_log_format(mut self, log_format: LogFormat) -> Self {
+        self.log_format = log_format;
+        self
+    }
+
+    pub fn log_format(&self) -> LogFormat {
+        self.log_format
     }
 
     pub fn root(&self) -> &Path {
         &self.root
     }
 
-    pub fn ensure_root(&self) -> anyhow::Result<()> {
+    /// Directory holding this run's ticket directories, if the runs layout
+    /// is in use.
+    pub fn run_dir(&self) -> Option<&Path> {
+        self.run_dir.as_deref()
+    }
+
+    pub fn runs_dir(&self) -> PathBuf {
+        self.root.join("runs")
+    }
+
+    /// Symlink (a pointer file on Windows) to the newest run directory.
+    pub fn latest_link(&self) -> PathBuf {
+        self.root.join("latest")
+    }
+
+    /// Switches to the runs layout: creates `runs/<run_id>`, adding a numeric
+    /// suffix if a run with that id already exists, and points `latest` at it.
+    pub fn start_run(mut self, run_id: &str) -> anyhow::Result<Self> {
+        let runs_dir = self.runs_dir();
+        fs::create_dir_all(&runs_dir)
+            .with_context(|| format!("failed to create {}", runs_dir.display()))?;
+        let mut name = run_id.to_string();
+        let mut suffix = 1;
+        let run_dir = loop {
+            let candidate = runs_dir.join(&name);
+            match fs::create_dir(&candidate) {
+                Ok(()) => break candidate,
+                Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+                    suffix += 1;
+                    name = format!("{run_id}-{suffix}");
+                }
+                Err(err) => {
+                    return Err(err)
+                        .with_context(|| format!("failed to create {}", candidate.display()));
+                }
+            }
+        };
+        point_latest_at(&self.latest_link(), &name)?;
+        self.run_dir = Some(run_dir);
+        Ok(self)
+    }
+
+    /// Switches to an existing run directory, `runs/<run_id>`, so a resumed
+    /// run writes next to that run's logs; `latest` names the newest run.
+    /// Points `latest` at the reopened run.
+    pub fn open_run(mut self, run_id: &str) -> anyhow::Result<Self> {
+        let name = if run_id == "latest" {
+            self.latest_run_name()
+                .with_context(|| format!("{} does not exist", self.latest_link().display()))?
+        } else {
+            run_id.to_string()
+        };
+        let run_dir = self.runs_dir().join(&name);
+        if !run_dir.is_dir() {
+            anyhow::bail!("run directory {} does not exist", run_dir.display());
+        }
+        point_latest_at(&self.latest_link(), &name)?;
+        self.run_dir = Some(run_dir);
+        Ok(self)
+    }
+
+    /// The run directory `latest` points at, if it still exists.
+    pub fn latest_run_dir(&self) -> Option<PathBuf> {
+        let run_dir = self.runs_dir().join(self.latest_run_name()?);
+        run_dir.is_dir().then_some(run_dir)
+    }
+
+    /// Name of the run directory `latest` points at.
+    fn latest_run_name(&self) -> Option<String> {
+        let latest = self.latest_link();
+        #[cfg(unix)]
+        let target = fs::read_link(&latest).ok()?;
+        #[cfg(not(unix))]
+        let target = PathBuf::from(fs::read_to_string(&latest).ok()?.trim());
+        Some(target.file_name()?.to_string_lossy().into_owned())
+    }
+
+    /// Existing run directories, oldest first.
+    pub fn run_dirs(&self) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(self.runs_dir()) else {
+            return Vec::new();
+        };
+        let mut dirs: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_dir())
+            .collect();
+        // Run ids are timestamps, so names sort chronologically.
+        dirs.sort();
+        dirs
+    }
+
+    /// Every ticket directory, in the flat location and in each run.
+    pub fn existing_ticket_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs = Vec::new();
+        for parent in std::iter::once(self.root.clone()).chain(self.run_dirs()) {
+            let Ok(entries) = fs::read_dir(&parent) else {
+                continue;
+            };
+            let mut found: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_name().to_string_lossy().starts_with("ticket-"))
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .collect();
+            found.sort();
+            dirs.extend(found);
+        }
+        dirs
+    }
+
+    /// Worker and review session logs directly inside `ticket_dir`.
+    pub fn session_log_files(ticket_dir: &Path) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(ticket_dir) else {
+            return Vec::new();
+        };
+        let mut files: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                let name = entry.file_name();
+                let name = name.to_string_lossy();
+                [SessionPhase::Worker, SessionPhase::Review]
+                    .into_iter()
+                    .any(|phase| log_attempt(&name, phase).is_some())
+            })
+            .map(|entry| entry.path())
+            .collect();
+        files.sort();
+        files
+    }
+
+    /// Every directory a ticket's artifacts may have been written to: the
+    /// flat location and its directory in each run.
+    pub fn all_ticket_dirs(&self, ticket_id: &str) -> Vec<PathBuf> {
+        let name = ticket_dir_name(ticket_id);
+        let parent = self.ticket_log_dirs.get(ticket_id);
+        std::iter::once(parent.unwrap_or(&self.root).join(&name))
+            .chain(self.run_dirs().into_iter().map(|run| {
+                match parent {
+                    Some(log_dir) => log_dir
+                        .join("runs")
+                        .join(run.file_name().unwrap_or_default())
+                        .join(&name),
+                    None => run.join(&name),
+                }
+            }))
+            .filter(|dir| dir.is_dir())
+            .collect()
+    }
+
+    /// Creates the artifacts root and checks that it is writable, so that a
+    /// read-only or foreign-owned directory is reported before any session
+    /// runs. A newly created root gets a `.gitignore` that keeps its contents
+    /// out of git and a `workflow.meta.json` describing `manifest`.
+    pub fn ensure_root(&self, manifest: &WorkflowManifest) -> anyhow::Result<()> {
+        let created = !self.root.exists();
         fs::create_dir_all(&self.root)
-            .with_context(|| format!("failed to create {}", self.root.display()))
+            .and_then(|()| probe_writable(&self.root))
+            .with_context(|| {
+                format!(
+                    "artifacts directory {} is not writable; pass --artifacts-dir to use \
+                    another location",
+                    self.root.display()
+                )
+            })?;
+        if created {
+            write_new_file(&self.root.join(".gitignore"), b"*\n")?;
+            let meta = serde_json::to_vec_pretty(&WorkflowMeta::for_manifest(manifest))?;
+            write_new_file(&self.meta_file(), &meta)?;
+        }
+        Ok(())
+    }
+
+    /// Written once, when the root is created. See [`WorkflowMeta`].
+    pub fn meta_file(&self) -> PathBuf {
+        self.root.join("workflow.meta.json")
+    }
+
+    /// The root's metadata, if it has a readable `workflow.meta.json`.
+    pub fn read_meta(&self) -> Option<WorkflowMeta> {
+        let contents = fs::read(self.meta_file()).ok()?;
+        serde_json::from_slice(&contents).ok()
+    }
+
+    /// Held while a run or a clean works on the artifacts directory.
+    pub fn lock_file(&self) -> PathBuf {
+        self.root.join("run.lock")
+    }
+
+    /// Written by `workflow cancel` to ask the run holding the lock to stop.
+    pub fn cancel_file(&self) -> PathBuf {
+        self.root.join("cancel.json")
     }
 
     pub fn state_file(&self) -> PathBuf {
         self.root.join("state.json")
     }
 
+    /// Markdown summary written at the end of every run.
+    pub fn summary_file(&self) -> PathBuf {
+        self.root.join("summary.md")
+    }
+
+    /// Status report snapshot a run with `report_interval` keeps rewriting.
+    pub fn report_file(&self) -> PathBuf {
+        self.root.join("report.json")
+    }
+
+    /// Machine-readable outcome of the last run, written even when the run
+    /// stopped on an error.
+    pub fn result_file(&self) -> PathBuf {
+        self.root.join("result.json")
+    }
+
+    /// Append-only log of every run's events; see [`crate::WorkflowEvent`].
+    pub fn events_file(&self) -> PathBuf {
+        self.root.join(EVENTS_FILE)
+    }
+
     pub fn ticket_dir(&self, ticket_id: &str) -> PathBuf {
-        self.root.join(format!("ticket-{}", sanitize(ticket_id)))
+        let parent = match (self.ticket_log_dirs.get(ticket_id), &self.run_dir) {
+            (Some(log_dir), Some(run_dir)) => log_dir
+                .join("runs")
+                .join(run_dir.file_name().unwrap_or_default()),
+            (Some(log_dir), None) => log_dir.clone(),
+            (None, Some(run_dir)) => run_dir.clone(),
+            (None, None) => self.root.clone(),
+        };
+        parent.join(ticket_dir_name(ticket_id))
     }
 
+    /// Creates a ticket's directory and its patch directory and checks that
+    /// they are writable.
     pub fn ensure_ticket_dir(&self, ticket_id: &str) -> anyhow::Result<PathBuf> {
         let dir = self.ticket_dir(ticket_id);
-        fs::create_dir_all(&dir).with_context(|| format!("failed to create {}", dir.display()))?;
+        let patch_dir = self.patch_dir(ticket_id);
+        fs::create_dir_all(&patch_dir)
+            .and_then(|()| probe_writable(&dir))
+            .and_then(|()| probe_writable(&patch_dir))
+            .with_context(|| {
+                format!(
+                    "ticket directory {} is not writable; pass --artifacts-dir to use \
+                    another location",
+                    dir.display()
+                )
+            })?;
         Ok(dir)
     }
 
-    pub fn worker_log_path(&self, ticket_id: &str) -> PathBuf {
-        self.ticket_dir(ticket_id).join("worker.log")
+    pub fn worker_log_path(&self, ticket_id: &str, attempt: i32) -> PathBuf {
+        self.session_log_path(ticket_id, SessionPhase::Worker, attempt)
+    }
+
+    pub fn review_log_path(&self, ticket_id: &str, attempt: i32) -> PathBuf {
+        self.session_log_path(ticket_id, SessionPhase::Review, attempt)
+    }
+
+    /// Log path for a numbered attempt, e.g. `worker.2.md`.
+    pub fn session_log_path(&self, ticket_id: &str, phase: SessionPhase, attempt: i32) -> PathBuf {
+        self.ticket_dir(ticket_id).join(format!(
+            "{}.{attempt}.{}",
+            phase.as_str(),
+            self.log_format.extension()
+        ))
+    }
+
+    /// Attempt numbers that already have a log file for this phase, in any
+    /// log format, ascending.
+    pub fn existing_log_attempts(&self, ticket_id: &str, phase: SessionPhase) -> Vec<i32> {
+        let mut attempts: Vec<i32> = self
+            .log_attempt_files(ticket_id, phase)
+            .into_iter()
+            .map(|(attempt, _)| attempt)
+            .collect();
+        attempts.dedup();
+        attempts
+    }
+
+    /// Session log files for a phase with their attempt numbers, ascending.
+    fn log_attempt_files(&self, ticket_id: &str, phase: SessionPhase) -> Vec<(i32, PathBuf)> {
+        Self::attempt_log_files(&self.ticket_dir(ticket_id), phase)
     }
 
-    pub fn review_log_path(&self, ticket_id: &str) -> PathBuf {
-        self.ticket_dir(ticket_id).join("review.log")
+    /// Session log files for a phase directly inside `ticket_dir`, with their
+    /// attempt numbers, ascending.
+    pub fn attempt_log_files(ticket_dir: &Path, phase: SessionPhase) -> Vec<(i32, PathBuf)> {
+        let Ok(entries) = fs::read_dir(ticket_dir) else {
+            return Vec::new();
+        };
+        let mut files: Vec<(i32, PathBuf)> = entries
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                Some((log_attempt(&name, phase)?, entry.path()))
+            })
+            .collect();
+        files.sort();
+        files
+    }
+
+    /// Deletes the oldest attempt logs for a phase so at most `keep` remain,
+    /// returning the deleted paths.
+    pub fn prune_log_attempts(
+        &self,
+        ticket_id: &str,
+        phase: SessionPhase,
+        keep: i32,
+    ) -> anyhow::Result<Vec<PathBuf>> {
+        let attempts = self.existing_log_attempts(ticket_id, phase);
+        let keep = usize::try_from(keep).unwrap_or(0);
+        let Some(oldest_kept) = attempts
+            .len()
+            .checked_sub(keep)
+            .and_then(|excess| attempts.get(excess).copied())
+        else {
+            return Ok(Vec::new());
+        };
+        let mut removed = Vec::new();
+        for (attempt, path) in self.log_attempt_files(ticket_id, phase) {
+            if attempt < oldest_kept {
+                fs::remove_file(&path)
+                    .with_context(|| format!("failed to remove {}", path.display()))?;
+                removed.push(path);
+            }
+        }
+        Ok(removed)
+    }
+
+    /// Final prompt sent to the worker, after wrapping and defaults.
+    pub fn worker_prompt_path(&self, ticket_id: &str) -> PathBuf {
+        self.ticket_dir(ticket_id).join("worker.prompt.txt")
+    }
+
+    pub fn review_prompt_path(&self, ticket_id: &str) -> PathBuf {
+        self.ticket_dir(ticket_id).join("review.prompt.txt")
+    }
+
+    pub fn worker_output_path(&self, ticket_id: &str) -> PathBuf {
+        self.ticket_dir(ticket_id).join("worker.output.md")
+    }
+
+    pub fn review_output_path(&self, ticket_id: &str) -> PathBuf {
+        self.ticket_dir(ticket_id).join("review.output.md")
     }
 
     pub fn patch_dir(&self, ticket_id: &str) -> PathBuf {
         self.ticket_dir(ticket_id).join("patches")
     }
+
+    pub fn worktree_dir(&self, ticket_id: &str) -> PathBuf {
+        self.ticket_dir(ticket_id).join(WORKTREE_DIR)
+    }
+}
+
+/// Attempt number of a session log file name such as `worker.3.md`.
+fn log_attempt(name: &str, phase: SessionPhase) -> Option<i32> {
+    let (attempt, extension) = name
+        .strip_prefix(phase.as_str())?
+        .strip_prefix('.')?
+        .split_once('.')?;
+    if !LOG_EXTENSIONS.contains(&extension) {
+        return None;
+    }
+    attempt.parse().ok().filter(|attempt| *attempt > 0)
+}
+
+/// Writes `contents` to `path` unless the file already exists.
+fn write_new_file(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    match fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+    {
+        Ok(mut file) => file
+            .write_all(contents)
+            .with_context(|| format!("failed to write {}", path.display())),
+        Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => Ok(()),
+        Err(err) => Err(err).with_context(|| format!("failed to create {}", path.display())),
+    }
+}
+
+/// Creates and removes a probe file; directory permissions alone do not
+/// account for read-only mounts or ACLs.
+fn probe_writable(dir: &Path) -> std::io::Result<()> {
+    let probe = dir.join(format!(".write-probe-{}", std::process::id()));
+    fs::write(&probe, b"")?;
+    fs::remove_file(&probe)
+}
+
+/// Replaces `latest` with a pointer to `runs/<run_name>`. A symlink on Unix;
+/// elsewhere a file holding the relative path, since creating symlinks on
+/// Windows needs extra privileges.
+fn point_latest_at(latest: &Path, run_name: &str) -> anyhow::Result<()> {
+    let target = Path::new("runs").join(run_name);
+    if fs::symlink_metadata(latest).is_ok() {
+        fs::remove_file(latest)
+            .with_context(|| format!("failed to replace {}", latest.display()))?;
+    }
+    #[cfg(unix)]
+    let result = std::os::unix::fs::symlink(&target, latest);
+    #[cfg(not(unix))]
+    let result = fs::write(latest, format!("{}\n", target.display()));
+    result.with_context(|| format!("failed to update {}", latest.display()))
+}
+
+pub(crate) fn ticket_dir_name(ticket_id: &str) -> String {
+    format!("ticket-{}", sanitize(ticket_id))
 }
 
 fn sanitize(id: &str) -> String {
@@ -61,6 +549,7 @@ fn sanitize(id: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::manifest::TicketSpec;
 
     #[test]
     fn ticket_dirs_are_sanitized() {
@@ -68,8 +557,215 @@ mod tests {
         let dir = layout.ticket_dir("ABC/123");
         assert!(dir.ends_with("ticket-ABC_123"));
         assert_eq!(
-            layout.worker_log_path("hello world"),
-            PathBuf::from("/tmp/workflow/ticket-hello_world/worker.log")
+            layout.worker_log_path("hello world", 1),
+            PathBuf::from("/tmp/workflow/ticket-hello_world/worker.1.md")
         );
+        let layout = layout.with_log_format(LogFormat::Json);
+        assert!(layout.review_log_path("T1", 3).ends_with("review.3.json"));
+    }
+
+    #[test]
+    fn resolve_prefers_dir_then_root() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest = WorkflowManifest {
+            source_path: dir.path().join("workflow.yaml"),
+            name: Some("demo".to_string()),
+            ..Default::default()
+        };
+        let root = |artifacts_dir: Option<&str>, artifacts_root: Option<&str>| {
+            WorkflowLayout::resolve(
+                &manifest,
+                artifacts_dir.map(Path::new),
+                artifacts_root.map(Path::new),
+            )
+            .root()
+            .to_path_buf()
+        };
+
+        assert_eq!(root(Some("/flag"), Some("/root")), PathBuf::from("/flag"));
+        assert_eq!(root(None, Some("/root")), PathBuf::from("/root/demo"));
+        assert_eq!(root(None, None), dir.path().join(".codex/workflows/demo"));
+        assert_eq!(
+            WorkflowLayout::default_for(&manifest).root(),
+            dir.path().join(".codex/workflows/demo")
+        );
+    }
+
+    #[test]
+    fn lists_and_prunes_attempt_logs() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let layout = WorkflowLayout::new(dir.path().to_path_buf());
+        layout.ensure_ticket_dir("T1").expect("ticket dir");
+        for attempt in [1, 2, 10] {
+            fs::write(layout.worker_log_path("T1", attempt), "log").expect("write log");
+        }
+        fs::write(layout.review_log_path("T1", 1), "log").expect("write log");
+        fs::write(layout.ticket_dir("T1").join("worker.output.md"), "").expect("write");
+        fs::write(layout.ticket_dir("T1").join("worker.3.log"), "").expect("write");
+
+        assert_eq!(
+            layout.existing_log_attempts("T1", SessionPhase::Worker),
+            vec![1, 2, 3, 10]
+        );
+        let removed = layout
+            .prune_log_attempts("T1", SessionPhase::Worker, 2)
+            .expect("prune");
+        assert_eq!(
+            removed,
+            vec![
+                layout.worker_log_path("T1", 1),
+                layout.worker_log_path("T1", 2)
+            ]
+        );
+        assert_eq!(
+            layout.existing_log_attempts("T1", SessionPhase::Worker),
+            vec![3, 10]
+        );
+        assert_eq!(
+            layout.existing_log_attempts("T1", SessionPhase::Review),
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn ensure_ticket_dir_creates_patch_dir() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let layout = WorkflowLayout::new(dir.path().join("artifacts"));
+        layout
+            .ensure_root(&WorkflowManifest::default())
+            .expect("ensure root");
+        layout.ensure_ticket_dir("T1").expect("ticket dir");
+        assert!(layout.patch_dir("T1").is_dir());
+        assert_eq!(
+            fs::read_dir(layout.ticket_dir("T1"))
+                .expect("read dir")
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn runs_get_their_own_ticket_dirs() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path().join("artifacts");
+        let flat = WorkflowLayout::new(root.clone());
+        flat.ensure_ticket_dir("T1").expect("flat ticket dir");
+
+        let first = flat.clone().start_run("20260101T000000Z").expect("run");
+        let second = flat.clone().start_run("20260101T000000Z").expect("run");
+        assert_eq!(
+            first.run_dir(),
+            Some(root.join("runs/20260101T000000Z").as_path())
+        );
+        assert_eq!(
+            second.ticket_dir("T1"),
+            root.join("runs/20260101T000000Z-2/ticket-T1")
+        );
+        assert_eq!(second.state_file(), root.join("state.json"));
+        first.ensure_ticket_dir("T1").expect("run ticket dir");
+        #[cfg(unix)]
+        assert_eq!(
+            fs::read_link(flat.latest_link()).expect("latest"),
+            PathBuf::from("runs/20260101T000000Z-2")
+        );
+        assert_eq!(
+            flat.all_ticket_dirs("T1"),
+            vec![root.join("ticket-T1"), first.ticket_dir("T1")]
+        );
+    }
+
+    #[test]
+    fn reopens_an_existing_run() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path().join("artifacts");
+        let flat = WorkflowLayout::new(root.clone());
+        let first = flat.clone().start_run("20260101T000000Z").expect("run");
+        flat.clone().start_run("20260102T000000Z").expect("run");
+
+        let reopened = flat.clone().open_run("20260101T000000Z").expect("open");
+        assert_eq!(reopened.run_dir(), first.run_dir());
+        assert_eq!(
+            flat.clone()
+                .open_run("latest")
+                .expect("open latest")
+                .run_dir(),
+            first.run_dir()
+        );
+        let err = flat.open_run("missing").expect_err("missing run");
+        assert!(err.to_string().contains("does not exist"));
+    }
+
+    #[test]
+    fn new_root_gets_gitignore_and_meta() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest = WorkflowManifest {
+            source_path: dir.path().join("workflow.yaml"),
+            name: Some("demo".to_string()),
+            ..Default::default()
+        };
+        let layout = WorkflowLayout::new(dir.path().join("artifacts"));
+        layout.ensure_root(&manifest).expect("ensure root");
+        assert_eq!(
+            fs::read_to_string(layout.root().join(".gitignore")).expect("gitignore"),
+            "*\n"
+        );
+        let meta = layout.read_meta().expect("meta");
+        assert_eq!(meta.workflow_name, "demo");
+        assert_eq!(meta.manifest_path, dir.path().join("workflow.yaml"));
+
+        // An existing root is left as the user arranged it.
+        let existing = WorkflowLayout::new(dir.path().join("existing"));
+        fs::create_dir(existing.root()).expect("create root");
+        existing.ensure_root(&manifest).expect("ensure root");
+        assert!(!existing.root().join(".gitignore").exists());
+        assert_eq!(existing.read_meta(), None);
+    }
+
+    #[test]
+    fn ticket_log_dir_overrides_the_ticket_directory() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest = WorkflowManifest {
+            source_path: dir.path().join("workflow.yaml"),
+            tickets: vec![
+                TicketSpec {
+                    id: "T1".to_string(),
+                    log_dir: Some(PathBuf::from("shared")),
+                    ..Default::default()
+                },
+                TicketSpec {
+                    id: "T2".to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let root = dir.path().join("artifacts");
+        let flat = WorkflowLayout::new(root.clone()).with_ticket_log_dirs(&manifest);
+        let shared = dir.path().join("shared");
+        assert_eq!(flat.ticket_dir("T1"), shared.join("ticket-T1"));
+        assert_eq!(flat.ticket_dir("T2"), root.join("ticket-T2"));
+
+        let run = flat.clone().start_run("20260101T000000Z").expect("run");
+        run.ensure_ticket_dir("T1").expect("ticket dir");
+        assert_eq!(
+            run.worker_log_path("T1", 1),
+            shared.join("runs/20260101T000000Z/ticket-T1/worker.1.md")
+        );
+        assert_eq!(run.state_file(), root.join("state.json"));
+        assert_eq!(flat.all_ticket_dirs("T1"), vec![run.ticket_dir("T1")]);
+    }
+
+    #[test]
+    fn unwritable_root_suggests_artifacts_dir() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let file = dir.path().join("not-a-dir");
+        fs::write(&file, "").expect("write file");
+        let layout = WorkflowLayout::new(file.join("artifacts"));
+        let err = layout
+            .ensure_root(&WorkflowManifest::default())
+            .expect_err("root under a file");
+        let message = err.to_string();
+        assert!(message.contains(&file.join("artifacts").display().to_string()));
+        assert!(message.contains("--artifacts-dir"));
     }
 }
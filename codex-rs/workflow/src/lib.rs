@@ -1,16 +1,130 @@
+mod archive;
+mod cancel;
+mod capture;
+mod clean;
+mod complete;
+mod confirm;
+mod context_budget;
+mod disk;
+mod error;
+mod events;
+mod fingerprint;
+mod git_branch;
+mod git_diff;
+mod github_import;
+mod github_pr;
+mod graph;
+mod init;
 mod layout;
+mod list;
+mod lock;
+mod log_format;
+mod logs;
 mod manifest;
+mod memory;
+mod metrics;
+mod notify_command;
 mod orchestrator;
+mod preflight;
+mod process;
+mod prompt_command;
+mod report;
+mod review;
+mod secrets;
 mod session;
 mod state;
+mod summary;
+mod template;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
+mod ticket_id;
+mod usage;
+mod validate;
+mod webhook;
 
+pub use archive::ArchiveFormat;
+pub use archive::ArchiveOptions;
+pub use archive::ArchiveReport;
+pub use archive::archive_workflow;
+pub use cancel::CancelOptions;
+pub use cancel::CancelReport;
+pub use cancel::cancel_workflow;
+pub use capture::DEFAULT_MAX_LOG_BYTES;
+pub use clean::CleanOptions;
+pub use clean::CleanReport;
+pub use clean::clean_workflow;
+pub use complete::CompleteOptions;
+pub use complete::complete_ticket;
+pub use confirm::TicketConfirmer;
+pub use confirm::TicketDecision;
+pub use confirm::TicketPlan;
+pub use error::StoppedRun;
+pub use error::WorkflowError;
+pub use events::EVENT_VERSION;
+pub use events::RunSettings;
+pub use events::WorkflowEvent;
+pub use events::read_events;
+pub use github_import::GITHUB_TOKEN_ENV_VARS;
+pub use github_import::GithubImportOptions;
+pub use github_import::GithubImportReport;
+pub use github_import::import_github_issues;
+pub use graph::GraphFormat;
+pub use graph::render_graph;
+pub use init::InitOptions;
+pub use init::InitReport;
+pub use init::ManifestFormat;
+pub use init::example_manifest;
+pub use init::init_manifest;
+pub use layout::ARTIFACTS_ROOT_ENV_VAR;
 pub use layout::WorkflowLayout;
+pub use layout::WorkflowMeta;
+pub use list::DEFAULT_LIST_GLOB;
+pub use list::ListOptions;
+pub use list::ListReport;
+pub use list::StatusCount;
+pub use list::WorkflowListing;
+pub use list::list_workflows;
+pub use lock::LockInfo;
+pub use log_format::LogFormat;
+pub use logs::SessionLogQuery;
+pub use logs::find_session_log;
+pub use logs::is_live_session_log;
+pub use manifest::GitSettings;
+pub use manifest::Notifications;
+pub use manifest::Requirement;
+pub use manifest::ReviewFailurePolicy;
+pub use manifest::TicketDefaults;
 pub use manifest::TicketSpec;
 pub use manifest::WorkflowManifest;
+pub use orchestrator::PARALLELISM_ENV_VAR;
+pub use orchestrator::PromptPreview;
 pub use orchestrator::WorkflowRunOptions;
 pub use orchestrator::WorkflowStatusReport;
 pub use orchestrator::load_status;
+pub use orchestrator::preview_prompt;
+pub use orchestrator::resolve_max_parallel;
 pub use orchestrator::run_workflow;
+pub use orchestrator::run_workflow_with_runner;
+pub use report::ReportFormat;
+pub use report::format_duration;
+pub use session::PrintCommand;
+pub use session::SessionChunk;
+pub use session::SessionLauncher;
+pub use session::SessionRequest;
+pub use session::SessionResult;
+pub use session::SessionRunner;
+pub use state::AttemptRecord;
+pub use state::RequirementOutcome;
+pub use state::ReviewReport;
+pub use state::ReviewVerdict;
+pub use state::SessionPhase;
 pub use state::TicketRunState;
 pub use state::TicketStatus;
+pub use state::TicketWorktree;
 pub use state::WorkflowState;
+pub use ticket_id::resolve_ticket_id;
+pub use usage::ArtifactSizes;
+pub use usage::DiskUsage;
+pub use usage::measure_disk_usage;
+pub use validate::IssueSeverity;
+pub use validate::ManifestIssue;
@@ -0,0 +1,307 @@
+//! Discovery of workflows under a directory for `workflow list`.
+
+use crate::layout::WorkflowLayout;
+use crate::manifest::WorkflowManifest;
+use crate::state::TicketStatus;
+use crate::state::WorkflowState;
+use anyhow::Context;
+use anyhow::Result;
+use chrono::DateTime;
+use chrono::Utc;
+use ignore::WalkBuilder;
+use ignore::overrides::OverrideBuilder;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Manifests `workflow list` looks for unless told otherwise.
+pub const DEFAULT_LIST_GLOB: &str = "**/*.workflow.{yaml,toml}";
+
+/// The order statuses are counted in.
+const STATUS_ORDER: [TicketStatus; 8] = [
+    TicketStatus::Complete,
+    TicketStatus::Failed,
+    TicketStatus::Blocked,
+    TicketStatus::Skipped,
+    TicketStatus::RunningWorker,
+    TicketStatus::NeedsReview,
+    TicketStatus::RunningReview,
+    TicketStatus::Pending,
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct ListOptions {
+    /// Directory to scan.
+    pub root: PathBuf,
+    /// Manifest glob relative to `root`; defaults to [`DEFAULT_LIST_GLOB`].
+    pub glob: Option<String>,
+    /// Root of the artifacts directories, whose workflows are listed as
+    /// well.
+    pub artifacts_root: Option<PathBuf>,
+}
+
+/// One workflow found by [`list_workflows`].
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkflowListing {
+    pub name: String,
+    /// `None` for state whose manifest is gone or unrecorded.
+    pub manifest_path: Option<PathBuf>,
+    /// `None` for a manifest that has never run.
+    pub state_path: Option<PathBuf>,
+    /// Tickets per status, omitting statuses no ticket has.
+    pub counts: Vec<StatusCount>,
+    /// When a session last started or finished.
+    pub last_activity: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusCount {
+    pub status: TicketStatus,
+    pub count: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct ListReport {
+    pub workflows: Vec<WorkflowListing>,
+    /// Files that were skipped because they could not be read or parsed.
+    pub warnings: Vec<String>,
+}
+
+/// Finds the manifests under `opts.root` that match the glob, skipping
+/// gitignored and hidden directories, plus every workflow with state in a
+/// `.codex/workflows` directory along the way or under the artifacts root.
+/// Unreadable manifests and state are reported as warnings.
+pub fn list_workflows(opts: &ListOptions) -> Result<ListReport> {
+    let glob = opts.glob.as_deref().unwrap_or(DEFAULT_LIST_GLOB);
+    let overrides = OverrideBuilder::new(&opts.root)
+        .add(glob)
+        .and_then(|builder| builder.build())
+        .with_context(|| format!("invalid manifest glob {glob}"))?;
+    let mut report = ListReport::default();
+    let mut manifests = Vec::new();
+    let mut artifact_dirs = Vec::new();
+    artifact_dirs.extend(opts.artifacts_root.clone());
+    let walk = WalkBuilder::new(&opts.root)
+        .overrides(overrides)
+        .require_git(false)
+        .build();
+    for entry in walk {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                report.warnings.push(err.to_string());
+                continue;
+            }
+        };
+        if entry.file_type().is_some_and(|kind| kind.is_dir()) {
+            // `.codex` is hidden and usually gitignored, so look for it
+            // directly rather than through the walk.
+            let workflows = entry.path().join(".codex").join("workflows");
+            if workflows.is_dir() {
+                artifact_dirs.push(workflows);
+            }
+        } else {
+            manifests.push(entry.into_path());
+        }
+    }
+    manifests.sort();
+
+    let mut seen_manifests: HashSet<PathBuf> = HashSet::new();
+    let mut seen_states: HashSet<PathBuf> = HashSet::new();
+    for path in manifests {
+        let Some(listing) = manifest_listing(&path, opts, &mut report.warnings) else {
+            continue;
+        };
+        seen_manifests.insert(absolute(&path));
+        seen_states.extend(listing.state_path.as_deref().map(absolute));
+        report.workflows.push(listing);
+    }
+
+    for dir in artifact_dirs {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut roots: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.join("state.json").is_file())
+            .collect();
+        roots.sort();
+        for root in roots {
+            let layout = WorkflowLayout::new(root);
+            let state_path = layout.state_file();
+            if !seen_states.insert(absolute(&state_path)) {
+                continue;
+            }
+            let manifest_path = layout
+                .read_meta()
+                .map(|meta| meta.manifest_path)
+                .filter(|path| path.is_file());
+            if let Some(manifest_path) = &manifest_path
+                && !seen_manifests.insert(absolute(manifest_path))
+            {
+                // Listed already, with its state resolved elsewhere.
+                continue;
+            }
+            match WorkflowState::load(&state_path) {
+                Ok(state) => report.workflows.push(WorkflowListing {
+                    name: state.workflow_name.clone(),
+                    manifest_path,
+                    state_path: Some(state_path),
+                    counts: status_counts(state.tickets.values().map(|ticket| &ticket.status)),
+                    last_activity: last_activity(&state),
+                }),
+                Err(err) => report.warnings.push(format!(
+                    "skipped {}: {:#}",
+                    state_path.display(),
+                    anyhow::Error::from(err)
+                )),
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn manifest_listing(
+    path: &Path,
+    opts: &ListOptions,
+    warnings: &mut Vec<String>,
+) -> Option<WorkflowListing> {
+    let manifest = match WorkflowManifest::load(path) {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            warnings.push(format!(
+                "skipped {}: {:#}",
+                path.display(),
+                anyhow::Error::from(err)
+            ));
+            return None;
+        }
+    };
+    let layout = WorkflowLayout::resolve(&manifest, None, opts.artifacts_root.as_deref());
+    let state_path = layout.state_file();
+    let state = if state_path.is_file() {
+        match WorkflowState::load(&state_path) {
+            Ok(state) => Some(state),
+            Err(err) => {
+                warnings.push(format!(
+                    "ignored {}: {:#}",
+                    state_path.display(),
+                    anyhow::Error::from(err)
+                ));
+                None
+            }
+        }
+    } else {
+        None
+    };
+    // Tickets the state does not know yet count as pending.
+    let statuses = manifest.tickets.iter().map(|ticket| {
+        state
+            .as_ref()
+            .and_then(|state| state.tickets.get(&ticket.id))
+            .map_or(&TicketStatus::Pending, |entry| &entry.status)
+    });
+    Some(WorkflowListing {
+        name: manifest.workflow_name(),
+        manifest_path: Some(path.to_path_buf()),
+        counts: status_counts(statuses),
+        last_activity: state.as_ref().and_then(last_activity),
+        state_path: state.map(|_| state_path),
+    })
+}
+
+pub(crate) fn status_counts<'a>(
+    statuses: impl Iterator<Item = &'a TicketStatus>,
+) -> Vec<StatusCount> {
+    let statuses: Vec<&TicketStatus> = statuses.collect();
+    STATUS_ORDER
+        .into_iter()
+        .map(|status| StatusCount {
+            count: statuses.iter().filter(|other| **other == &status).count(),
+            status,
+        })
+        .filter(|count| count.count > 0)
+        .collect()
+}
+
+fn last_activity(state: &WorkflowState) -> Option<DateTime<Utc>> {
+    state
+        .tickets
+        .values()
+        .flat_map(|ticket| {
+            let attempts = ticket
+                .attempts
+                .iter()
+                .flat_map(|attempt| [Some(attempt.started_at), attempt.finished_at]);
+            [ticket.started_at, ticket.finished_at]
+                .into_iter()
+                .chain(attempts)
+        })
+        .flatten()
+        .max()
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_manifests_and_orphaned_state_but_not_ignored_files() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let root = dir.path();
+        fs::create_dir_all(root.join("svc/target")).expect("mkdir");
+        fs::write(root.join(".gitignore"), "target/\n").expect("write");
+        let manifest = "name: api\ntickets:\n  - id: T1\n    summary: One\n  \
+                        - id: T2\n    summary: Two\n";
+        fs::write(root.join("svc/api.workflow.yaml"), manifest).expect("write");
+        fs::write(root.join("svc/target/copy.workflow.yaml"), manifest).expect("write");
+        fs::write(root.join("broken.workflow.toml"), "tickets = [").expect("write");
+
+        let manifest = WorkflowManifest::load(&root.join("svc/api.workflow.yaml")).expect("load");
+        let mut state = WorkflowState::initialize(&manifest);
+        let ticket = state.ticket_mut("T1").expect("T1");
+        ticket.mark_running(TicketStatus::RunningWorker);
+        ticket.mark_finished(TicketStatus::Complete, None);
+        let finished = ticket.finished_at;
+        state
+            .save(&root.join("svc/.codex/workflows/api/state.json"))
+            .expect("save");
+        let orphan = WorkflowState {
+            workflow_name: "old".to_string(),
+            ..WorkflowState::initialize(&WorkflowManifest::default())
+        };
+        orphan
+            .save(&root.join(".codex/workflows/old/state.json"))
+            .expect("save");
+
+        let report = list_workflows(&ListOptions {
+            root: root.to_path_buf(),
+            ..Default::default()
+        })
+        .expect("list");
+
+        let names: Vec<&str> = report.workflows.iter().map(|w| w.name.as_str()).collect();
+        assert_eq!(names, vec!["api", "old"]);
+        let api = &report.workflows[0];
+        let counts: Vec<(TicketStatus, usize)> = api
+            .counts
+            .iter()
+            .map(|count| (count.status.clone(), count.count))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![(TicketStatus::Complete, 1), (TicketStatus::Pending, 1)]
+        );
+        assert_eq!(api.last_activity, finished);
+        assert_eq!(report.workflows[1].manifest_path, None);
+        assert_eq!(report.warnings.len(), 1);
+        assert!(report.warnings[0].contains("broken.workflow.toml"));
+    }
+}
@@ -0,0 +1,256 @@
+//! Exclusive lock on a workflow's artifacts directory.
+
+use anyhow::Context;
+use chrono::DateTime;
+use chrono::Utc;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use sysinfo::System;
+
+/// How often a run refreshes its lock's heartbeat while sessions run.
+pub(crate) const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Age after which a heartbeat no longer vouches for a holder that cannot be
+/// checked directly.
+const STALE_HEARTBEAT_SECS: i64 = 10 * 60;
+
+/// Contents of the lock file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockInfo {
+    pub pid: u32,
+    pub acquired_at: DateTime<Utc>,
+    /// Host the holder runs on; unset in locks written by older versions.
+    #[serde(default)]
+    pub hostname: Option<String>,
+    /// Refreshed on every state save and periodically while sessions run;
+    /// unset in locks written by older versions.
+    #[serde(default)]
+    pub heartbeat_at: Option<DateTime<Utc>>,
+}
+
+impl LockInfo {
+    /// Whether the holder may still be running. A local holder is checked by
+    /// its PID; one on another host is trusted while its heartbeat is recent.
+    pub fn holder_is_alive(&self) -> bool {
+        let local = self
+            .hostname
+            .as_ref()
+            .is_none_or(|host| Some(host) == System::host_name().as_ref());
+        if local {
+            return pid_is_alive(self.pid);
+        }
+        (Utc::now() - self.last_heartbeat()).num_seconds() < STALE_HEARTBEAT_SECS
+    }
+
+    /// When the holder last showed signs of life.
+    pub fn last_heartbeat(&self) -> DateTime<Utc> {
+        self.heartbeat_at.unwrap_or(self.acquired_at)
+    }
+}
+
+/// Held for as long as a run (or a clean) works on the artifacts directory;
+/// the lock file is removed on drop. A lock left behind by a process that no
+/// longer exists is taken over.
+///
+/// The lock file only ever appears complete, linked into place from a
+/// temporary file, and a stale one is moved aside before it is removed, so
+/// two processes taking over the same stale lock cannot both succeed.
+#[derive(Debug)]
+pub(crate) struct RunLock {
+    path: PathBuf,
+    info: LockInfo,
+}
+
+impl RunLock {
+    pub(crate) fn acquire(path: &Path) -> anyhow::Result<Self> {
+        let now = Utc::now();
+        let info = LockInfo {
+            pid: std::process::id(),
+            acquired_at: now,
+            hostname: System::host_name(),
+            heartbeat_at: Some(now),
+        };
+        let pid = info.pid;
+        let tmp_path = path.with_extension(format!("lock.{pid}.tmp"));
+        fs::write(&tmp_path, serde_json::to_vec(&info)?)
+            .with_context(|| format!("failed to write {}", tmp_path.display()))?;
+        let result = Self::link_into_place(&tmp_path, path);
+        let _ = fs::remove_file(&tmp_path);
+        result?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            info,
+        })
+    }
+
+    /// Hard-links the complete lock at `tmp_path` to `path`, which fails if
+    /// `path` exists, taking over stale locks for up to three attempts.
+    fn link_into_place(tmp_path: &Path, path: &Path) -> anyhow::Result<()> {
+        for _ in 0..3 {
+            let err = match fs::hard_link(tmp_path, path) {
+                Ok(()) => return Ok(()),
+                Err(err) => err,
+            };
+            if err.kind() != ErrorKind::AlreadyExists {
+                return Err(err).with_context(|| format!("failed to create {}", path.display()));
+            }
+            let Ok(contents) = fs::read(path) else {
+                // Released in the meantime.
+                continue;
+            };
+            let holder: Option<LockInfo> = serde_json::from_slice(&contents).ok();
+            if let Some(holder) = holder.filter(LockInfo::holder_is_alive) {
+                anyhow::bail!(
+                    "workflow is in use by process {} since {} (lock file {})",
+                    holder.pid,
+                    holder.acquired_at.to_rfc3339(),
+                    path.display()
+                );
+            }
+            // Stale or unreadable: the holder is gone. Move the lock aside
+            // first and make sure it is the one judged stale, not a fresh
+            // lock another process put in its place meanwhile.
+            let stale_path = path.with_extension(format!("lock.{}.stale", std::process::id()));
+            match fs::rename(path, &stale_path) {
+                Ok(()) => {}
+                Err(err) if err.kind() == ErrorKind::NotFound => continue,
+                Err(err) => {
+                    return Err(err).with_context(|| {
+                        format!("failed to remove stale lock {}", path.display())
+                    });
+                }
+            }
+            if fs::read(&stale_path).ok().as_deref() != Some(contents.as_slice()) {
+                // Put the other process's lock back, unless a third one
+                // holds the path by now.
+                let _ = fs::hard_link(&stale_path, path);
+            }
+            let _ = fs::remove_file(&stale_path);
+        }
+        anyhow::bail!("failed to acquire lock {}", path.display())
+    }
+
+    /// Rewrites the lock file with the current time as its heartbeat.
+    pub(crate) fn heartbeat(&self) -> anyhow::Result<()> {
+        let info = LockInfo {
+            heartbeat_at: Some(Utc::now()),
+            ..self.info.clone()
+        };
+        // Readers must never see a half-written lock, which they would
+        // take for a stale one.
+        let tmp_path = self.path.with_extension("lock.tmp");
+        fs::write(&tmp_path, serde_json::to_vec(&info)?)
+            .with_context(|| format!("failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("failed to write {}", self.path.display()))
+    }
+}
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+pub(crate) fn read_lock(path: &Path) -> Option<LockInfo> {
+    let contents = fs::read(path).ok()?;
+    serde_json::from_slice(&contents).ok()
+}
+
+#[cfg(unix)]
+fn pid_is_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // SAFETY: signal 0 only checks whether the process exists.
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() != Some(libc::ESRCH)
+}
+
+/// Without a cheap liveness check, assume the holder is still running.
+#[cfg(not(unix))]
+fn pid_is_alive(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn second_acquire_fails_until_released() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("run.lock");
+        let lock = RunLock::acquire(&path).expect("acquire");
+        let err = RunLock::acquire(&path).expect_err("held");
+        assert!(err.to_string().contains("in use by process"));
+        drop(lock);
+        assert!(!path.exists());
+        RunLock::acquire(&path).expect("reacquire");
+    }
+
+    #[test]
+    fn heartbeats_refresh_the_lock_and_vouch_for_remote_holders() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("run.lock");
+        let lock = RunLock::acquire(&path).expect("acquire");
+        let before = read_lock(&path).expect("lock info");
+        lock.heartbeat().expect("heartbeat");
+        let after = read_lock(&path).expect("lock info");
+        assert_eq!(after.acquired_at, before.acquired_at);
+        assert!(after.last_heartbeat() >= before.last_heartbeat());
+
+        let remote = LockInfo {
+            hostname: Some("elsewhere.invalid".to_string()),
+            ..after
+        };
+        assert!(remote.holder_is_alive());
+        let abandoned = LockInfo {
+            heartbeat_at: Some(Utc::now() - chrono::TimeDelta::hours(2)),
+            ..remote
+        };
+        assert!(!abandoned.holder_is_alive());
+    }
+
+    #[test]
+    fn takes_over_unreadable_lock() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("run.lock");
+        fs::write(&path, "garbage").expect("write lock");
+        RunLock::acquire(&path).expect("acquire");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn takes_over_a_dead_holders_lock_without_leaving_files_behind() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("run.lock");
+        let mut child = std::process::Command::new("true").spawn().expect("spawn");
+        child.wait().expect("wait");
+        let dead = LockInfo {
+            pid: child.id(),
+            acquired_at: Utc::now(),
+            hostname: System::host_name(),
+            heartbeat_at: None,
+        };
+        fs::write(&path, serde_json::to_vec(&dead).expect("json")).expect("write lock");
+
+        let lock = RunLock::acquire(&path).expect("acquire");
+        assert_eq!(
+            read_lock(&path).map(|info| info.pid),
+            Some(std::process::id())
+        );
+        let files: Vec<_> = fs::read_dir(dir.path())
+            .expect("read dir")
+            .map(|entry| entry.expect("entry").file_name())
+            .collect();
+        assert_eq!(files, ["run.lock"]);
+        drop(lock);
+        assert!(!path.exists());
+    }
+}
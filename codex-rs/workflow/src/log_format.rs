@@ -0,0 +1,435 @@
+//! Session log files: format selection and rendering of captured output.
+
+use crate::session::SessionResult;
+use anyhow::Context;
+use chrono::DateTime;
+use chrono::Utc;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// File format of the per-session logs (`worker.N.md`, `worker.N.log` or
+/// `worker.N.json`). The default changed from plain `.log` files to
+/// Markdown; `Plain` keeps the old extension.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// A readable document with fenced prompt and output sections.
+    #[default]
+    Markdown,
+    /// The same content as plain text, with `== Title ==` section rules.
+    Plain,
+    /// A single JSON object, for tooling.
+    Json,
+}
+
+impl LogFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            LogFormat::Markdown => "md",
+            LogFormat::Plain => "log",
+            LogFormat::Json => "json",
+        }
+    }
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "markdown" | "md" => Ok(LogFormat::Markdown),
+            "plain" | "text" => Ok(LogFormat::Plain),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!(
+                "unknown log format `{other}` (expected markdown, plain or json)"
+            )),
+        }
+    }
+}
+
+/// How session output is rendered into the logs.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LogStyle {
+    /// Keep ANSI escape sequences instead of stripping them.
+    pub ansi: bool,
+    /// Prefix each output line with the time elapsed since the session began.
+    pub timestamps: bool,
+}
+
+impl Default for LogStyle {
+    fn default() -> Self {
+        Self {
+            ansi: false,
+            timestamps: true,
+        }
+    }
+}
+
+/// Everything recorded in a session log. `result` already has secrets
+/// redacted; `prompt` and `env` must be redacted by the caller as well.
+pub(crate) struct SessionLog<'a> {
+    pub prompt: &'a str,
+    pub result: &'a SessionResult,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// The session's time limit, if it had one.
+    pub timeout: Option<Duration>,
+    /// The codex config profile the session ran with, if one was given.
+    pub profile: Option<&'a str>,
+    /// The binary the session ran.
+    pub codex_bin: &'a Path,
+    /// Attempt number of the session within its phase, if known.
+    pub attempt: Option<i32>,
+    pub model: Option<&'a str>,
+    pub working_dir: &'a Path,
+    /// Environment variables the session got on top of the inherited ones.
+    pub env: BTreeMap<String, String>,
+    pub stdout_times: &'a [Duration],
+    pub stderr_times: &'a [Duration],
+    pub style: LogStyle,
+}
+
+pub(crate) fn write_session_log(
+    path: &Path,
+    format: LogFormat,
+    log: &SessionLog<'_>,
+) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    let contents = match format {
+        LogFormat::Markdown => render_markdown(log),
+        LogFormat::Plain => render_plain(log),
+        LogFormat::Json => render_json(log)?,
+    };
+    // Replaces the live log in one step, so a crash while writing still
+    // leaves that behind.
+    let tmp_path = path.with_extension(format!("{}.tmp", format.extension()));
+    std::fs::write(&tmp_path, contents)
+        .and_then(|()| std::fs::rename(&tmp_path, path))
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// What is known about a session when it starts, for the preamble of its
+/// [`LiveLog`](crate::capture::LiveLog). Redacted by the caller.
+pub(crate) struct LiveSessionLog<'a> {
+    pub prompt: &'a str,
+    pub command_line: &'a str,
+    pub started_at: DateTime<Utc>,
+    pub timeout: Option<Duration>,
+    pub profile: Option<&'a str>,
+    pub codex_bin: &'a Path,
+    pub attempt: Option<i32>,
+    pub model: Option<&'a str>,
+    pub working_dir: &'a Path,
+    pub env: &'a BTreeMap<String, String>,
+}
+
+/// First line of a session log whose session has not finished. Whatever the
+/// log format, the live log is plain text until the finished log replaces it.
+pub(crate) const LIVE_LOG_TITLE: &str = "Codex session (running)";
+
+/// The live log up to its output records: the facts known at the start, the
+/// command, environment and prompt, and an `Output` heading.
+pub(crate) fn render_live_preamble(log: &LiveSessionLog<'_>) -> String {
+    let mut doc = format!(
+        "{LIVE_LOG_TITLE}\n\nStarted: {}\n",
+        log.started_at.to_rfc3339()
+    );
+    if let Some(attempt) = log.attempt {
+        doc.push_str(&format!("Attempt: {attempt}\n"));
+    }
+    if let Some(timeout) = log.timeout {
+        doc.push_str(&format!("Timeout: {}s\n", timeout.as_secs_f64()));
+    }
+    if let Some(model) = log.model {
+        doc.push_str(&format!("Model: {model}\n"));
+    }
+    if let Some(profile) = log.profile {
+        doc.push_str(&format!("Profile: {profile}\n"));
+    }
+    doc.push_str(&format!("Binary: {}\n", log.codex_bin.display()));
+    doc.push_str(&format!("Working dir: {}\n", log.working_dir.display()));
+    let mut sections = vec![("Command", log.command_line.to_string())];
+    if !log.env.is_empty() {
+        let env: String = log
+            .env
+            .iter()
+            .map(|(key, value)| format!("{key}={value}\n"))
+            .collect();
+        sections.push(("Environment", env));
+    }
+    sections.push(("Prompt", log.prompt.to_string()));
+    for (title, body) in sections {
+        doc.push_str(&format!("\n== {title} ==\n{body}"));
+        if !body.is_empty() && !body.ends_with('\n') {
+            doc.push('\n');
+        }
+    }
+    doc.push_str("\n== Output ==\n");
+    doc
+}
+
+/// The `Key: value` facts at the top of a log, shared by the text formats.
+fn header_fields(log: &SessionLog<'_>) -> Vec<(&'static str, String)> {
+    let result = log.result;
+    let exit = if result.timed_out {
+        "timed out".to_string()
+    } else {
+        match result.status_code {
+            Some(code) => code.to_string(),
+            None => "terminated by signal".to_string(),
+        }
+    };
+    let mut fields = vec![
+        ("Started", log.started_at.to_rfc3339()),
+        ("Finished", log.finished_at.to_rfc3339()),
+    ];
+    if let Some(attempt) = log.attempt {
+        fields.push(("Attempt", attempt.to_string()));
+    }
+    if let Some(timeout) = log.timeout {
+        fields.push(("Timeout", format!("{}s", timeout.as_secs_f64())));
+    }
+    if let Some(model) = log.model {
+        fields.push(("Model", model.to_string()));
+    }
+    if let Some(profile) = log.profile {
+        fields.push(("Profile", profile.to_string()));
+    }
+    fields.push(("Binary", log.codex_bin.display().to_string()));
+    fields.push(("Working dir", log.working_dir.display().to_string()));
+    fields.push(("Exit status", exit));
+    if let Some(session_id) = &result.session_id {
+        fields.push(("Session id", session_id.clone()));
+    }
+    fields
+}
+
+/// The titled sections below the header, with the info string of their
+/// Markdown fence.
+fn sections(log: &SessionLog<'_>) -> Vec<(&'static str, &'static str, String)> {
+    let mut sections = vec![("Command", "sh", log.result.command_line.clone())];
+    if !log.env.is_empty() {
+        let env: String = log
+            .env
+            .iter()
+            .map(|(key, value)| format!("{key}={value}\n"))
+            .collect();
+        sections.push(("Environment", "text", env));
+    }
+    sections.push(("Prompt", "text", log.prompt.to_string()));
+    sections.push((
+        "Stdout",
+        "text",
+        render_stream(&log.result.stdout, log.stdout_times, log.style),
+    ));
+    sections.push((
+        "Stderr",
+        "text",
+        render_stream(&log.result.stderr, log.stderr_times, log.style),
+    ));
+    sections
+}
+
+fn render_markdown(log: &SessionLog<'_>) -> String {
+    let result = log.result;
+    let mut doc = String::from("# Codex session\n\n");
+    for warning in &result.warnings {
+        doc.push_str(&format!("> **Warning:** {warning}\n"));
+    }
+    if !result.warnings.is_empty() {
+        doc.push('\n');
+    }
+    for (key, value) in header_fields(log) {
+        doc.push_str(&format!("- {key}: {value}\n"));
+    }
+    for (title, info, body) in sections(log) {
+        push_fenced_section(&mut doc, title, info, &body);
+    }
+    doc
+}
+
+fn render_plain(log: &SessionLog<'_>) -> String {
+    let mut doc = String::from("Codex session\n\n");
+    for warning in &log.result.warnings {
+        doc.push_str(&format!("Warning: {warning}\n"));
+    }
+    for (key, value) in header_fields(log) {
+        doc.push_str(&format!("{key}: {value}\n"));
+    }
+    for (title, _, body) in sections(log) {
+        doc.push_str(&format!("\n== {title} ==\n{body}"));
+        if !body.is_empty() && !body.ends_with('\n') {
+            doc.push('\n');
+        }
+    }
+    doc
+}
+
+/// Appends `## title` and `body` in a fence longer than any backtick run in
+/// the body, so output containing fences cannot break out of the block.
+fn push_fenced_section(doc: &mut String, title: &str, info: &str, body: &str) {
+    let longest_run = body.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run.max(2) + 1);
+    doc.push_str(&format!("\n## {title}\n\n{fence}{info}\n{body}"));
+    if !body.is_empty() && !body.ends_with('\n') {
+        doc.push('\n');
+    }
+    doc.push_str(&fence);
+    doc.push('\n');
+}
+
+fn render_json(log: &SessionLog<'_>) -> anyhow::Result<String> {
+    let result = log.result;
+    let strip = |text: &str| {
+        if log.style.ansi {
+            text.to_string()
+        } else {
+            strip_ansi(text)
+        }
+    };
+    let millis = |times: &[Duration]| {
+        times
+            .iter()
+            .map(|time| u64::try_from(time.as_millis()).unwrap_or(u64::MAX))
+            .collect::<Vec<_>>()
+    };
+    let mut value = serde_json::json!({
+        "command": result.command_line,
+        "prompt": log.prompt,
+        "started_at": log.started_at.to_rfc3339(),
+        "finished_at": log.finished_at.to_rfc3339(),
+        "timeout_secs": log.timeout.map(|timeout| timeout.as_secs_f64()),
+        "profile": log.profile,
+        "codex_bin": log.codex_bin,
+        "attempt": log.attempt,
+        "model": log.model,
+        "working_dir": log.working_dir,
+        "env": log.env,
+        "exit_code": result.status_code,
+        "success": result.success,
+        "timed_out": result.timed_out,
+        "truncated_bytes": result.truncated_bytes,
+        "session_id": result.session_id,
+        "warnings": result.warnings,
+        "stdout": strip(&result.stdout),
+        "stderr": strip(&result.stderr),
+    });
+    if log.style.timestamps {
+        value["stdout_line_elapsed_ms"] = millis(log.stdout_times).into();
+        value["stderr_line_elapsed_ms"] = millis(log.stderr_times).into();
+    }
+    let mut rendered = serde_json::to_string_pretty(&value)?;
+    rendered.push('\n');
+    Ok(rendered)
+}
+
+/// Renders one output stream. `line_times[i]` is when line `i` arrived; lines
+/// without a recorded time reuse the last one.
+fn render_stream(text: &str, line_times: &[Duration], style: LogStyle) -> String {
+    let text = if style.ansi {
+        text.to_string()
+    } else {
+        strip_ansi(text)
+    };
+    if !style.timestamps {
+        return text;
+    }
+    let mut rendered = String::with_capacity(text.len() + text.len() / 8);
+    let mut last = Duration::ZERO;
+    for (index, line) in text.split_inclusive('\n').enumerate() {
+        if let Some(time) = line_times.get(index) {
+            last = *time;
+        }
+        rendered.push_str(&format_elapsed(last));
+        rendered.push(' ');
+        rendered.push_str(line);
+    }
+    rendered
+}
+
+/// `[HH:MM:SS]`, with hours growing past two digits if needed.
+pub(crate) fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    format!(
+        "[{:02}:{:02}:{:02}]",
+        secs / 3600,
+        (secs / 60) % 60,
+        secs % 60
+    )
+}
+
+/// Removes CSI (`ESC [ ... final`), OSC (`ESC ] ... BEL` or `ESC ] ... ESC \`)
+/// and other two-character escape sequences.
+pub(crate) fn strip_ansi(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('\u{40}'..='\u{7e}').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\u{7}' {
+                        break;
+                    }
+                    if c == '\u{1b}' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_color_and_title_sequences() {
+        let text = "\u{1b}[1;32mok\u{1b}[0m done\u{1b}]0;title\u{7}!\u{1b}]8;;x\u{1b}\\";
+        assert_eq!(strip_ansi(text), "ok done!");
+    }
+
+    #[test]
+    fn prefixes_lines_with_elapsed_time() {
+        let times = [Duration::from_secs(1), Duration::from_secs(3725)];
+        let rendered = render_stream("one\ntwo\nthree", &times, LogStyle::default());
+        assert_eq!(rendered, "[00:00:01] one\n[01:02:05] two\n[01:02:05] three");
+
+        let style = LogStyle {
+            ansi: true,
+            timestamps: false,
+        };
+        assert_eq!(render_stream("\u{1b}[1mx\n", &[], style), "\u{1b}[1mx\n");
+    }
+
+    #[test]
+    fn fences_survive_backticks_in_output() {
+        let mut doc = String::new();
+        push_fenced_section(&mut doc, "Stdout", "text", "```rust\nfn main() {}\n```\n");
+        assert_eq!(
+            doc,
+            "\n## Stdout\n\n````text\n```rust\nfn main() {}\n```\n````\n"
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! Locating a ticket's session logs for `workflow logs`.
+
+use crate::layout::WorkflowLayout;
+use crate::log_format::LIVE_LOG_TITLE;
+use crate::manifest::WorkflowManifest;
+use crate::state::SessionPhase;
+use anyhow::Result;
+use std::io::Read;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone)]
+pub struct SessionLogQuery {
+    pub manifest_path: PathBuf,
+    pub base_dir: Option<PathBuf>,
+    pub artifacts_dir: Option<PathBuf>,
+    /// Root of the artifacts directories; see [`WorkflowLayout::resolve`].
+    pub artifacts_root: Option<PathBuf>,
+    pub ticket_id: String,
+    pub phase: SessionPhase,
+    /// Attempt number; defaults to the latest attempt.
+    pub attempt: Option<i32>,
+}
+
+/// Path of a ticket's session log in the newest ticket directory that has
+/// it, that is the one of the most recent run the ticket wrote that log in.
+/// Fails with the expected path if there is no such log yet.
+pub fn find_session_log(query: &SessionLogQuery) -> Result<PathBuf> {
+    let mut manifest = WorkflowManifest::load(&query.manifest_path)?;
+    manifest.base_dir = query.base_dir.clone();
+    manifest.ticket(&query.ticket_id)?;
+    let layout = WorkflowLayout::resolve(
+        &manifest,
+        query.artifacts_dir.as_deref(),
+        query.artifacts_root.as_deref(),
+    )
+    .with_log_format(manifest.log_format.unwrap_or_default());
+    let phase = query.phase.as_str();
+    let ticket_dirs = layout.all_ticket_dirs(&query.ticket_id);
+    // A later run may have stopped before this phase, or a resumed run may
+    // have started over at attempt 1, so look further back.
+    let found = ticket_dirs.iter().rev().find_map(|ticket_dir| {
+        let logs = WorkflowLayout::attempt_log_files(ticket_dir, query.phase);
+        match query.attempt {
+            Some(attempt) => logs.into_iter().find(|(number, _)| *number == attempt),
+            None => logs.into_iter().last(),
+        }
+    });
+    if let Some((_, path)) = found {
+        return Ok(path);
+    }
+    let Some(ticket_dir) = ticket_dirs.last() else {
+        anyhow::bail!(
+            "ticket {} has no {phase} logs yet; expected them in {}",
+            query.ticket_id,
+            layout.ticket_dir(&query.ticket_id).display()
+        );
+    };
+    let attempt = query.attempt.unwrap_or(1);
+    let expected = ticket_dir.join(format!(
+        "{phase}.{attempt}.{}",
+        layout.log_format().extension()
+    ));
+    anyhow::bail!(
+        "ticket {} has no {phase} log for attempt {attempt}; expected {}",
+        query.ticket_id,
+        expected.display()
+    )
+}
+
+/// Whether `log` is a session log whose session is still running, or whose
+/// run was killed before the finished log replaced it. Reads only the first
+/// line.
+pub fn is_live_session_log(log: impl Read) -> std::io::Result<bool> {
+    let limit = u64::try_from(LIVE_LOG_TITLE.len() + 1).unwrap_or(u64::MAX);
+    let mut title = Vec::new();
+    log.take(limit).read_to_end(&mut title)?;
+    Ok(title.strip_suffix(b"\n") == Some(LIVE_LOG_TITLE.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn finds_the_latest_attempt_in_the_newest_run() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest_path = dir.path().join("demo.yaml");
+        fs::write(
+            &manifest_path,
+            "tickets:\n  - id: T1\n    summary: One\n  - id: T2\n    summary: Two\n",
+        )
+        .expect("write manifest");
+        let flat = WorkflowLayout::new(dir.path().join("artifacts"));
+        for run_id in ["20260101T000000Z", "20260102T000000Z"] {
+            let run = flat.clone().start_run(run_id).expect("run");
+            run.ensure_ticket_dir("T1").expect("ticket dir");
+            for attempt in [1, 2] {
+                fs::write(run.worker_log_path("T1", attempt), "log").expect("write log");
+            }
+        }
+        let query = SessionLogQuery {
+            manifest_path,
+            base_dir: None,
+            artifacts_dir: Some(flat.root().to_path_buf()),
+            artifacts_root: None,
+            ticket_id: "T1".to_string(),
+            phase: SessionPhase::Worker,
+            attempt: None,
+        };
+
+        let newest = flat.run_dirs().pop().expect("run dir").join("ticket-T1");
+        // A newer run that stopped before the worker wrote a log.
+        flat.clone()
+            .start_run("20260103T000000Z")
+            .expect("run")
+            .ensure_ticket_dir("T1")
+            .expect("ticket dir");
+        assert_eq!(
+            find_session_log(&query).expect("latest"),
+            newest.join("worker.2.md")
+        );
+        let first = SessionLogQuery {
+            attempt: Some(1),
+            ..query.clone()
+        };
+        assert_eq!(
+            find_session_log(&first).expect("attempt 1"),
+            newest.join("worker.1.md")
+        );
+
+        let review = SessionLogQuery {
+            phase: SessionPhase::Review,
+            ..query.clone()
+        };
+        let message = find_session_log(&review)
+            .expect_err("no review")
+            .to_string();
+        let newer = flat.run_dirs().pop().expect("run dir").join("ticket-T1");
+        assert!(message.contains(&newer.join("review.1.md").display().to_string()));
+        let unknown = SessionLogQuery {
+            ticket_id: "T9".to_string(),
+            ..query
+        };
+        let message = find_session_log(&unknown).expect_err("unknown").to_string();
+        assert!(message.contains("T1, T2"));
+    }
+
+    #[test]
+    fn tells_live_logs_from_finished_ones() {
+        let live = format!("{LIVE_LOG_TITLE}\n\nStarted: now\n");
+        assert!(is_live_session_log(live.as_bytes()).expect("read"));
+        assert!(!is_live_session_log(&b"# Codex session\n\n- Started: now\n"[..]).expect("read"));
+        assert!(!is_live_session_log(&b""[..]).expect("read"));
+    }
+}
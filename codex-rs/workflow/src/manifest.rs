@@ -1,56 +1,377 @@
-use anyhow::Context;
+use crate::error::WorkflowError;
+use crate::log_format::LogFormat;
+use crate::template;
+use crate::validate::ManifestIssue;
+use crate::validate::unknown_fields;
+use schemars::JsonSchema;
 use serde::Deserialize;
-use std::collections::HashSet;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::fs;
+use std::path::Component;
 use std::path::Path;
 use std::path::PathBuf;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct WorkflowManifest {
     #[serde(skip)]
     pub source_path: PathBuf,
+    /// Overrides the manifest's directory as the base for relative
+    /// `working_dir`s and the default artifacts directory.
+    #[serde(skip)]
+    pub base_dir: Option<PathBuf>,
     #[serde(default)]
     pub name: Option<String>,
     #[serde(default)]
     pub overview: Option<String>,
     #[serde(default)]
     pub tickets: Vec<TicketSpec>,
+    /// Ticket fields every ticket that does not set them inherits.
+    #[serde(default)]
+    pub defaults: TicketDefaults,
+    /// Run each review as a continuation of the worker's codex session so the
+    /// reviewer sees the full conversation rather than only the worker log.
+    #[serde(default)]
+    pub review_resumes_worker_session: bool,
+    /// Continue the ticket's previous worker session when a worker is rerun,
+    /// whether by a retry or by a later `--resume`.
+    #[serde(default)]
+    pub worker_resumes_session: bool,
+    /// Point the reviewer at the worker's diff since the commit the ticket
+    /// started from, commits included, instead of the whole working tree.
+    #[serde(default)]
+    pub review_against_diff: bool,
+    /// How many times a failed worker session is rerun before the ticket fails.
+    #[serde(default)]
+    pub max_retries: i32,
+    /// How many times a review with a `changes_requested` verdict sends the
+    /// ticket back to the worker before the ticket fails.
+    #[serde(default)]
+    pub max_reworks: i32,
+    /// Format of the session logs; `--log-format` takes precedence.
+    #[serde(default)]
+    pub log_format: Option<LogFormat>,
+    /// Only retry worker sessions that exited with one of these codes. Empty
+    /// means any failure is retried.
+    #[serde(default)]
+    pub retry_on_exit_codes: Vec<i32>,
+    /// Factor each worker retry multiplies the session timeout by, so a
+    /// ticket that is slow rather than stuck gets more time. Defaults to 1.0,
+    /// a constant timeout; `--timeout-backoff` takes precedence.
+    #[serde(default)]
+    pub timeout_backoff: Option<f64>,
+    /// Seconds a worker session may run before it is terminated, with every
+    /// process it started. `--worker-timeout` and `--session-timeout` take
+    /// precedence.
+    #[serde(default)]
+    pub worker_timeout: Option<u64>,
+    /// The same for review sessions, which usually need far less time.
+    /// `--review-timeout` and `--session-timeout` take precedence.
+    #[serde(default)]
+    pub review_timeout: Option<u64>,
+    /// codex config profile sessions run with (`codex exec --profile`) unless
+    /// their ticket or `--profile` names another, e.g. to run this workflow
+    /// against its own API account.
+    #[serde(default)]
+    pub config_profile: Option<String>,
+    /// `key=value` config overrides every session gets, as with `-c`, applied
+    /// on top of the profile. `-c` flags given to `workflow run` come last
+    /// and win over both.
+    #[serde(default)]
+    pub config_overrides: Vec<String>,
+    /// Whether sessions get `--skip-git-repo-check`, letting codex run in
+    /// working directories outside a git repository. Defaults to true; set it
+    /// to false to have codex refuse those. `--require-git-repo` takes
+    /// precedence.
+    #[serde(default)]
+    pub skip_git_repo_check: Option<bool>,
+    /// Estimated tokens a session's prompt may take before the run warns
+    /// that it will likely overflow the model's context, or fails the ticket
+    /// with `--strict-context`. `--prompt-token-budget` takes precedence.
+    #[serde(default)]
+    pub prompt_token_budget: Option<i64>,
+    /// Where to report finished tickets and runs.
+    #[serde(default)]
+    pub notifications: Notifications,
+    /// Branch handling in the tickets' working directories.
+    #[serde(default)]
+    pub git: GitSettings,
+    /// Keys serde ignored while parsing, reported by `validate_full`.
+    #[serde(skip)]
+    pub(crate) unknown_fields: Vec<String>,
 }
 
 impl WorkflowManifest {
-    pub fn load(path: &Path) -> anyhow::Result<Self> {
-        let contents = fs::read_to_string(path)
-            .with_context(|| format!("failed to read workflow manifest {}", path.display()))?;
+    pub fn load(path: &Path) -> Result<Self, WorkflowError> {
+        let manifest = Self::parse(path)?;
+        manifest.validate()?;
+        Ok(manifest)
+    }
+
+    /// JSON Schema of the manifest file format, for editors and CI checks.
+    /// It covers YAML and TOML manifests alike.
+    pub fn json_schema() -> serde_json::Value {
+        serde_json::to_value(schemars::schema_for!(WorkflowManifest)).unwrap_or_default()
+    }
+
+    /// Reads and expands the manifest without checking it; see
+    /// [`WorkflowManifest::validate_full`] for the checks `load` would apply.
+    pub fn parse(path: &Path) -> Result<Self, WorkflowError> {
+        let contents = fs::read_to_string(path).map_err(|source| WorkflowError::ManifestRead {
+            path: path.to_path_buf(),
+            source,
+        })?;
         let ext = path
             .extension()
             .and_then(|s| s.to_str())
             .unwrap_or_default()
             .to_ascii_lowercase();
+        let parse_error =
+            |source: Box<dyn std::error::Error + Send + Sync>| WorkflowError::ManifestParse {
+                path: path.to_path_buf(),
+                source,
+            };
         let mut manifest: WorkflowManifest = match ext.as_str() {
-            "yml" | "yaml" => serde_yaml::from_str(&contents).context("parse workflow manifest")?,
-            "toml" | "tml" => toml::from_str(&contents).context("parse workflow manifest")?,
+            "yml" | "yaml" => {
+                serde_yaml::from_str(&contents).map_err(|err| parse_error(err.into()))?
+            }
+            "toml" | "tml" => toml::from_str(&contents).map_err(|err| parse_error(err.into()))?,
             _ => serde_yaml::from_str(&contents)
                 .or_else(|_| toml::from_str(&contents))
-                .context("parse workflow manifest (yaml or toml)")?,
+                .map_err(|err| parse_error(err.into()))?,
+        };
+        // Untyped copy, only used to spot keys the typed parse ignored.
+        let raw: Option<serde_json::Value> = match ext.as_str() {
+            "toml" | "tml" => toml::from_str(&contents).ok(),
+            _ => serde_yaml::from_str(&contents)
+                .ok()
+                .or_else(|| toml::from_str(&contents).ok()),
         };
         manifest.source_path = path.to_path_buf();
-        manifest.validate()?;
+        manifest.unknown_fields = raw.as_ref().map(unknown_fields).unwrap_or_default();
+        manifest.apply_defaults(raw.as_ref());
+        manifest.split_dependency_phases();
+        manifest.expand_matrix()?;
+        manifest.read_ticket_files()?;
         Ok(manifest)
     }
 
-    fn validate(&self) -> anyhow::Result<()> {
-        if self.tickets.is_empty() {
-            anyhow::bail!("workflow manifest must contain at least one ticket");
+    /// Reads each ticket's `prompt_file`, `review_prompt_file` and
+    /// `requirements_file`. Files that do not exist are left for
+    /// [`WorkflowManifest::validate_full`] to report.
+    fn read_ticket_files(&mut self) -> Result<(), WorkflowError> {
+        let manifest_dir = self.manifest_dir();
+        let read = |path: &Path| {
+            let path = normalize_path(&manifest_dir.join(path));
+            if !path.is_file() {
+                return Ok(None);
+            }
+            fs::read_to_string(&path)
+                .map(Some)
+                .map_err(|source| WorkflowError::ManifestRead { path, source })
+        };
+        for ticket in &mut self.tickets {
+            if ticket.prompt.is_none()
+                && let Some(path) = &ticket.prompt_file
+            {
+                ticket.prompt = read(path)?;
+            }
+            if ticket.review_prompt.is_none()
+                && let Some(path) = &ticket.review_prompt_file
+            {
+                ticket.review_prompt = read(path)?;
+            }
+            if let Some(path) = &ticket.requirements_file
+                && let Some(text) = read(path)?
+            {
+                ticket.requirements.extend(
+                    text.lines()
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty())
+                        .map(|line| Requirement::Text(line.to_string())),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Fills in the fields each ticket leaves out from `defaults`. A ticket's
+    /// `env` is merged into the default one instead, its own values winning.
+    /// Presence is judged from the file, so a ticket can opt out of a default
+    /// list with an empty one.
+    fn apply_defaults(&mut self, raw: Option<&serde_json::Value>) {
+        let raw_tickets = raw
+            .and_then(|raw| raw.get("tickets"))
+            .and_then(|tickets| tickets.as_array());
+        let defaults = &self.defaults;
+        for (index, ticket) in self.tickets.iter_mut().enumerate() {
+            let raw_ticket = raw_tickets.and_then(|tickets| tickets.get(index));
+            let unset = |key: &str| raw_ticket.is_none_or(|raw| raw.get(key).is_none());
+            if unset("working_dir") && defaults.working_dir.is_some() {
+                ticket.working_dir = defaults.working_dir.clone();
+            }
+            if unset("review_working_dir") && defaults.review_working_dir.is_some() {
+                ticket.review_working_dir = defaults.review_working_dir.clone();
+            }
+            if unset("log_dir") && defaults.log_dir.is_some() {
+                ticket.log_dir = defaults.log_dir.clone();
+            }
+            if unset("prompt") && defaults.prompt.is_some() {
+                ticket.prompt = defaults.prompt.clone();
+            }
+            if unset("review_prompt") && defaults.review_prompt.is_some() {
+                ticket.review_prompt = defaults.review_prompt.clone();
+            }
+            for (key, value) in &defaults.env {
+                ticket
+                    .env
+                    .entry(key.clone())
+                    .or_insert_with(|| value.clone());
+            }
+            if let Some(extra_args) = defaults.extra_args.as_ref().filter(|_| unset("extra_args")) {
+                ticket.extra_args = extra_args.clone();
+            }
+            if let Some(artifacts) = defaults
+                .expected_artifacts
+                .as_ref()
+                .filter(|_| unset("expected_artifacts"))
+            {
+                ticket.expected_artifacts = artifacts.clone();
+            }
+            if let Some(inputs) = defaults.inputs.as_ref().filter(|_| unset("inputs")) {
+                ticket.inputs = inputs.clone();
+            }
+            if let Some(allow_failure) = defaults.allow_failure.filter(|_| unset("allow_failure")) {
+                ticket.allow_failure = allow_failure;
+            }
+            if let Some(policy) = defaults
+                .on_review_failure
+                .filter(|_| unset("on_review_failure"))
+            {
+                ticket.on_review_failure = policy;
+            }
         }
-        let mut seen: HashSet<&str> = HashSet::new();
-        for ticket in &self.tickets {
-            if !seen.insert(ticket.id.as_str()) {
-                anyhow::bail!("duplicate ticket id {}", ticket.id);
+    }
+
+    /// Strips the `:worker` and `:complete` suffixes from `depends_on`,
+    /// noting the `:worker` ones in `worker_dependencies`. An entry that is a
+    /// ticket id as it stands is left alone, colon or not.
+    fn split_dependency_phases(&mut self) {
+        let ids: BTreeSet<String> = self
+            .tickets
+            .iter()
+            .map(|ticket| ticket.id.clone())
+            .collect();
+        for ticket in &mut self.tickets {
+            for dependency in &mut ticket.depends_on {
+                if ids.contains(dependency.as_str()) {
+                    continue;
+                }
+                let Some((id, phase)) = dependency.rsplit_once(':') else {
+                    continue;
+                };
+                let id = id.to_string();
+                match phase {
+                    "worker" => {
+                        ticket.worker_dependencies.insert(id.clone());
+                    }
+                    "complete" => {}
+                    _ => continue,
+                }
+                *dependency = id;
+            }
+        }
+    }
+
+    /// Replaces every ticket with a `matrix` by one ticket per combination of
+    /// its values. A dependency on a matrix ticket becomes a dependency on
+    /// each of its instances that agrees with the dependent on the variables
+    /// they share, so `build-{{service}}` waits only for the matching
+    /// `test-{{service}}`, while a plain ticket waits for all instances.
+    fn expand_matrix(&mut self) -> Result<(), WorkflowError> {
+        // Matrix ticket id -> (instance id, matrix values) of each instance.
+        let mut instances: BTreeMap<String, Vec<MatrixInstance>> = BTreeMap::new();
+        let mut tickets = Vec::with_capacity(self.tickets.len());
+        for ticket in std::mem::take(&mut self.tickets) {
+            if ticket.matrix.is_empty() {
+                tickets.push(ticket);
+                continue;
+            }
+            if let Some((name, _)) = ticket.matrix.iter().find(|(_, values)| values.is_empty()) {
+                return Err(WorkflowError::Validation {
+                    issues: vec![ManifestIssue::error(
+                        Some(&ticket.id),
+                        Some("matrix"),
+                        format!(
+                            "matrix variable {name} of ticket {} has no values",
+                            ticket.id
+                        ),
+                    )],
+                });
+            }
+            let expansions = instances.entry(ticket.id.clone()).or_default();
+            for vars in matrix_combinations(&ticket.matrix) {
+                let instance = ticket.instantiate(vars);
+                expansions.push((instance.id.clone(), instance.matrix_values.clone()));
+                tickets.push(instance);
+            }
+        }
+        for ticket in &mut tickets {
+            let mut depends_on = Vec::new();
+            let mut worker_dependencies = BTreeSet::new();
+            for dependency in &ticket.depends_on {
+                let on_worker = ticket.worker_dependencies.contains(dependency);
+                let Some(expansions) = instances.get(dependency) else {
+                    depends_on.push(dependency.clone());
+                    if on_worker {
+                        worker_dependencies.insert(dependency.clone());
+                    }
+                    continue;
+                };
+                let agrees = |vars: &BTreeMap<String, String>| {
+                    vars.iter().all(|(name, value)| {
+                        ticket
+                            .matrix_values
+                            .get(name)
+                            .is_none_or(|own| own == value)
+                    })
+                };
+                let matching: Vec<&String> = expansions
+                    .iter()
+                    .filter(|(_, vars)| agrees(vars))
+                    .map(|(id, _)| id)
+                    .collect();
+                let instance_ids: Vec<String> = if matching.is_empty() {
+                    expansions.iter().map(|(id, _)| id.clone()).collect()
+                } else {
+                    matching.into_iter().cloned().collect()
+                };
+                if on_worker {
+                    worker_dependencies.extend(instance_ids.iter().cloned());
+                }
+                depends_on.extend(instance_ids);
             }
+            ticket.depends_on = depends_on;
+            ticket.worker_dependencies = worker_dependencies;
         }
+        self.tickets = tickets;
         Ok(())
     }
 
+    fn validate(&self) -> Result<(), WorkflowError> {
+        let issues: Vec<ManifestIssue> = self
+            .structural_issues()
+            .into_iter()
+            .filter(ManifestIssue::is_error)
+            .collect();
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(WorkflowError::Validation { issues })
+        }
+    }
+
     pub fn manifest_dir(&self) -> PathBuf {
         self.source_path
             .parent()
@@ -58,6 +379,68 @@ impl WorkflowManifest {
             .unwrap_or_else(|| PathBuf::from("."))
     }
 
+    /// Directory that relative paths resolve against: the `base_dir` override
+    /// if set, otherwise the directory containing the manifest.
+    pub fn base_dir(&self) -> PathBuf {
+        self.base_dir.clone().unwrap_or_else(|| self.manifest_dir())
+    }
+
+    /// The ticket with this id; the error lists the valid ids.
+    pub fn ticket(&self, ticket_id: &str) -> anyhow::Result<&TicketSpec> {
+        match self.tickets.iter().find(|ticket| ticket.id == ticket_id) {
+            Some(ticket) => Ok(ticket),
+            None => {
+                let ids: Vec<&str> = self
+                    .tickets
+                    .iter()
+                    .map(|ticket| ticket.id.as_str())
+                    .collect();
+                anyhow::bail!(
+                    "unknown ticket {ticket_id}; the manifest has: {}",
+                    ids.join(", ")
+                )
+            }
+        }
+    }
+
+    /// Ids of the tickets with `allow_failure` set.
+    pub fn allowed_failures(&self) -> Vec<String> {
+        self.tickets
+            .iter()
+            .filter(|ticket| ticket.allow_failure)
+            .map(|ticket| ticket.id.clone())
+            .collect()
+    }
+
+    /// First line of each ticket's summary, by ticket id.
+    pub fn summaries(&self) -> BTreeMap<String, String> {
+        self.tickets
+            .iter()
+            .map(|ticket| {
+                let summary = ticket.summary.lines().next().unwrap_or_default();
+                (ticket.id.clone(), summary.to_string())
+            })
+            .collect()
+    }
+
+    /// Ticket ids with every ticket after its dependencies, otherwise in
+    /// manifest order. Tickets caught in a dependency cycle come last.
+    pub fn dependency_order(&self) -> Vec<&str> {
+        let mut ordered: Vec<&str> = Vec::with_capacity(self.tickets.len());
+        let mut remaining: Vec<&TicketSpec> = self.tickets.iter().collect();
+        while !remaining.is_empty() {
+            let ready = remaining.iter().position(|ticket| {
+                ticket
+                    .depends_on
+                    .iter()
+                    .all(|dependency| ordered.contains(&dependency.as_str()))
+            });
+            let ticket = remaining.remove(ready.unwrap_or(0));
+            ordered.push(&ticket.id);
+        }
+        ordered
+    }
+
     pub fn workflow_name(&self) -> String {
         if let Some(name) = &self.name {
             return name.clone();
@@ -70,39 +453,482 @@ impl WorkflowManifest {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct TicketSpec {
     pub id: String,
     pub summary: String,
+    /// Background for the ticket beyond its one-line summary, included in
+    /// its worker and review prompts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
     #[serde(default)]
-    pub requirements: Vec<String>,
+    pub requirements: Vec<Requirement>,
     #[serde(default)]
     pub working_dir: Option<PathBuf>,
+    /// Directory the review session runs in, if not the worker's
+    /// `working_dir`; relative paths resolve the same way. It only has to
+    /// exist once the review is due. For a ticket in a worktree, a
+    /// directory inside the worker's checkout is taken from the worktree.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review_working_dir: Option<PathBuf>,
+    /// codex config profile this ticket's sessions run with (`codex exec
+    /// --profile`), in place of `--profile` and the manifest's
+    /// `config_profile`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Binary this ticket's review session runs in place of the worker's,
+    /// e.g. a wrapper script or another codex version. Paths with a
+    /// directory part resolve against the manifest's directory; bare names
+    /// are looked up on `PATH`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reviewer_bin: Option<PathBuf>,
+    /// Directory for this ticket's logs, prompts, outputs and patches in
+    /// place of the artifacts root, e.g. a shared volume. Relative paths
+    /// resolve against the manifest's directory.
+    #[serde(default)]
+    pub log_dir: Option<PathBuf>,
     #[serde(default)]
     pub prompt: Option<String>,
     #[serde(default)]
     pub review_prompt: Option<String>,
+    /// File the worker prompt is read from when `prompt` is unset, relative
+    /// to the manifest's directory. It is read as is when the manifest loads.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_file: Option<PathBuf>,
+    /// File the review prompt is read from when `review_prompt` is unset,
+    /// like `prompt_file`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review_prompt_file: Option<PathBuf>,
+    /// File with one more requirement per non-empty line, added after
+    /// `requirements`. Relative to the manifest's directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requirements_file: Option<PathBuf>,
+    /// Extra environment variables for this ticket's sessions. These take
+    /// precedence over entries loaded from `--secrets-file`.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Extra arguments for codex exec, appended after any run-wide `extra_args`.
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+    /// Ids of tickets that must complete before this one starts. An id
+    /// followed by `:worker`, e.g. `T1:worker`, only waits for that ticket's
+    /// worker to succeed, so this one runs while it is still in review;
+    /// `T1:complete` is the same as `T1`.
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Entries of `depends_on` that were given as `ID:worker`. Loading a
+    /// manifest strips the suffixes, leaving ticket ids in `depends_on`.
+    #[serde(skip)]
+    pub worker_dependencies: BTreeSet<String>,
+    /// Files the ticket must leave behind, relative to its working directory.
+    /// The ticket is blocked if any is missing once its worker or review
+    /// succeeds.
+    #[serde(default)]
+    pub expected_artifacts: Vec<PathBuf>,
+    /// Globs of files, relative to the working directory, whose contents
+    /// count towards the ticket's fingerprint for `--skip-unchanged`.
+    #[serde(default)]
+    pub inputs: Vec<PathBuf>,
+    /// A `Failed` or `Blocked` outcome of this ticket does not make the run
+    /// exit with an error.
+    #[serde(default)]
+    pub allow_failure: bool,
+    /// What happens when the reviewer still requests changes once the ticket
+    /// has used up its reworks.
+    #[serde(default)]
+    pub on_review_failure: ReviewFailurePolicy,
+    /// Runs the ticket once per combination of these values, as tickets with
+    /// ids like `<id>-<value>`. The values replace `{{ name }}` placeholders
+    /// in the summary, prompts, requirements, working directory, env and
+    /// extra arguments.
+    #[serde(default)]
+    pub matrix: BTreeMap<String, Vec<String>>,
+    /// The matrix values this ticket was expanded with; empty for tickets
+    /// without a matrix.
+    #[serde(skip)]
+    pub matrix_values: BTreeMap<String, String>,
+}
+
+/// The manifest's `defaults`: values for ticket fields that tickets leave
+/// out. Each has the meaning of the ticket field of the same name.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub struct TicketDefaults {
+    #[serde(default)]
+    pub working_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub review_working_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub log_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub prompt: Option<String>,
+    #[serde(default)]
+    pub review_prompt: Option<String>,
+    /// Merged with each ticket's `env`, whose values win.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub extra_args: Option<Vec<String>>,
+    #[serde(default)]
+    pub expected_artifacts: Option<Vec<PathBuf>>,
+    #[serde(default)]
+    pub inputs: Option<Vec<PathBuf>>,
+    #[serde(default)]
+    pub allow_failure: Option<bool>,
+    #[serde(default)]
+    pub on_review_failure: Option<ReviewFailurePolicy>,
+}
+
+/// Where a run reports its progress besides the artifacts directory.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub struct Notifications {
+    /// Receives a POST of each ticket reaching a final status and of the end
+    /// of the run, with the event as written to `events.jsonl` as the body.
+    /// `--webhook-url` takes precedence.
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    /// A program and its arguments, e.g. `terminal-notifier`, run for the
+    /// same events as the webhook with the event as JSON on stdin.
+    /// `{{ ticket.id }}`, `{{ status }}`, `{{ note }}` and `{{ workflow.name }}`
+    /// in the arguments are filled in. Failures are recorded in
+    /// `events.jsonl` and never fail the run. The `workflow.notify_command`
+    /// config key takes precedence.
+    #[serde(default)]
+    pub notify_command: Vec<String>,
+}
+
+/// How a run uses git in each ticket's working directory.
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+pub struct GitSettings {
+    /// Check out a branch of the ticket's own before its worker starts,
+    /// reusing it on retries and reworks. Tickets sharing a working
+    /// directory then only run in parallel with `use_worktrees`.
+    #[serde(default)]
+    pub branch_per_ticket: bool,
+    /// Name of the ticket branches, with `{{ workflow.name }}` and
+    /// `{{ ticket.id }}` placeholders. Defaults to
+    /// `workflow/{{ workflow.name }}/{{ ticket.id }}`.
+    #[serde(default)]
+    pub branch_template: Option<String>,
+    /// Branch or commit new ticket branches and worktrees start from.
+    /// Defaults to what is checked out when they are created.
+    #[serde(default)]
+    pub base: Option<String>,
+    /// Check the base out again once a ticket completes, instead of leaving
+    /// its branch checked out.
+    #[serde(default)]
+    pub return_to_base: bool,
+    /// Once a ticket completes, commit the changes in its working
+    /// directory, leaving out paths that already had changes when it
+    /// started; `--no-autocommit` turns this off for a run.
+    #[serde(default)]
+    pub autocommit: bool,
+    /// Message of those commits, with `{{ ticket.id }}`,
+    /// `{{ ticket.summary }}` and `{{ workflow.name }}` placeholders.
+    /// Defaults to the ticket id and summary and a line naming the workflow.
+    #[serde(default)]
+    pub commit_message_template: Option<String>,
+    /// Run each ticket in a `git worktree` of its own under its artifacts
+    /// directory, so tickets sharing a working directory can run in
+    /// parallel. With `autocommit` the ticket's work outlives the worktree on
+    /// its branch, which the worktree checks out even without
+    /// `branch_per_ticket`. Every worktree starts from `base`, so a ticket's
+    /// worktree does not contain the commits of the tickets it depends on.
+    #[serde(default)]
+    pub use_worktrees: bool,
+    /// Leave each ticket's worktree in place once the ticket finishes
+    /// instead of removing it.
+    #[serde(default)]
+    pub keep_worktrees: bool,
+    /// Once a ticket completes on its branch, push the branch to `remote`
+    /// and open a GitHub pull request for it, or update the one already
+    /// open. The token is read from `GITHUB_TOKEN` or `GH_TOKEN`. Failures
+    /// only add a warning to the ticket's note.
+    #[serde(default)]
+    pub open_pr: bool,
+    /// Remote the ticket branches are pushed to. Defaults to `origin`.
+    #[serde(default)]
+    pub remote: Option<String>,
+    /// Repository of the pull requests as `owner/name`. Defaults to the one
+    /// the remote's URL names.
+    #[serde(default)]
+    pub pr_repo: Option<String>,
+    /// Branch the pull requests merge into. Defaults to the branch each
+    /// ticket branch was created from.
+    #[serde(default)]
+    pub pr_base: Option<String>,
+    /// Title of the pull requests, with `{{ ticket.id }}`,
+    /// `{{ ticket.summary }}` and `{{ workflow.name }}` placeholders.
+    /// Defaults to the ticket id and summary.
+    #[serde(default)]
+    pub pr_title_template: Option<String>,
+    /// Body of the pull requests, with the title's placeholders plus
+    /// `{{ ticket.requirements }}`, `{{ review.summary }}` and
+    /// `{{ workflow.artifacts }}` (Markdown lists). Defaults to the ticket's
+    /// summary, requirements, review summary and artifact paths.
+    #[serde(default)]
+    pub pr_body_template: Option<String>,
+    /// GitHub API to open the pull requests with. Defaults to
+    /// `https://api.github.com`; GitHub Enterprise serves it under
+    /// `https://<host>/api/v3`.
+    #[serde(default)]
+    pub github_api_url: Option<String>,
+}
+
+/// How strictly a failed review gates the rest of the workflow.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReviewFailurePolicy {
+    /// The ticket fails, and tickets depending on it are blocked.
+    #[default]
+    Fail,
+    /// The ticket completes with the reviewer's blocking issues in its note,
+    /// so its dependents still run.
+    Complete,
+    /// The ticket is blocked, like a failure that `--unblock` can send back
+    /// to the worker.
+    Block,
+}
+
+/// A ticket requirement. Plain strings are accepted for brevity; the
+/// `{ id, text }` form lets reviewers report on each requirement by id.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum Requirement {
+    Text(String),
+    Structured { id: String, text: String },
+}
+
+impl Requirement {
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            Requirement::Text(_) => None,
+            Requirement::Structured { id, .. } => Some(id),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        match self {
+            Requirement::Text(text) | Requirement::Structured { text, .. } => text,
+        }
+    }
+
+    fn map_text(&self, f: impl FnOnce(&str) -> String) -> Self {
+        match self {
+            Requirement::Text(text) => Requirement::Text(f(text)),
+            Requirement::Structured { id, text } => Requirement::Structured {
+                id: id.clone(),
+                text: f(text),
+            },
+        }
+    }
+
+    /// Renders the requirement for a prompt, prefixed with its id if it has one.
+    pub fn render(&self) -> String {
+        match self.id() {
+            Some(id) => format!("[{id}] {}", self.text()),
+            None => self.text().to_string(),
+        }
+    }
 }
 
 impl TicketSpec {
+    pub fn requirement_ids(&self) -> Vec<&str> {
+        self.requirements
+            .iter()
+            .filter_map(Requirement::id)
+            .collect()
+    }
+
+    /// A concrete ticket for one combination of matrix values.
+    fn instantiate(&self, vars: BTreeMap<String, String>) -> Self {
+        let render = |text: &str| template::render(text, &vars);
+        let suffix: Vec<&str> = vars.values().map(String::as_str).collect();
+        Self {
+            id: format!("{}-{}", self.id, suffix.join("-")),
+            summary: render(&self.summary),
+            description: self.description.as_deref().map(render),
+            requirements: self
+                .requirements
+                .iter()
+                .map(|requirement| requirement.map_text(render))
+                .collect(),
+            working_dir: self
+                .working_dir
+                .as_ref()
+                .map(|dir| PathBuf::from(render(&dir.to_string_lossy()))),
+            review_working_dir: self
+                .review_working_dir
+                .as_ref()
+                .map(|dir| PathBuf::from(render(&dir.to_string_lossy()))),
+            profile: self.profile.as_deref().map(render),
+            reviewer_bin: self
+                .reviewer_bin
+                .as_ref()
+                .map(|bin| PathBuf::from(render(&bin.to_string_lossy()))),
+            log_dir: self
+                .log_dir
+                .as_ref()
+                .map(|dir| PathBuf::from(render(&dir.to_string_lossy()))),
+            prompt: self.prompt.as_deref().map(render),
+            review_prompt: self.review_prompt.as_deref().map(render),
+            prompt_file: self
+                .prompt_file
+                .as_ref()
+                .map(|path| PathBuf::from(render(&path.to_string_lossy()))),
+            review_prompt_file: self
+                .review_prompt_file
+                .as_ref()
+                .map(|path| PathBuf::from(render(&path.to_string_lossy()))),
+            requirements_file: self
+                .requirements_file
+                .as_ref()
+                .map(|path| PathBuf::from(render(&path.to_string_lossy()))),
+            env: self
+                .env
+                .iter()
+                .map(|(key, value)| (key.clone(), render(value)))
+                .collect(),
+            extra_args: self.extra_args.iter().map(|arg| render(arg)).collect(),
+            depends_on: self.depends_on.clone(),
+            worker_dependencies: self.worker_dependencies.clone(),
+            allow_failure: self.allow_failure,
+            on_review_failure: self.on_review_failure,
+            expected_artifacts: self
+                .expected_artifacts
+                .iter()
+                .map(|path| PathBuf::from(render(&path.to_string_lossy())))
+                .collect(),
+            inputs: self
+                .inputs
+                .iter()
+                .map(|path| PathBuf::from(render(&path.to_string_lossy())))
+                .collect(),
+            matrix: BTreeMap::new(),
+            matrix_values: vars.clone(),
+        }
+    }
+
+    /// `working_dir` resolved against `manifest_dir`, with `.` and `..`
+    /// segments folded away so YAML and TOML spellings of a path agree.
     pub fn resolved_working_dir(&self, manifest_dir: &Path) -> PathBuf {
         match &self.working_dir {
-            Some(path) if path.is_absolute() => path.clone(),
-            Some(path) => manifest_dir.join(path),
-            None => manifest_dir.to_path_buf(),
+            Some(path) => normalize_path(&manifest_dir.join(path)),
+            None => normalize_path(manifest_dir),
         }
     }
+
+    /// `review_working_dir` resolved like [`TicketSpec::resolved_working_dir`].
+    pub fn resolved_review_working_dir(&self, manifest_dir: &Path) -> Option<PathBuf> {
+        self.review_working_dir
+            .as_ref()
+            .map(|path| normalize_path(&manifest_dir.join(path)))
+    }
+
+    /// `reviewer_bin` with a relative path resolved against `manifest_dir`;
+    /// a bare name is left for a `PATH` lookup.
+    pub fn resolved_reviewer_bin(&self, manifest_dir: &Path) -> Option<PathBuf> {
+        self.reviewer_bin.as_ref().map(|bin| {
+            if bin.components().count() > 1 {
+                normalize_path(&manifest_dir.join(bin))
+            } else {
+                bin.clone()
+            }
+        })
+    }
+
+    /// Entries of `expected_artifacts` that do not exist under `working_dir`.
+    pub fn missing_artifacts(&self, working_dir: &Path) -> Vec<PathBuf> {
+        self.expected_artifacts
+            .iter()
+            .filter(|path| !working_dir.join(path).exists())
+            .cloned()
+            .collect()
+    }
+
+    /// The ticket's `log_dir`, if it has one, made absolute.
+    pub fn resolved_log_dir(&self, manifest_dir: &Path) -> Option<PathBuf> {
+        let path = self.log_dir.as_ref()?;
+        Some(if path.is_absolute() {
+            path.clone()
+        } else {
+            manifest_dir.join(path)
+        })
+    }
+}
+
+type MatrixInstance = (String, BTreeMap<String, String>);
+
+/// Every assignment of one value to each matrix variable, in variable order.
+fn matrix_combinations(matrix: &BTreeMap<String, Vec<String>>) -> Vec<BTreeMap<String, String>> {
+    let mut combinations = vec![BTreeMap::new()];
+    for (name, values) in matrix {
+        combinations = combinations
+            .into_iter()
+            .flat_map(|vars| {
+                values.iter().map(move |value| {
+                    let mut vars = vars.clone();
+                    vars.insert(name.clone(), value.clone());
+                    vars
+                })
+            })
+            .collect();
+    }
+    combinations
 }
 
 impl Default for WorkflowManifest {
     fn default() -> Self {
         Self {
             source_path: PathBuf::new(),
+            base_dir: None,
             name: None,
             overview: None,
             tickets: Vec::new(),
+            defaults: TicketDefaults::default(),
+            review_resumes_worker_session: false,
+            worker_resumes_session: false,
+            review_against_diff: false,
+            max_retries: 0,
+            max_reworks: 0,
+            log_format: None,
+            retry_on_exit_codes: Vec::new(),
+            timeout_backoff: None,
+            worker_timeout: None,
+            review_timeout: None,
+            config_profile: None,
+            config_overrides: Vec::new(),
+            skip_git_repo_check: None,
+            prompt_token_budget: None,
+            notifications: Notifications::default(),
+            git: GitSettings::default(),
+            unknown_fields: Vec::new(),
+        }
+    }
+}
+
+/// `path` without `.` segments and with each `..` folded into the segment
+/// before it. Purely lexical, so symlinks are not followed; a leading `..` of
+/// a relative path is kept.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                // `/..` is `/`.
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            other => normalized.push(other.as_os_str()),
         }
     }
+    if normalized.as_os_str().is_empty() {
+        normalized.push(".");
+    }
+    normalized
 }
 
 #[cfg(test)]
@@ -134,4 +960,344 @@ tickets:
         let resolved = ticket.resolved_working_dir(manifest.manifest_dir().as_path());
         assert_eq!(resolved, manifest.manifest_dir());
     }
+
+    #[test]
+    fn working_dirs_resolve_to_the_same_clean_path_in_yaml_and_toml() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let project = dir.path().join("project");
+        fs::create_dir(&project).expect("create project dir");
+        let yaml = r#"
+tickets:
+  - id: sibling
+    summary: Works next to the project
+    working_dir: ../sibling
+  - id: nested
+    summary: Works deep inside it
+    working_dir: services/api/../web/./src
+    review_working_dir: ./services/../review
+"#;
+        let toml = r#"
+[[tickets]]
+id = "sibling"
+summary = "Works next to the project"
+working_dir = "../sibling"
+
+[[tickets]]
+id = "nested"
+summary = "Works deep inside it"
+working_dir = "services/api/../web/./src"
+review_working_dir = "./services/../review"
+"#;
+        fs::write(project.join("demo.yaml"), yaml).expect("write yaml");
+        fs::write(project.join("demo.toml"), toml).expect("write toml");
+
+        for name in ["demo.yaml", "demo.toml"] {
+            let manifest = WorkflowManifest::load(&project.join(name)).expect("load");
+            let manifest_dir = manifest.manifest_dir();
+            let resolved =
+                |index: usize| manifest.tickets[index].resolved_working_dir(manifest_dir.as_path());
+            assert_eq!(resolved(0), dir.path().join("sibling"), "{name}");
+            assert_eq!(resolved(1), project.join("services/web/src"), "{name}");
+            assert_eq!(
+                manifest.tickets[1].resolved_review_working_dir(manifest_dir.as_path()),
+                Some(project.join("review")),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn normalize_path_folds_dot_segments_lexically() {
+        assert_eq!(normalize_path(Path::new("/a/./b/../c")), Path::new("/a/c"));
+        assert_eq!(normalize_path(Path::new("/..")), Path::new("/"));
+        assert_eq!(
+            normalize_path(Path::new("../a/../../b")),
+            Path::new("../../b")
+        );
+        assert_eq!(normalize_path(Path::new("a/..")), Path::new("."));
+    }
+
+    #[test]
+    fn tickets_inherit_the_fields_they_leave_out_from_defaults() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest_path = dir.path().join("demo.yaml");
+        let contents = r#"
+defaults:
+  working_dir: services/{{ service }}
+  review_working_dir: qa
+  env: { RUST_LOG: info, REGION: eu }
+  extra_args: [--full-auto]
+  allow_failure: true
+  on_review_failure: block
+tickets:
+  - id: T1
+    summary: Uses the defaults
+  - id: T2
+    summary: Overrides them
+    working_dir: .
+    review_working_dir: review
+    env: { RUST_LOG: debug }
+    extra_args: []
+    allow_failure: false
+    on_review_failure: fail
+  - id: build
+    summary: Build {{ service }}
+    matrix: { service: [api] }
+"#;
+        fs::write(&manifest_path, contents).expect("write manifest");
+        let manifest = WorkflowManifest::load(&manifest_path).expect("load");
+        assert!(manifest.unknown_fields.is_empty());
+
+        let inherited = &manifest.tickets[0];
+        assert_eq!(
+            inherited.working_dir.as_deref(),
+            Some(Path::new("services/{{ service }}"))
+        );
+        assert_eq!(
+            inherited.review_working_dir.as_deref(),
+            Some(Path::new("qa"))
+        );
+        assert_eq!(inherited.env["RUST_LOG"], "info");
+        assert_eq!(inherited.extra_args, ["--full-auto"]);
+        assert!(inherited.allow_failure);
+        assert_eq!(inherited.on_review_failure, ReviewFailurePolicy::Block);
+
+        let own = &manifest.tickets[1];
+        assert_eq!(own.working_dir.as_deref(), Some(Path::new(".")));
+        assert_eq!(own.review_working_dir.as_deref(), Some(Path::new("review")));
+        assert_eq!(own.env["RUST_LOG"], "debug");
+        assert_eq!(own.env["REGION"], "eu");
+        assert!(own.extra_args.is_empty());
+        assert!(!own.allow_failure);
+        assert_eq!(own.on_review_failure, ReviewFailurePolicy::Fail);
+
+        // Defaults are filled in before matrix tickets are expanded.
+        assert_eq!(manifest.tickets[2].id, "build-api");
+        assert_eq!(
+            manifest.tickets[2].working_dir.as_deref(),
+            Some(Path::new("services/api"))
+        );
+    }
+
+    #[test]
+    fn orders_tickets_after_their_dependencies() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest_path = dir.path().join("demo.yaml");
+        let contents = r#"
+tickets:
+  - id: deploy
+    summary: Deploy
+    depends_on: [build, test]
+  - id: test
+    summary: Test
+    depends_on: [build]
+  - id: docs
+    summary: Docs
+  - id: build
+    summary: Build
+"#;
+        fs::write(&manifest_path, contents).expect("write manifest");
+        let manifest = WorkflowManifest::load(&manifest_path).expect("load");
+        assert_eq!(
+            manifest.dependency_order(),
+            vec!["docs", "build", "test", "deploy"]
+        );
+    }
+
+    #[test]
+    fn accepts_plain_and_structured_requirements() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest_path = dir.path().join("demo.toml");
+        let contents = r#"
+[[tickets]]
+id = "T1"
+summary = "Mixed requirements"
+requirements = [
+  "Plain requirement",
+  { id = "R1", text = "Tracked requirement" },
+]
+"#;
+        fs::write(&manifest_path, contents).expect("write manifest");
+        let manifest = WorkflowManifest::load(&manifest_path).expect("load");
+        let ticket = &manifest.tickets[0];
+        assert_eq!(ticket.requirements[0].render(), "Plain requirement");
+        assert_eq!(ticket.requirements[1].render(), "[R1] Tracked requirement");
+        assert_eq!(ticket.requirement_ids(), vec!["R1"]);
+    }
+
+    #[test]
+    fn rejects_duplicate_requirement_ids() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest_path = dir.path().join("demo.yaml");
+        let contents = r#"
+tickets:
+  - id: T1
+    summary: Duplicate ids
+    requirements:
+      - { id: R1, text: First }
+      - { id: R1, text: Second }
+"#;
+        fs::write(&manifest_path, contents).expect("write manifest");
+        let err = WorkflowManifest::load(&manifest_path).expect_err("duplicate ids");
+        assert!(err.to_string().contains("duplicate requirement id R1"));
+    }
+
+    #[test]
+    fn rejects_unknown_and_cyclic_dependencies() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest_path = dir.path().join("demo.yaml");
+        fs::write(
+            &manifest_path,
+            "tickets:\n  - id: T1\n    summary: One\n    depends_on: [T9]\n",
+        )
+        .expect("write manifest");
+        let err = WorkflowManifest::load(&manifest_path).expect_err("unknown dependency");
+        assert!(err.to_string().contains("depends on unknown ticket T9"));
+        assert!(matches!(
+            &err,
+            WorkflowError::Validation { issues }
+                if issues[0].location.as_deref() == Some("T1.depends_on")
+        ));
+
+        fs::write(
+            &manifest_path,
+            r#"
+tickets:
+  - id: T1
+    summary: One
+    depends_on: [T2]
+  - id: T2
+    summary: Two
+    depends_on: [T1]
+  - id: T3
+    summary: Three
+"#,
+        )
+        .expect("write manifest");
+        let err = WorkflowManifest::load(&manifest_path).expect_err("cycle");
+        assert!(err.to_string().contains("dependency cycle"));
+    }
+
+    #[test]
+    fn reads_prompt_and_requirements_files() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        fs::create_dir(dir.path().join("prompts")).expect("create prompts dir");
+        fs::write(dir.path().join("prompts/api.md"), "Work on api\n").expect("write prompt");
+        fs::write(dir.path().join("prompts/review.md"), "Review it\n").expect("write prompt");
+        fs::write(
+            dir.path().join("prompts/requirements.txt"),
+            "Keep tests green\n\n  Update docs\n",
+        )
+        .expect("write requirements");
+        let manifest_path = dir.path().join("demo.yaml");
+        let contents = r#"
+tickets:
+  - id: T1
+    summary: First
+    requirements: [Be careful]
+    prompt_file: prompts/{{service}}.md
+    review_prompt_file: prompts/review.md
+    requirements_file: prompts/requirements.txt
+    matrix:
+      service: [api]
+  - id: T2
+    summary: Second
+    prompt: Inline wins
+    prompt_file: prompts/missing.md
+"#;
+        fs::write(&manifest_path, contents).expect("write manifest");
+        let manifest = WorkflowManifest::load(&manifest_path).expect("load");
+        let first = &manifest.tickets[0];
+        assert_eq!(first.prompt.as_deref(), Some("Work on api\n"));
+        assert_eq!(first.review_prompt.as_deref(), Some("Review it\n"));
+        let requirements: Vec<&str> = first.requirements.iter().map(Requirement::text).collect();
+        assert_eq!(
+            requirements,
+            vec!["Be careful", "Keep tests green", "Update docs"]
+        );
+        assert_eq!(manifest.tickets[1].prompt.as_deref(), Some("Inline wins"));
+    }
+
+    #[test]
+    fn expands_matrix_tickets_and_fans_out_dependencies() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest_path = dir.path().join("demo.yaml");
+        let contents = r#"
+tickets:
+  - id: test
+    summary: Test {{ service }}
+    working_dir: services/{{service}}
+    matrix:
+      service: [api, billing]
+  - id: deploy
+    summary: Deploy {{service}} to {{env}}
+    depends_on: [test]
+    matrix:
+      service: [api, billing]
+      env: [prod]
+  - id: announce
+    summary: Announce
+    depends_on: [deploy]
+"#;
+        fs::write(&manifest_path, contents).expect("write manifest");
+        let manifest = WorkflowManifest::load(&manifest_path).expect("load");
+        let ids: Vec<&str> = manifest.tickets.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec![
+                "test-api",
+                "test-billing",
+                "deploy-prod-api",
+                "deploy-prod-billing",
+                "announce"
+            ]
+        );
+        let billing = &manifest.tickets[1];
+        assert_eq!(billing.summary, "Test billing");
+        assert_eq!(billing.working_dir, Some(PathBuf::from("services/billing")));
+        assert_eq!(manifest.tickets[2].summary, "Deploy api to prod");
+        assert_eq!(manifest.tickets[3].depends_on, vec!["test-billing"]);
+        assert_eq!(
+            manifest.tickets[4].depends_on,
+            vec!["deploy-prod-api", "deploy-prod-billing"]
+        );
+    }
+
+    #[test]
+    fn splits_dependency_phases_and_fans_them_out_over_matrix_tickets() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest_path = dir.path().join("demo.yaml");
+        let contents = r#"
+tickets:
+  - id: test
+    summary: Test {{ service }}
+    matrix:
+      service: [api, billing]
+  - id: deploy
+    summary: Deploy {{service}}
+    depends_on: ["test:complete"]
+    matrix:
+      service: [api, billing]
+  - id: "build:arm"
+    summary: Build for arm
+  - id: announce
+    summary: Announce
+    depends_on: ["deploy:worker", "test-api", "build:arm"]
+"#;
+        fs::write(&manifest_path, contents).expect("write manifest");
+        let manifest = WorkflowManifest::load(&manifest_path).expect("load");
+        let deploy_billing = &manifest.tickets[3];
+        assert_eq!(deploy_billing.id, "deploy-billing");
+        assert_eq!(deploy_billing.depends_on, vec!["test-billing"]);
+        assert!(deploy_billing.worker_dependencies.is_empty());
+        let announce = &manifest.tickets[5];
+        assert_eq!(
+            announce.depends_on,
+            vec!["deploy-api", "deploy-billing", "test-api", "build:arm"]
+        );
+        assert_eq!(
+            announce.worker_dependencies,
+            BTreeSet::from(["deploy-api".to_string(), "deploy-billing".to_string()])
+        );
+    }
 }
@@ -0,0 +1,64 @@
+//! Holds back new sessions while the host is short on memory.
+
+use std::time::Duration;
+use sysinfo::MemoryRefreshKind;
+use sysinfo::RefreshKind;
+use sysinfo::System;
+
+/// How often a paused scheduler rechecks free memory while sessions run.
+pub(crate) const MEMORY_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+pub(crate) struct MemoryGuard {
+    min_free: Option<i64>,
+    system: Option<System>,
+}
+
+impl MemoryGuard {
+    pub(crate) fn new(min_free: Option<i64>) -> Self {
+        let system = min_free.map(|_| {
+            System::new_with_specifics(
+                RefreshKind::nothing().with_memory(MemoryRefreshKind::nothing().with_ram()),
+            )
+        });
+        Self { min_free, system }
+    }
+
+    /// Whether another session may start now. With nothing in flight a
+    /// session always starts, since waiting on our own children is pointless
+    /// and the run must make progress.
+    pub(crate) fn allows_launch(&mut self, in_flight: usize) -> bool {
+        let (Some(min_free), Some(system)) = (self.min_free, self.system.as_mut()) else {
+            return true;
+        };
+        if in_flight == 0 {
+            return true;
+        }
+        system.refresh_memory_specifics(MemoryRefreshKind::nothing().with_ram());
+        let available = i64::try_from(system.available_memory()).unwrap_or(i64::MAX);
+        has_headroom(available, min_free)
+    }
+}
+
+fn has_headroom(available: i64, min_free: i64) -> bool {
+    // Platforms sysinfo cannot read report zero; do not stall on those.
+    available == 0 || available >= min_free
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn launches_without_threshold_or_when_idle() {
+        assert!(MemoryGuard::new(None).allows_launch(8));
+        assert!(MemoryGuard::new(Some(i64::MAX)).allows_launch(0));
+        assert!(!MemoryGuard::new(Some(i64::MAX)).allows_launch(1));
+    }
+
+    #[test]
+    fn compares_available_memory_with_threshold() {
+        assert!(has_headroom(2048, 1024));
+        assert!(!has_headroom(512, 1024));
+        assert!(has_headroom(0, 1024));
+    }
+}
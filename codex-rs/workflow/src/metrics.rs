@@ -0,0 +1,251 @@
+//! `--metrics-file`: a snapshot of the run in the Prometheus text format,
+//! for node_exporter's textfile collector, rewritten whenever the state is
+//! saved.
+//!
+//! Every metric carries a `workflow` label with the workflow's name:
+//!
+//! - `codex_workflow_tickets{status}` (gauge): tickets in each status,
+//!   zero included, e.g. `status="running_worker"`.
+//! - `codex_workflow_sessions_total{phase}` (counter): worker and review
+//!   sessions launched, those of the runs this one resumed included.
+//! - `codex_workflow_session_failures_total{phase}` (counter): the sessions
+//!   among those that ended without exiting successfully, timeouts included.
+//! - `codex_workflow_start_time_seconds` (gauge): when the run started, in
+//!   seconds since the Unix epoch.
+//! - `codex_workflow_ticket_duration_seconds{ticket}` (summary): `_sum` and
+//!   `_count` of the durations of the ticket's finished sessions.
+//!
+//! These names and labels are kept stable for dashboards; new metrics may
+//! be added.
+
+use crate::state::SessionPhase;
+use crate::state::TicketStatus;
+use crate::state::WorkflowState;
+use anyhow::Context;
+use anyhow::Result;
+use chrono::DateTime;
+use chrono::Utc;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Statuses reported by `codex_workflow_tickets`, in order.
+const STATUSES: [TicketStatus; 8] = [
+    TicketStatus::Pending,
+    TicketStatus::RunningWorker,
+    TicketStatus::NeedsReview,
+    TicketStatus::RunningReview,
+    TicketStatus::Complete,
+    TicketStatus::Failed,
+    TicketStatus::Blocked,
+    TicketStatus::Skipped,
+];
+
+pub(crate) struct MetricsFile {
+    path: PathBuf,
+    started_at: DateTime<Utc>,
+}
+
+impl MetricsFile {
+    pub(crate) fn new(path: &Path, started_at: DateTime<Utc>) -> Self {
+        MetricsFile {
+            path: path.to_path_buf(),
+            started_at,
+        }
+    }
+
+    /// Replaces the file with a snapshot of `state`: written next to it and
+    /// renamed over it, so a scraper never reads half a file.
+    pub(crate) fn write(&self, state: &WorkflowState) -> Result<()> {
+        if let Some(parent) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        let mut tmp_name = self.path.file_name().unwrap_or_default().to_os_string();
+        tmp_name.push(".tmp");
+        let tmp_path = self.path.with_file_name(tmp_name);
+        fs::write(&tmp_path, render(state, self.started_at))
+            .with_context(|| format!("failed to write {}", tmp_path.display()))?;
+        fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("failed to replace {}", self.path.display()))
+    }
+}
+
+fn render(state: &WorkflowState, started_at: DateTime<Utc>) -> String {
+    let workflow = format!("workflow=\"{}\"", escape_label(&state.workflow_name));
+    let mut out = String::new();
+
+    out.push_str("# HELP codex_workflow_tickets Tickets in each status.\n");
+    out.push_str("# TYPE codex_workflow_tickets gauge\n");
+    for status in &STATUSES {
+        let count = state
+            .tickets
+            .values()
+            .filter(|ticket| ticket.status == *status)
+            .count();
+        let _ = writeln!(
+            out,
+            "codex_workflow_tickets{{{workflow},status=\"{}\"}} {count}",
+            status_label(status)
+        );
+    }
+
+    let attempts = || state.tickets.values().flat_map(|ticket| &ticket.attempts);
+    out.push_str("# HELP codex_workflow_sessions_total Sessions launched.\n");
+    out.push_str("# TYPE codex_workflow_sessions_total counter\n");
+    for phase in [SessionPhase::Worker, SessionPhase::Review] {
+        let count = attempts().filter(|attempt| attempt.phase == phase).count();
+        let _ = writeln!(
+            out,
+            "codex_workflow_sessions_total{{{workflow},phase=\"{}\"}} {count}",
+            phase.as_str()
+        );
+    }
+    out.push_str(
+        "# HELP codex_workflow_session_failures_total Sessions that ended without exiting successfully.\n",
+    );
+    out.push_str("# TYPE codex_workflow_session_failures_total counter\n");
+    for phase in [SessionPhase::Worker, SessionPhase::Review] {
+        let count = attempts()
+            .filter(|attempt| {
+                attempt.phase == phase
+                    && attempt.finished_at.is_some()
+                    && attempt.status_code != Some(0)
+            })
+            .count();
+        let _ = writeln!(
+            out,
+            "codex_workflow_session_failures_total{{{workflow},phase=\"{}\"}} {count}",
+            phase.as_str()
+        );
+    }
+
+    out.push_str(
+        "# HELP codex_workflow_start_time_seconds When the run started, in seconds since the Unix epoch.\n",
+    );
+    out.push_str("# TYPE codex_workflow_start_time_seconds gauge\n");
+    let _ = writeln!(
+        out,
+        "codex_workflow_start_time_seconds{{{workflow}}} {}",
+        started_at.timestamp()
+    );
+
+    out.push_str(
+        "# HELP codex_workflow_ticket_duration_seconds Durations of each ticket's finished sessions.\n",
+    );
+    out.push_str("# TYPE codex_workflow_ticket_duration_seconds summary\n");
+    for ticket in state.tickets.values() {
+        let durations: Vec<f64> = ticket
+            .attempts
+            .iter()
+            .filter_map(|attempt| {
+                let finished_at = attempt.finished_at?;
+                Some((finished_at - attempt.started_at).num_milliseconds().max(0) as f64 / 1000.0)
+            })
+            .collect();
+        let labels = format!("{workflow},ticket=\"{}\"", escape_label(&ticket.ticket_id));
+        let _ = writeln!(
+            out,
+            "codex_workflow_ticket_duration_seconds_sum{{{labels}}} {}",
+            durations.iter().fold(0.0, |sum, secs| sum + secs)
+        );
+        let _ = writeln!(
+            out,
+            "codex_workflow_ticket_duration_seconds_count{{{labels}}} {}",
+            durations.len()
+        );
+    }
+    out
+}
+
+/// The status as it appears in `state.json`, e.g. `needs_review`.
+fn status_label(status: &TicketStatus) -> String {
+    serde_json::to_value(status)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Label values escape backslashes, double quotes and newlines.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::AttemptRecord;
+    use crate::state::TicketRunState;
+    use chrono::Duration;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn writes_a_textfile_snapshot() {
+        let started_at = DateTime::parse_from_rfc3339("2026-01-01T12:00:00Z")
+            .expect("timestamp")
+            .with_timezone(&Utc);
+        let attempt = |phase, attempt, millis: Option<i64>, status_code| AttemptRecord {
+            phase,
+            attempt,
+            log_path: PathBuf::new(),
+            started_at,
+            finished_at: millis.map(|millis| started_at + Duration::milliseconds(millis)),
+            status_code,
+            truncated_bytes: 0,
+            timeout_secs: None,
+            profile: None,
+            log_pruned: false,
+        };
+        let mut t1 = TicketRunState::new("T1");
+        t1.status = TicketStatus::Complete;
+        t1.attempts = vec![
+            attempt(SessionPhase::Worker, 1, Some(30_000), Some(1)),
+            attempt(SessionPhase::Worker, 2, Some(90_500), Some(0)),
+            attempt(SessionPhase::Review, 1, Some(10_000), Some(0)),
+        ];
+        let mut t2 = TicketRunState::new("T2");
+        t2.status = TicketStatus::RunningWorker;
+        t2.attempts = vec![attempt(SessionPhase::Worker, 1, None, None)];
+        let state = WorkflowState {
+            workflow_name: "nightly \"db\"".to_string(),
+            run_dir: None,
+            tickets: BTreeMap::from([("T1".to_string(), t1), ("T2".to_string(), t2)]),
+        };
+
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("textfile/workflow.prom");
+        MetricsFile::new(&path, started_at)
+            .write(&state)
+            .expect("write metrics");
+        let text = fs::read_to_string(&path).expect("read metrics");
+        let samples: Vec<&str> = text.lines().filter(|line| !line.starts_with('#')).collect();
+        let w = r#"workflow="nightly \"db\"""#;
+        assert_eq!(
+            samples,
+            vec![
+                format!(r#"codex_workflow_tickets{{{w},status="pending"}} 0"#),
+                format!(r#"codex_workflow_tickets{{{w},status="running_worker"}} 1"#),
+                format!(r#"codex_workflow_tickets{{{w},status="needs_review"}} 0"#),
+                format!(r#"codex_workflow_tickets{{{w},status="running_review"}} 0"#),
+                format!(r#"codex_workflow_tickets{{{w},status="complete"}} 1"#),
+                format!(r#"codex_workflow_tickets{{{w},status="failed"}} 0"#),
+                format!(r#"codex_workflow_tickets{{{w},status="blocked"}} 0"#),
+                format!(r#"codex_workflow_tickets{{{w},status="skipped"}} 0"#),
+                format!(r#"codex_workflow_sessions_total{{{w},phase="worker"}} 3"#),
+                format!(r#"codex_workflow_sessions_total{{{w},phase="review"}} 1"#),
+                format!(r#"codex_workflow_session_failures_total{{{w},phase="worker"}} 1"#),
+                format!(r#"codex_workflow_session_failures_total{{{w},phase="review"}} 0"#),
+                format!("codex_workflow_start_time_seconds{{{w}}} 1767268800"),
+                format!(r#"codex_workflow_ticket_duration_seconds_sum{{{w},ticket="T1"}} 130.5"#),
+                format!(r#"codex_workflow_ticket_duration_seconds_count{{{w},ticket="T1"}} 3"#),
+                format!(r#"codex_workflow_ticket_duration_seconds_sum{{{w},ticket="T2"}} 0"#),
+                format!(r#"codex_workflow_ticket_duration_seconds_count{{{w},ticket="T2"}} 0"#),
+            ]
+        );
+        assert!(!dir.path().join("textfile/workflow.prom.tmp").exists());
+    }
+}
@@ -0,0 +1,281 @@
+//! Runs the manifest's `notifications.notify_command`, e.g.
+//! `terminal-notifier`, for the events the webhook hears about.
+//!
+//! `{{ ticket.id }}`, `{{ status }}`, `{{ note }}` and `{{ workflow.name }}`
+//! in the arguments are filled in like prompt placeholders, and the event as
+//! it appears in `events.jsonl` is written to stdin. Commands run in the
+//! background so a slow one never holds up the run.
+
+use crate::events::WorkflowEvent;
+use crate::template;
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::task::JoinHandle;
+
+/// Limit on each run of the command; it is killed after that.
+const NOTIFY_COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long the end of a run waits for commands still running.
+const NOTIFY_COMMAND_FLUSH_TIMEOUT: Duration = Duration::from_secs(15);
+
+pub(crate) struct NotifyCommand {
+    argv: Vec<String>,
+    runs: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl NotifyCommand {
+    pub(crate) fn new(argv: &[String]) -> Result<Self> {
+        if argv.first().is_none_or(|program| program.trim().is_empty()) {
+            anyhow::bail!("notify command must name a program to run");
+        }
+        Ok(NotifyCommand {
+            argv: argv.to_vec(),
+            runs: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Runs the command for `event` in the background. `on_failure` receives
+    /// the reason if it could not run, timed out, or exited unsuccessfully.
+    pub(crate) fn send(
+        &self,
+        event: &WorkflowEvent,
+        body: Vec<u8>,
+        on_failure: impl FnOnce(String) + Send + 'static,
+    ) {
+        let vars = event_vars(event);
+        let argv: Vec<String> = self
+            .argv
+            .iter()
+            .map(|arg| template::render(arg, &vars))
+            .collect();
+        let run = tokio::spawn(async move {
+            if let Err(err) = run(&argv, &body).await {
+                on_failure(err);
+            }
+        });
+        self.runs
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(run);
+    }
+
+    /// Gives commands still running a bounded time to finish.
+    pub(crate) async fn close(self) {
+        let runs = self
+            .runs
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        let _ = tokio::time::timeout(NOTIFY_COMMAND_FLUSH_TIMEOUT, async {
+            for run in runs {
+                let _ = run.await;
+            }
+        })
+        .await;
+    }
+}
+
+/// The placeholders of a notification. The end of the run has no ticket; its
+/// status is `complete` or `failed`, and its note lists the failed tickets or
+/// the error that stopped the run.
+fn event_vars(event: &WorkflowEvent) -> BTreeMap<String, String> {
+    let (workflow_name, ticket_id, status, note) = match event {
+        WorkflowEvent::TicketStatusChanged {
+            workflow_name,
+            ticket_id,
+            to,
+            note,
+            ..
+        } => (
+            workflow_name.as_str(),
+            ticket_id.as_str(),
+            serde_json::to_value(to)
+                .ok()
+                .and_then(|status| status.as_str().map(str::to_string))
+                .unwrap_or_default(),
+            note.clone().unwrap_or_default(),
+        ),
+        WorkflowEvent::RunFinished {
+            workflow_name,
+            error: Some(error),
+            ..
+        } => (
+            workflow_name.as_str(),
+            "",
+            "failed".to_string(),
+            error.clone(),
+        ),
+        WorkflowEvent::RunFinished {
+            workflow_name,
+            failed,
+            ..
+        } if failed.is_empty() => (
+            workflow_name.as_str(),
+            "",
+            "complete".to_string(),
+            String::new(),
+        ),
+        WorkflowEvent::RunFinished {
+            workflow_name,
+            failed,
+            ..
+        } => (
+            workflow_name.as_str(),
+            "",
+            "failed".to_string(),
+            format!("Failed: {}", failed.join(", ")),
+        ),
+        _ => ("", "", String::new(), String::new()),
+    };
+    BTreeMap::from([
+        ("workflow.name".to_string(), workflow_name.to_string()),
+        ("ticket.id".to_string(), ticket_id.to_string()),
+        ("status".to_string(), status),
+        ("note".to_string(), note),
+    ])
+}
+
+async fn run(argv: &[String], body: &[u8]) -> Result<(), String> {
+    let mut child = Command::new(&argv[0])
+        .args(&argv[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|err| format!("failed to run {}: {err}", argv[0]))?;
+    let stdin = child.stdin.take();
+    let write_input = async {
+        if let Some(mut stdin) = stdin {
+            // The command does not have to read its input.
+            let _ = stdin.write_all(body).await;
+        }
+    };
+    let finished = tokio::time::timeout(NOTIFY_COMMAND_TIMEOUT, async {
+        let ((), output) = tokio::join!(write_input, child.wait_with_output());
+        output
+    })
+    .await;
+    let output = match finished {
+        Ok(output) => output.map_err(|err| format!("failed to run {}: {err}", argv[0]))?,
+        Err(_) => {
+            return Err(format!(
+                "{} did not finish within {}s",
+                argv[0],
+                NOTIFY_COMMAND_TIMEOUT.as_secs()
+            ));
+        }
+    };
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Err(
+        match stderr.lines().rev().find(|line| !line.trim().is_empty()) {
+            Some(line) => format!("{} failed with {}: {}", argv[0], output.status, line.trim()),
+            None => format!("{} failed with {}", argv[0], output.status),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::TicketStatus;
+    use chrono::Utc;
+    use std::sync::Arc;
+
+    fn failed_ticket() -> WorkflowEvent {
+        WorkflowEvent::TicketStatusChanged {
+            workflow_name: "demo".to_string(),
+            ticket_id: "T1".to_string(),
+            from: TicketStatus::RunningWorker,
+            to: TicketStatus::Failed,
+            note: Some("Worker exited with 2".to_string()),
+            worker_log: None,
+            review_log: None,
+            done: 1,
+            total: 2,
+            at: Utc::now(),
+        }
+    }
+
+    async fn notify(argv: &[&str], body: &[u8]) -> Option<String> {
+        let argv: Vec<String> = argv.iter().map(ToString::to_string).collect();
+        let command = NotifyCommand::new(&argv).expect("notify command");
+        let failure = Arc::new(Mutex::new(None));
+        let reported = Arc::clone(&failure);
+        command.send(&failed_ticket(), body.to_vec(), move |err| {
+            *reported.lock().expect("lock") = Some(err);
+        });
+        command.close().await;
+        failure.lock().expect("lock").take()
+    }
+
+    #[tokio::test]
+    async fn fills_in_the_arguments_and_writes_the_event_to_stdin() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let out = dir.path().join("out.txt");
+        let script = format!("printf '%s|' \"$@\" > {0}; cat >> {0}", out.display());
+        let failure = notify(
+            &[
+                "sh",
+                "-c",
+                &script,
+                "sh",
+                "{{ workflow.name }}",
+                "{{ticket.id}}",
+                "{{ status }}",
+                "{{ note }}",
+            ],
+            b"{\"type\":\"ticket_status_changed\"}\n",
+        )
+        .await;
+        assert_eq!(failure, None);
+        assert_eq!(
+            std::fs::read_to_string(&out).expect("read output"),
+            "demo|T1|failed|Worker exited with 2|{\"type\":\"ticket_status_changed\"}\n"
+        );
+
+        let vars = event_vars(&WorkflowEvent::RunFinished {
+            workflow_name: "demo".to_string(),
+            failed: vec!["T1".to_string(), "T3".to_string()],
+            error: None,
+            at: Utc::now(),
+        });
+        assert_eq!(vars["status"], "failed");
+        assert_eq!(vars["note"], "Failed: T1, T3");
+        assert_eq!(vars["ticket.id"], "");
+
+        let vars = event_vars(&WorkflowEvent::RunFinished {
+            workflow_name: "demo".to_string(),
+            failed: Vec::new(),
+            error: Some("disk full".to_string()),
+            at: Utc::now(),
+        });
+        assert_eq!(vars["status"], "failed");
+        assert_eq!(vars["note"], "disk full");
+    }
+
+    #[tokio::test]
+    async fn reports_failures() {
+        assert!(NotifyCommand::new(&[]).is_err());
+        let failure = notify(&["sh", "-c", "echo no display >&2; exit 4"], b"{}\n").await;
+        assert_eq!(
+            failure.as_deref(),
+            Some("sh failed with exit status: 4: no display")
+        );
+        let failure = notify(&["/nonexistent/notifier"], b"{}\n").await;
+        assert!(
+            failure
+                .as_deref()
+                .is_some_and(|err| err.starts_with("failed to run /nonexistent/notifier")),
+            "{failure:?}"
+        );
+    }
+}
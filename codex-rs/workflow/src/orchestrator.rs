@@ -1,32 +1,294 @@
+use crate::cancel::CANCEL_POLL_INTERVAL;
+use crate::cancel::CancelRequest;
+use crate::capture::DEFAULT_MAX_LOG_BYTES;
+use crate::confirm::TicketConfirmer;
+use crate::confirm::TicketDecision;
+use crate::confirm::TicketPlan;
+use crate::context_budget::estimate_tokens;
+use crate::disk::DiskGuard;
+use crate::error::StoppedRun;
+use crate::error::WorkflowError;
+use crate::events::EventSink;
+use crate::events::RunSettings;
+use crate::events::WorkflowEvent;
+use crate::fingerprint::ticket_fingerprint;
+use crate::git_branch::DEFAULT_REMOTE;
+use crate::git_branch::add_worktree;
+use crate::git_branch::changed_paths;
+use crate::git_branch::checkout_base;
+use crate::git_branch::checkout_ticket_branch;
+use crate::git_branch::commit_changes;
+use crate::git_branch::current_head;
+use crate::git_branch::push_branch;
+use crate::git_branch::remote_url;
+use crate::git_branch::remove_worktree;
+use crate::git_branch::ticket_branch_name;
+use crate::git_branch::ticket_commit_message;
+use crate::git_branch::ticket_pull_request;
+use crate::git_diff::capture_git_diff;
+use crate::git_diff::head_commit;
+use crate::github_pr::PullRequest;
+use crate::github_pr::open_pull_request;
+use crate::github_pr::repo_from_remote_url;
+use crate::layout::EVENTS_FILE;
 use crate::layout::WorkflowLayout;
+use crate::layout::WorkflowMeta;
+use crate::lock::HEARTBEAT_INTERVAL;
+use crate::lock::LockInfo;
+use crate::lock::RunLock;
+use crate::lock::read_lock;
+use crate::log_format::LogFormat;
+use crate::log_format::strip_ansi;
+use crate::manifest::ReviewFailurePolicy;
 use crate::manifest::TicketSpec;
 use crate::manifest::WorkflowManifest;
+use crate::memory::MEMORY_POLL_INTERVAL;
+use crate::memory::MemoryGuard;
+use crate::metrics::MetricsFile;
+use crate::preflight::Preflight;
+use crate::preflight::retry_delay;
+use crate::prompt_command::PROMPT_COMMAND_TIMEOUT;
+use crate::prompt_command::PromptCommandFailed;
+use crate::prompt_command::PromptCommandInput;
+use crate::prompt_command::run_prompt_command;
+use crate::review::parse_requirement_verdicts;
+use crate::review::parse_review_report;
+use crate::secrets::load_secrets_file;
+use crate::secrets::redact;
+use crate::session::PrintCommand;
 use crate::session::SessionLauncher;
 use crate::session::SessionRequest;
+use crate::session::SessionResult;
+use crate::session::SessionRunner;
+use crate::state::ReviewVerdict;
+use crate::state::SessionPhase;
+use crate::state::TicketRunState;
 use crate::state::TicketStatus;
+use crate::state::TicketWorktree;
 use crate::state::WorkflowState;
+use crate::summary::write_run_result;
+use crate::summary::write_run_summary;
+use crate::usage::DiskUsage;
+use crate::usage::measure_disk_usage;
+use crate::validate::ManifestIssue;
 use anyhow::Context;
 use anyhow::Result;
 use anyhow::bail;
+use chrono::DateTime;
+use chrono::Utc;
 use codex_common::CliConfigOverrides;
+use futures::StreamExt;
+use futures::stream::FuturesUnordered;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashSet;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::time::Duration;
+use std::time::Instant;
 use textwrap::wrap;
+use tokio::sync::mpsc;
+use tracing::Instrument;
+use tracing::debug;
+use tracing::info_span;
+use tracing::warn;
 
+#[derive(Default)]
 pub struct WorkflowRunOptions {
     pub manifest_path: PathBuf,
+    /// Base for relative `working_dir`s and the default artifacts directory,
+    /// instead of the manifest's directory.
+    pub base_dir: Option<PathBuf>,
     pub artifacts_dir: Option<PathBuf>,
+    /// Root of the artifacts directories, e.g. from
+    /// `CODEX_WORKFLOW_ARTIFACTS_DIR` or the `workflow.artifacts_root` config
+    /// key. See [`WorkflowLayout::resolve`].
+    pub artifacts_root: Option<PathBuf>,
     pub resume: bool,
+    /// Start at this ticket: every ticket ordered before it by dependencies
+    /// and manifest order is marked complete, and it runs even if it had
+    /// finished.
+    pub resume_from: Option<String>,
+    /// Without `resume`, keep tickets that completed in the previous run and
+    /// whose fingerprint (spec and `inputs` files) has not changed since,
+    /// provided their dependencies are kept too.
+    pub skip_unchanged: bool,
+    /// Ticket ids or glob patterns (`backend-*`) of the tickets to run; empty
+    /// runs every ticket. Each pattern must match at least one ticket.
+    pub tickets: Vec<String>,
     pub codex_bin: Option<PathBuf>,
+    /// Binary review sessions of tickets without their own `reviewer_bin`
+    /// run; `codex_bin` when unset.
+    pub reviewer_bin: Option<PathBuf>,
+    /// Arguments that replace `exec` at the start of every codex invocation,
+    /// split like a shell command line; empty for none.
+    pub codex_subcommand: Option<String>,
     pub config_overrides: CliConfigOverrides,
     pub worker_model: Option<String>,
     pub reviewer_model: Option<String>,
+    /// codex config profile for the sessions of tickets without their own
+    /// `profile`; it replaces the manifest's `config_profile`.
+    pub profile: Option<String>,
+    pub prompt_via_argv: bool,
+    /// Command that builds the worker and review prompts of tickets without
+    /// a `prompt`/`review_prompt` of their own, in place of the built-in
+    /// prompts. It receives the ticket as JSON on stdin and prints the prompt.
+    pub prompt_command: Option<String>,
+    /// Print each codex invocation to stderr before it starts.
+    pub print_command: Option<PrintCommand>,
+    pub secrets_file: Option<PathBuf>,
+    /// Ticket ids to move from `Blocked` back to `Pending` before resuming.
+    pub unblock: Vec<String>,
+    pub unblock_all: bool,
+    /// Move every `Failed` and `Blocked` ticket back to `Pending` and run
+    /// only those, leaving the others as they are. Used with `resume`.
+    pub only_failed: bool,
+    /// Keep at most this many attempt logs per ticket and phase.
+    pub keep_log_attempts: Option<i32>,
+    /// Resume the worker's codex session for the review. Also enabled by the
+    /// manifest's `review_resumes_worker_session`.
+    pub review_resumes_worker_session: bool,
+    /// Run worker retries, and workers restarted by a later `--resume`, as a
+    /// continuation of the ticket's last worker session. Also enabled by the
+    /// manifest's `worker_resumes_session`.
+    pub worker_resumes_session: bool,
+    /// Capture the worker's changes since the ticket's starting commit and
+    /// have the reviewer focus on that diff. Also enabled by the manifest's
+    /// `review_against_diff`.
+    pub review_against_diff: bool,
+    /// Overrides the manifest's `skip_git_repo_check`, which defaults to
+    /// true.
+    pub skip_git_repo_check: Option<bool>,
+    /// Overrides the manifest's `prompt_token_budget`.
+    pub prompt_token_budget: Option<i64>,
+    /// Fail a ticket whose prompt is over the token budget instead of
+    /// warning and launching its session anyway.
+    pub strict_context: bool,
+    /// Do not commit completed tickets, even with the manifest's
+    /// `git.autocommit`.
+    pub no_autocommit: bool,
+    /// Token for the pull requests of the manifest's `git.open_pr`; see
+    /// [`crate::GITHUB_TOKEN_ENV_VARS`].
+    pub github_token: Option<String>,
+    /// Maximum number of tickets to run at once. See [`resolve_max_parallel`].
+    /// Whatever the limit, a free slot goes to the first ready ticket in
+    /// manifest order, and nothing about scheduling is random, so runs of
+    /// the same manifest whose sessions end the same way launch their
+    /// sessions in the same order.
+    pub max_parallel: Option<usize>,
+    /// Derive the parallelism from `CODEX_WORKFLOW_PARALLEL` or the number of
+    /// available cores when `max_parallel` is unset.
+    pub concurrency_from_env: bool,
+    /// Append this run as a new section of `summary.md` instead of replacing it.
+    pub append_summary: bool,
+    /// Extra arguments passed to every codex exec invocation, verbatim.
+    pub extra_args: Vec<String>,
+    /// Session log format; overrides the manifest's `log_format`.
+    pub log_format: Option<LogFormat>,
+    /// Keep ANSI escape sequences in session logs instead of stripping them.
+    pub log_ansi: bool,
+    /// Log session output without the per-line elapsed-time prefix.
+    pub omit_log_timestamps: bool,
+    /// Cap on the size of each session log; defaults to
+    /// [`DEFAULT_MAX_LOG_BYTES`](crate::DEFAULT_MAX_LOG_BYTES).
+    pub max_log_bytes: Option<i64>,
+    /// Hold back new sessions while the host has less than this many bytes of
+    /// available memory, unless nothing is running.
+    pub min_free_memory: Option<i64>,
+    /// Refuse to start while the artifacts directory's volume has less than
+    /// this many bytes free, and hold back new sessions while it runs low.
+    pub min_free_disk: Option<i64>,
+    /// Overrides the manifest's `max_retries` for failed worker sessions.
+    pub max_retries: Option<i32>,
+    /// Overrides the manifest's `max_reworks`.
+    pub max_reworks: Option<i32>,
+    /// Overrides the manifest's `retry_on_exit_codes` when non-empty.
+    pub retry_on_exit_codes: Vec<i32>,
+    /// Terminate any worker or review session that runs longer than this.
+    pub session_timeout: Option<Duration>,
+    /// Overrides `session_timeout` and the manifest's `worker_timeout` for
+    /// worker sessions.
+    pub worker_timeout: Option<Duration>,
+    /// Overrides `session_timeout` and the manifest's `review_timeout` for
+    /// review sessions.
+    pub review_timeout: Option<Duration>,
+    /// Overrides the manifest's `timeout_backoff`.
+    pub timeout_backoff: Option<f64>,
+    /// On resume, drop state entries for tickets no longer in the manifest
+    /// instead of only warning about them.
+    pub prune_orphans: bool,
+    /// With `prune_orphans`, also delete the orphans' ticket directories.
+    pub delete_orphan_artifacts: bool,
+    /// On resume, fail if the state has entries for tickets no longer in the
+    /// manifest.
+    pub fail_on_orphans: bool,
+    /// Abort the run once a ticket is blocked by a failed dependency instead
+    /// of continuing with the tickets that can still run.
+    pub strict_deps: bool,
+    /// Write ticket artifacts directly under the artifacts directory, as
+    /// earlier versions did, instead of a new `runs/<timestamp>` directory.
+    pub reuse_run_dir: bool,
+    /// Write into this existing run directory (a name under `runs/`, or
+    /// `latest`) instead of starting a new one. Used with `resume`, which
+    /// otherwise continues in the `latest` run directory if there is one.
+    pub resume_run: Option<String>,
+    /// Consulted before each ticket launches. Tickets run one at a time while
+    /// this is set, regardless of `max_parallel`.
+    pub confirmer: Option<Arc<dyn TicketConfirmer>>,
+    /// Receives a [`WorkflowEvent`] for every state transition of the run.
+    pub events: Option<mpsc::UnboundedSender<WorkflowEvent>>,
+    /// Also write the events as JSON lines to this Unix socket path or
+    /// `HOST:PORT` TCP address.
+    pub events_socket: Option<String>,
+    /// Copy session output to stderr as it arrives, in addition to the logs.
+    pub echo_session_output: bool,
+    /// Overrides the manifest's `notifications.webhook_url`.
+    pub webhook_url: Option<String>,
+    /// Overrides the manifest's `notifications.notify_command`.
+    pub notify_command: Option<Vec<String>>,
+    /// Before the first ticket launches, wait until this URL answers with a
+    /// status below `500`, probing again with growing pauses.
+    pub preflight_url: Option<String>,
+    /// Like `preflight_url`, a command (split like a shell command line,
+    /// without a shell) that must exit successfully.
+    pub preflight_command: Option<String>,
+    /// Also probe before every worker and review session.
+    pub preflight_each_session: bool,
+    /// Keep a Prometheus textfile snapshot of the run at this path,
+    /// rewritten whenever the state is saved.
+    pub metrics_file: Option<PathBuf>,
+    /// Rewrite `report.json` in the artifacts directory with the run's status
+    /// report, as `status --json` renders it, this often while the run goes
+    /// on, and once more when it ends.
+    pub report_interval: Option<Duration>,
 }
 
+#[derive(Debug)]
 pub struct WorkflowStatusReport {
     pub workflow_name: String,
     pub state_path: PathBuf,
-    pub tickets: Vec<crate::state::TicketRunState>,
+    /// Run directory the tickets' logs belong to; `None` for the flat layout.
+    pub run_dir: Option<PathBuf>,
+    pub tickets: Vec<TicketRunState>,
+    /// Problems worth surfacing that did not fail the run.
+    pub warnings: Vec<String>,
+    /// Sizes of the artifacts, once [`WorkflowStatusReport::with_disk_usage`]
+    /// has measured them.
+    pub disk_usage: Option<DiskUsage>,
+    /// Tickets whose failure does not count against the run; see
+    /// [`TicketSpec::allow_failure`].
+    pub allowed_failures: Vec<String>,
+    /// First line of each ticket's summary in the manifest, by ticket id.
+    pub summaries: BTreeMap<String, String>,
+    /// The lock of the run working on the workflow, if `load_status` found
+    /// one; see [`LockInfo::holder_is_alive`] for whether it is stale.
+    pub run: Option<LockInfo>,
+    /// Values [`WorkflowStatusReport::to_json`] redacts from the prompts it
+    /// includes; see [`WorkflowStatusReport::with_secrets_file`].
+    pub secret_values: Vec<String>,
 }
 
 impl WorkflowStatusReport {
@@ -35,303 +297,4789 @@ impl WorkflowStatusReport {
         Self {
             workflow_name: state.workflow_name,
             state_path,
+            run_dir: state.run_dir,
             tickets,
+            warnings: Vec::new(),
+            disk_usage: None,
+            allowed_failures: Vec::new(),
+            summaries: BTreeMap::new(),
+            run: None,
+            secret_values: Vec::new(),
         }
     }
+
+    /// Redacts the values of the dotenv-style `path`, as `--secrets-file`
+    /// gives them to a run, from the prompts [`WorkflowStatusReport::to_json`]
+    /// includes.
+    pub fn with_secrets_file(mut self, path: &Path) -> Result<Self> {
+        self.secret_values = load_secrets_file(path)?.into_values().collect();
+        Ok(self)
+    }
+
+    /// Tickets that ended `Failed` or `Blocked`, other than allowed failures.
+    pub fn failed_tickets(&self) -> Vec<&TicketRunState> {
+        self.tickets
+            .iter()
+            .filter(|ticket| matches!(ticket.status, TicketStatus::Failed | TicketStatus::Blocked))
+            .filter(|ticket| !self.allowed_failures.contains(&ticket.ticket_id))
+            .collect()
+    }
+
+    /// The workflow's event log, next to the state file.
+    pub fn events_path(&self) -> PathBuf {
+        self.state_path.with_file_name(EVENTS_FILE)
+    }
+
+    pub fn has_failures(&self) -> bool {
+        !self.failed_tickets().is_empty()
+    }
+
+    /// Whether no ticket is pending, running, or waiting for its review, so
+    /// there is nothing left for a run to do.
+    pub fn is_finished(&self) -> bool {
+        self.tickets.iter().all(|ticket| {
+            matches!(
+                ticket.status,
+                TicketStatus::Complete
+                    | TicketStatus::Failed
+                    | TicketStatus::Blocked
+                    | TicketStatus::Skipped
+            )
+        })
+    }
+
+    /// Tickets that have not finished: neither `Complete` nor `Skipped`, nor
+    /// an allowed failure.
+    pub fn unfinished_tickets(&self) -> Vec<&TicketRunState> {
+        self.tickets
+            .iter()
+            .filter(|ticket| match ticket.status {
+                TicketStatus::Complete | TicketStatus::Skipped => false,
+                TicketStatus::Failed | TicketStatus::Blocked => {
+                    !self.allowed_failures.contains(&ticket.ticket_id)
+                }
+                _ => true,
+            })
+            .collect()
+    }
+
+    /// Measures the disk usage of the artifacts directory holding the state
+    /// file.
+    pub fn with_disk_usage(mut self) -> Self {
+        let root = self
+            .state_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let ticket_ids: Vec<String> = self
+            .tickets
+            .iter()
+            .map(|ticket| ticket.ticket_id.clone())
+            .collect();
+        self.disk_usage = Some(measure_disk_usage(&WorkflowLayout::new(root), &ticket_ids));
+        self
+    }
+
+    /// Renders the report for `status --json`. Prompt files and command lines
+    /// are only included with `include_prompts`, in which case the prompt text
+    /// is inlined as `worker_prompt_text` / `review_prompt_text`.
+    pub fn to_json(&self, include_prompts: bool) -> Result<serde_json::Value> {
+        let mut tickets = Vec::with_capacity(self.tickets.len());
+        for ticket in &self.tickets {
+            let mut value = serde_json::to_value(ticket)?;
+            let Some(fields) = value.as_object_mut() else {
+                bail!("ticket {} did not serialize to an object", ticket.ticket_id);
+            };
+            if include_prompts {
+                let prompt_text = |path: Option<&Path>| -> Result<serde_json::Value> {
+                    Ok(read_prompt_file(path)?
+                        .map(|prompt| redact(&prompt, &self.secret_values))
+                        .into())
+                };
+                fields.insert(
+                    "worker_prompt_text".to_string(),
+                    prompt_text(ticket.worker_prompt.as_deref())?,
+                );
+                fields.insert(
+                    "review_prompt_text".to_string(),
+                    prompt_text(ticket.review_prompt.as_deref())?,
+                );
+            } else {
+                for key in [
+                    "worker_prompt",
+                    "review_prompt",
+                    "worker_command",
+                    "review_command",
+                ] {
+                    fields.remove(key);
+                }
+            }
+            if let Some(sizes) = self
+                .disk_usage
+                .as_ref()
+                .and_then(|usage| usage.tickets.get(&ticket.ticket_id))
+            {
+                fields.insert("disk_usage".to_string(), serde_json::to_value(sizes)?);
+            }
+            tickets.push(value);
+        }
+        let mut report = serde_json::json!({
+            "workflow_name": self.workflow_name,
+            "state_path": self.state_path,
+            "run_dir": self.run_dir,
+            "warnings": self.warnings,
+            "tickets": tickets,
+        });
+        if let Some(usage) = &self.disk_usage {
+            report["disk_usage"] = serde_json::to_value(usage.total)?;
+        }
+        if let Some(run) = &self.run {
+            report["run"] = serde_json::to_value(run)?;
+            report["run"]["alive"] = run.holder_is_alive().into();
+        }
+        Ok(report)
+    }
+}
+
+/// Writes `report` as `status --json` renders it, through a temporary file so
+/// a poller never reads half a report.
+fn write_report_json(path: &Path, report: &WorkflowStatusReport) -> Result<()> {
+    let json = serde_json::to_vec_pretty(&report.to_json(false)?)?;
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, json)
+        .with_context(|| format!("failed to write {}", tmp_path.display()))?;
+    std::fs::rename(&tmp_path, path).with_context(|| format!("failed to write {}", path.display()))
+}
+
+fn read_prompt_file(path: Option<&Path>) -> Result<Option<String>> {
+    match path {
+        Some(path) if path.exists() => std::fs::read_to_string(path)
+            .map(Some)
+            .with_context(|| format!("failed to read prompt file {}", path.display())),
+        _ => Ok(None),
+    }
+}
+
+/// Environment variable consulted by `concurrency_from_env`.
+pub const PARALLELISM_ENV_VAR: &str = "CODEX_WORKFLOW_PARALLEL";
+
+/// Lines of a failed session's stderr quoted in the ticket's note.
+const FAILURE_STDERR_LINES: usize = 5;
+
+/// Characters kept of each quoted stderr line.
+const FAILURE_STDERR_LINE_CHARS: usize = 200;
+
+pub async fn run_workflow(
+    opts: WorkflowRunOptions,
+) -> std::result::Result<WorkflowStatusReport, WorkflowError> {
+    let launcher = session_launcher(&opts)?;
+    run_workflow_with_runner(opts, &launcher).await
 }
 
-pub async fn run_workflow(opts: WorkflowRunOptions) -> Result<WorkflowStatusReport> {
-    let manifest = WorkflowManifest::load(&opts.manifest_path)?;
-    let layout = WorkflowLayout::new(resolve_artifacts_dir(&manifest, &opts.artifacts_dir));
-    layout.ensure_root()?;
+/// The codex process launcher `run_workflow` uses for `opts`.
+fn session_launcher(opts: &WorkflowRunOptions) -> Result<SessionLauncher> {
+    let codex_bin = opts
+        .codex_bin
+        .clone()
+        .or_else(|| std::env::current_exe().ok())
+        .unwrap_or_else(|| PathBuf::from("codex"));
+    let secrets = match &opts.secrets_file {
+        Some(path) => load_secrets_file(path)?,
+        None => Default::default(),
+    };
+    let config_flags = opts.config_overrides.raw_overrides.clone();
+    let mut launcher = SessionLauncher::new(codex_bin, config_flags);
+    if let Some(subcommand) = &opts.codex_subcommand {
+        let args = shlex::split(subcommand)
+            .with_context(|| format!("invalid codex subcommand {subcommand:?}"))?;
+        launcher = launcher.with_subcommand(args);
+    }
+    Ok(launcher
+        .with_prompt_via_argv(opts.prompt_via_argv)
+        .with_secrets(secrets)
+        .with_log_ansi(opts.log_ansi)
+        .with_log_timestamps(!opts.omit_log_timestamps)
+        .with_max_log_bytes(opts.max_log_bytes.unwrap_or(DEFAULT_MAX_LOG_BYTES))
+        .with_print_command(opts.print_command)
+        .with_echo_output(opts.echo_session_output))
+}
+
+/// What the next session of a ticket would be sent, as shown by
+/// `workflow prompt`.
+#[derive(Debug)]
+pub struct PromptPreview {
+    pub prompt: String,
+    pub model: Option<String>,
+    pub working_dir: PathBuf,
+    /// The codex command line, with the prompt elided.
+    pub command_line: String,
+    /// Rough size of `prompt` in tokens.
+    pub estimated_tokens: i64,
+    /// The `prompt_token_budget` the run would hold the prompt to.
+    pub token_budget: Option<i64>,
+}
+
+/// Builds the prompt and codex invocation a run with `opts` would use for
+/// `ticket_id`'s next `phase` session, from the saved state if there is one,
+/// without launching anything or writing any state. Paths under the
+/// artifacts directory are shown for the flat layout, since a run's
+/// directory is only named when it starts.
+pub async fn preview_prompt(
+    opts: &WorkflowRunOptions,
+    ticket_id: &str,
+    phase: SessionPhase,
+) -> Result<PromptPreview> {
+    let mut manifest = WorkflowManifest::load(&opts.manifest_path)?;
+    manifest.base_dir = opts.base_dir.clone();
+    let ticket = manifest.ticket(ticket_id)?;
+    let log_format = opts.log_format.or(manifest.log_format).unwrap_or_default();
+    let layout = WorkflowLayout::resolve(
+        &manifest,
+        opts.artifacts_dir.as_deref(),
+        opts.artifacts_root.as_deref(),
+    )
+    .with_log_format(log_format);
+    let state_path = layout.state_file();
+    let state = if state_path.exists() {
+        Some(WorkflowState::load(&state_path)?)
+    } else {
+        None
+    };
+    let ticket_state = state.as_ref().and_then(|state| state.ticket(ticket_id));
+    let request = session_request(phase, ticket, &manifest, &layout, opts, ticket_state).await?;
+    let command_line = session_launcher(opts)?.command_line(
+        &request,
+        request.resume_session.as_deref(),
+        request.last_message_path.as_deref(),
+    );
+    Ok(PromptPreview {
+        estimated_tokens: estimate_tokens(&request.prompt),
+        token_budget: opts.prompt_token_budget.or(manifest.prompt_token_budget),
+        prompt: request.prompt,
+        model: request.model,
+        working_dir: request.working_dir,
+        command_line,
+    })
+}
+
+/// Runs a workflow with a caller-provided [`SessionRunner`] instead of
+/// spawning codex processes. `codex_bin`, `prompt_via_argv`,
+/// `echo_session_output`, and the log styling and size options are ignored
+/// since they only configure the default process launcher; `secrets_file`
+/// only redacts the prompt files.
+pub async fn run_workflow_with_runner(
+    opts: WorkflowRunOptions,
+    runner: &dyn SessionRunner,
+) -> std::result::Result<WorkflowStatusReport, WorkflowError> {
+    let started_at = Utc::now();
+    let mut manifest = WorkflowManifest::load(&opts.manifest_path)?;
+    manifest.base_dir = opts.base_dir.clone();
+    let span = info_span!("workflow_run", workflow = %manifest.workflow_name());
+    let allowed_failures = manifest.allowed_failures();
+    let summaries = manifest.summaries();
+    let mut run_layout = None;
+    let result = run_manifest(manifest, opts, runner, started_at, &mut run_layout)
+        .instrument(span)
+        .await;
+    // Failures before the run took over its artifacts directory leave
+    // nothing of this run to report.
+    let Some(layout) = run_layout else {
+        return Ok(result?);
+    };
+    let error = match result {
+        Ok(report) => {
+            // The run itself finished; a missing `result.json` should not
+            // turn it into a failure.
+            if let Err(err) = write_run_result(&layout.result_file(), &report, None) {
+                warn!("{err:#}");
+            }
+            return Ok(report);
+        }
+        Err(err) => WorkflowError::from(err),
+    };
+    let state_path = layout.state_file();
+    let Ok(state) = WorkflowState::load(&state_path) else {
+        return Err(error);
+    };
+    let mut report = WorkflowStatusReport::from_state(state, state_path);
+    report.allowed_failures = allowed_failures;
+    report.summaries = summaries;
+    // The run's own error is the one to surface.
+    let _ = write_run_result(&layout.result_file(), &report, Some(&error));
+    Err(WorkflowError::RunStopped(Box::new(StoppedRun {
+        error,
+        report,
+    })))
+}
+
+/// [`run_workflow_with_runner`] once the manifest is loaded, inside the run's
+/// span. `run_layout` is set once the state file describes this run.
+async fn run_manifest(
+    manifest: WorkflowManifest,
+    opts: WorkflowRunOptions,
+    runner: &dyn SessionRunner,
+    started_at: DateTime<Utc>,
+    run_layout: &mut Option<WorkflowLayout>,
+) -> Result<WorkflowStatusReport> {
+    let issues: Vec<ManifestIssue> = manifest
+        .validate_full()
+        .into_iter()
+        .filter(ManifestIssue::is_error)
+        .collect();
+    if !issues.is_empty() {
+        return Err(WorkflowError::Validation { issues }.into());
+    }
+    let mut selected = select_tickets(&manifest, &opts.tickets)?;
+    let max_parallel = if opts.confirmer.is_some() {
+        1
+    } else {
+        resolve_max_parallel(&opts)
+    };
+    check_parallel_git(&manifest, &opts, &selected, max_parallel)?;
+    let preflight = Preflight::new(
+        opts.preflight_url.as_deref(),
+        opts.preflight_command.as_deref(),
+    )?;
+    let log_format = opts.log_format.or(manifest.log_format).unwrap_or_default();
+    let mut layout = WorkflowLayout::resolve(
+        &manifest,
+        opts.artifacts_dir.as_deref(),
+        opts.artifacts_root.as_deref(),
+    )
+    .with_log_format(log_format);
+    // Pre-flight: surface permission problems and missing binaries before any
+    // session spends tokens.
+    layout.ensure_root(&manifest)?;
+    runner.check_program(None)?;
+    let reviewer_bins: BTreeSet<PathBuf> = manifest
+        .tickets
+        .iter()
+        .filter(|ticket| selected.contains(ticket.id.as_str()))
+        .filter_map(|ticket| reviewer_bin(ticket, &manifest, &opts))
+        .collect();
+    for program in &reviewer_bins {
+        runner.check_program(Some(program))?;
+    }
+    let disk = DiskGuard::new(layout.root(), opts.min_free_disk);
+    disk.check()?;
+    let lock = RunLock::acquire(&layout.lock_file())?;
+    CancelRequest::clear(&layout)?;
+    // A resumed run keeps writing into the run directory it stopped in.
+    let resume_run = opts.resume_run.as_deref().or_else(|| {
+        (opts.resume && !opts.reuse_run_dir && layout.latest_run_dir().is_some())
+            .then_some("latest")
+    });
+    if let Some(run_id) = resume_run {
+        layout = layout.open_run(run_id)?;
+    } else if !opts.reuse_run_dir {
+        layout = layout.start_run(&started_at.format("%Y%m%dT%H%M%SZ").to_string())?;
+    }
     let state_path = layout.state_file();
 
+    let mut warnings = Vec::new();
     let mut state = if opts.resume && state_path.exists() {
         let mut state = WorkflowState::load(&state_path)?;
+        handle_orphans(&mut state, &manifest, &layout, &opts, &mut warnings)?;
         state.sync_with_manifest(&manifest);
+        for ticket_id in &opts.unblock {
+            state.unblock(ticket_id)?;
+        }
+        if opts.unblock_all {
+            state.unblock_all();
+        }
+        state
+    } else if opts.skip_unchanged && state_path.exists() {
+        let previous = WorkflowState::load(&state_path)?;
+        let mut state = WorkflowState::initialize(&manifest);
+        keep_unchanged_tickets(&mut state, &previous, &manifest);
         state
     } else {
         WorkflowState::initialize(&manifest)
     };
+    if opts.only_failed {
+        let retried = state.reset_failed();
+        selected.retain(|id| retried.iter().any(|retried| retried == id));
+    }
+    if let Some(ticket_id) = &opts.resume_from {
+        manifest.ticket(ticket_id)?;
+        let earlier: Vec<&str> = manifest
+            .dependency_order()
+            .into_iter()
+            .take_while(|id| *id != ticket_id.as_str())
+            .collect();
+        state.resume_from(ticket_id, &earlier)?;
+    }
+    // What resume, --skip-unchanged and --resume-from decided for each ticket.
+    for (ticket_id, entry) in &state.tickets {
+        debug!(
+            ticket = ticket_id.as_str(),
+            status = ?entry.status,
+            note = entry.note.as_deref(),
+            "ticket state at start"
+        );
+    }
+    state.run_dir = layout.run_dir().map(Path::to_path_buf);
+    state.save(&state_path)?;
+    *run_layout = Some(layout.clone());
+    let metrics = opts
+        .metrics_file
+        .as_deref()
+        .map(|path| MetricsFile::new(path, started_at));
+    if let Some(metrics) = &metrics {
+        metrics.write(&state)?;
+    }
 
-    let codex_bin = opts
-        .codex_bin
-        .clone()
-        .or_else(|| std::env::current_exe().ok())
-        .unwrap_or_else(|| PathBuf::from("codex"));
-    let config_flags = opts.config_overrides.raw_overrides.clone();
-    let launcher = SessionLauncher::new(codex_bin, config_flags);
+    let events = EventSink::connect(
+        opts.events.clone(),
+        opts.events_socket.as_deref(),
+        Some(&layout.events_file()),
+        opts.webhook_url
+            .as_deref()
+            .or(manifest.notifications.webhook_url.as_deref()),
+        opts.notify_command
+            .as_deref()
+            .unwrap_or(&manifest.notifications.notify_command),
+    )
+    .await?;
+    events.emit(WorkflowEvent::RunStarted {
+        workflow_name: state.workflow_name.clone(),
+        run_dir: state.run_dir.clone(),
+        settings: RunSettings {
+            resume: opts.resume,
+            resume_from: opts.resume_from.clone(),
+            tickets: opts.tickets.clone(),
+            max_parallel,
+            max_retries: opts.max_retries.unwrap_or(manifest.max_retries),
+            max_reworks: opts.max_reworks.unwrap_or(manifest.max_reworks),
+            session_timeout_secs: opts.session_timeout.map(|timeout| timeout.as_secs()),
+            worker_timeout_secs: phase_timeout(SessionPhase::Worker, &manifest, &opts)
+                .map(|timeout| timeout.as_secs()),
+            review_timeout_secs: phase_timeout(SessionPhase::Review, &manifest, &opts)
+                .map(|timeout| timeout.as_secs()),
+            worker_model: opts.worker_model.clone(),
+            reviewer_model: opts.reviewer_model.clone(),
+            strict_deps: opts.strict_deps,
+        },
+        at: Utc::now(),
+    });
+    let workflow_name = state.workflow_name.clone();
+    // Everything after `RunStarted` ends in `RunFinished`, errors included.
+    let outcome: Result<(WorkflowStatusReport, Option<String>)> = async {
+        let secret_values: Vec<String> = match &opts.secrets_file {
+            Some(path) => load_secrets_file(path)?.into_values().collect(),
+            None => Vec::new(),
+        };
+        let ctx = RunContext {
+            manifest: &manifest,
+            layout: &layout,
+            runner,
+            state_path: &state_path,
+            opts: &opts,
+            events: &events,
+            preflight: preflight.as_ref(),
+            metrics: metrics.as_ref(),
+            lock: &lock,
+            secret_values,
+            state: Mutex::new(state),
+        };
+        ctx.wait_for_preflight(None).await;
+        let (mut pending, unselected): (Vec<&TicketSpec>, Vec<&TicketSpec>) = manifest
+            .tickets
+            .iter()
+            .partition(|ticket| selected.contains(ticket.id.as_str()));
+        let mut in_flight = FuturesUnordered::new();
+        let mut strict_failure: Option<String> = None;
+        let mut memory = MemoryGuard::new(opts.min_free_memory);
+        // Unselected tickets are left alone like deferred ones, so selected
+        // tickets waiting on them stay pending instead of becoming blocked.
+        let mut deferred: HashSet<&str> = unselected.iter().map(|ticket| ticket.id.as_str()).collect();
+        let mut quit_at: Option<String> = None;
+        let mut cancel: Option<CancelRequest> = None;
+        let mut last_heartbeat = Instant::now();
+        let mut last_report: Option<Instant> = None;
+        loop {
+            // Long sessions save no state, so keep the heartbeat fresh here.
+            if last_heartbeat.elapsed() >= HEARTBEAT_INTERVAL {
+                let _ = lock.heartbeat();
+                last_heartbeat = Instant::now();
+            }
+            if let Some(interval) = opts.report_interval
+                && last_report.is_none_or(|at| at.elapsed() >= interval)
+            {
+                // Pollers see the next snapshot if this one cannot be written.
+                let _ = ctx.write_report_snapshot();
+                last_report = Some(Instant::now());
+            }
+            // A soft request can still be upgraded to a hard one.
+            if let Some(request) = CancelRequest::read(&layout) {
+                cancel = Some(request);
+            }
+            if cancel.as_ref().is_some_and(|request| request.hard) {
+                break;
+            }
+            let mut blocked_any = false;
+            let mut launch_paused = false;
+            if strict_failure.is_none() && quit_at.is_none() && cancel.is_none() {
+                let mut waiting = Vec::new();
+                // `pending` keeps manifest order, which makes it the launch
+                // order among ready tickets.
+                for ticket in pending.drain(..) {
+                    match dependency_gate(ticket, &ctx) {
+                        DependencyGate::Done => {}
+                        DependencyGate::Ready
+                            if strict_failure.is_none()
+                                && quit_at.is_none()
+                                && in_flight.len() < max_parallel
+                                && !launch_paused =>
+                        {
+                            if !memory.allows_launch(in_flight.len())
+                                || !disk.allows_launch(in_flight.len())
+                            {
+                                launch_paused = true;
+                                waiting.push(ticket);
+                                continue;
+                            }
+                            match confirm_ticket(ticket, &ctx).await? {
+                                TicketDecision::Run => in_flight.push(process_ticket(ticket, &ctx)),
+                                TicketDecision::Defer => {
+                                    deferred.insert(ticket.id.as_str());
+                                }
+                                TicketDecision::Skip => {
+                                    ctx.update_ticket(&ticket.id, |entry| {
+                                        entry.mark_finished(
+                                            TicketStatus::Skipped,
+                                            Some("Skipped on request".to_string()),
+                                        );
+                                    })?;
+                                    // Dependents may be ready now.
+                                    blocked_any = true;
+                                }
+                                TicketDecision::Quit => {
+                                    quit_at = Some(ticket.id.clone());
+                                    waiting.push(ticket);
+                                }
+                            }
+                        }
+                        DependencyGate::Ready | DependencyGate::Waiting => waiting.push(ticket),
+                        DependencyGate::Blocked(note) => {
+                            if opts.strict_deps && strict_failure.is_none() {
+                                strict_failure = Some(format!("ticket {}: {note}", ticket.id));
+                            }
+                            ctx.update_ticket(&ticket.id, |entry| {
+                                entry.mark_finished(TicketStatus::Blocked, Some(note));
+                            })?;
+                            blocked_any = true;
+                        }
+                    }
+                }
+                pending = waiting;
+            }
+            let next = if launch_paused {
+                // Recheck free memory and disk periodically rather than only when
+                // a running ticket finishes.
+                tokio::select! {
+                    result = in_flight.next() => result,
+                    _ = tokio::time::sleep(MEMORY_POLL_INTERVAL) => continue,
+                }
+            } else {
+                // Look for a cancellation request while sessions run.
+                tokio::select! {
+                    result = in_flight.next() => result,
+                    _ = tokio::time::sleep(CANCEL_POLL_INTERVAL), if !in_flight.is_empty() => continue,
+                }
+            };
+            match next {
+                Some(result) => result?,
+                // Blocking a ticket can unblock the evaluation of its dependents.
+                None if blocked_any => continue,
+                None => break,
+            }
+        }
+        // Dropping the running tickets' futures kills their sessions.
+        drop(in_flight);
+        if cancel.as_ref().is_some_and(|request| request.hard) {
+            for ticket in &manifest.tickets {
+                let status = ctx.ticket_state(&ticket.id).map(|entry| entry.status);
+                let (phase, status, note) = match status {
+                    Some(TicketStatus::RunningWorker) => (
+                        SessionPhase::Worker,
+                        TicketStatus::Pending,
+                        "Worker session killed by workflow cancel --hard",
+                    ),
+                    Some(TicketStatus::RunningReview) => (
+                        SessionPhase::Review,
+                        TicketStatus::NeedsReview,
+                        "Review session killed by workflow cancel --hard",
+                    ),
+                    _ => continue,
+                };
+                ctx.update_ticket(&ticket.id, |entry| {
+                    entry.finish_attempt(phase, None, 0);
+                    entry.status = status;
+                    entry.note = Some(note.to_string());
+                })?;
+            }
+        }
+        // Tickets waiting on a deferred ticket stay pending along with it.
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for ticket in &pending {
+                if !deferred.contains(ticket.id.as_str())
+                    && ticket
+                        .depends_on
+                        .iter()
+                        .any(|dependency| deferred.contains(dependency.as_str()))
+                {
+                    deferred.insert(ticket.id.as_str());
+                    changed = true;
+                }
+            }
+        }
+        pending.retain(|ticket| !deferred.contains(ticket.id.as_str()));
+        if strict_failure.is_none() && quit_at.is_none() && cancel.is_none() {
+            // Anything still waiting depends on a ticket that stopped short of
+            // completing without failing outright.
+            for ticket in pending {
+                ctx.update_ticket(&ticket.id, |entry| {
+                    entry.mark_finished(
+                        TicketStatus::Blocked,
+                        Some("Dependencies did not complete".to_string()),
+                    );
+                })?;
+            }
+        }
 
-    for ticket in &manifest.tickets {
-        process_ticket(
-            ticket,
-            &manifest,
-            &layout,
-            &mut state,
-            &launcher,
-            &state_path,
-            &opts,
-        )
-        .await?;
+        let state = ctx.into_state();
+        state.save(&state_path)?;
+        if let Some(metrics) = &metrics {
+            let _ = metrics.write(&state);
+        }
+        let mut report = WorkflowStatusReport::from_state(state, state_path);
+        report.allowed_failures = manifest.allowed_failures();
+        report.summaries = manifest.summaries();
+        if let Some(ticket_id) = quit_at {
+            warnings.push(format!(
+                "Run stopped on request before ticket {ticket_id}; pass --resume to continue"
+            ));
+            events.emit(WorkflowEvent::RunInterrupted {
+                reason: format!("stopped on request before ticket {ticket_id}"),
+                at: Utc::now(),
+            });
+        }
+        if let Some(request) = cancel {
+            CancelRequest::clear(&layout)?;
+            warnings.push("Run cancelled by workflow cancel; pass --resume to continue".to_string());
+            let reason = if request.hard {
+                "cancelled by workflow cancel --hard"
+            } else {
+                "cancelled by workflow cancel"
+            };
+            events.emit(WorkflowEvent::RunInterrupted {
+                reason: reason.to_string(),
+                at: Utc::now(),
+            });
+        }
+        report.warnings = warnings;
+        if opts.report_interval.is_some() {
+            write_report_json(&layout.report_file(), &report)?;
+        }
+        write_run_summary(
+            &layout.summary_file(),
+            &report.workflow_name,
+            &report.tickets,
+            started_at,
+            Utc::now(),
+            opts.append_summary,
+        )?;
+        let strict_error = strict_failure
+            .map(|failure| format!("aborting workflow due to --strict-deps: {failure}"));
+        Ok((report, strict_error))
+    }
+    .await;
+    let (failed, error) = match &outcome {
+        Ok((report, strict_error)) => (
+            report
+                .failed_tickets()
+                .iter()
+                .map(|ticket| ticket.ticket_id.clone())
+                .collect(),
+            strict_error.clone(),
+        ),
+        Err(err) => (Vec::new(), Some(format!("{err:#}"))),
+    };
+    events.emit(WorkflowEvent::RunFinished {
+        workflow_name,
+        failed,
+        error,
+        at: Utc::now(),
+    });
+    events.close().await;
+    let (report, strict_error) = outcome?;
+    if let Some(error) = strict_error {
+        bail!(error);
     }
+    Ok(report)
+}
 
-    state.save(&state_path)?;
-    Ok(WorkflowStatusReport::from_state(state, state_path))
+/// Number of tickets to run concurrently: an explicit `max_parallel` wins;
+/// otherwise `concurrency_from_env` reads `CODEX_WORKFLOW_PARALLEL`, falling
+/// back to the number of available cores. Without either, tickets run one at a
+/// time.
+pub fn resolve_max_parallel(opts: &WorkflowRunOptions) -> usize {
+    if let Some(max_parallel) = opts.max_parallel {
+        return max_parallel.max(1);
+    }
+    if !opts.concurrency_from_env {
+        return 1;
+    }
+    std::env::var(PARALLELISM_ENV_VAR)
+        .ok()
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .filter(|value| *value > 0)
+        .or_else(|| {
+            std::thread::available_parallelism()
+                .ok()
+                .map(std::num::NonZeroUsize::get)
+        })
+        .unwrap_or(1)
+}
+
+/// Fails when `git.branch_per_ticket` or `git.autocommit` would run git in a
+/// working directory that selected tickets share while they run in
+/// parallel, where one ticket's checkout would switch the branch under
+/// another and its commit would take in the other's changes.
+/// `git.use_worktrees` gives each ticket a checkout of its own instead.
+fn check_parallel_git(
+    manifest: &WorkflowManifest,
+    opts: &WorkflowRunOptions,
+    selected: &HashSet<&str>,
+    max_parallel: usize,
+) -> Result<()> {
+    let settings = &manifest.git;
+    let autocommit = settings.autocommit && !opts.no_autocommit;
+    if max_parallel <= 1 || settings.use_worktrees || !(settings.branch_per_ticket || autocommit) {
+        return Ok(());
+    }
+    let base_dir = manifest.base_dir();
+    let mut owners: BTreeMap<PathBuf, &str> = BTreeMap::new();
+    for ticket in manifest
+        .tickets
+        .iter()
+        .filter(|ticket| selected.contains(ticket.id.as_str()))
+    {
+        let working_dir = ticket.resolved_working_dir(&base_dir);
+        if let Some(other) = owners.insert(working_dir.clone(), &ticket.id) {
+            anyhow::bail!(
+                "tickets {other} and {} share working directory {} and their git \
+                branches or commits would get in each other's way with max_parallel \
+                {max_parallel}; set git.use_worktrees or run them one at a time",
+                ticket.id,
+                working_dir.display()
+            );
+        }
+    }
+    Ok(())
 }
 
+/// Loads the saved state, looking for it where [`run_workflow`] with the same
+/// `artifacts_dir`, `artifacts_root`, and `base_dir` would write it.
 pub fn load_status(
     manifest_path: &Path,
     artifacts_dir: Option<PathBuf>,
-) -> Result<Option<WorkflowStatusReport>> {
-    let manifest = WorkflowManifest::load(manifest_path)?;
-    let layout = WorkflowLayout::new(resolve_artifacts_dir(&manifest, &artifacts_dir));
+    artifacts_root: Option<PathBuf>,
+    base_dir: Option<PathBuf>,
+) -> std::result::Result<Option<WorkflowStatusReport>, WorkflowError> {
+    let mut manifest = WorkflowManifest::load(manifest_path)?;
+    manifest.base_dir = base_dir;
+    let layout = WorkflowLayout::resolve(
+        &manifest,
+        artifacts_dir.as_deref(),
+        artifacts_root.as_deref(),
+    );
     let state_path = layout.state_file();
     if !state_path.exists() {
         return Ok(None);
     }
     let state = WorkflowState::load(&state_path)?;
-    Ok(Some(WorkflowStatusReport::from_state(state, state_path)))
-}
-
-async fn process_ticket(
-    ticket: &TicketSpec,
-    manifest: &WorkflowManifest,
-    layout: &WorkflowLayout,
-    state: &mut WorkflowState,
-    launcher: &SessionLauncher,
-    state_path: &Path,
-    opts: &WorkflowRunOptions,
-) -> Result<()> {
-    let status = match state.ticket(&ticket.id) {
-        Some(entry) => entry.status.clone(),
-        None => return Ok(()),
-    };
-
-    match status {
-        TicketStatus::Complete => Ok(()),
-        TicketStatus::Failed | TicketStatus::Blocked => Ok(()),
-        TicketStatus::NeedsReview | TicketStatus::RunningReview => {
-            run_review(ticket, manifest, layout, state, launcher, state_path, opts).await
-        }
-        _ => {
-            run_worker(ticket, manifest, layout, state, launcher, state_path, opts).await?;
-            run_review(ticket, manifest, layout, state, launcher, state_path, opts).await
+    let orphans = state.orphaned_tickets(&manifest);
+    let mut report = WorkflowStatusReport::from_state(state, state_path);
+    report.allowed_failures = manifest.allowed_failures();
+    report.summaries = manifest.summaries();
+    report.run = read_lock(&layout.lock_file());
+    if let Some(meta) = layout.read_meta() {
+        let expected = WorkflowMeta::for_manifest(&manifest);
+        if meta.workflow_name != expected.workflow_name
+            || meta.manifest_path != expected.manifest_path
+        {
+            report.warnings.push(format!(
+                "{} was created for workflow {} ({}), not this manifest",
+                layout.root().display(),
+                meta.workflow_name,
+                meta.manifest_path.display()
+            ));
         }
     }
+    if !orphans.is_empty() {
+        report.warnings.push(orphan_warning(&orphans));
+    }
+    Ok(Some(report))
 }
 
-async fn run_worker(
-    ticket: &TicketSpec,
+/// Reports, prunes, or rejects state entries for tickets that were removed
+/// from the manifest, according to the run options.
+fn handle_orphans(
+    state: &mut WorkflowState,
     manifest: &WorkflowManifest,
     layout: &WorkflowLayout,
-    state: &mut WorkflowState,
-    launcher: &SessionLauncher,
-    state_path: &Path,
     opts: &WorkflowRunOptions,
+    warnings: &mut Vec<String>,
 ) -> Result<()> {
-    let worker_log = layout.worker_log_path(&ticket.id);
-    layout.ensure_ticket_dir(&ticket.id)?;
-    let working_dir = ticket.resolved_working_dir(&manifest.manifest_dir());
-    if !working_dir.exists() {
+    let orphans = state.orphaned_tickets(manifest);
+    if orphans.is_empty() {
+        return Ok(());
+    }
+    if opts.fail_on_orphans {
         bail!(
-            "working directory {} does not exist for ticket {}",
-            working_dir.display(),
-            ticket.id
+            "workflow state has entries for tickets no longer in the manifest: {}",
+            orphans.join(", ")
         );
     }
-    let patch_dir = layout.patch_dir(&ticket.id);
-    std::fs::create_dir_all(&patch_dir)
-        .with_context(|| format!("failed to create {}", patch_dir.display()))?;
-    let prompt = ticket
-        .prompt
-        .clone()
-        .unwrap_or_else(|| build_worker_prompt(manifest, ticket, layout));
-    let request = SessionRequest {
-        prompt,
-        working_dir,
-        log_path: worker_log.clone(),
-        model: opts.worker_model.clone(),
-    };
-    if let Some(ticket_state) = state.ticket_mut(&ticket.id) {
-        ticket_state.set_worker_log(worker_log.clone());
-        ticket_state.mark_running(TicketStatus::RunningWorker);
-    }
-    state.save(state_path)?;
-    let result = launcher.run(request).await?;
-    let ticket_state = state
-        .ticket_mut(&ticket.id)
-        .expect("ticket state exists after worker run");
-    if result.success {
-        ticket_state.status = TicketStatus::NeedsReview;
-        ticket_state.note = Some("Worker completed successfully".to_string());
-    } else {
-        ticket_state.mark_finished(
-            TicketStatus::Failed,
-            Some(format!(
-                "Worker failed with status {:?}",
-                result.status_code
-            )),
-        );
+    if !opts.prune_orphans {
+        warnings.push(orphan_warning(&orphans));
+        return Ok(());
     }
-    state.save(state_path)?;
+    state.remove_tickets(&orphans);
+    if opts.delete_orphan_artifacts {
+        for ticket_id in &orphans {
+            for dir in layout.all_ticket_dirs(ticket_id) {
+                std::fs::remove_dir_all(&dir)
+                    .with_context(|| format!("failed to remove {}", dir.display()))?;
+            }
+        }
+    }
+    warnings.push(format!(
+        "Pruned state for tickets no longer in the manifest: {}",
+        orphans.join(", ")
+    ));
     Ok(())
 }
 
-async fn run_review(
-    ticket: &TicketSpec,
-    manifest: &WorkflowManifest,
-    layout: &WorkflowLayout,
-    state: &mut WorkflowState,
-    launcher: &SessionLauncher,
-    state_path: &Path,
-    opts: &WorkflowRunOptions,
-) -> Result<()> {
-    let status = match state.ticket(&ticket.id) {
-        Some(entry) => entry.status.clone(),
-        None => return Ok(()),
-    };
+fn orphan_warning(orphans: &[String]) -> String {
+    format!(
+        "State has entries for tickets no longer in the manifest: {}; \
+        pass --prune-orphans to remove them",
+        orphans.join(", ")
+    )
+}
 
-    if status == TicketStatus::Failed
-        || status == TicketStatus::Complete
-        || status == TicketStatus::Blocked
-    {
-        return Ok(());
+/// Everything a ticket needs while it runs. Tickets may run concurrently, so
+/// the state sits behind a mutex that is only held for short, synchronous
+/// updates (never across an `.await`).
+struct RunContext<'a> {
+    manifest: &'a WorkflowManifest,
+    layout: &'a WorkflowLayout,
+    runner: &'a dyn SessionRunner,
+    state_path: &'a Path,
+    opts: &'a WorkflowRunOptions,
+    events: &'a EventSink,
+    preflight: Option<&'a Preflight>,
+    metrics: Option<&'a MetricsFile>,
+    lock: &'a RunLock,
+    /// Values of `secrets_file`, redacted from the prompt files.
+    secret_values: Vec<String>,
+    state: Mutex<WorkflowState>,
+}
+
+impl RunContext<'_> {
+    fn lock_state(&self) -> MutexGuard<'_, WorkflowState> {
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
     }
 
-    if !matches!(
-        status,
-        TicketStatus::NeedsReview | TicketStatus::RunningReview
-    ) {
-        return Ok(());
+    fn ticket_state(&self, ticket_id: &str) -> Option<TicketRunState> {
+        self.lock_state().ticket(ticket_id).cloned()
     }
 
-    let review_log = layout.review_log_path(&ticket.id);
-    let working_dir = ticket.resolved_working_dir(&manifest.manifest_dir());
-    if !working_dir.exists() {
-        bail!(
-            "working directory {} does not exist for ticket {}",
-            working_dir.display(),
-            ticket.id
-        );
+    /// Writes the report of the run so far to `report.json`.
+    fn write_report_snapshot(&self) -> Result<()> {
+        let state = self.lock_state().clone();
+        let mut report = WorkflowStatusReport::from_state(state, self.state_path.to_path_buf());
+        report.allowed_failures = self.manifest.allowed_failures();
+        report.summaries = self.manifest.summaries();
+        write_report_json(&self.layout.report_file(), &report)
     }
-    let prompt = ticket
-        .review_prompt
-        .clone()
-        .unwrap_or_else(|| build_review_prompt(manifest, ticket, layout));
-    let request = SessionRequest {
-        prompt,
-        working_dir,
-        log_path: review_log.clone(),
-        model: opts
-            .reviewer_model
-            .clone()
-            .or_else(|| opts.worker_model.clone()),
-    };
 
-    if let Some(entry) = state.ticket_mut(&ticket.id) {
-        entry.set_review_log(review_log.clone());
-        entry.mark_running(TicketStatus::RunningReview);
+    /// Applies `update` to a ticket's state, persists the whole state, and
+    /// emits an event if the ticket's status changed.
+    fn update_ticket<R>(
+        &self,
+        ticket_id: &str,
+        update: impl FnOnce(&mut TicketRunState) -> R,
+    ) -> Result<R> {
+        let mut state = self.lock_state();
+        let entry = state
+            .ticket_mut(ticket_id)
+            .with_context(|| format!("no state recorded for ticket {ticket_id}"))?;
+        let from = entry.status.clone();
+        let result = update(entry);
+        let changed = (entry.status != from).then(|| {
+            (
+                entry.status.clone(),
+                entry.note.clone(),
+                entry.worker_log.clone(),
+                entry.review_log.clone(),
+            )
+        });
+        let event = changed.map(|(to, note, worker_log, review_log)| {
+            debug!(
+                ticket = ticket_id,
+                ?from,
+                ?to,
+                note = note.as_deref(),
+                "ticket status changed"
+            );
+            let (done, total) = state.progress();
+            WorkflowEvent::TicketStatusChanged {
+                workflow_name: state.workflow_name.clone(),
+                ticket_id: ticket_id.to_string(),
+                from,
+                to,
+                note,
+                worker_log,
+                review_log,
+                done,
+                total,
+                at: Utc::now(),
+            }
+        });
+        state.save(self.state_path)?;
+        if let Some(metrics) = self.metrics {
+            // Checked when the run started; a later failure, e.g. a full
+            // disk, should not stop the run.
+            let _ = metrics.write(&state);
+        }
+        // Same for the heartbeat `workflow status` reads.
+        let _ = self.lock.heartbeat();
+        drop(state);
+        if let Some(event) = event {
+            self.events.emit(event);
+        }
+        Ok(result)
     }
-    state.save(state_path)?;
 
-    let result = launcher.run(request).await?;
-    let entry = state
-        .ticket_mut(&ticket.id)
-        .expect("ticket state exists after review");
-    if result.success {
-        entry.mark_finished(TicketStatus::Complete, Some("Review passed".to_string()));
-    } else {
-        entry.mark_finished(
-            TicketStatus::Failed,
-            Some(format!(
-                "Review failed with status {:?}",
-                result.status_code
-            )),
+    fn session_started(&self, ticket_id: &str, phase: SessionPhase, attempt: i32, log_path: &Path) {
+        self.events.emit(WorkflowEvent::SessionStarted {
+            ticket_id: ticket_id.to_string(),
+            phase,
+            attempt,
+            log_path: log_path.to_path_buf(),
+            at: Utc::now(),
+        });
+    }
+
+    /// Whether a session with `prompt` may launch. A prompt estimated over
+    /// the token budget is reported, and fails the ticket with
+    /// `strict_context`.
+    fn check_prompt_budget(
+        &self,
+        ticket_id: &str,
+        phase: SessionPhase,
+        prompt: &str,
+    ) -> Result<bool> {
+        let Some(budget) = self
+            .opts
+            .prompt_token_budget
+            .or(self.manifest.prompt_token_budget)
+        else {
+            return Ok(true);
+        };
+        let estimated_tokens = estimate_tokens(prompt);
+        if estimated_tokens <= budget {
+            return Ok(true);
+        }
+        let strict = self.opts.strict_context;
+        self.events.emit(WorkflowEvent::PromptOverBudget {
+            ticket_id: ticket_id.to_string(),
+            phase,
+            estimated_tokens,
+            budget,
+            strict,
+            at: Utc::now(),
+        });
+        if !strict {
+            return Ok(true);
+        }
+        let label = match phase {
+            SessionPhase::Worker => "Worker",
+            SessionPhase::Review => "Review",
+        };
+        self.update_ticket(ticket_id, |entry| {
+            entry.mark_finished(
+                TicketStatus::Failed,
+                Some(format!(
+                    "{label} prompt is ~{estimated_tokens} tokens, over the budget of {budget}"
+                )),
+            );
+        })?;
+        Ok(false)
+    }
+
+    /// The request for the ticket's next `phase` session, or `None` after
+    /// failing the ticket when its prompt command produced no prompt.
+    async fn ticket_session_request(
+        &self,
+        phase: SessionPhase,
+        ticket: &TicketSpec,
+    ) -> Result<Option<SessionRequest>> {
+        let ticket_state = self.ticket_state(&ticket.id);
+        let request = session_request(
+            phase,
+            ticket,
+            self.manifest,
+            self.layout,
+            self.opts,
+            ticket_state.as_ref(),
+        )
+        .await;
+        match request {
+            Ok(request) => Ok(Some(request)),
+            Err(err) => {
+                let failed = err.downcast::<PromptCommandFailed>()?;
+                self.update_ticket(&ticket.id, |entry| {
+                    entry.mark_finished(TicketStatus::Failed, Some(failed.to_string()));
+                })?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Runs a session inside a span carrying the ticket, phase, attempt and
+    /// model.
+    async fn run_session(
+        &self,
+        ticket_id: &str,
+        phase: SessionPhase,
+        attempt: i32,
+        request: SessionRequest,
+    ) -> Result<SessionResult> {
+        let span = info_span!(
+            "session",
+            ticket = ticket_id,
+            phase = phase.as_str(),
+            attempt,
+            model = request.model.as_deref(),
         );
+        async {
+            let result = match self.runner.run(request).await {
+                Ok(result) => result,
+                Err(err) => {
+                    if let Some(WorkflowError::LauncherSpawn { .. }) = err.downcast_ref() {
+                        self.session_not_started(ticket_id, phase)?;
+                    }
+                    return Err(err);
+                }
+            };
+            debug!(
+                success = result.success,
+                timed_out = result.timed_out,
+                exit_code = result.status_code,
+                "session exited"
+            );
+            Ok(result)
+        }
+        .instrument(span)
+        .await
     }
-    state.save(state_path)?;
-    Ok(())
-}
 
-fn build_worker_prompt(
-    manifest: &WorkflowManifest,
-    ticket: &TicketSpec,
-    layout: &WorkflowLayout,
-) -> String {
-    let mut sections = Vec::new();
-    if let Some(overview) = &manifest.overview {
-        sections.push(format!("Workflow overview:\n{overview}\n"));
+    /// Puts a ticket whose session could not be started back where the next
+    /// run picks that session up; nothing ran, so it did not fail.
+    fn session_not_started(&self, ticket_id: &str, phase: SessionPhase) -> Result<()> {
+        let (status, note) = match phase {
+            SessionPhase::Worker => (TicketStatus::Pending, "Worker session could not be started"),
+            SessionPhase::Review => (
+                TicketStatus::NeedsReview,
+                "Review session could not be started",
+            ),
+        };
+        self.update_ticket(ticket_id, |entry| {
+            entry.discard_attempt(phase);
+            entry.status = status;
+            entry.note = Some(note.to_string());
+        })
     }
-    sections.push(format!("Ticket {}: {}\n", ticket.id, ticket.summary));
-    if !ticket.requirements.is_empty() {
-        let reqs = ticket
-            .requirements
-            .iter()
-            .map(|req| format!("- {req}"))
-            .collect::<Vec<_>>()
-            .join("\n");
-        sections.push(format!("Requirements:\n{reqs}\n"));
+
+    fn session_finished(
+        &self,
+        ticket_id: &str,
+        phase: SessionPhase,
+        attempt: i32,
+        result: &SessionResult,
+    ) {
+        self.events.emit(WorkflowEvent::SessionFinished {
+            ticket_id: ticket_id.to_string(),
+            phase,
+            attempt,
+            success: result.success,
+            timed_out: result.timed_out,
+            exit_code: result.status_code,
+            at: Utc::now(),
+        });
     }
-    let patch_dir = layout.patch_dir(&ticket.id).display().to_string();
-    sections.push(format!(
-        "Work inside the repository directory and save any generated patches or notes under {patch_dir}. \
-        Log your progress clearly."
-    ));
-    wrap_sections(&sections)
-}
 
-fn build_review_prompt(
-    manifest: &WorkflowManifest,
-    ticket: &TicketSpec,
-    layout: &WorkflowLayout,
-) -> String {
-    let mut sections = Vec::new();
-    if let Some(overview) = &manifest.overview {
-        sections.push(format!("Workflow overview:\n{overview}\n"));
+    /// Runs the preflight probes until they pass, pausing longer after each
+    /// failure. Before the first ticket (`ticket_id` is `None`), a `workflow
+    /// cancel` request also ends the wait.
+    async fn wait_for_preflight(&self, ticket_id: Option<&str>) {
+        let Some(preflight) = self.preflight else {
+            return;
+        };
+        let mut failures = 0;
+        loop {
+            let result = preflight.check().await;
+            let delay = result.is_err().then(|| {
+                failures += 1;
+                retry_delay(failures)
+            });
+            self.events.emit(WorkflowEvent::PreflightChecked {
+                ticket_id: ticket_id.map(str::to_string),
+                passed: result.is_ok(),
+                error: result.err(),
+                retry_in_secs: delay.map(|delay| delay.as_secs()),
+                at: Utc::now(),
+            });
+            let Some(delay) = delay else {
+                return;
+            };
+            if ticket_id.is_some() {
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+            let deadline = Instant::now() + delay;
+            while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+                if CancelRequest::read(self.layout).is_some() {
+                    return;
+                }
+                tokio::time::sleep(left.min(CANCEL_POLL_INTERVAL)).await;
+            }
+        }
     }
-    sections.push(format!(
-        "Review ticket {} ({}) for correctness and completeness.",
-        ticket.id, ticket.summary
-    ));
-    if !ticket.requirements.is_empty() {
-        let reqs = ticket
-            .requirements
-            .iter()
-            .map(|req| format!("- {req}"))
-            .collect::<Vec<_>>()
-            .join("\n");
-        sections.push(format!(
-            "Confirm that the following requirements are satisfied:\n{reqs}\n"
-        ));
+
+    fn into_state(self) -> WorkflowState {
+        self.state
+            .into_inner()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
     }
-    let worker_log = layout.worker_log_path(&ticket.id).display().to_string();
-    sections.push(format!(
-        "Consult the worker log at {worker_log} and ensure all changes are tested. \
-        Provide a concise approval or list blocking issues."
-    ));
-    wrap_sections(&sections)
 }
 
-fn wrap_sections(sections: &[String]) -> String {
-    let mut result = String::new();
-    for section in sections {
-        let wrapped = wrap(section, 100);
-        for line in wrapped {
-            result.push_str(line.trim_end());
-            result.push('\n');
+enum DependencyGate {
+    /// The ticket already reached a final status; there is nothing to run.
+    Done,
+    Ready,
+    Waiting,
+    Blocked(String),
+}
+
+/// Ids of the tickets matching any of `patterns`, or every ticket when there
+/// are none. Fails if a pattern matches no ticket.
+pub(crate) fn select_tickets<'a>(
+    manifest: &'a WorkflowManifest,
+    patterns: &[String],
+) -> Result<HashSet<&'a str>> {
+    if patterns.is_empty() {
+        return Ok(manifest
+            .tickets
+            .iter()
+            .map(|ticket| ticket.id.as_str())
+            .collect());
+    }
+    let mut selected = HashSet::new();
+    for pattern in patterns {
+        let glob = glob::Pattern::new(pattern)
+            .with_context(|| format!("invalid ticket pattern {pattern:?}"))?;
+        let mut matched = false;
+        for ticket in &manifest.tickets {
+            // Exact ids win so ids containing glob characters stay selectable.
+            if ticket.id == *pattern || glob.matches(&ticket.id) {
+                selected.insert(ticket.id.as_str());
+                matched = true;
+            }
+        }
+        if !matched {
+            bail!("--ticket {pattern:?} does not match any ticket in the manifest");
         }
-        result.push('\n');
     }
-    result.trim().to_string()
+    Ok(selected)
 }
 
-fn resolve_artifacts_dir(manifest: &WorkflowManifest, override_dir: &Option<PathBuf>) -> PathBuf {
-    match override_dir {
-        Some(dir) => dir.clone(),
-        None => manifest
-            .manifest_dir()
-            .join(".codex")
-            .join("workflows")
-            .join(manifest.workflow_name()),
+/// Decides whether a ticket can start based on the state of its dependencies.
+fn dependency_gate(ticket: &TicketSpec, ctx: &RunContext<'_>) -> DependencyGate {
+    let state = ctx.lock_state();
+    let status_of = |id: &str| state.ticket(id).map(|entry| entry.status.clone());
+    if matches!(
+        status_of(&ticket.id),
+        None | Some(
+            TicketStatus::Complete
+                | TicketStatus::Failed
+                | TicketStatus::Blocked
+                | TicketStatus::Skipped
+        )
+    ) {
+        return DependencyGate::Done;
+    }
+    let mut waiting = false;
+    for dependency in &ticket.depends_on {
+        match status_of(dependency) {
+            Some(TicketStatus::Complete | TicketStatus::Skipped) => {}
+            // `ID:worker` only waits for the worker to succeed.
+            Some(TicketStatus::NeedsReview | TicketStatus::RunningReview)
+                if ticket.worker_dependencies.contains(dependency) => {}
+            Some(TicketStatus::Failed) => {
+                return DependencyGate::Blocked(format!("Dependency {dependency} failed"));
+            }
+            Some(TicketStatus::Blocked) => {
+                return DependencyGate::Blocked(format!("Dependency {dependency} is blocked"));
+            }
+            _ => waiting = true,
+        }
+    }
+    if waiting {
+        DependencyGate::Waiting
+    } else {
+        DependencyGate::Ready
+    }
+}
+
+async fn confirm_ticket(ticket: &TicketSpec, ctx: &RunContext<'_>) -> Result<TicketDecision> {
+    let Some(confirmer) = ctx.opts.confirmer.clone() else {
+        return Ok(TicketDecision::Run);
+    };
+    let status = ctx
+        .ticket_state(&ticket.id)
+        .map(|entry| entry.status)
+        .unwrap_or(TicketStatus::Pending);
+    let working_dir = ticket.resolved_working_dir(&ctx.manifest.base_dir());
+    let plan = TicketPlan::new(ticket, working_dir, status);
+    // Confirmers may wait on terminal input, which must not stall the runtime.
+    tokio::task::spawn_blocking(move || confirmer.confirm(&plan))
+        .await
+        .context("ticket confirmation did not finish")?
+}
+
+#[tracing::instrument(name = "ticket", skip_all, fields(ticket = %ticket.id))]
+async fn process_ticket(ticket: &TicketSpec, ctx: &RunContext<'_>) -> Result<()> {
+    let status = match ctx.ticket_state(&ticket.id) {
+        Some(entry) => entry.status,
+        None => return Ok(()),
+    };
+    if status.is_final() {
+        debug!(?status, "ticket already finished");
+        return Ok(());
+    }
+    debug!(?status, "starting ticket");
+    if !add_ticket_worktree(ticket, ctx).await? {
+        return Ok(());
+    }
+    let result = run_sessions(ticket, status, ctx).await;
+    // Even a ticket that failed or errored gives its worktree back; one left
+    // unfinished by a cancelled run keeps it for the next run.
+    let finished = result.is_err()
+        || ctx
+            .ticket_state(&ticket.id)
+            .is_some_and(|entry| entry.status.is_final());
+    if !finished {
+        return result;
+    }
+    let cleanup = remove_ticket_worktree(ticket, ctx).await;
+    result.and(cleanup)
+}
+
+/// Runs the ticket's worker and review sessions, reworks included.
+async fn run_sessions(
+    ticket: &TicketSpec,
+    status: TicketStatus,
+    ctx: &RunContext<'_>,
+) -> Result<()> {
+    match status {
+        TicketStatus::NeedsReview | TicketStatus::RunningReview => run_review(ticket, ctx).await?,
+        _ => {
+            run_worker(ticket, ctx).await?;
+            run_review(ticket, ctx).await?;
+        }
+    }
+    // A review that requested changes sends the ticket back to the worker.
+    while ctx
+        .ticket_state(&ticket.id)
+        .is_some_and(|entry| entry.status == TicketStatus::Pending)
+    {
+        run_worker(ticket, ctx).await?;
+        run_review(ticket, ctx).await?;
+    }
+    Ok(())
+}
+
+async fn run_worker(ticket: &TicketSpec, ctx: &RunContext<'_>) -> Result<()> {
+    let RunContext {
+        manifest,
+        layout,
+        opts,
+        ..
+    } = *ctx;
+    let working_dir = session_working_dir(ticket, manifest, ctx.ticket_state(&ticket.id).as_ref());
+    if !working_dir.exists() {
+        return Err(WorkflowError::WorkingDirMissing {
+            ticket_id: ticket.id.clone(),
+            path: working_dir,
+        }
+        .into());
+    }
+    if !switch_to_ticket_branch(ticket, ctx, &working_dir).await? {
+        return Ok(());
+    }
+    let Some(base_request) = ctx
+        .ticket_session_request(SessionPhase::Worker, ticket)
+        .await?
+    else {
+        return Ok(());
+    };
+    if !ctx.check_prompt_budget(&ticket.id, SessionPhase::Worker, &base_request.prompt)? {
+        return Ok(());
+    }
+    layout.ensure_ticket_dir(&ticket.id)?;
+    let prompt_path = layout.worker_prompt_path(&ticket.id);
+    write_prompt_file(&prompt_path, &base_request.prompt, &ctx.secret_values)?;
+    let retry_policy = RetryPolicy::resolve(manifest, opts);
+    let resume_session = manifest.worker_resumes_session || opts.worker_resumes_session;
+    let diff_base = worker_diff_base(ticket, ctx, &working_dir).await?;
+    record_preexisting_changes(ticket, ctx, &working_dir).await?;
+    let mut retries = 0;
+    loop {
+        let attempt = next_attempt(ctx, &ticket.id, SessionPhase::Worker);
+        let worker_log = layout.worker_log_path(&ticket.id, attempt);
+        let timeout = retry_policy.timeout(base_request.timeout, retries);
+        let request = SessionRequest {
+            log_path: worker_log.clone(),
+            attempt: Some(attempt),
+            timeout,
+            resume_session: ctx
+                .ticket_state(&ticket.id)
+                .and_then(|entry| entry.worker_session_id)
+                .filter(|_| resume_session),
+            ..base_request.clone()
+        };
+        if opts.preflight_each_session {
+            ctx.wait_for_preflight(Some(&ticket.id)).await;
+        }
+        ctx.update_ticket(&ticket.id, |entry| {
+            entry.start_attempt(
+                SessionPhase::Worker,
+                attempt,
+                worker_log.clone(),
+                timeout,
+                request.profile.clone(),
+            );
+            entry.worker_prompt = Some(prompt_path.clone());
+            entry.mark_running(TicketStatus::RunningWorker);
+        })?;
+        ctx.session_started(&ticket.id, SessionPhase::Worker, attempt, &worker_log);
+        let result = ctx
+            .run_session(&ticket.id, SessionPhase::Worker, attempt, request)
+            .await?;
+        ctx.session_finished(&ticket.id, SessionPhase::Worker, attempt, &result);
+        prune_logs(ctx, &ticket.id, SessionPhase::Worker)?;
+        let retries_left = retries < retry_policy.max_retries;
+        let retry = !result.success && retries_left && retry_policy.allows(&result);
+        let diff = if result.success {
+            capture_git_diff(
+                &working_dir,
+                &layout.patch_dir(&ticket.id),
+                diff_base.as_deref(),
+            )
+            .await?
+        } else {
+            None
+        };
+        let missing = ticket.missing_artifacts(&working_dir);
+        ctx.update_ticket(&ticket.id, |entry| {
+            entry.finish_attempt(
+                SessionPhase::Worker,
+                result.status_code,
+                result.truncated_bytes,
+            );
+            // Keep the last known session when an attempt reports none, so
+            // later attempts and the review can still continue it.
+            if result.session_id.is_some() {
+                entry.worker_session_id = result.session_id.clone();
+            }
+            entry.worker_command = Some(result.command_line.clone());
+            entry.worker_model = base_request.model.clone();
+            if result.last_message.is_some() {
+                entry.worker_output = Some(layout.worker_output_path(&ticket.id));
+            }
+            let failure = session_failure_note("Worker", &result);
+            if result.success {
+                entry.worker_diff = diff.as_ref().map(|diff| diff.diff_path.clone());
+                entry.changed_files = diff.as_ref().map(|diff| diff.changed_files);
+                if missing.is_empty() {
+                    entry.status = TicketStatus::NeedsReview;
+                    entry.note = Some("Worker completed successfully".to_string());
+                } else {
+                    entry.mark_finished(
+                        TicketStatus::Blocked,
+                        Some(missing_artifacts_note(&missing)),
+                    );
+                }
+            } else if retry {
+                entry.note = Some(with_stderr_tail(
+                    format!(
+                        "{failure}; retrying ({} of {})",
+                        retries + 1,
+                        retry_policy.max_retries
+                    ),
+                    &result,
+                ));
+            } else if retries_left {
+                entry.mark_finished(
+                    TicketStatus::Failed,
+                    Some(with_stderr_tail(
+                        format!("{failure}; exit code is not retryable"),
+                        &result,
+                    )),
+                );
+            } else {
+                entry.mark_finished(
+                    TicketStatus::Failed,
+                    Some(with_stderr_tail(failure, &result)),
+                );
+            }
+        })?;
+        if !retry {
+            return Ok(());
+        }
+        retries += 1;
+        ctx.events.emit(WorkflowEvent::SessionRetrying {
+            ticket_id: ticket.id.clone(),
+            phase: SessionPhase::Worker,
+            retry: retries,
+            max_retries: retry_policy.max_retries,
+            at: Utc::now(),
+        });
+    }
+}
+
+/// With `review_against_diff`, the commit the worker's diff is taken
+/// against: `HEAD` before the ticket's first worker session, kept across
+/// retries and reworks.
+async fn worker_diff_base(
+    ticket: &TicketSpec,
+    ctx: &RunContext<'_>,
+    working_dir: &Path,
+) -> Result<Option<String>> {
+    if !(ctx.manifest.review_against_diff || ctx.opts.review_against_diff) {
+        return Ok(None);
+    }
+    if let Some(base) = ctx
+        .ticket_state(&ticket.id)
+        .and_then(|entry| entry.diff_base)
+    {
+        return Ok(Some(base));
+    }
+    let base = head_commit(working_dir).await;
+    if base.is_some() {
+        ctx.update_ticket(&ticket.id, |entry| entry.diff_base = base.clone())?;
+    }
+    Ok(base)
+}
+
+/// With `git.autocommit`, remembers which paths already had changes before
+/// the ticket's first worker session, so its commit leaves them out.
+async fn record_preexisting_changes(
+    ticket: &TicketSpec,
+    ctx: &RunContext<'_>,
+    working_dir: &Path,
+) -> Result<()> {
+    if !ctx.manifest.git.autocommit
+        || ctx.opts.no_autocommit
+        || ctx
+            .ticket_state(&ticket.id)
+            .is_some_and(|entry| entry.preexisting_changes.is_some())
+    {
+        return Ok(());
+    }
+    // Outside a git work tree there is nothing to commit anyway.
+    let paths = changed_paths(working_dir).await.unwrap_or_default();
+    ctx.update_ticket(&ticket.id, |entry| {
+        entry.preexisting_changes = Some(paths);
+    })
+}
+
+/// When a failed worker session is rerun: up to `max_retries` times, and only
+/// for the listed exit codes if there are any. Each retry multiplies the
+/// session timeout by `timeout_backoff`.
+struct RetryPolicy<'a> {
+    max_retries: i32,
+    exit_codes: &'a [i32],
+    timeout_backoff: f64,
+}
+
+impl<'a> RetryPolicy<'a> {
+    fn resolve(manifest: &'a WorkflowManifest, opts: &'a WorkflowRunOptions) -> Self {
+        let exit_codes = if opts.retry_on_exit_codes.is_empty() {
+            &manifest.retry_on_exit_codes
+        } else {
+            &opts.retry_on_exit_codes
+        };
+        Self {
+            max_retries: opts.max_retries.unwrap_or(manifest.max_retries),
+            exit_codes,
+            timeout_backoff: opts
+                .timeout_backoff
+                .or(manifest.timeout_backoff)
+                .unwrap_or(1.0),
+        }
+    }
+
+    /// The timeout of the worker session after `retries` failed ones.
+    fn timeout(&self, base: Option<Duration>, retries: i32) -> Option<Duration> {
+        let factor = self.timeout_backoff.powi(retries);
+        base.map(|base| {
+            Duration::try_from_secs_f64(base.as_secs_f64() * factor).unwrap_or(Duration::MAX)
+        })
+    }
+
+    fn allows(&self, result: &SessionResult) -> bool {
+        self.exit_codes.is_empty()
+            || result
+                .status_code
+                .is_some_and(|code| self.exit_codes.contains(&code))
+    }
+}
+
+async fn run_review(ticket: &TicketSpec, ctx: &RunContext<'_>) -> Result<()> {
+    let RunContext {
+        manifest,
+        layout,
+        opts,
+        ..
+    } = *ctx;
+    let Some(ticket_state) = ctx.ticket_state(&ticket.id) else {
+        return Ok(());
+    };
+    if !matches!(
+        ticket_state.status,
+        TicketStatus::NeedsReview | TicketStatus::RunningReview
+    ) {
+        return Ok(());
+    }
+
+    let attempt = next_attempt(ctx, &ticket.id, SessionPhase::Review);
+    let review_log = layout.review_log_path(&ticket.id, attempt);
+    // The worker's directory holds its changes; the review may run elsewhere,
+    // and then needs it only for the git steps around the review.
+    let working_dir = session_working_dir(ticket, manifest, Some(&ticket_state));
+    let review_dir = review_working_dir(ticket, manifest, Some(&ticket_state));
+    let git = &manifest.git;
+    let git_needs_worker_dir =
+        git.branch_per_ticket || (git.autocommit && !opts.no_autocommit) || git.open_pr;
+    if (review_dir == working_dir || git_needs_worker_dir) && !working_dir.exists() {
+        return Err(WorkflowError::WorkingDirMissing {
+            ticket_id: ticket.id.clone(),
+            path: working_dir,
+        }
+        .into());
+    }
+    if !review_dir.exists() {
+        return Err(WorkflowError::ReviewWorkingDirMissing {
+            ticket_id: ticket.id.clone(),
+            path: review_dir,
+        }
+        .into());
+    }
+    if !switch_to_ticket_branch(ticket, ctx, &working_dir).await? {
+        return Ok(());
+    }
+    let Some(request) = ctx
+        .ticket_session_request(SessionPhase::Review, ticket)
+        .await?
+    else {
+        return Ok(());
+    };
+    let request = SessionRequest {
+        log_path: review_log.clone(),
+        attempt: Some(attempt),
+        ..request
+    };
+    if !ctx.check_prompt_budget(&ticket.id, SessionPhase::Review, &request.prompt)? {
+        return Ok(());
+    }
+    layout.ensure_ticket_dir(&ticket.id)?;
+    let prompt_path = layout.review_prompt_path(&ticket.id);
+    write_prompt_file(&prompt_path, &request.prompt, &ctx.secret_values)?;
+    let timeout = request.timeout;
+    if opts.preflight_each_session {
+        ctx.wait_for_preflight(Some(&ticket.id)).await;
+    }
+
+    ctx.update_ticket(&ticket.id, |entry| {
+        entry.start_attempt(
+            SessionPhase::Review,
+            attempt,
+            review_log.clone(),
+            timeout,
+            request.profile.clone(),
+        );
+        entry.review_prompt = Some(prompt_path);
+        entry.review_working_dir = Some(review_dir.clone());
+        entry.mark_running(TicketStatus::RunningReview);
+    })?;
+    ctx.session_started(&ticket.id, SessionPhase::Review, attempt, &review_log);
+
+    let result = ctx
+        .run_session(&ticket.id, SessionPhase::Review, attempt, request)
+        .await?;
+    ctx.session_finished(&ticket.id, SessionPhase::Review, attempt, &result);
+    prune_logs(ctx, &ticket.id, SessionPhase::Review)?;
+    let missing = ticket.missing_artifacts(&working_dir);
+    // An unreadable input only means the ticket is not skipped next time.
+    // Hashed against the manifest's working directory, as
+    // `keep_unchanged_tickets` checks it, rather than a worktree's.
+    let fingerprint =
+        ticket_fingerprint(ticket, &ticket.resolved_working_dir(&manifest.base_dir())).ok();
+    let max_reworks = opts.max_reworks.unwrap_or(manifest.max_reworks);
+    ctx.update_ticket(&ticket.id, |entry| {
+        entry.finish_attempt(
+            SessionPhase::Review,
+            result.status_code,
+            result.truncated_bytes,
+        );
+        entry.review_session_id = result.session_id.clone();
+        entry.review_command = Some(result.command_line.clone());
+        if let Some(message) = &result.last_message {
+            entry.review_output = Some(layout.review_output_path(&ticket.id));
+            let summary = message.trim();
+            entry.review_summary = (!summary.is_empty()).then(|| summary.to_string());
+        }
+        let verdict_source = result.last_message.as_deref().unwrap_or(&result.stdout);
+        entry.requirement_results =
+            parse_requirement_verdicts(verdict_source, &ticket.requirement_ids());
+        entry.review_report = parse_review_report(verdict_source);
+        let changes_requested = entry
+            .review_report
+            .as_ref()
+            .filter(|report| report.verdict == ReviewVerdict::ChangesRequested)
+            .map(|report| report.blocking_issues.clone());
+        if !result.success {
+            entry.mark_finished(
+                TicketStatus::Failed,
+                Some(with_stderr_tail(
+                    session_failure_note("Review", &result),
+                    &result,
+                )),
+            );
+        } else if let Some(issues) = changes_requested {
+            let requested = format!(
+                "Reviewer requested changes ({} blocking issue(s))",
+                issues.len()
+            );
+            if entry.reworks < max_reworks {
+                entry.reworks += 1;
+                entry.status = TicketStatus::Pending;
+                entry.note = Some(format!(
+                    "{requested}; reworking ({} of {max_reworks})",
+                    entry.reworks
+                ));
+                return;
+            }
+            match ticket.on_review_failure {
+                ReviewFailurePolicy::Fail => {
+                    entry.mark_finished(TicketStatus::Failed, Some(requested));
+                }
+                ReviewFailurePolicy::Block => {
+                    entry.mark_finished(TicketStatus::Blocked, Some(requested));
+                }
+                ReviewFailurePolicy::Complete if !missing.is_empty() => {
+                    entry.mark_finished(
+                        TicketStatus::Blocked,
+                        Some(missing_artifacts_note(&missing)),
+                    );
+                }
+                ReviewFailurePolicy::Complete => {
+                    let mut note = format!("Completed despite review: {requested}");
+                    for issue in &issues {
+                        note.push_str(&format!("\n- {issue}"));
+                    }
+                    entry.fingerprint = fingerprint;
+                    entry.mark_finished(TicketStatus::Complete, Some(note));
+                }
+            }
+        } else if !missing.is_empty() {
+            entry.mark_finished(
+                TicketStatus::Blocked,
+                Some(missing_artifacts_note(&missing)),
+            );
+        } else {
+            entry.fingerprint = fingerprint;
+            entry.mark_finished(TicketStatus::Complete, Some("Review passed".to_string()));
+        }
+    })?;
+    commit_ticket_changes(ticket, ctx, &working_dir).await?;
+    open_ticket_pull_request(ticket, ctx, &working_dir).await?;
+    return_to_base_branch(ticket, ctx, &working_dir).await
+}
+
+/// With `git.autocommit`, commits the changes in the working directory once
+/// the ticket completed, except to paths that had changes before it started;
+/// a failure blocks the ticket.
+async fn commit_ticket_changes(
+    ticket: &TicketSpec,
+    ctx: &RunContext<'_>,
+    working_dir: &Path,
+) -> Result<()> {
+    let settings = &ctx.manifest.git;
+    if !settings.autocommit || ctx.opts.no_autocommit {
+        return Ok(());
+    }
+    if !ctx
+        .ticket_state(&ticket.id)
+        .is_some_and(|entry| entry.status == TicketStatus::Complete)
+    {
+        return Ok(());
+    }
+    let workflow_name = ctx.lock_state().workflow_name.clone();
+    let message = ticket_commit_message(settings, &workflow_name, ticket);
+    let excluded = ctx
+        .ticket_state(&ticket.id)
+        .and_then(|entry| entry.preexisting_changes)
+        .unwrap_or_default();
+    match commit_changes(working_dir, &message, &excluded).await {
+        Ok((commit, left_out)) => ctx.update_ticket(&ticket.id, |entry| {
+            if commit.is_none() {
+                // Keep the first line a one-line summary of the ticket.
+                let note = entry.note.take().unwrap_or_default();
+                entry.note = Some(match note.split_once('\n') {
+                    Some((first, rest)) => format!("{first}; nothing to commit\n{rest}"),
+                    None => format!("{note}; nothing to commit"),
+                });
+            }
+            if !left_out.is_empty() {
+                let note = entry.note.take().unwrap_or_default();
+                entry.note = Some(format!(
+                    "{note}\nNot committed, changed before the ticket started: {}",
+                    left_out.join(", ")
+                ));
+            }
+            entry.commit = commit;
+        }),
+        Err(stderr) => ctx.update_ticket(&ticket.id, |entry| {
+            entry.mark_finished(
+                TicketStatus::Blocked,
+                Some(format!("git commit failed: {stderr}")),
+            );
+        }),
+    }
+}
+
+/// With `git.open_pr`, pushes a completed ticket's branch and opens a pull
+/// request for it, or updates the open one. A failure only adds a warning to
+/// the ticket's note.
+async fn open_ticket_pull_request(
+    ticket: &TicketSpec,
+    ctx: &RunContext<'_>,
+    working_dir: &Path,
+) -> Result<()> {
+    let settings = &ctx.manifest.git;
+    if !settings.open_pr {
+        return Ok(());
+    }
+    let Some(entry) = ctx
+        .ticket_state(&ticket.id)
+        .filter(|entry| entry.status == TicketStatus::Complete)
+    else {
+        return Ok(());
+    };
+    let Some(branch) = entry.branch.clone() else {
+        return Ok(());
+    };
+    let remote = settings.remote.as_deref().unwrap_or(DEFAULT_REMOTE);
+    let opened = async {
+        push_branch(working_dir, remote, &branch)
+            .await
+            .map_err(|stderr| format!("git push of branch {branch} failed: {stderr}"))?;
+        let repo = match &settings.pr_repo {
+            Some(repo) => repo.clone(),
+            None => {
+                let url = remote_url(working_dir, remote).await?;
+                repo_from_remote_url(&url).ok_or_else(|| {
+                    format!("remote {remote} ({url}) is not a GitHub repository; set git.pr_repo")
+                })?
+            }
+        };
+        let token = ctx
+            .opts
+            .github_token
+            .as_deref()
+            .ok_or("no GitHub token; set GITHUB_TOKEN or GH_TOKEN")?;
+        let base = settings
+            .pr_base
+            .clone()
+            .or_else(|| entry.base_branch.clone())
+            .ok_or("no base branch for the pull request; set git.pr_base")?;
+        let workflow_name = ctx.lock_state().workflow_name.clone();
+        let (title, body) = ticket_pull_request(settings, &workflow_name, ticket, &entry);
+        let pr = PullRequest {
+            repo: &repo,
+            head: &branch,
+            base: &base,
+            title: &title,
+            body: &body,
+        };
+        open_pull_request(settings.github_api_url.as_deref(), token, &pr).await
+    }
+    .await;
+    ctx.update_ticket(&ticket.id, |entry| match opened {
+        Ok(url) => entry.pull_request = Some(url),
+        Err(err) => {
+            let note = entry.note.take().unwrap_or_default();
+            entry.note = Some(format!("{note}\nWarning: no pull request opened: {err}"));
+        }
+    })
+}
+
+/// With `git.branch_per_ticket`, checks out the ticket's branch before a
+/// session, creating it on first use. Returns `false` after blocking the
+/// ticket because git failed.
+async fn switch_to_ticket_branch(
+    ticket: &TicketSpec,
+    ctx: &RunContext<'_>,
+    working_dir: &Path,
+) -> Result<bool> {
+    let settings = &ctx.manifest.git;
+    if !settings.branch_per_ticket {
+        return Ok(true);
+    }
+    let entry = ctx.ticket_state(&ticket.id);
+    let branch = match entry.as_ref().and_then(|entry| entry.branch.clone()) {
+        Some(branch) => branch,
+        None => {
+            let workflow_name = ctx.lock_state().workflow_name.clone();
+            ticket_branch_name(settings, &workflow_name, &ticket.id)
+        }
+    };
+    let base = match entry
+        .and_then(|entry| entry.base_branch)
+        .or_else(|| settings.base.clone())
+    {
+        Some(base) => Ok(base),
+        None => current_head(working_dir).await,
+    };
+    let checkout = match base {
+        Ok(base) => checkout_ticket_branch(working_dir, &branch, &base)
+            .await
+            .map(|()| base),
+        Err(err) => Err(err),
+    };
+    match checkout {
+        Ok(base) => {
+            ctx.update_ticket(&ticket.id, |entry| {
+                entry.branch = Some(branch);
+                entry.base_branch = Some(base);
+            })?;
+            Ok(true)
+        }
+        Err(stderr) => {
+            ctx.update_ticket(&ticket.id, |entry| {
+                entry.mark_finished(
+                    TicketStatus::Blocked,
+                    Some(format!("git checkout of branch {branch} failed: {stderr}")),
+                );
+            })?;
+            Ok(false)
+        }
+    }
+}
+
+/// With `git.return_to_base`, checks the ticket's base out again once the
+/// ticket completed; a failure blocks the ticket.
+async fn return_to_base_branch(
+    ticket: &TicketSpec,
+    ctx: &RunContext<'_>,
+    working_dir: &Path,
+) -> Result<()> {
+    let settings = &ctx.manifest.git;
+    if !(settings.branch_per_ticket && settings.return_to_base) {
+        return Ok(());
+    }
+    let Some(entry) = ctx.ticket_state(&ticket.id) else {
+        return Ok(());
+    };
+    let (TicketStatus::Complete, Some(base)) = (entry.status, entry.base_branch) else {
+        return Ok(());
+    };
+    if let Err(stderr) = checkout_base(working_dir, &base).await {
+        ctx.update_ticket(&ticket.id, |entry| {
+            entry.mark_finished(
+                TicketStatus::Blocked,
+                Some(format!("git checkout of base {base} failed: {stderr}")),
+            );
+        })?;
+    }
+    Ok(())
+}
+
+/// With `git.use_worktrees`, adds the ticket's worktree under its artifacts
+/// directory unless one from an earlier run is still there. Returns `false`
+/// after blocking the ticket because git failed.
+async fn add_ticket_worktree(ticket: &TicketSpec, ctx: &RunContext<'_>) -> Result<bool> {
+    let settings = &ctx.manifest.git;
+    if !settings.use_worktrees {
+        return Ok(true);
+    }
+    if ctx
+        .ticket_state(&ticket.id)
+        .and_then(|entry| entry.worktree)
+        .is_some_and(|worktree| worktree.working_dir.exists())
+    {
+        return Ok(true);
+    }
+    let working_dir = ticket.resolved_working_dir(&ctx.manifest.base_dir());
+    if !working_dir.exists() {
+        // The session reports the missing directory.
+        return Ok(true);
+    }
+    let path = ctx.layout.worktree_dir(&ticket.id);
+    let path = std::path::absolute(&path).unwrap_or(path);
+    // Without `branch_per_ticket`, `autocommit` would commit on a detached
+    // `HEAD` that is gone with the worktree, so the worktree gets the
+    // ticket's branch right away.
+    let branch = (settings.autocommit && !ctx.opts.no_autocommit && !settings.branch_per_ticket)
+        .then(|| {
+            ctx.ticket_state(&ticket.id)
+                .and_then(|entry| entry.branch)
+                .unwrap_or_else(|| {
+                    let workflow_name = ctx.lock_state().workflow_name.clone();
+                    ticket_branch_name(settings, &workflow_name, &ticket.id)
+                })
+        });
+    match add_worktree(
+        &working_dir,
+        &path,
+        settings.base.as_deref(),
+        branch.as_deref(),
+    )
+    .await
+    {
+        Ok(working_dir) => {
+            ctx.update_ticket(&ticket.id, |entry| {
+                entry.worktree = Some(TicketWorktree { path, working_dir });
+                if branch.is_some() {
+                    entry.branch = branch;
+                }
+            })?;
+            Ok(true)
+        }
+        Err(stderr) => {
+            ctx.update_ticket(&ticket.id, |entry| {
+                entry.mark_finished(
+                    TicketStatus::Blocked,
+                    Some(format!("git worktree add failed: {stderr}")),
+                );
+            })?;
+            Ok(false)
+        }
+    }
+}
+
+/// Removes the ticket's worktree unless `git.keep_worktrees` is set. A
+/// failure, e.g. because of uncommitted changes, leaves the worktree in
+/// place and is added to the ticket's note.
+async fn remove_ticket_worktree(ticket: &TicketSpec, ctx: &RunContext<'_>) -> Result<()> {
+    if ctx.manifest.git.keep_worktrees {
+        return Ok(());
+    }
+    let Some(worktree) = ctx
+        .ticket_state(&ticket.id)
+        .and_then(|entry| entry.worktree)
+    else {
+        return Ok(());
+    };
+    let working_dir = ticket.resolved_working_dir(&ctx.manifest.base_dir());
+    let removal = remove_worktree(&working_dir, &worktree.path).await;
+    ctx.update_ticket(&ticket.id, |entry| match removal {
+        Ok(()) => entry.worktree = None,
+        Err(stderr) => {
+            let failure = format!("git worktree remove failed: {stderr}");
+            entry.note = Some(match entry.note.take() {
+                Some(note) => format!("{note}\n{failure}"),
+                None => failure,
+            });
+        }
+    })
+}
+
+/// Where the ticket's sessions run: its worktree with `git.use_worktrees`,
+/// otherwise its working directory.
+fn session_working_dir(
+    ticket: &TicketSpec,
+    manifest: &WorkflowManifest,
+    ticket_state: Option<&TicketRunState>,
+) -> PathBuf {
+    match ticket_state.and_then(|entry| entry.worktree.as_ref()) {
+        Some(worktree) => worktree.working_dir.clone(),
+        None => ticket.resolved_working_dir(&manifest.base_dir()),
+    }
+}
+
+/// The binary a ticket's review session runs in place of the launcher's, if
+/// the ticket or the run names one.
+fn reviewer_bin(
+    ticket: &TicketSpec,
+    manifest: &WorkflowManifest,
+    opts: &WorkflowRunOptions,
+) -> Option<PathBuf> {
+    ticket
+        .resolved_reviewer_bin(&manifest.manifest_dir())
+        .or_else(|| opts.reviewer_bin.clone())
+}
+
+/// Where a ticket's review session runs: its `review_working_dir`, if it has
+/// one, otherwise where its worker runs.
+fn review_working_dir(
+    ticket: &TicketSpec,
+    manifest: &WorkflowManifest,
+    ticket_state: Option<&TicketRunState>,
+) -> PathBuf {
+    let Some(review_dir) = ticket.resolved_review_working_dir(&manifest.base_dir()) else {
+        return session_working_dir(ticket, manifest, ticket_state);
+    };
+    let Some(worktree) = ticket_state.and_then(|entry| entry.worktree.as_ref()) else {
+        return review_dir;
+    };
+    // A review directory inside the worker's checkout is looked up in the
+    // ticket's worktree, which holds the worker's changes; one outside it
+    // stays where it is.
+    let Ok(suffix) = worktree.working_dir.strip_prefix(&worktree.path) else {
+        return review_dir;
+    };
+    let working_dir = ticket.resolved_working_dir(&manifest.base_dir());
+    let checkout = working_dir.ancestors().nth(suffix.components().count());
+    match checkout.and_then(|checkout| review_dir.strip_prefix(checkout).ok()) {
+        Some(relative) if relative.as_os_str().is_empty() => worktree.path.clone(),
+        Some(relative) => worktree.path.join(relative),
+        None => review_dir,
+    }
+}
+
+/// Copies the previous state of every ticket that completed with the
+/// fingerprint it has now, unless one of its dependencies is not kept: a
+/// rerun dependency reruns its dependents.
+fn keep_unchanged_tickets(
+    state: &mut WorkflowState,
+    previous: &WorkflowState,
+    manifest: &WorkflowManifest,
+) {
+    let base_dir = manifest.base_dir();
+    let mut unchanged: Vec<&TicketSpec> = manifest
+        .tickets
+        .iter()
+        .filter(|ticket| {
+            let Some(recorded) = previous.ticket(&ticket.id) else {
+                return false;
+            };
+            recorded.status == TicketStatus::Complete
+                && recorded.fingerprint.is_some()
+                && ticket_fingerprint(ticket, &ticket.resolved_working_dir(&base_dir)).ok()
+                    == recorded.fingerprint
+        })
+        .collect();
+    loop {
+        let kept: HashSet<&str> = unchanged.iter().map(|ticket| ticket.id.as_str()).collect();
+        let before = unchanged.len();
+        unchanged.retain(|ticket| {
+            ticket
+                .depends_on
+                .iter()
+                .all(|dependency| kept.contains(dependency.as_str()))
+        });
+        if unchanged.len() == before {
+            break;
+        }
+    }
+    for ticket in unchanged {
+        if let (Some(entry), Some(recorded)) =
+            (state.ticket_mut(&ticket.id), previous.ticket(&ticket.id))
+        {
+            debug!(
+                ticket = ticket.id.as_str(),
+                "keeping ticket unchanged since it last completed"
+            );
+            *entry = recorded.clone();
+            entry.note = Some("Unchanged since it last completed".to_string());
+        }
+    }
+}
+
+fn missing_artifacts_note(missing: &[PathBuf]) -> String {
+    let paths: Vec<String> = missing
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect();
+    format!("Missing expected artifacts: {}", paths.join(", "))
+}
+
+fn session_failure_note(phase: &str, result: &SessionResult) -> String {
+    if result.timed_out {
+        format!("{phase} timed out")
+    } else {
+        format!("{phase} failed with status {:?}", result.status_code)
+    }
+}
+
+/// `note` followed by the last lines of a failed session's stderr, without
+/// ANSI escapes, so the status view hints at the cause without the log.
+fn with_stderr_tail(mut note: String, result: &SessionResult) -> String {
+    let stderr = strip_ansi(&result.stderr);
+    let lines: Vec<&str> = stderr
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    for line in &lines[lines.len().saturating_sub(FAILURE_STDERR_LINES)..] {
+        note.push_str("\n  ");
+        if line.chars().count() > FAILURE_STDERR_LINE_CHARS {
+            note.extend(line.chars().take(FAILURE_STDERR_LINE_CHARS - 1));
+            note.push('…');
+        } else {
+            note.push_str(line);
+        }
+    }
+    note
+}
+
+/// The time limit of `phase` sessions: `--worker-timeout` or
+/// `--review-timeout`, else `--session-timeout`, else the manifest's
+/// `worker_timeout` or `review_timeout`.
+fn phase_timeout(
+    phase: SessionPhase,
+    manifest: &WorkflowManifest,
+    opts: &WorkflowRunOptions,
+) -> Option<Duration> {
+    let (option, configured) = match phase {
+        SessionPhase::Worker => (opts.worker_timeout, manifest.worker_timeout),
+        SessionPhase::Review => (opts.review_timeout, manifest.review_timeout),
+    };
+    option
+        .or(opts.session_timeout)
+        .or(configured.map(Duration::from_secs))
+}
+
+/// The request for a ticket's next `phase` session, shared by the run and
+/// `preview_prompt`. `log_path` is left empty for the caller to fill in.
+async fn session_request(
+    phase: SessionPhase,
+    ticket: &TicketSpec,
+    manifest: &WorkflowManifest,
+    layout: &WorkflowLayout,
+    opts: &WorkflowRunOptions,
+    ticket_state: Option<&TicketRunState>,
+) -> Result<SessionRequest> {
+    let working_dir = match phase {
+        SessionPhase::Worker => session_working_dir(ticket, manifest, ticket_state),
+        SessionPhase::Review => review_working_dir(ticket, manifest, ticket_state),
+    };
+    let fresh_state = TicketRunState::new(&ticket.id);
+    let state = ticket_state.unwrap_or(&fresh_state);
+    let prompt_override = match phase {
+        SessionPhase::Worker => &ticket.prompt,
+        SessionPhase::Review => &ticket.review_prompt,
+    };
+    let mut prompt = match (prompt_override, &opts.prompt_command) {
+        (Some(prompt), _) => prompt.clone(),
+        (None, Some(command)) => {
+            let input = PromptCommandInput {
+                phase,
+                workflow_name: manifest.workflow_name(),
+                overview: manifest.overview.as_deref(),
+                ticket,
+                state: ticket_state,
+                working_dir: &working_dir,
+                patch_dir: layout.patch_dir(&ticket.id),
+            };
+            run_prompt_command(command, &input, PROMPT_COMMAND_TIMEOUT).await?
+        }
+        (None, None) => match phase {
+            SessionPhase::Worker => build_worker_prompt(manifest, ticket, layout),
+            SessionPhase::Review => build_review_prompt(
+                manifest,
+                ticket,
+                state,
+                manifest.review_against_diff || opts.review_against_diff,
+            ),
+        },
+    };
+    let (model, last_message_path, resumes) = match phase {
+        SessionPhase::Worker => {
+            if let Some(rework) = rework_section(state) {
+                prompt = format!("{}\n\n{rework}", prompt.trim_end());
+            }
+            (
+                opts.worker_model.clone(),
+                layout.worker_output_path(&ticket.id),
+                manifest.worker_resumes_session || opts.worker_resumes_session,
+            )
+        }
+        SessionPhase::Review => (
+            opts.reviewer_model
+                .clone()
+                .or_else(|| opts.worker_model.clone()),
+            layout.review_output_path(&ticket.id),
+            manifest.review_resumes_worker_session || opts.review_resumes_worker_session,
+        ),
+    };
+    let codex_bin = match phase {
+        SessionPhase::Worker => None,
+        SessionPhase::Review => reviewer_bin(ticket, manifest, opts),
+    };
+    let profile = ticket
+        .profile
+        .clone()
+        .or_else(|| opts.profile.clone())
+        .or_else(|| manifest.config_profile.clone());
+    Ok(SessionRequest {
+        prompt,
+        working_dir,
+        log_path: PathBuf::new(),
+        attempt: None,
+        model,
+        profile,
+        codex_bin,
+        env: ticket.env.clone(),
+        last_message_path: Some(last_message_path),
+        resume_session: state.worker_session_id.clone().filter(|_| resumes),
+        config_overrides: manifest.config_overrides.clone(),
+        extra_args: session_extra_args(opts, ticket),
+        timeout: phase_timeout(phase, manifest, opts),
+        log_format: layout.log_format(),
+        skip_git_repo_check: opts
+            .skip_git_repo_check
+            .or(manifest.skip_git_repo_check)
+            .unwrap_or(true),
+    })
+}
+
+fn write_prompt_file(path: &Path, prompt: &str, secret_values: &[String]) -> Result<()> {
+    std::fs::write(path, redact(prompt, secret_values))
+        .with_context(|| format!("failed to write prompt file {}", path.display()))
+}
+
+fn build_worker_prompt(
+    manifest: &WorkflowManifest,
+    ticket: &TicketSpec,
+    layout: &WorkflowLayout,
+) -> String {
+    let mut sections = Vec::new();
+    if let Some(overview) = &manifest.overview {
+        sections.push(format!("Workflow overview:\n{overview}\n"));
+    }
+    sections.push(format!("Ticket {}: {}\n", ticket.id, ticket.summary));
+    if let Some(description) = &ticket.description {
+        sections.push(format!("Description:\n{}\n", description.trim_end()));
+    }
+    if !ticket.requirements.is_empty() {
+        let reqs = ticket
+            .requirements
+            .iter()
+            .map(|req| format!("- {}", req.render()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        sections.push(format!("Requirements:\n{reqs}\n"));
+    }
+    let patch_dir = layout.patch_dir(&ticket.id).display().to_string();
+    sections.push(format!(
+        "Work inside the repository directory and save any generated patches or notes under {patch_dir}. \
+        Log your progress clearly."
+    ));
+    wrap_sections(&sections)
+}
+
+/// Blocking issues of the review that sent the ticket back for rework.
+fn rework_section(ticket_state: &TicketRunState) -> Option<String> {
+    let report = ticket_state.review_report.as_ref()?;
+    if report.verdict != ReviewVerdict::ChangesRequested {
+        return None;
+    }
+    let mut section =
+        "The reviewer requested changes to your previous attempt. Address these blocking issues:"
+            .to_string();
+    for issue in &report.blocking_issues {
+        section.push_str(&format!("\n- {issue}"));
+    }
+    Some(section)
+}
+
+fn build_review_prompt(
+    manifest: &WorkflowManifest,
+    ticket: &TicketSpec,
+    ticket_state: &TicketRunState,
+    against_diff: bool,
+) -> String {
+    let mut sections = Vec::new();
+    if let Some(overview) = &manifest.overview {
+        sections.push(format!("Workflow overview:\n{overview}\n"));
+    }
+    sections.push(format!(
+        "Review ticket {} ({}) for correctness and completeness.",
+        ticket.id, ticket.summary
+    ));
+    if let Some(description) = &ticket.description {
+        sections.push(format!("Description:\n{}\n", description.trim_end()));
+    }
+    if !ticket.requirements.is_empty() {
+        let reqs = ticket
+            .requirements
+            .iter()
+            .map(|req| format!("- {}", req.render()))
+            .collect::<Vec<_>>()
+            .join("\n");
+        sections.push(format!(
+            "Confirm that the following requirements are satisfied:\n{reqs}\n"
+        ));
+    }
+    let requirement_ids = ticket.requirement_ids();
+    if !requirement_ids.is_empty() {
+        sections.push(format!(
+            "For each requirement with an id, end your review with one line per id in the form \
+            `[<id>] satisfied` or `[<id>] unsatisfied` (ids: {}).",
+            requirement_ids.join(", ")
+        ));
+    }
+    if let (Some(diff), Some(changed_files)) =
+        (&ticket_state.worker_diff, ticket_state.changed_files)
+    {
+        sections.push(if against_diff {
+            format!(
+                "Review the worker's changes as captured in the diff at {} ({changed_files} \
+                file(s) changed) rather than the whole codebase; read other files only for the \
+                context a change needs.",
+                diff.display()
+            )
+        } else {
+            format!(
+                "The worker changed {changed_files} file(s); the full diff is at {}.",
+                diff.display()
+            )
+        });
+    }
+    sections.push(
+        "End your review with a fenced ```json block of the form {\"verdict\": \"approved\" | \
+        \"changes_requested\", \"blocking_issues\": [...], \"suggestions\": [...]}."
+            .to_string(),
+    );
+    match ticket_state.worker_log.as_deref() {
+        Some(worker_log) => sections.push(format!(
+            "Consult the worker log at {} and ensure all changes are tested. \
+            Provide a concise approval or list blocking issues.",
+            worker_log.display()
+        )),
+        None => sections.push(
+            "Ensure all changes are tested. Provide a concise approval or list blocking issues."
+                .to_string(),
+        ),
+    }
+    wrap_sections(&sections)
+}
+
+fn session_extra_args(opts: &WorkflowRunOptions, ticket: &TicketSpec) -> Vec<String> {
+    opts.extra_args
+        .iter()
+        .chain(&ticket.extra_args)
+        .cloned()
+        .collect()
+}
+
+/// Next attempt number for a phase. Existing log files are consulted as well
+/// as the recorded history so a fresh state never overwrites an older log.
+fn next_attempt(ctx: &RunContext<'_>, ticket_id: &str, phase: SessionPhase) -> i32 {
+    let recorded = ctx
+        .ticket_state(ticket_id)
+        .map(|entry| entry.attempt_count(phase))
+        .unwrap_or(0);
+    let on_disk = ctx
+        .layout
+        .existing_log_attempts(ticket_id, phase)
+        .last()
+        .copied()
+        .unwrap_or(0);
+    recorded.max(on_disk) + 1
+}
+
+fn prune_logs(ctx: &RunContext<'_>, ticket_id: &str, phase: SessionPhase) -> Result<()> {
+    let Some(keep) = ctx.opts.keep_log_attempts else {
+        return Ok(());
+    };
+    let removed = ctx
+        .layout
+        .prune_log_attempts(ticket_id, phase, keep.max(1))?;
+    if removed.is_empty() {
+        return Ok(());
+    }
+    ctx.update_ticket(ticket_id, |entry| {
+        for attempt in &mut entry.attempts {
+            if removed.contains(&attempt.log_path) {
+                attempt.log_pruned = true;
+            }
+        }
+    })
+}
+
+fn wrap_sections(sections: &[String]) -> String {
+    let mut result = String::new();
+    for section in sections {
+        let wrapped = wrap(section, 100);
+        for line in wrapped {
+            result.push_str(line.trim_end());
+            result.push('\n');
+        }
+        result.push('\n');
+    }
+    result.trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::EVENT_VERSION;
+    use crate::events::read_events;
+    use crate::test_support::MockSessionRunner;
+    use crate::test_support::failed_session;
+    use crate::test_support::successful_session;
+
+    const SINGLE_TICKET: &str = "name: demo\ntickets:\n  - id: T1\n    summary: First ticket\n";
+
+    const DEPENDENT_TICKETS: &str = r#"
+name: demo
+tickets:
+  - id: T1
+    summary: First ticket
+  - id: T2
+    summary: Needs T1
+    depends_on: [T1]
+  - id: T3
+    summary: Independent
+"#;
+
+    fn write_manifest(dir: &Path, contents: &str) -> PathBuf {
+        let path = dir.join("workflow.yaml");
+        std::fs::write(&path, contents).expect("write manifest");
+        path
+    }
+
+    fn run_options(dir: &Path) -> WorkflowRunOptions {
+        manifest_options(dir, SINGLE_TICKET)
+    }
+
+    fn manifest_options(dir: &Path, contents: &str) -> WorkflowRunOptions {
+        WorkflowRunOptions {
+            manifest_path: write_manifest(dir, contents),
+            artifacts_dir: Some(dir.join("artifacts")),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn runs_worker_then_review() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let runner = MockSessionRunner::new();
+        let report = run_workflow_with_runner(run_options(dir.path()), &runner)
+            .await
+            .expect("run workflow");
+
+        assert_eq!(report.tickets[0].status, TicketStatus::Complete);
+        let requests = runner.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].log_path.ends_with("worker.1.md"));
+        assert!(requests[1].log_path.ends_with("review.1.md"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn streams_events_to_channel_and_socket() {
+        use tokio::io::AsyncReadExt;
+
+        let dir = tempfile::tempdir().expect("tempdir");
+        let socket_path = dir.path().join("events.sock");
+        let listener = tokio::net::UnixListener::bind(&socket_path).expect("bind");
+        let consumer = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.expect("accept");
+            let mut lines = String::new();
+            stream.read_to_string(&mut lines).await.expect("read");
+            lines
+        });
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let runner = MockSessionRunner::new();
+        let opts = WorkflowRunOptions {
+            events: Some(sender),
+            events_socket: Some(socket_path.display().to_string()),
+            ..run_options(dir.path())
+        };
+        run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("run workflow");
+
+        let mut transitions = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            if let WorkflowEvent::TicketStatusChanged { to, done, .. } = event {
+                transitions.push((to, done));
+            }
+        }
+        assert_eq!(
+            transitions,
+            vec![
+                (TicketStatus::RunningWorker, 0),
+                (TicketStatus::NeedsReview, 0),
+                (TicketStatus::RunningReview, 0),
+                (TicketStatus::Complete, 1),
+            ]
+        );
+        let lines = consumer.await.expect("consumer");
+        let types: Vec<String> = lines
+            .lines()
+            .map(|line| {
+                let event: serde_json::Value = serde_json::from_str(line).expect("json line");
+                event["type"].as_str().unwrap_or_default().to_string()
+            })
+            .collect();
+        assert_eq!(types.first().map(String::as_str), Some("run_started"));
+        assert_eq!(types.last().map(String::as_str), Some("run_finished"));
+        assert_eq!(types.len(), 10);
+        let logged = read_events(&dir.path().join("artifacts/events.jsonl"), usize::MAX)
+            .expect("read event log");
+        assert_eq!(logged.len(), types.len());
+        assert!(matches!(
+            logged[2],
+            WorkflowEvent::SessionStarted {
+                phase: SessionPhase::Worker,
+                attempt: 1,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn notify_command_runs_for_final_statuses_and_the_end_of_the_run() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let out = dir.path().join("notified.txt");
+        let manifest = format!(
+            "name: demo\nnotifications:\n  notify_command: [sh, -c, 'echo \"$1 $2 $3\" >> {}', sh, '{{{{ workflow.name }}}}', '{{{{ ticket.id }}}}', '{{{{ status }}}}']\ntickets:\n  - id: T1\n    summary: First ticket\n",
+            out.display()
+        );
+        let runner = MockSessionRunner::new();
+        run_workflow_with_runner(manifest_options(dir.path(), &manifest), &runner)
+            .await
+            .expect("run workflow");
+        let notified = std::fs::read_to_string(&out).expect("read notifications");
+        let mut lines: Vec<&str> = notified.lines().collect();
+        lines.sort_unstable();
+        assert_eq!(lines, vec!["demo  complete", "demo T1 complete"]);
+
+        let runner = MockSessionRunner::new();
+        let opts = WorkflowRunOptions {
+            notify_command: Some(vec!["false".to_string()]),
+            ..manifest_options(dir.path(), &manifest)
+        };
+        let report = run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("rerun workflow");
+        assert_eq!(report.tickets[0].status, TicketStatus::Complete);
+        let failed = read_events(&dir.path().join("artifacts/events.jsonl"), usize::MAX)
+            .expect("read event log")
+            .into_iter()
+            .filter(|event| matches!(event, WorkflowEvent::NotifyCommandFailed { .. }))
+            .count();
+        assert_eq!(failed, 2);
+    }
+
+    #[tokio::test]
+    async fn webhook_failures_are_logged_without_failing_tickets() {
+        use wiremock::Mock;
+        use wiremock::MockServer;
+        use wiremock::ResponseTemplate;
+        use wiremock::matchers::method;
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(403))
+            .mount(&server)
+            .await;
+        let dir = tempfile::tempdir().expect("tempdir");
+        let runner = MockSessionRunner::new();
+        let opts = WorkflowRunOptions {
+            webhook_url: Some(server.uri()),
+            ..run_options(dir.path())
+        };
+        let report = run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("run workflow");
+
+        assert_eq!(report.tickets[0].status, TicketStatus::Complete);
+        let posted: Vec<serde_json::Value> = server
+            .received_requests()
+            .await
+            .expect("recorded requests")
+            .iter()
+            .map(|request| serde_json::from_slice(&request.body).expect("json body"))
+            .collect();
+        assert_eq!(posted.len(), 2);
+        assert_eq!(posted[0]["type"], "ticket_status_changed");
+        assert_eq!(posted[0]["to"], "complete");
+        assert_eq!(posted[0]["event_version"], EVENT_VERSION);
+        assert_eq!(posted[1]["type"], "run_finished");
+        let mut failures: Vec<(String, Option<String>)> =
+            read_events(&dir.path().join("artifacts/events.jsonl"), usize::MAX)
+                .expect("read event log")
+                .into_iter()
+                .filter_map(|event| match event {
+                    WorkflowEvent::WebhookFailed {
+                        event_type,
+                        ticket_id,
+                        ..
+                    } => Some((event_type, ticket_id)),
+                    _ => None,
+                })
+                .collect();
+        failures.sort();
+        assert_eq!(
+            failures,
+            vec![
+                ("run_finished".to_string(), None),
+                ("ticket_status_changed".to_string(), Some("T1".to_string())),
+            ]
+        );
+    }
+
+    const BRANCH_PER_TICKET: &str = "name: demo\ngit:\n  branch_per_ticket: true\n  return_to_base: true\ntickets:\n  - id: T1\n    summary: First ticket\n";
+
+    #[tokio::test]
+    async fn runs_each_ticket_on_its_own_branch() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        if !init_git_repo(dir.path()) {
+            // git is not installed.
+            return;
+        }
+
+        let runner = MockSessionRunner::new();
+        let report =
+            run_workflow_with_runner(manifest_options(dir.path(), BRANCH_PER_TICKET), &runner)
+                .await
+                .expect("run workflow");
+
+        let ticket = &report.tickets[0];
+        assert_eq!(ticket.status, TicketStatus::Complete);
+        assert_eq!(ticket.branch.as_deref(), Some("workflow/demo/T1"));
+        assert_eq!(ticket.base_branch.as_deref(), Some("main"));
+        assert!(
+            git(
+                dir.path(),
+                &["rev-parse", "--verify", "refs/heads/workflow/demo/T1"]
+            )
+            .is_some()
+        );
+        assert_eq!(
+            git(dir.path(), &["symbolic-ref", "--short", "HEAD"]).as_deref(),
+            Some("main")
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_parallel_branches_in_a_shared_working_dir() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest = "name: demo\ngit:\n  branch_per_ticket: true\ntickets:\n  - id: T1\n    summary: First ticket\n  - id: T2\n    summary: Second ticket\n";
+        let runner = MockSessionRunner::new();
+        let err = run_workflow_with_runner(
+            WorkflowRunOptions {
+                max_parallel: Some(2),
+                ..manifest_options(dir.path(), manifest)
+            },
+            &runner,
+        )
+        .await
+        .expect_err("shared working dir");
+        assert!(err.to_string().contains("git.use_worktrees"), "{err}");
+
+        let err = run_workflow_with_runner(
+            WorkflowRunOptions {
+                max_parallel: Some(2),
+                ..manifest_options(
+                    dir.path(),
+                    &manifest.replace("branch_per_ticket", "autocommit"),
+                )
+            },
+            &runner,
+        )
+        .await
+        .expect_err("shared working dir");
+        assert!(err.to_string().contains("git.use_worktrees"), "{err}");
+        assert!(runner.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn autocommits_completed_tickets() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        if !init_git_repo(dir.path()) {
+            // git is not installed.
+            return;
+        }
+        let manifest = "name: demo\ngit:\n  autocommit: true\ntickets:\n  - id: T1\n    summary: First ticket\n";
+        let opts = manifest_options(dir.path(), manifest);
+        assert!(git(dir.path(), &["add", "workflow.yaml"]).is_some());
+        assert!(git(dir.path(), &["commit", "--quiet", "-m", "manifest"]).is_some());
+        // Changed before the ticket started, so not part of its commit.
+        std::fs::write(dir.path().join("notes.txt"), "mine\n").expect("write notes");
+
+        let runner = EditingRunner(MockSessionRunner::new());
+        let report = run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("run workflow");
+        let ticket = &report.tickets[0];
+        assert_eq!(ticket.status, TicketStatus::Complete);
+        assert_eq!(ticket.commit, git(dir.path(), &["rev-parse", "HEAD"]));
+        assert_eq!(
+            git(dir.path(), &["log", "-1", "--format=%B"]).as_deref(),
+            Some("T1: First ticket\n\nGenerated by codex workflow demo")
+        );
+        assert_eq!(
+            git(dir.path(), &["show", "--name-only", "--format=", "HEAD"]).as_deref(),
+            Some("change.txt")
+        );
+        assert_eq!(
+            ticket.note.as_deref(),
+            Some("Review passed\nNot committed, changed before the ticket started: notes.txt")
+        );
+
+        std::fs::remove_file(dir.path().join("notes.txt")).expect("remove notes");
+        let report = run_workflow_with_runner(manifest_options(dir.path(), manifest), &runner)
+            .await
+            .expect("rerun workflow");
+        let ticket = &report.tickets[0];
+        assert_eq!(ticket.commit, None);
+        assert_eq!(
+            ticket.note.as_deref(),
+            Some("Review passed; nothing to commit")
+        );
+    }
+
+    /// Writes `change.txt` into the working directory of each worker
+    /// session, as an agent editing the code would.
+    struct EditingRunner(MockSessionRunner);
+
+    #[async_trait::async_trait]
+    impl SessionRunner for EditingRunner {
+        async fn run(&self, request: SessionRequest) -> Result<SessionResult> {
+            let worker = request
+                .log_path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with("worker"));
+            if worker {
+                std::fs::write(request.working_dir.join("change.txt"), "work\n")?;
+            }
+            self.0.run(request).await
+        }
+    }
+
+    /// Trimmed stdout of a successful git command in `dir`.
+    fn git(dir: &Path, args: &[&str]) -> Option<String> {
+        std::process::Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Makes `dir` a repository on `main` with an empty first commit; `false`
+    /// when git is not available.
+    fn init_git_repo(dir: &Path) -> bool {
+        git(dir, &["init", "--quiet", "-b", "main"]).is_some()
+            && git(dir, &["config", "user.name", "test"]).is_some()
+            && git(dir, &["config", "user.email", "test@example.com"]).is_some()
+            && git(dir, &["commit", "--quiet", "--allow-empty", "-m", "init"]).is_some()
+    }
+
+    #[tokio::test]
+    async fn runs_tickets_in_worktrees() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        if !init_git_repo(dir.path()) {
+            // git is not installed.
+            return;
+        }
+        let manifest = "name: demo\ngit:\n  use_worktrees: true\ntickets:\n  - id: T1\n    summary: First ticket\n";
+
+        let runner = MockSessionRunner::new();
+        let report = run_workflow_with_runner(manifest_options(dir.path(), manifest), &runner)
+            .await
+            .expect("run workflow");
+        let ticket = &report.tickets[0];
+        assert_eq!(ticket.status, TicketStatus::Complete);
+        assert_eq!(ticket.worktree, None);
+        let requests = runner.requests();
+        assert_eq!(requests.len(), 2);
+        let worktree = &requests[0].working_dir;
+        assert!(worktree.ends_with("ticket-T1/worktree"), "{worktree:?}");
+        assert_eq!(&requests[1].working_dir, worktree);
+        assert!(!worktree.exists());
+
+        let not_a_repo = tempfile::tempdir().expect("tempdir");
+        let report =
+            run_workflow_with_runner(manifest_options(not_a_repo.path(), manifest), &runner)
+                .await
+                .expect("run workflow");
+        let ticket = &report.tickets[0];
+        assert_eq!(ticket.status, TicketStatus::Blocked);
+        let note = ticket.note.as_deref().unwrap_or_default();
+        assert!(note.starts_with("git worktree add failed: "), "{note}");
+    }
+
+    #[tokio::test]
+    async fn reviews_worktree_tickets_in_their_worktree() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        if !init_git_repo(dir.path()) {
+            // git is not installed.
+            return;
+        }
+        std::fs::create_dir_all(dir.path().join("docs")).expect("create review dir");
+        std::fs::write(dir.path().join("docs/README.md"), "docs\n").expect("write docs");
+        git(dir.path(), &["add", "docs"]).expect("git add");
+        git(dir.path(), &["commit", "--quiet", "-m", "docs"]).expect("git commit");
+        let manifest = "name: demo\ngit:\n  use_worktrees: true\ntickets:\n  - id: T1\n    summary: First ticket\n    review_working_dir: docs\n";
+
+        let runner = MockSessionRunner::new();
+        let report = run_workflow_with_runner(manifest_options(dir.path(), manifest), &runner)
+            .await
+            .expect("run workflow");
+        assert_eq!(report.tickets[0].status, TicketStatus::Complete);
+        let requests = runner.requests();
+        assert_eq!(requests.len(), 2);
+        let worktree = &requests[0].working_dir;
+        assert!(worktree.ends_with("ticket-T1/worktree"), "{worktree:?}");
+        assert_eq!(requests[1].working_dir, worktree.join("docs"));
+    }
+
+    #[tokio::test]
+    async fn autocommits_worktree_tickets_on_their_branch() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        if !init_git_repo(dir.path()) {
+            // git is not installed.
+            return;
+        }
+        let manifest = "name: demo\ngit:\n  use_worktrees: true\n  autocommit: true\ntickets:\n  - id: T1\n    summary: First ticket\n";
+
+        let runner = EditingRunner(MockSessionRunner::new());
+        let report = run_workflow_with_runner(manifest_options(dir.path(), manifest), &runner)
+            .await
+            .expect("run workflow");
+        let ticket = &report.tickets[0];
+        assert_eq!(ticket.status, TicketStatus::Complete);
+        assert_eq!(ticket.branch.as_deref(), Some("workflow/demo/T1"));
+        assert_eq!(ticket.worktree, None);
+        assert_eq!(
+            ticket.commit,
+            git(dir.path(), &["rev-parse", "workflow/demo/T1"])
+        );
+        assert_eq!(
+            git(
+                dir.path(),
+                &["show", "--name-only", "--format=", "workflow/demo/T1"]
+            )
+            .as_deref(),
+            Some("change.txt")
+        );
+        assert_eq!(
+            git(dir.path(), &["branch", "--show-current"]).as_deref(),
+            Some("main")
+        );
+    }
+
+    #[tokio::test]
+    async fn opens_pull_requests_for_completed_ticket_branches() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let remote = tempfile::tempdir().expect("tempdir");
+        if !init_git_repo(dir.path()) {
+            // git is not installed.
+            return;
+        }
+        let remote_path = remote.path().to_string_lossy().into_owned();
+        assert!(git(remote.path(), &["init", "--quiet", "--bare"]).is_some());
+        assert!(git(dir.path(), &["remote", "add", "origin", &remote_path]).is_some());
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::method("GET"))
+            .and(wiremock::matchers::path("/repos/acme/app/pulls"))
+            .respond_with(wiremock::ResponseTemplate::new(200).set_body_string("[]"))
+            .mount(&server)
+            .await;
+        wiremock::Mock::given(wiremock::matchers::method("POST"))
+            .and(wiremock::matchers::path("/repos/acme/app/pulls"))
+            .and(wiremock::matchers::body_partial_json(serde_json::json!({
+                "head": "workflow/demo/T1",
+                "base": "main",
+                "title": "T1: First ticket",
+            })))
+            .respond_with(wiremock::ResponseTemplate::new(201).set_body_string(
+                r#"{"number": 3, "html_url": "https://github.com/acme/app/pull/3"}"#,
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let manifest = format!(
+            "name: demo\ngit:\n  branch_per_ticket: true\n  autocommit: true\n  open_pr: true\n  \
+            pr_repo: acme/app\n  github_api_url: {}\ntickets:\n  - id: T1\n    summary: First ticket\n",
+            server.uri()
+        );
+
+        let runner = EditingRunner(MockSessionRunner::new());
+        let opts = WorkflowRunOptions {
+            github_token: Some("secret".to_string()),
+            ..manifest_options(dir.path(), &manifest)
+        };
+        let report = run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("run workflow");
+        let ticket = &report.tickets[0];
+        assert_eq!(ticket.status, TicketStatus::Complete);
+        assert_eq!(
+            ticket.pull_request.as_deref(),
+            Some("https://github.com/acme/app/pull/3")
+        );
+        assert_eq!(
+            git(remote.path(), &["rev-parse", "refs/heads/workflow/demo/T1"]),
+            ticket.commit
+        );
+
+        // Without a token the ticket still completes, with a warning.
+        let runner = MockSessionRunner::new();
+        let report = run_workflow_with_runner(manifest_options(dir.path(), &manifest), &runner)
+            .await
+            .expect("rerun workflow");
+        let ticket = &report.tickets[0];
+        assert_eq!(ticket.status, TicketStatus::Complete);
+        assert_eq!(ticket.pull_request, None);
+        let note = ticket.note.as_deref().unwrap_or_default();
+        assert!(
+            note.ends_with(
+                "\nWarning: no pull request opened: no GitHub token; set GITHUB_TOKEN or GH_TOKEN"
+            ),
+            "{note}"
+        );
+    }
+
+    #[tokio::test]
+    async fn git_failures_block_the_ticket() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let runner = MockSessionRunner::new();
+        let report =
+            run_workflow_with_runner(manifest_options(dir.path(), BRANCH_PER_TICKET), &runner)
+                .await
+                .expect("run workflow");
+
+        let ticket = &report.tickets[0];
+        assert_eq!(ticket.status, TicketStatus::Blocked);
+        let note = ticket.note.as_deref().unwrap_or_default();
+        assert!(
+            note.starts_with("git checkout of branch workflow/demo/T1 failed: "),
+            "{note}"
+        );
+        assert!(runner.requests().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn codex_binaries_that_cannot_start_leave_tickets_pending() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let err = run_workflow(WorkflowRunOptions {
+            codex_bin: Some(dir.path().join("missing-codex")),
+            ..run_options(dir.path())
+        })
+        .await
+        .expect_err("missing binary");
+        assert!(
+            matches!(
+                &err,
+                WorkflowError::LauncherSpawn { source, .. }
+                    if source.kind() == std::io::ErrorKind::NotFound
+            ),
+            "{err:?}"
+        );
+
+        // Found but not executable: the worker fails to spawn.
+        let not_executable = dir.path().join("codex.txt");
+        std::fs::write(&not_executable, "not a program").expect("write file");
+        let err = run_workflow(WorkflowRunOptions {
+            codex_bin: Some(not_executable.clone()),
+            ..run_options(dir.path())
+        })
+        .await
+        .expect_err("binary without permission to run");
+        assert!(
+            err.to_string().starts_with(&format!(
+                "failed to start codex binary {} (permission denied)",
+                not_executable.display()
+            )),
+            "{err}"
+        );
+        let report = err.partial_report().expect("partial report");
+        assert_eq!(report.tickets[0].status, TicketStatus::Pending);
+        assert!(report.tickets[0].attempts.is_empty());
+        let state = WorkflowState::load(&report.state_path).expect("load state");
+        assert_eq!(
+            state.ticket("T1").expect("ticket").status,
+            TicketStatus::Pending
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn prompt_command_replaces_built_in_prompts() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let runner = MockSessionRunner::new();
+        let opts = WorkflowRunOptions {
+            // The input starts with `{"phase":"worker"` or `{"phase":"review"`.
+            prompt_command: Some("cut -c11-16".to_string()),
+            ..run_options(dir.path())
+        };
+        let report = run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("run workflow");
+
+        assert_eq!(report.tickets[0].status, TicketStatus::Complete);
+        let prompts: Vec<String> = runner
+            .requests()
+            .into_iter()
+            .map(|request| request.prompt.trim().to_string())
+            .collect();
+        assert_eq!(prompts, vec!["worker", "review"]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn failing_prompt_command_fails_only_its_ticket() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let runner = MockSessionRunner::new();
+        let opts = WorkflowRunOptions {
+            // Fails for T1 only; T2 depends on it and T3 is independent.
+            prompt_command: Some(
+                r#"sh -c 'grep -q "\"id\":\"T1\"" && { echo no context for T1 >&2; exit 4; }; echo prompt'"#
+                    .to_string(),
+            ),
+            ..manifest_options(dir.path(), DEPENDENT_TICKETS)
+        };
+        let report = run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("run workflow");
+
+        let statuses: Vec<TicketStatus> = report
+            .tickets
+            .iter()
+            .map(|ticket| ticket.status.clone())
+            .collect();
+        assert_eq!(
+            statuses,
+            [
+                TicketStatus::Failed,
+                TicketStatus::Blocked,
+                TicketStatus::Complete
+            ]
+        );
+        let note = report.tickets[0].note.as_deref().expect("note");
+        assert!(note.contains("no context for T1"), "{note}");
+        assert_eq!(runner.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn preview_matches_the_prompt_a_run_sends() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let opts = WorkflowRunOptions {
+            codex_bin: Some(PathBuf::from("codex")),
+            worker_model: Some("gpt-test".to_string()),
+            reuse_run_dir: true,
+            ..run_options(dir.path())
+        };
+        let preview = preview_prompt(&opts, "T1", SessionPhase::Worker)
+            .await
+            .expect("preview");
+        assert!(!dir.path().join("artifacts").exists());
+        assert_eq!(preview.model.as_deref(), Some("gpt-test"));
+        assert_eq!(preview.working_dir, dir.path());
+        assert!(
+            preview
+                .command_line
+                .starts_with("codex exec --skip-git-repo-check -m gpt-test")
+        );
+        let err = preview_prompt(&opts, "T9", SessionPhase::Worker)
+            .await
+            .expect_err("unknown ticket");
+        assert!(err.to_string().contains("the manifest has: T1"));
+
+        let runner = MockSessionRunner::new();
+        run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("run workflow");
+        assert_eq!(runner.requests()[0].prompt, preview.prompt);
+    }
+
+    #[tokio::test]
+    async fn cli_config_overrides_follow_the_manifest_ones() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let opts = WorkflowRunOptions {
+            codex_bin: Some(PathBuf::from("codex")),
+            config_overrides: CliConfigOverrides {
+                raw_overrides: vec!["model=o3".to_string()],
+            },
+            reuse_run_dir: true,
+            ..manifest_options(
+                dir.path(),
+                "name: demo\nconfig_profile: team-b\nconfig_overrides:\n  - model=gpt-5\ntickets:\n  - id: T1\n    summary: First ticket\n",
+            )
+        };
+        let preview = preview_prompt(&opts, "T1", SessionPhase::Worker)
+            .await
+            .expect("preview");
+        assert!(
+            preview.command_line.starts_with(
+                "codex exec -c 'model=gpt-5' -c 'model=o3' --skip-git-repo-check \
+                --profile team-b"
+            ),
+            "{}",
+            preview.command_line
+        );
+    }
+
+    #[tokio::test]
+    async fn ticket_profiles_win_over_the_run_profile() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest = "name: demo\nconfig_profile: team-b\nconfig_overrides:\n  - model=gpt-5\n\
+            tickets:\n  - id: T1\n    summary: First ticket\n    profile: careful\n  \
+            - id: T2\n    summary: Second ticket\n";
+        let runner = MockSessionRunner::new();
+        let opts = WorkflowRunOptions {
+            profile: Some("fast".to_string()),
+            ..manifest_options(dir.path(), manifest)
+        };
+        let report = run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("run workflow");
+
+        let requests = runner.requests();
+        let profiles: Vec<Option<&str>> = requests
+            .iter()
+            .map(|request| request.profile.as_deref())
+            .collect();
+        assert_eq!(
+            profiles,
+            vec![Some("careful"), Some("careful"), Some("fast"), Some("fast")]
+        );
+        // `--profile` replaces the manifest's `config_profile`.
+        assert_eq!(requests[0].config_overrides, vec!["model=gpt-5"]);
+        let attempt_profiles: Vec<Option<&str>> = report
+            .tickets
+            .iter()
+            .flat_map(|ticket| &ticket.attempts)
+            .map(|attempt| attempt.profile.as_deref())
+            .collect();
+        assert_eq!(attempt_profiles, profiles);
+    }
+
+    /// Reports `missing` as not found, as the launcher does for binaries
+    /// that do not exist.
+    struct MissingProgramRunner {
+        missing: PathBuf,
+        inner: MockSessionRunner,
+    }
+
+    #[async_trait::async_trait]
+    impl SessionRunner for MissingProgramRunner {
+        async fn run(&self, request: SessionRequest) -> Result<SessionResult> {
+            self.inner.run(request).await
+        }
+
+        fn check_program(&self, program: Option<&Path>) -> Result<()> {
+            if program == Some(self.missing.as_path()) {
+                bail!("codex binary {} not found", self.missing.display());
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn reviews_run_the_reviewer_bin() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest = "name: demo\ntickets:\n  - id: T1\n    summary: First ticket\n    \
+            reviewer_bin: scripts/review.sh\n  - id: T2\n    summary: Second ticket\n";
+        let options = || WorkflowRunOptions {
+            reviewer_bin: Some(PathBuf::from("codex-next")),
+            ..manifest_options(dir.path(), manifest)
+        };
+        let runner = MockSessionRunner::new();
+        run_workflow_with_runner(options(), &runner)
+            .await
+            .expect("run workflow");
+
+        let bins: Vec<Option<PathBuf>> = runner
+            .requests()
+            .iter()
+            .map(|request| request.codex_bin.clone())
+            .collect();
+        assert_eq!(
+            bins,
+            vec![
+                None,
+                Some(dir.path().join("scripts/review.sh")),
+                None,
+                Some(PathBuf::from("codex-next")),
+            ]
+        );
+
+        // A reviewer binary that cannot be found fails the run up front.
+        let runner = MissingProgramRunner {
+            missing: PathBuf::from("codex-next"),
+            inner: MockSessionRunner::new(),
+        };
+        let err = run_workflow_with_runner(options(), &runner)
+            .await
+            .expect_err("missing reviewer binary");
+        assert!(err.to_string().contains("codex-next not found"), "{err}");
+        assert!(runner.inner.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reviews_run_in_their_own_working_dir() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        std::fs::create_dir_all(dir.path().join("app")).expect("create worker dir");
+        std::fs::create_dir_all(dir.path().join("qa")).expect("create review dir");
+        let manifest = "name: demo\ntickets:\n  - id: T1\n    summary: First ticket\n    \
+            working_dir: app\n    review_working_dir: qa\n  - id: T2\n    \
+            summary: Second ticket\n    working_dir: app\n    review_working_dir: gone\n";
+        let runner = MockSessionRunner::new();
+        let err = run_workflow_with_runner(manifest_options(dir.path(), manifest), &runner)
+            .await
+            .expect_err("T2's review directory is missing");
+
+        let requests = runner.requests();
+        assert_eq!(requests[0].working_dir, dir.path().join("app"));
+        assert_eq!(requests[1].working_dir, dir.path().join("qa"));
+        assert_eq!(requests[2].working_dir, dir.path().join("app"));
+        assert_eq!(requests.len(), 3);
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "review working directory {} does not exist for ticket T2",
+                dir.path().join("gone").display()
+            )
+        );
+        let report = err.partial_report().expect("partial report");
+        assert_eq!(
+            report.tickets[0].review_working_dir,
+            Some(dir.path().join("qa"))
+        );
+        assert_eq!(report.tickets[1].status, TicketStatus::NeedsReview);
+    }
+
+    /// Deletes the working directory of each worker session once it ends.
+    struct RemovingRunner(MockSessionRunner);
+
+    #[async_trait::async_trait]
+    impl SessionRunner for RemovingRunner {
+        async fn run(&self, request: SessionRequest) -> Result<SessionResult> {
+            let worker = request
+                .log_path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with("worker"));
+            let working_dir = request.working_dir.clone();
+            let result = self.0.run(request).await;
+            if worker {
+                std::fs::remove_dir_all(working_dir)?;
+            }
+            result
+        }
+    }
+
+    #[tokio::test]
+    async fn reviews_in_the_worker_dir_need_it_to_exist() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        std::fs::create_dir_all(dir.path().join("app")).expect("create worker dir");
+        let manifest =
+            "name: demo\ntickets:\n  - id: T1\n    summary: First ticket\n    working_dir: app\n";
+        let runner = RemovingRunner(MockSessionRunner::new());
+        let err = run_workflow_with_runner(manifest_options(dir.path(), manifest), &runner)
+            .await
+            .expect_err("T1's working directory is gone");
+
+        assert!(
+            matches!(
+                err.stopped_by(),
+                WorkflowError::WorkingDirMissing { ticket_id, .. } if ticket_id == "T1"
+            ),
+            "{err:?}"
+        );
+        assert_eq!(runner.0.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn reviews_elsewhere_do_not_need_the_worker_dir() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        std::fs::create_dir_all(dir.path().join("app")).expect("create worker dir");
+        std::fs::create_dir_all(dir.path().join("review")).expect("create review dir");
+        let manifest = "name: demo\ntickets:\n  - id: T1\n    summary: First ticket\n    working_dir: app\n    review_working_dir: review\n";
+        let runner = RemovingRunner(MockSessionRunner::new());
+        let report = run_workflow_with_runner(manifest_options(dir.path(), manifest), &runner)
+            .await
+            .expect("run workflow");
+
+        assert_eq!(report.tickets[0].status, TicketStatus::Complete);
+        let requests = runner.0.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].working_dir.ends_with("review"));
+    }
+
+    #[tokio::test]
+    async fn writes_result_json_for_a_finished_run() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let runner = MockSessionRunner::new();
+        run_workflow_with_runner(run_options(dir.path()), &runner)
+            .await
+            .expect("run workflow");
+
+        let result = std::fs::read(dir.path().join("artifacts/result.json")).expect("result.json");
+        let result: serde_json::Value = serde_json::from_slice(&result).expect("parse result");
+        assert_eq!(result["workflow_name"], "demo");
+        assert_eq!(result["success"], true);
+        assert_eq!(result["error"], serde_json::Value::Null);
+        assert_eq!(result["failed"], serde_json::json!([]));
+        assert_eq!(
+            result["counts"],
+            serde_json::json!([{ "status": "complete", "count": 1 }])
+        );
+        assert_eq!(result["tickets"][0]["id"], "T1");
+        assert_eq!(result["tickets"][0]["status"], "complete");
+        assert_eq!(result["tickets"][0]["allow_failure"], false);
+    }
+
+    #[tokio::test]
+    async fn writes_result_json_for_a_stopped_run() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest = "name: demo\ntickets:\n  - id: T1\n    summary: First ticket\n    review_working_dir: gone\n";
+        let runner = MockSessionRunner::new();
+        let err = run_workflow_with_runner(manifest_options(dir.path(), manifest), &runner)
+            .await
+            .expect_err("T1's review directory is missing");
+        assert!(
+            matches!(
+                err.stopped_by(),
+                WorkflowError::ReviewWorkingDirMissing { ticket_id, .. } if ticket_id == "T1"
+            ),
+            "{err:?}"
+        );
+
+        let result = std::fs::read(dir.path().join("artifacts/result.json")).expect("result.json");
+        let result: serde_json::Value = serde_json::from_slice(&result).expect("parse result");
+        assert_eq!(result["workflow_name"], "demo");
+        assert_eq!(result["success"], false);
+        assert_eq!(result["error"], err.to_string());
+        assert_eq!(result["tickets"][0]["id"], "T1");
+        assert_eq!(result["tickets"][0]["status"], "needs_review");
+
+        let events = read_events(&dir.path().join("artifacts/events.jsonl"), usize::MAX)
+            .expect("read event log");
+        let Some(WorkflowEvent::RunFinished {
+            error: Some(error), ..
+        }) = events.last()
+        else {
+            panic!("expected the run to finish with its error: {events:?}");
+        };
+        assert!(error.contains("review working directory"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn worker_failure_skips_review() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let runner = MockSessionRunner::with_responses([failed_session(2)]);
+        let report = run_workflow_with_runner(run_options(dir.path()), &runner)
+            .await
+            .expect("run workflow");
+
+        assert_eq!(report.tickets[0].status, TicketStatus::Failed);
+        assert_eq!(runner.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn failure_notes_quote_the_end_of_stderr() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let stderr = (1..=7)
+            .map(|line| format!("\u{1b}[31mline {line}\u{1b}[0m\n"))
+            .collect::<String>()
+            + &"x".repeat(300)
+            + "\n\n";
+        let runner = MockSessionRunner::with_responses([SessionResult {
+            stderr,
+            ..failed_session(2)
+        }]);
+        let report = run_workflow_with_runner(run_options(dir.path()), &runner)
+            .await
+            .expect("run workflow");
+
+        let note = report.tickets[0].note.as_deref().unwrap_or_default();
+        let lines: Vec<&str> = note.lines().collect();
+        assert_eq!(
+            lines[..5],
+            [
+                "Worker failed with status Some(2)",
+                "  line 4",
+                "  line 5",
+                "  line 6",
+                "  line 7",
+            ]
+        );
+        assert_eq!(lines[5].chars().count(), 2 + 200);
+        assert!(lines[5].ends_with("x…"), "{note}");
+    }
+
+    #[tokio::test]
+    async fn review_failure_fails_ticket() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let runner = MockSessionRunner::with_responses([successful_session(), failed_session(1)]);
+        let report = run_workflow_with_runner(run_options(dir.path()), &runner)
+            .await
+            .expect("run workflow");
+
+        assert_eq!(report.tickets[0].status, TicketStatus::Failed);
+        assert_eq!(runner.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn resume_runs_only_pending_review() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let opts = run_options(dir.path());
+        let manifest = WorkflowManifest::load(&opts.manifest_path).expect("load manifest");
+        let layout = WorkflowLayout::new(dir.path().join("artifacts"));
+        layout.ensure_root(&manifest).expect("ensure root");
+        let mut state = WorkflowState::initialize(&manifest);
+        state.ticket_mut("T1").expect("ticket").status = TicketStatus::NeedsReview;
+        state.save(&layout.state_file()).expect("save state");
+
+        let runner = MockSessionRunner::new();
+        let report = run_workflow_with_runner(
+            WorkflowRunOptions {
+                resume: true,
+                ..opts
+            },
+            &runner,
+        )
+        .await
+        .expect("run workflow");
+
+        assert_eq!(report.tickets[0].status, TicketStatus::Complete);
+        let requests = runner.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].log_path.ends_with("review.1.md"));
+    }
+
+    #[test]
+    fn max_parallel_prefers_explicit_value() {
+        let opts = WorkflowRunOptions {
+            max_parallel: Some(4),
+            concurrency_from_env: true,
+            ..Default::default()
+        };
+        assert_eq!(resolve_max_parallel(&opts), 4);
+
+        let opts = WorkflowRunOptions {
+            max_parallel: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(resolve_max_parallel(&opts), 1);
+    }
+
+    #[test]
+    fn max_parallel_defaults_to_sequential() {
+        assert_eq!(resolve_max_parallel(&WorkflowRunOptions::default()), 1);
+
+        let opts = WorkflowRunOptions {
+            concurrency_from_env: true,
+            ..Default::default()
+        };
+        assert!(resolve_max_parallel(&opts) >= 1);
+    }
+
+    #[tokio::test]
+    async fn runs_only_tickets_matching_selection() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let runner = MockSessionRunner::new();
+        let report = run_workflow_with_runner(
+            WorkflowRunOptions {
+                tickets: vec!["T[23]".to_string()],
+                ..manifest_options(dir.path(), DEPENDENT_TICKETS)
+            },
+            &runner,
+        )
+        .await
+        .expect("run workflow");
+
+        let status_of = |id: &str| {
+            report
+                .tickets
+                .iter()
+                .find(|ticket| ticket.ticket_id == id)
+                .map(|ticket| ticket.status.clone())
+        };
+        assert_eq!(status_of("T1"), Some(TicketStatus::Pending));
+        // T2 waits on the unselected T1 rather than being blocked.
+        assert_eq!(status_of("T2"), Some(TicketStatus::Pending));
+        assert_eq!(status_of("T3"), Some(TicketStatus::Complete));
+        assert_eq!(runner.requests().len(), 2);
+
+        let err = run_workflow_with_runner(
+            WorkflowRunOptions {
+                tickets: vec!["backend-*".to_string()],
+                ..manifest_options(dir.path(), DEPENDENT_TICKETS)
+            },
+            &runner,
+        )
+        .await
+        .expect_err("unmatched pattern");
+        assert!(err.to_string().contains("does not match any ticket"));
+    }
+
+    #[tokio::test]
+    async fn only_failed_reruns_failed_and_blocked_tickets() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let opts = manifest_options(
+            dir.path(),
+            r#"
+name: demo
+tickets:
+  - id: T1
+    summary: Failed last time
+  - id: T2
+    summary: Blocked on T1
+    depends_on: [T1]
+  - id: T3
+    summary: Completed last time
+  - id: T4
+    summary: Never started
+    depends_on: [T3]
+"#,
+        );
+        let manifest = WorkflowManifest::load(&opts.manifest_path).expect("load manifest");
+        let layout = WorkflowLayout::new(dir.path().join("artifacts"));
+        layout.ensure_root(&manifest).expect("ensure root");
+        let mut state = WorkflowState::initialize(&manifest);
+        state
+            .ticket_mut("T1")
+            .expect("ticket")
+            .mark_finished(TicketStatus::Failed, None);
+        state
+            .ticket_mut("T2")
+            .expect("ticket")
+            .mark_finished(TicketStatus::Blocked, Some("Dependency T1 failed".into()));
+        state
+            .ticket_mut("T3")
+            .expect("ticket")
+            .mark_finished(TicketStatus::Complete, None);
+        state.save(&layout.state_file()).expect("save state");
+
+        let runner = MockSessionRunner::new();
+        let report = run_workflow_with_runner(
+            WorkflowRunOptions {
+                resume: true,
+                only_failed: true,
+                ..opts
+            },
+            &runner,
+        )
+        .await
+        .expect("run workflow");
+
+        let status_of = |id: &str| {
+            report
+                .tickets
+                .iter()
+                .find(|ticket| ticket.ticket_id == id)
+                .map(|ticket| ticket.status.clone())
+        };
+        assert_eq!(status_of("T1"), Some(TicketStatus::Complete));
+        assert_eq!(status_of("T2"), Some(TicketStatus::Complete));
+        assert_eq!(status_of("T3"), Some(TicketStatus::Complete));
+        assert_eq!(status_of("T4"), Some(TicketStatus::Pending));
+        // Worker and review for T1 and T2 only.
+        let requests = runner.requests();
+        assert_eq!(requests.len(), 4);
+        assert!(
+            requests
+                .iter()
+                .all(|request| !request.log_path.to_string_lossy().contains("ticket-T3"))
+        );
+    }
+
+    #[tokio::test]
+    async fn resume_from_completes_earlier_tickets_and_reruns_the_named_one() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let runner = MockSessionRunner::new();
+        let report = run_workflow_with_runner(
+            WorkflowRunOptions {
+                resume_from: Some("T3".to_string()),
+                reuse_run_dir: true,
+                ..manifest_options(dir.path(), DEPENDENT_TICKETS)
+            },
+            &runner,
+        )
+        .await
+        .expect("run workflow");
+        let note_of = |report: &WorkflowStatusReport, id: &str| {
+            report
+                .tickets
+                .iter()
+                .find(|ticket| ticket.ticket_id == id)
+                .and_then(|ticket| ticket.note.clone())
+        };
+        assert!(
+            report
+                .tickets
+                .iter()
+                .all(|ticket| ticket.status == TicketStatus::Complete)
+        );
+        assert_eq!(
+            note_of(&report, "T2").as_deref(),
+            Some("Marked complete by --resume-from T3")
+        );
+        assert_eq!(runner.requests().len(), 2);
+
+        let report = run_workflow_with_runner(
+            WorkflowRunOptions {
+                resume: true,
+                resume_from: Some("T2".to_string()),
+                reuse_run_dir: true,
+                ..manifest_options(dir.path(), DEPENDENT_TICKETS)
+            },
+            &runner,
+        )
+        .await
+        .expect("resume workflow");
+        assert_eq!(
+            note_of(&report, "T1").as_deref(),
+            Some("Marked complete by --resume-from T3")
+        );
+        assert_eq!(note_of(&report, "T2").as_deref(), Some("Review passed"));
+        // Only T2 reran; T3 stayed complete.
+        assert_eq!(runner.requests().len(), 4);
+        assert!(
+            runner.requests()[2]
+                .log_path
+                .to_string_lossy()
+                .contains("ticket-T2")
+        );
+
+        let err = run_workflow_with_runner(
+            WorkflowRunOptions {
+                resume_from: Some("T9".to_string()),
+                ..manifest_options(dir.path(), DEPENDENT_TICKETS)
+            },
+            &runner,
+        )
+        .await
+        .expect_err("unknown ticket");
+        assert!(err.to_string().contains("unknown ticket T9"));
+    }
+
+    #[tokio::test]
+    async fn status_warns_when_artifacts_belong_to_another_manifest() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let runner = MockSessionRunner::new();
+        run_workflow_with_runner(run_options(dir.path()), &runner)
+            .await
+            .expect("run workflow");
+        let artifacts = Some(dir.path().join("artifacts"));
+        let report = load_status(
+            &dir.path().join("workflow.yaml"),
+            artifacts.clone(),
+            None,
+            None,
+        )
+        .expect("status")
+        .expect("state");
+        assert!(report.warnings.is_empty());
+
+        let other = dir.path().join("other.yaml");
+        std::fs::write(
+            &other,
+            "name: other\ntickets:\n  - id: T1\n    summary: One\n",
+        )
+        .expect("write manifest");
+        let report = load_status(&other, artifacts, None, None)
+            .expect("status")
+            .expect("state");
+        assert!(report.warnings[0].contains("created for workflow demo"));
+    }
+
+    #[test]
+    fn review_prompt_mentions_captured_diff() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest =
+            WorkflowManifest::load(&write_manifest(dir.path(), SINGLE_TICKET)).expect("manifest");
+        let mut state = TicketRunState::new("T1");
+        let prompt = build_review_prompt(&manifest, &manifest.tickets[0], &state, true);
+        assert!(!prompt.contains("worker.diff"));
+
+        state.worker_diff = Some(PathBuf::from("/artifacts/ticket-T1/patches/worker.diff"));
+        state.changed_files = Some(3);
+        let prompt = build_review_prompt(&manifest, &manifest.tickets[0], &state, false);
+        assert!(prompt.contains("The worker changed 3 file(s)"));
+        assert!(prompt.contains("patches/worker.diff"));
+        let prompt = build_review_prompt(&manifest, &manifest.tickets[0], &state, true);
+        assert!(prompt.contains("Review the worker's changes as captured in the diff at"));
+        assert!(prompt.contains("(3 file(s) changed) rather than the whole codebase"));
+    }
+
+    #[tokio::test]
+    async fn changes_requested_sends_ticket_back_to_worker() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let changes_requested = SessionResult {
+            last_message: Some(
+                "```json\n{\"verdict\": \"changes_requested\", \"blocking_issues\": \
+                [\"Add a regression test\"]}\n```\n"
+                    .to_string(),
+            ),
+            ..successful_session()
+        };
+        let runner = MockSessionRunner::with_responses([
+            successful_session(),
+            changes_requested.clone(),
+            successful_session(),
+            changes_requested,
+        ]);
+        let report = run_workflow_with_runner(
+            WorkflowRunOptions {
+                max_reworks: Some(1),
+                ..run_options(dir.path())
+            },
+            &runner,
+        )
+        .await
+        .expect("run workflow");
+
+        let ticket = &report.tickets[0];
+        assert_eq!(ticket.status, TicketStatus::Failed);
+        assert_eq!(ticket.reworks, 1);
+        let review = ticket.review_report.as_ref().expect("review report");
+        assert_eq!(review.blocking_issues, vec!["Add a regression test"]);
+        let requests = runner.requests();
+        assert_eq!(requests.len(), 4);
+        assert!(!requests[0].prompt.contains("Add a regression test"));
+        assert!(requests[2].prompt.contains("- Add a regression test"));
+        assert!(requests[1].prompt.contains("changes_requested"));
+    }
+
+    #[tokio::test]
+    async fn review_failure_policy_can_complete_or_block_the_ticket() {
+        let changes_requested = SessionResult {
+            last_message: Some(
+                "```json\n{\"verdict\": \"changes_requested\", \"blocking_issues\": \
+                [\"Add a regression test\"]}\n```\n"
+                    .to_string(),
+            ),
+            ..successful_session()
+        };
+        let manifest = "tickets:\n  - id: T1\n    summary: One\n    on_review_failure: complete\n  \
+            - id: T2\n    summary: Two\n    depends_on: [T1]\n    on_review_failure: block\n";
+        let dir = tempfile::tempdir().expect("tempdir");
+        let runner = MockSessionRunner::with_responses([
+            successful_session(),
+            changes_requested.clone(),
+            successful_session(),
+            changes_requested,
+        ]);
+        let report = run_workflow_with_runner(manifest_options(dir.path(), manifest), &runner)
+            .await
+            .expect("run workflow");
+
+        let completed = &report.tickets[0];
+        assert_eq!(completed.status, TicketStatus::Complete);
+        assert_eq!(
+            completed.note.as_deref(),
+            Some(
+                "Completed despite review: Reviewer requested changes (1 blocking issue(s))\n\
+                - Add a regression test"
+            )
+        );
+        assert_eq!(report.tickets[1].status, TicketStatus::Blocked);
+        assert_eq!(runner.requests().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn missing_expected_artifacts_block_the_ticket() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        std::fs::write(dir.path().join("present.sql"), "").expect("write artifact");
+        let runner = MockSessionRunner::new();
+        let opts = manifest_options(
+            dir.path(),
+            "tickets:\n  - id: T1\n    summary: One\n    expected_artifacts: \
+            [present.sql, tests/missing.rs]\n",
+        );
+        let report = run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("run workflow");
+
+        assert_eq!(report.tickets[0].status, TicketStatus::Blocked);
+        assert_eq!(
+            report.tickets[0].note.as_deref(),
+            Some("Missing expected artifacts: tests/missing.rs")
+        );
+        // Blocked after the worker, so no review was spent on it.
+        assert_eq!(runner.requests().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn allowed_failures_do_not_count_as_failures() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let runner = MockSessionRunner::with_responses([failed_session(1), failed_session(1)]);
+        let opts = manifest_options(
+            dir.path(),
+            "tickets:\n  - id: T1\n    summary: One\n    allow_failure: true\n  \
+            - id: T2\n    summary: Two\n",
+        );
+        let report = run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("run workflow");
+
+        let failed: Vec<&str> = report
+            .failed_tickets()
+            .iter()
+            .map(|ticket| ticket.ticket_id.as_str())
+            .collect();
+        assert_eq!(failed, vec!["T2"]);
+        assert!(report.has_failures());
+        assert_eq!(report.unfinished_tickets().len(), 1);
+        assert!(report.is_finished());
+    }
+
+    #[tokio::test]
+    async fn skip_unchanged_reruns_changed_tickets_and_their_dependents() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        std::fs::write(dir.path().join("input.txt"), "one").expect("write input");
+        let runner = MockSessionRunner::new();
+        let opts = || WorkflowRunOptions {
+            skip_unchanged: true,
+            ..manifest_options(
+                dir.path(),
+                "tickets:\n  - id: T1\n    summary: One\n    inputs: [input.txt]\n  \
+                - id: T2\n    summary: Two\n    depends_on: [T1]\n  \
+                - id: T3\n    summary: Three\n",
+            )
+        };
+        run_workflow_with_runner(opts(), &runner)
+            .await
+            .expect("first run");
+        assert_eq!(runner.requests().len(), 6);
+
+        let report = run_workflow_with_runner(opts(), &runner)
+            .await
+            .expect("unchanged run");
+        assert_eq!(runner.requests().len(), 6);
+        assert!(
+            report
+                .tickets
+                .iter()
+                .all(|ticket| ticket.status == TicketStatus::Complete)
+        );
+
+        std::fs::write(dir.path().join("input.txt"), "two").expect("edit input");
+        run_workflow_with_runner(opts(), &runner)
+            .await
+            .expect("edited run");
+        let rerun: Vec<String> = runner.requests()[6..]
+            .iter()
+            .map(|request| request.log_path.display().to_string())
+            .collect();
+        assert_eq!(rerun.len(), 4);
+        assert!(rerun.iter().all(|path| !path.contains("ticket-T3")));
+    }
+
+    #[test]
+    fn progress_counts_finished_tickets() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest =
+            WorkflowManifest::load(&write_manifest(dir.path(), DEPENDENT_TICKETS)).expect("load");
+        let mut state = WorkflowState::initialize(&manifest);
+        for (ticket_id, status) in [
+            ("T1", TicketStatus::Complete),
+            ("T3", TicketStatus::RunningWorker),
+        ] {
+            state.ticket_mut(ticket_id).expect("ticket").status = status;
+        }
+        assert_eq!(state.progress(), (1, 3));
+    }
+
+    #[tokio::test]
+    async fn failed_dependency_blocks_dependents() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let runner = MockSessionRunner::with_responses([failed_session(1)]);
+        let report =
+            run_workflow_with_runner(manifest_options(dir.path(), DEPENDENT_TICKETS), &runner)
+                .await
+                .expect("run workflow");
+
+        let statuses: Vec<TicketStatus> = report.tickets.iter().map(|t| t.status.clone()).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                TicketStatus::Failed,
+                TicketStatus::Blocked,
+                TicketStatus::Complete
+            ]
+        );
+        assert_eq!(
+            report.tickets[1].note.as_deref(),
+            Some("Dependency T1 failed")
+        );
+        assert_eq!(runner.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn ready_tickets_launch_in_manifest_order() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let runner = MockSessionRunner::new();
+        let manifest = "name: demo\ntickets:\n  - id: A\n    summary: A\n  - id: B\n    \
+            summary: B\n  - id: C\n    summary: C\n    depends_on: [B]\n  - id: D\n    \
+            summary: D\n";
+        run_workflow_with_runner(manifest_options(dir.path(), manifest), &runner)
+            .await
+            .expect("run workflow");
+
+        // D is ready long before C, but C comes first in the manifest.
+        let workers: Vec<String> = runner
+            .requests()
+            .iter()
+            .filter(|request| request.log_path.ends_with("worker.1.md"))
+            .filter_map(|request| request.log_path.parent()?.file_name())
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            workers,
+            vec!["ticket-A", "ticket-B", "ticket-C", "ticket-D"]
+        );
+    }
+
+    #[tokio::test]
+    async fn strict_deps_aborts_the_run() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let runner = MockSessionRunner::with_responses([failed_session(1)]);
+        let opts = WorkflowRunOptions {
+            strict_deps: true,
+            ..manifest_options(dir.path(), DEPENDENT_TICKETS)
+        };
+        let state_path = WorkflowLayout::new(dir.path().join("artifacts")).state_file();
+        let Err(err) = run_workflow_with_runner(opts, &runner).await else {
+            panic!("expected --strict-deps to fail the run");
+        };
+
+        assert!(err.to_string().contains("Dependency T1 failed"));
+        assert_eq!(runner.requests().len(), 1);
+        let events = read_events(&dir.path().join("artifacts/events.jsonl"), usize::MAX)
+            .expect("read event log");
+        assert!(matches!(
+            events.last(),
+            Some(WorkflowEvent::RunFinished { failed, error: Some(error), .. })
+                if failed == &["T1", "T2"] && error.contains("--strict-deps")
+        ));
+        let state = WorkflowState::load(&state_path).expect("load state");
+        assert_eq!(
+            state.ticket("T2").expect("T2").status,
+            TicketStatus::Blocked
+        );
+        assert_eq!(
+            state.ticket("T3").expect("T3").status,
+            TicketStatus::Pending
+        );
+    }
+
+    #[tokio::test]
+    async fn status_json_includes_prompts_on_request() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let runner = MockSessionRunner::new();
+        let report = run_workflow_with_runner(run_options(dir.path()), &runner)
+            .await
+            .expect("run workflow");
+
+        let json = report.to_json(false).expect("json");
+        let ticket = &json["tickets"][0];
+        assert_eq!(ticket["status"], "complete");
+        assert!(ticket.get("worker_command").is_none());
+        assert!(ticket.get("worker_prompt_text").is_none());
+
+        let json = report.to_json(true).expect("json");
+        let ticket = &json["tickets"][0];
+        assert_eq!(
+            ticket["worker_prompt_text"].as_str(),
+            Some(runner.requests()[0].prompt.as_str())
+        );
+        assert_eq!(ticket["review_command"], "codex exec -");
+
+        let secrets_file = dir.path().join("secrets.env");
+        std::fs::write(&secrets_file, "SUMMARY=First ticket\n").expect("write secrets");
+        let json = report
+            .with_secrets_file(&secrets_file)
+            .expect("load secrets")
+            .to_json(true)
+            .expect("json");
+        let prompt = json["tickets"][0]["worker_prompt_text"]
+            .as_str()
+            .unwrap_or_default();
+        assert!(!prompt.contains("First ticket"), "{prompt}");
+        assert!(prompt.contains("[REDACTED]"), "{prompt}");
+
+        let other = tempfile::tempdir().expect("tempdir");
+        let report = run_workflow_with_runner(
+            WorkflowRunOptions {
+                secrets_file: Some(secrets_file),
+                ..run_options(other.path())
+            },
+            &runner,
+        )
+        .await
+        .expect("run workflow");
+        let prompt_path = report.tickets[0]
+            .worker_prompt
+            .clone()
+            .expect("prompt path");
+        let prompt = std::fs::read_to_string(prompt_path).expect("read prompt");
+        assert!(!prompt.contains("First ticket"), "{prompt}");
+        assert!(prompt.contains("[REDACTED]"), "{prompt}");
+    }
+
+    #[tokio::test]
+    async fn retries_only_listed_exit_codes() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let runner = MockSessionRunner::with_responses([failed_session(7), failed_session(2)]);
+        let opts = WorkflowRunOptions {
+            max_retries: Some(3),
+            retry_on_exit_codes: vec![7],
+            ..run_options(dir.path())
+        };
+        let report = run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("run workflow");
+
+        let ticket = &report.tickets[0];
+        assert_eq!(ticket.status, TicketStatus::Failed);
+        assert_eq!(
+            ticket.note.as_deref(),
+            Some("Worker failed with status Some(2); exit code is not retryable")
+        );
+        let requests = runner.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].log_path.ends_with("worker.2.md"));
+    }
+
+    #[tokio::test]
+    async fn marks_the_attempts_whose_logs_were_pruned() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let runner = MockSessionRunner::with_responses([failed_session(1), failed_session(1)]);
+        let opts = WorkflowRunOptions {
+            max_retries: Some(2),
+            keep_log_attempts: Some(1),
+            ..run_options(dir.path())
+        };
+        let report = run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("run workflow");
+
+        let attempts: Vec<(SessionPhase, i32, bool, bool)> = report.tickets[0]
+            .attempts
+            .iter()
+            .map(|attempt| {
+                (
+                    attempt.phase,
+                    attempt.attempt,
+                    attempt.log_pruned,
+                    attempt.log_path.exists(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            attempts,
+            vec![
+                (SessionPhase::Worker, 1, true, false),
+                (SessionPhase::Worker, 2, true, false),
+                (SessionPhase::Worker, 3, false, true),
+                (SessionPhase::Review, 1, false, true),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_scale_the_session_timeout() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let runner = MockSessionRunner::with_responses([failed_session(1), failed_session(1)]);
+        let opts = WorkflowRunOptions {
+            max_retries: Some(2),
+            session_timeout: Some(Duration::from_secs(10)),
+            timeout_backoff: Some(1.5),
+            ..run_options(dir.path())
+        };
+        let report = run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("run workflow");
+
+        let timeouts: Vec<Option<Duration>> = runner
+            .requests()
+            .iter()
+            .map(|request| request.timeout)
+            .collect();
+        let secs = Duration::from_secs_f64;
+        assert_eq!(
+            timeouts,
+            vec![
+                Some(secs(10.0)),
+                Some(secs(15.0)),
+                Some(secs(22.5)),
+                Some(secs(10.0))
+            ]
+        );
+        let recorded: Vec<Option<f64>> = report.tickets[0]
+            .attempts
+            .iter()
+            .map(|attempt| attempt.timeout_secs)
+            .collect();
+        assert_eq!(
+            recorded,
+            vec![Some(10.0), Some(15.0), Some(22.5), Some(10.0)]
+        );
+    }
+
+    #[tokio::test]
+    async fn workers_and_reviews_get_their_own_timeouts() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest = "name: demo\nworker_timeout: 600\nreview_timeout: 60\ntickets:\n  - id: T1\n    summary: First ticket\n";
+        let runner = MockSessionRunner::new();
+        run_workflow_with_runner(manifest_options(dir.path(), manifest), &runner)
+            .await
+            .expect("run workflow");
+        let timeouts = |runner: &MockSessionRunner| -> Vec<Option<Duration>> {
+            runner
+                .requests()
+                .iter()
+                .map(|request| request.timeout)
+                .collect()
+        };
+        let secs = |secs| Some(Duration::from_secs(secs));
+        assert_eq!(timeouts(&runner), vec![secs(600), secs(60)]);
+
+        let runner = MockSessionRunner::new();
+        let opts = WorkflowRunOptions {
+            session_timeout: Some(Duration::from_secs(300)),
+            review_timeout: Some(Duration::from_secs(30)),
+            ..manifest_options(dir.path(), manifest)
+        };
+        run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("rerun workflow");
+        assert_eq!(timeouts(&runner), vec![secs(300), secs(30)]);
+    }
+
+    #[tokio::test]
+    async fn failed_preflight_probes_pause_the_run() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        // Fails until its second run, like a provider coming back up.
+        let marker = dir.path().join("probed");
+        let command = format!(
+            "sh -c 'test -e {0} || {{ touch {0}; echo unreachable >&2; exit 1; }}'",
+            marker.display()
+        );
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let runner = MockSessionRunner::new();
+        let opts = WorkflowRunOptions {
+            events: Some(sender),
+            preflight_command: Some(command),
+            preflight_each_session: true,
+            ..run_options(dir.path())
+        };
+        let report = run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("run workflow");
+
+        assert_eq!(report.tickets[0].status, TicketStatus::Complete);
+        assert_eq!(runner.requests().len(), 2);
+        let mut probes = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            if let WorkflowEvent::PreflightChecked {
+                ticket_id,
+                passed,
+                error,
+                retry_in_secs,
+                ..
+            } = event
+            {
+                probes.push((ticket_id, passed, error.is_some(), retry_in_secs));
+            }
+        }
+        let t1 = Some("T1".to_string());
+        assert_eq!(
+            probes,
+            vec![
+                (None, false, true, Some(2)),
+                (None, true, false, None),
+                (t1.clone(), true, false, None),
+                (t1, true, false, None),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn skip_git_repo_check_defaults_to_on() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let skips = |runner: &MockSessionRunner| -> Vec<bool> {
+            runner
+                .requests()
+                .iter()
+                .map(|request| request.skip_git_repo_check)
+                .collect()
+        };
+        let runner = MockSessionRunner::new();
+        run_workflow_with_runner(run_options(dir.path()), &runner)
+            .await
+            .expect("run workflow");
+        assert_eq!(skips(&runner), vec![true, true]);
+
+        let manifest = "name: demo\nskip_git_repo_check: false\ntickets:\n  - id: T1\n    summary: First ticket\n";
+        let runner = MockSessionRunner::new();
+        run_workflow_with_runner(manifest_options(dir.path(), manifest), &runner)
+            .await
+            .expect("rerun workflow");
+        assert_eq!(skips(&runner), vec![false, false]);
+
+        let runner = MockSessionRunner::new();
+        let opts = WorkflowRunOptions {
+            skip_git_repo_check: Some(true),
+            ..manifest_options(dir.path(), manifest)
+        };
+        run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("rerun workflow");
+        assert_eq!(skips(&runner), vec![true, true]);
+    }
+
+    #[tokio::test]
+    async fn prompts_over_the_token_budget_warn_or_fail_the_ticket() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest = "name: demo\nprompt_token_budget: 10\ntickets:\n  - id: T1\n    summary: First ticket\n";
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        let runner = MockSessionRunner::new();
+        let opts = WorkflowRunOptions {
+            events: Some(sender),
+            ..manifest_options(dir.path(), manifest)
+        };
+        let report = run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("run workflow");
+        assert_eq!(report.tickets[0].status, TicketStatus::Complete);
+        assert_eq!(runner.requests().len(), 2);
+        let mut warnings = Vec::new();
+        while let Ok(event) = receiver.try_recv() {
+            if let WorkflowEvent::PromptOverBudget {
+                phase,
+                estimated_tokens,
+                budget,
+                strict,
+                ..
+            } = event
+            {
+                assert!(estimated_tokens > budget, "{estimated_tokens}");
+                warnings.push((phase, budget, strict));
+            }
+        }
+        assert_eq!(
+            warnings,
+            vec![
+                (SessionPhase::Worker, 10, false),
+                (SessionPhase::Review, 10, false),
+            ]
+        );
+
+        let runner = MockSessionRunner::new();
+        let opts = WorkflowRunOptions {
+            strict_context: true,
+            ..manifest_options(dir.path(), manifest)
+        };
+        let report = run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("rerun workflow");
+        assert_eq!(report.tickets[0].status, TicketStatus::Failed);
+        assert!(runner.requests().is_empty());
+        let note = report.tickets[0].note.as_deref().unwrap_or_default();
+        assert!(
+            note.starts_with("Worker prompt is ~") && note.ends_with("over the budget of 10"),
+            "{note}"
+        );
+
+        let runner = MockSessionRunner::new();
+        let opts = WorkflowRunOptions {
+            strict_context: true,
+            prompt_token_budget: Some(100_000),
+            ..manifest_options(dir.path(), manifest)
+        };
+        let report = run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("rerun workflow");
+        assert_eq!(report.tickets[0].status, TicketStatus::Complete);
+    }
+
+    #[tokio::test]
+    async fn metrics_file_tracks_the_run() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let metrics_file = dir.path().join("metrics/demo.prom");
+        let runner = MockSessionRunner::with_responses([failed_session(1)]);
+        let opts = WorkflowRunOptions {
+            max_retries: Some(1),
+            metrics_file: Some(metrics_file.clone()),
+            ..run_options(dir.path())
+        };
+        run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("run workflow");
+
+        let metrics = std::fs::read_to_string(&metrics_file).expect("read metrics");
+        for sample in [
+            r#"codex_workflow_tickets{workflow="demo",status="complete"} 1"#,
+            r#"codex_workflow_tickets{workflow="demo",status="pending"} 0"#,
+            r#"codex_workflow_sessions_total{workflow="demo",phase="worker"} 2"#,
+            r#"codex_workflow_session_failures_total{workflow="demo",phase="worker"} 1"#,
+            r#"codex_workflow_ticket_duration_seconds_count{workflow="demo",ticket="T1"} 3"#,
+        ] {
+            assert!(metrics.lines().any(|line| line == sample), "{metrics}");
+        }
+    }
+
+    #[tokio::test]
+    async fn report_interval_keeps_a_status_report_snapshot() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let runner = MockSessionRunner::new();
+        let opts = WorkflowRunOptions {
+            report_interval: Some(Duration::from_secs(60)),
+            ..manifest_options(dir.path(), DEPENDENT_TICKETS)
+        };
+        let report = run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("run workflow");
+
+        let snapshot: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(report.state_path.with_file_name("report.json"))
+                .expect("read report"),
+        )
+        .expect("parse report");
+        assert_eq!(snapshot, report.to_json(false).expect("report json"));
+        assert_eq!(snapshot["tickets"][2]["status"], "complete");
+    }
+
+    #[tokio::test]
+    async fn retried_worker_can_still_complete() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let runner = MockSessionRunner::with_responses([failed_session(7)]);
+        let opts = WorkflowRunOptions {
+            max_retries: Some(1),
+            ..run_options(dir.path())
+        };
+        let report = run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("run workflow");
+
+        assert_eq!(report.tickets[0].status, TicketStatus::Complete);
+        assert_eq!(runner.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn base_dir_overrides_manifest_location() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let config_dir = dir.path().join("config");
+        let code_dir = dir.path().join("code");
+        std::fs::create_dir_all(&config_dir).expect("config dir");
+        std::fs::create_dir_all(&code_dir).expect("code dir");
+        let runner = MockSessionRunner::new();
+        let opts = WorkflowRunOptions {
+            manifest_path: write_manifest(&config_dir, SINGLE_TICKET),
+            base_dir: Some(code_dir.clone()),
+            ..Default::default()
+        };
+        let report = run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("run workflow");
+
+        assert_eq!(runner.requests()[0].working_dir, code_dir);
+        assert!(
+            report
+                .state_path
+                .starts_with(code_dir.join(".codex/workflows/demo"))
+        );
+    }
+
+    #[tokio::test]
+    async fn resume_warns_about_and_prunes_orphans() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let opts = run_options(dir.path());
+        let manifest = WorkflowManifest::load(&opts.manifest_path).expect("load manifest");
+        let layout = WorkflowLayout::new(dir.path().join("artifacts"));
+        layout.ensure_root(&manifest).expect("ensure root");
+        let mut state = WorkflowState::initialize(&manifest);
+        state
+            .tickets
+            .insert("OLD".to_string(), TicketRunState::new("OLD"));
+        state.save(&layout.state_file()).expect("save state");
+        layout.ensure_ticket_dir("OLD").expect("orphan dir");
+
+        let report = run_workflow_with_runner(
+            WorkflowRunOptions {
+                resume: true,
+                ..run_options(dir.path())
+            },
+            &MockSessionRunner::new(),
+        )
+        .await
+        .expect("run workflow");
+        assert_eq!(report.tickets.len(), 2);
+        assert!(report.warnings[0].contains("no longer in the manifest: OLD"));
+
+        let report = run_workflow_with_runner(
+            WorkflowRunOptions {
+                resume: true,
+                prune_orphans: true,
+                delete_orphan_artifacts: true,
+                ..run_options(dir.path())
+            },
+            &MockSessionRunner::new(),
+        )
+        .await
+        .expect("run workflow");
+        assert_eq!(report.tickets.len(), 1);
+        assert!(report.warnings[0].starts_with("Pruned state"));
+        assert!(!layout.ticket_dir("OLD").exists());
+    }
+
+    #[tokio::test]
+    async fn each_run_writes_to_a_new_run_dir() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let artifacts = dir.path().join("artifacts");
+        let runner = MockSessionRunner::new();
+        let first = run_workflow_with_runner(run_options(dir.path()), &runner)
+            .await
+            .expect("run workflow");
+        let second = run_workflow_with_runner(run_options(dir.path()), &runner)
+            .await
+            .expect("run workflow");
+
+        let first_run = first.run_dir.expect("first run dir");
+        let second_run = second.run_dir.expect("second run dir");
+        assert_ne!(first_run, second_run);
+        assert!(second_run.starts_with(artifacts.join("runs")));
+        assert_eq!(second.state_path, artifacts.join("state.json"));
+        let requests = runner.requests();
+        assert!(requests[0].log_path.starts_with(&first_run));
+        assert!(requests[2].log_path.starts_with(&second_run));
+        #[cfg(unix)]
+        assert_eq!(
+            std::fs::canonicalize(artifacts.join("latest")).expect("latest"),
+            std::fs::canonicalize(&second_run).expect("run dir")
+        );
+
+        let flat = run_workflow_with_runner(
+            WorkflowRunOptions {
+                reuse_run_dir: true,
+                ..run_options(dir.path())
+            },
+            &runner,
+        )
+        .await
+        .expect("run workflow");
+        assert_eq!(flat.run_dir, None);
+        assert!(
+            runner.requests()[4]
+                .log_path
+                .starts_with(artifacts.join("ticket-T1"))
+        );
+    }
+
+    #[tokio::test]
+    async fn resume_continues_in_the_latest_run_dir() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let opts = || manifest_options(dir.path(), DEPENDENT_TICKETS);
+        let runner = MockSessionRunner::new();
+        let first = run_workflow_with_runner(
+            WorkflowRunOptions {
+                tickets: vec!["T1".to_string()],
+                ..opts()
+            },
+            &runner,
+        )
+        .await
+        .expect("run workflow");
+        let run_dir = first.run_dir.expect("run dir");
+        assert!(run_dir.join("ticket-T1").is_dir());
+        // Tickets that did not run get no directory.
+        assert!(!run_dir.join("ticket-T2").exists());
+        assert!(!run_dir.join("ticket-T3").exists());
+
+        let resumed = run_workflow_with_runner(
+            WorkflowRunOptions {
+                resume: true,
+                ..opts()
+            },
+            &runner,
+        )
+        .await
+        .expect("resume workflow");
+        assert_eq!(resumed.run_dir.as_ref(), Some(&run_dir));
+        assert_eq!(
+            WorkflowLayout::new(dir.path().join("artifacts"))
+                .run_dirs()
+                .len(),
+            1
+        );
+        let requests = runner.requests();
+        assert_eq!(requests.len(), 6);
+        assert!(
+            requests
+                .iter()
+                .all(|request| request.log_path.starts_with(&run_dir))
+        );
+    }
+
+    struct ScriptedConfirmer {
+        decisions: Mutex<Vec<TicketDecision>>,
+        asked: Mutex<Vec<String>>,
+    }
+
+    impl TicketConfirmer for ScriptedConfirmer {
+        fn confirm(&self, plan: &TicketPlan) -> Result<TicketDecision> {
+            self.asked
+                .lock()
+                .expect("asked poisoned")
+                .push(plan.ticket_id.clone());
+            Ok(self.decisions.lock().expect("decisions poisoned").remove(0))
+        }
+    }
+
+    fn scripted(decisions: Vec<TicketDecision>) -> Arc<ScriptedConfirmer> {
+        Arc::new(ScriptedConfirmer {
+            decisions: Mutex::new(decisions),
+            asked: Mutex::default(),
+        })
+    }
+
+    #[tokio::test]
+    async fn confirmer_can_skip_defer_and_quit() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let confirmer = scripted(vec![
+            TicketDecision::Skip,
+            TicketDecision::Defer,
+            TicketDecision::Run,
+        ]);
+        let runner = MockSessionRunner::new();
+        let report = run_workflow_with_runner(
+            WorkflowRunOptions {
+                confirmer: Some(confirmer.clone()),
+                ..manifest_options(dir.path(), DEPENDENT_TICKETS)
+            },
+            &runner,
+        )
+        .await
+        .expect("run workflow");
+        assert_eq!(
+            *confirmer.asked.lock().expect("asked poisoned"),
+            vec!["T1", "T2", "T3"]
+        );
+        let statuses: Vec<TicketStatus> = report
+            .tickets
+            .iter()
+            .map(|ticket| ticket.status.clone())
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                TicketStatus::Skipped,
+                TicketStatus::Pending,
+                TicketStatus::Complete
+            ]
+        );
+        assert_eq!(runner.requests().len(), 2);
+
+        let report = run_workflow_with_runner(
+            WorkflowRunOptions {
+                resume: true,
+                confirmer: Some(scripted(vec![TicketDecision::Quit])),
+                ..manifest_options(dir.path(), DEPENDENT_TICKETS)
+            },
+            &runner,
+        )
+        .await
+        .expect("run workflow");
+        assert_eq!(report.tickets[1].status, TicketStatus::Pending);
+        assert!(report.warnings[0].contains("stopped on request before ticket T2"));
+        assert_eq!(runner.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn worker_retry_resumes_previous_session() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let runner = MockSessionRunner::with_responses([SessionResult {
+            session_id: Some("first-session".to_string()),
+            ..failed_session(1)
+        }]);
+        let opts = WorkflowRunOptions {
+            max_retries: Some(1),
+            worker_resumes_session: true,
+            ..run_options(dir.path())
+        };
+        let report = run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("run workflow");
+
+        assert_eq!(
+            report.tickets[0].worker_session_id.as_deref(),
+            Some("first-session")
+        );
+        let requests = runner.requests();
+        assert_eq!(requests[0].resume_session, None);
+        assert_eq!(requests[1].resume_session.as_deref(), Some("first-session"));
+    }
+
+    /// Asks the run to stop from inside its first session, as `workflow
+    /// cancel` would; a hard request then leaves the session hanging until
+    /// the run kills it.
+    struct CancellingRunner {
+        marker: PathBuf,
+        hard: bool,
+        inner: MockSessionRunner,
+    }
+
+    #[async_trait::async_trait]
+    impl SessionRunner for CancellingRunner {
+        async fn run(&self, request: SessionRequest) -> Result<SessionResult> {
+            if self.inner.requests().is_empty() {
+                let request = CancelRequest {
+                    hard: self.hard,
+                    requested_at: Utc::now(),
+                };
+                std::fs::write(&self.marker, serde_json::to_vec(&request)?)?;
+                if self.hard {
+                    std::future::pending::<()>().await;
+                }
+            }
+            self.inner.run(request).await
+        }
+    }
+
+    /// Holds T1's review until T2's worker starts, failing it if that does
+    /// not happen within a few (paused) seconds.
+    struct SlowReviewRunner {
+        t2_started: tokio::sync::Notify,
+        inner: MockSessionRunner,
+    }
+
+    #[async_trait::async_trait]
+    impl SessionRunner for SlowReviewRunner {
+        async fn run(&self, request: SessionRequest) -> Result<SessionResult> {
+            let in_ticket_dir = |ticket: &str| {
+                request
+                    .log_path
+                    .parent()
+                    .and_then(Path::file_name)
+                    .is_some_and(|dir| dir == ticket)
+            };
+            let review = request
+                .log_path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with("review"));
+            if in_ticket_dir("ticket-T2") && !review {
+                self.t2_started.notify_one();
+            }
+            if in_ticket_dir("ticket-T1") && review {
+                let started =
+                    tokio::time::timeout(Duration::from_secs(5), self.t2_started.notified()).await;
+                if started.is_err() {
+                    return Ok(failed_session(1));
+                }
+            }
+            self.inner.run(request).await
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn worker_dependencies_start_while_the_dependency_is_in_review() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest = "name: demo\ntickets:\n  - id: T1\n    summary: First ticket\n  - id: T2\n    summary: Second ticket\n    depends_on: [\"T1:worker\"]\n";
+        let runner = SlowReviewRunner {
+            t2_started: tokio::sync::Notify::new(),
+            inner: MockSessionRunner::new(),
+        };
+        let opts = WorkflowRunOptions {
+            max_parallel: Some(2),
+            ..manifest_options(dir.path(), manifest)
+        };
+        let report = run_workflow_with_runner(opts, &runner)
+            .await
+            .expect("run workflow");
+
+        let statuses: Vec<TicketStatus> = report
+            .tickets
+            .iter()
+            .map(|ticket| ticket.status.clone())
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![TicketStatus::Complete, TicketStatus::Complete]
+        );
+        assert_eq!(runner.inner.requests().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn cancel_stops_launching_tickets_and_hard_cancel_kills_sessions() {
+        for hard in [false, true] {
+            let dir = tempfile::tempdir().expect("tempdir");
+            let opts = manifest_options(dir.path(), DEPENDENT_TICKETS);
+            let runner = CancellingRunner {
+                marker: dir.path().join("artifacts/cancel.json"),
+                hard,
+                inner: MockSessionRunner::new(),
+            };
+            let report = run_workflow_with_runner(opts, &runner)
+                .await
+                .expect("run workflow");
+
+            let statuses: Vec<TicketStatus> = report
+                .tickets
+                .iter()
+                .map(|ticket| ticket.status.clone())
+                .collect();
+            let first = if hard {
+                TicketStatus::Pending
+            } else {
+                TicketStatus::Complete
+            };
+            assert_eq!(
+                statuses,
+                vec![first, TicketStatus::Pending, TicketStatus::Pending]
+            );
+            if hard {
+                let ticket = &report.tickets[0];
+                assert_eq!(
+                    ticket.note.as_deref(),
+                    Some("Worker session killed by workflow cancel --hard")
+                );
+                assert!(ticket.attempts[0].finished_at.is_some());
+            }
+            assert!(
+                report
+                    .warnings
+                    .iter()
+                    .any(|warning| warning.starts_with("Run cancelled"))
+            );
+            assert!(!runner.marker.exists());
+        }
     }
 }
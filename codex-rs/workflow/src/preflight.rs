@@ -0,0 +1,181 @@
+//! Checks that the model provider is reachable before sessions launch
+//! (`--preflight-url`, `--preflight-command`), so an outage pauses the run
+//! instead of failing every session it starts.
+
+use anyhow::Context;
+use anyhow::Result;
+use reqwest::Url;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Limit on each probe, connecting included.
+const PREFLIGHT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Wait after the first failed probe; each further failure doubles it.
+const PREFLIGHT_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Longest wait between probes.
+const PREFLIGHT_MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+
+pub(crate) struct Preflight {
+    client: reqwest::Client,
+    url: Option<Url>,
+    /// The command line as given, and split into its arguments.
+    command: Option<(String, Vec<String>)>,
+}
+
+impl Preflight {
+    /// `None` when neither a URL nor a command is given.
+    pub(crate) fn new(url: Option<&str>, command: Option<&str>) -> Result<Option<Self>> {
+        if url.is_none() && command.is_none() {
+            return Ok(None);
+        }
+        let url = url
+            .map(|url| {
+                let parsed =
+                    Url::parse(url).with_context(|| format!("invalid preflight URL {url}"))?;
+                if !matches!(parsed.scheme(), "http" | "https") {
+                    anyhow::bail!(
+                        "preflight URL must use http or https, not {}",
+                        parsed.scheme()
+                    );
+                }
+                Ok(parsed)
+            })
+            .transpose()?;
+        let command = command
+            .map(|command| {
+                shlex::split(command)
+                    .filter(|argv| !argv.is_empty())
+                    .map(|argv| (command.to_string(), argv))
+                    .with_context(|| format!("invalid preflight command {command:?}"))
+            })
+            .transpose()?;
+        let client = reqwest::Client::builder()
+            .timeout(PREFLIGHT_TIMEOUT)
+            .build()
+            .context("failed to set up the preflight client")?;
+        Ok(Some(Preflight {
+            client,
+            url,
+            command,
+        }))
+    }
+
+    /// Runs the probes. The URL passes on any response below `500`, since
+    /// e.g. a `401` still shows the provider is up; the command passes when
+    /// it exits successfully within the time limit.
+    pub(crate) async fn check(&self) -> Result<(), String> {
+        if let Some(url) = &self.url {
+            let response = self
+                .client
+                .get(url.clone())
+                .send()
+                .await
+                .map_err(|err| format!("GET {url} failed: {err}"))?;
+            if response.status().is_server_error() {
+                return Err(format!("GET {url} returned {}", response.status()));
+            }
+        }
+        if let Some((command, argv)) = &self.command {
+            run_command(command, argv).await?;
+        }
+        Ok(())
+    }
+}
+
+/// How long to wait before probing again after `failures` failed probes in
+/// a row.
+pub(crate) fn retry_delay(failures: u32) -> Duration {
+    PREFLIGHT_RETRY_DELAY
+        .saturating_mul(2u32.saturating_pow(failures.saturating_sub(1)))
+        .min(PREFLIGHT_MAX_RETRY_DELAY)
+}
+
+async fn run_command(command: &str, argv: &[String]) -> Result<(), String> {
+    let child = Command::new(&argv[0])
+        .args(&argv[1..])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|err| format!("failed to run preflight command {command:?}: {err}"))?;
+    let output = tokio::time::timeout(PREFLIGHT_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| {
+            format!(
+                "preflight command {command:?} did not finish within {}s",
+                PREFLIGHT_TIMEOUT.as_secs()
+            )
+        })?
+        .map_err(|err| format!("failed to run preflight command {command:?}: {err}"))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    match stderr.lines().rev().find(|line| !line.trim().is_empty()) {
+        Some(line) => Err(format!(
+            "preflight command {command:?} failed with {}: {}",
+            output.status,
+            line.trim()
+        )),
+        None => Err(format!(
+            "preflight command {command:?} failed with {}",
+            output.status
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers::method;
+
+    #[test]
+    fn backs_off_up_to_a_limit() {
+        assert_eq!(retry_delay(1), Duration::from_secs(2));
+        assert_eq!(retry_delay(2), Duration::from_secs(4));
+        assert_eq!(retry_delay(4), Duration::from_secs(16));
+        assert_eq!(retry_delay(20), PREFLIGHT_MAX_RETRY_DELAY);
+        assert_eq!(retry_delay(u32::MAX), PREFLIGHT_MAX_RETRY_DELAY);
+    }
+
+    #[tokio::test]
+    async fn probes_the_url_and_the_command() {
+        assert!(Preflight::new(None, None).expect("no probes").is_none());
+        assert!(Preflight::new(Some("ftp://example.com"), None).is_err());
+        assert!(Preflight::new(None, Some("'unterminated")).is_err());
+
+        let server = MockServer::start().await;
+        let up = Preflight::new(Some(&server.uri()), None)
+            .expect("preflight")
+            .expect("probes");
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(401))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        assert_eq!(up.check().await, Ok(()));
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+        let err = up.check().await.expect_err("server error");
+        assert!(err.contains("503"), "{err}");
+
+        let passing = Preflight::new(None, Some("true"))
+            .expect("preflight")
+            .expect("probes");
+        assert_eq!(passing.check().await, Ok(()));
+        let failing = Preflight::new(None, Some("sh -c 'echo provider down >&2; exit 3'"))
+            .expect("preflight")
+            .expect("probes");
+        let err = failing.check().await.expect_err("command fails");
+        assert!(err.ends_with(": provider down"), "{err}");
+    }
+}
@@ -0,0 +1,76 @@
+//! Cleanup for codex child processes and everything they spawn.
+
+#[cfg(unix)]
+use std::time::Duration;
+
+/// How long the process group gets to exit after SIGTERM before SIGKILL.
+#[cfg(unix)]
+const KILL_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+/// Terminates a child's whole process tree when dropped, unless disarmed.
+///
+/// On Unix the child must have been spawned as the leader of its own process
+/// group (`process_group(0)`), so that shell commands started by codex are
+/// reaped along with it. On Windows the tree is killed with `taskkill /T`.
+#[derive(Debug)]
+pub(crate) struct ProcessTreeGuard {
+    pid: Option<u32>,
+}
+
+impl ProcessTreeGuard {
+    pub(crate) fn new(pid: Option<u32>) -> Self {
+        Self { pid }
+    }
+
+    /// The child exited on its own; leave its process group alone.
+    pub(crate) fn disarm(&mut self) {
+        self.pid = None;
+    }
+
+    /// Terminates the process tree now. Later calls (and the drop) are no-ops.
+    pub(crate) fn terminate(&mut self) {
+        if let Some(pid) = self.pid.take() {
+            terminate_tree(pid);
+        }
+    }
+}
+
+impl Drop for ProcessTreeGuard {
+    fn drop(&mut self) {
+        self.terminate();
+    }
+}
+
+#[cfg(unix)]
+fn terminate_tree(pid: u32) {
+    let Ok(pgid) = libc::pid_t::try_from(pid) else {
+        return;
+    };
+    // SAFETY: kill(2) has no memory-safety requirements; a negative pid
+    // addresses the process group led by the child.
+    unsafe {
+        libc::kill(-pgid, libc::SIGTERM);
+    }
+    // Drop cannot wait, so escalate from a detached thread. Signalling an
+    // already-empty group fails with ESRCH, which is ignored.
+    std::thread::spawn(move || {
+        std::thread::sleep(KILL_GRACE_PERIOD);
+        // SAFETY: as above.
+        unsafe {
+            libc::kill(-pgid, libc::SIGKILL);
+        }
+    });
+}
+
+#[cfg(windows)]
+fn terminate_tree(pid: u32) {
+    let _ = std::process::Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/T", "/F"])
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status();
+}
+
+#[cfg(not(any(unix, windows)))]
+fn terminate_tree(_pid: u32) {}
@@ -0,0 +1,163 @@
+//! User-supplied prompt builders (`--prompt-command`).
+
+use crate::manifest::TicketSpec;
+use crate::state::SessionPhase;
+use crate::state::TicketRunState;
+use anyhow::Context;
+use anyhow::Result;
+use serde::Serialize;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Longest a prompt command may run before it is killed.
+pub(crate) const PROMPT_COMMAND_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The prompt command ran but produced no prompt for this ticket. Unlike an
+/// invalid or missing command, this fails only the ticket.
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub(crate) struct PromptCommandFailed(pub String);
+
+/// The JSON document a prompt command reads from stdin.
+#[derive(Debug, Serialize)]
+pub(crate) struct PromptCommandInput<'a> {
+    pub phase: SessionPhase,
+    pub workflow_name: String,
+    pub overview: Option<&'a str>,
+    pub ticket: &'a TicketSpec,
+    /// The ticket's recorded state: attempts, logs, diff, last review.
+    pub state: Option<&'a TicketRunState>,
+    pub working_dir: &'a Path,
+    pub patch_dir: PathBuf,
+}
+
+/// Runs `command` (split like a shell command line, without a shell) with
+/// `input` as JSON on stdin and returns its stdout as the prompt. Fails with
+/// [`PromptCommandFailed`] if the command exits unsuccessfully, runs longer
+/// than `timeout`, or prints nothing.
+pub(crate) async fn run_prompt_command(
+    command: &str,
+    input: &PromptCommandInput<'_>,
+    timeout: Duration,
+) -> Result<String> {
+    let argv = shlex::split(command)
+        .filter(|argv| !argv.is_empty())
+        .with_context(|| format!("invalid prompt command {command:?}"))?;
+    let payload = serde_json::to_vec(input)?;
+    let mut child = Command::new(&argv[0])
+        .args(&argv[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("failed to run prompt command {command:?}"))?;
+    let stdin = child.stdin.take();
+    let write_input = async {
+        let Some(mut stdin) = stdin else {
+            return Ok(());
+        };
+        match stdin.write_all(&payload).await {
+            // The command does not have to read its input.
+            Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+            other => other,
+        }
+    };
+    let phase = input.phase.as_str();
+    let ticket_id = &input.ticket.id;
+    // Dropping the child on timeout kills it.
+    let Ok((written, output)) = tokio::time::timeout(timeout, async {
+        tokio::join!(write_input, child.wait_with_output())
+    })
+    .await
+    else {
+        return Err(PromptCommandFailed(format!(
+            "prompt command {command:?} timed out after {}s for {phase} of ticket {ticket_id}",
+            timeout.as_secs_f64()
+        ))
+        .into());
+    };
+    let output = output.with_context(|| format!("failed to run prompt command {command:?}"))?;
+    if !output.status.success() {
+        return Err(PromptCommandFailed(format!(
+            "prompt command {command:?} failed for {phase} of ticket {ticket_id} with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+        .into());
+    }
+    written.context("failed to write ticket to prompt command stdin")?;
+    let Ok(prompt) = String::from_utf8(output.stdout) else {
+        return Err(PromptCommandFailed(format!(
+            "prompt command {command:?} printed invalid UTF-8 for {phase} of ticket {ticket_id}"
+        ))
+        .into());
+    };
+    if prompt.trim().is_empty() {
+        return Err(PromptCommandFailed(format!(
+            "prompt command {command:?} printed no prompt for {phase} of ticket {ticket_id}"
+        ))
+        .into());
+    }
+    Ok(prompt)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn passes_ticket_json_and_reads_prompt() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let captured = dir.path().join("input.json");
+        let ticket = TicketSpec {
+            id: "T1".to_string(),
+            summary: "First ticket".to_string(),
+            ..Default::default()
+        };
+        let input = PromptCommandInput {
+            phase: SessionPhase::Review,
+            workflow_name: "demo".to_string(),
+            overview: None,
+            ticket: &ticket,
+            state: None,
+            working_dir: dir.path(),
+            patch_dir: dir.path().join("patches"),
+        };
+        let command = format!(
+            "sh -c 'cat > \"$0\"; printf \"custom prompt\"' {}",
+            captured.display()
+        );
+        let prompt = run_prompt_command(&command, &input, PROMPT_COMMAND_TIMEOUT)
+            .await
+            .expect("prompt command");
+        assert_eq!(prompt, "custom prompt");
+        let json: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&captured).expect("read input")).expect("json");
+        assert_eq!(json["phase"], "review");
+        assert_eq!(json["ticket"]["summary"], "First ticket");
+
+        let err = run_prompt_command(
+            "sh -c 'echo nope >&2; exit 3'",
+            &input,
+            PROMPT_COMMAND_TIMEOUT,
+        )
+        .await
+        .expect_err("failing command");
+        assert!(err.to_string().contains("nope"));
+        assert!(err.is::<PromptCommandFailed>());
+        let err = run_prompt_command("true", &input, PROMPT_COMMAND_TIMEOUT)
+            .await
+            .expect_err("empty prompt");
+        assert!(err.to_string().contains("no prompt"));
+        let err = run_prompt_command("sleep 5", &input, Duration::from_millis(100))
+            .await
+            .expect_err("slow command");
+        assert!(err.to_string().contains("timed out"), "{err}");
+        assert!(err.is::<PromptCommandFailed>());
+    }
+}
@@ -0,0 +1,671 @@
+//! Markdown, HTML, JUnit XML and CSV reports of a workflow's status for
+//! `workflow report`.
+
+use crate::log_format::strip_ansi;
+use crate::orchestrator::WorkflowStatusReport;
+use crate::state::TicketRunState;
+use crate::state::TicketStatus;
+use chrono::DateTime;
+use chrono::SecondsFormat;
+use chrono::TimeDelta;
+use chrono::Utc;
+use pulldown_cmark::Event;
+use pulldown_cmark::Options;
+use pulldown_cmark::Parser;
+use pulldown_cmark::html;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    #[default]
+    Markdown,
+    /// A self-contained HTML page.
+    Html,
+    /// JUnit XML, for CI systems that render test results.
+    Junit,
+    /// A row per ticket, for spreadsheets.
+    Csv,
+}
+
+impl ReportFormat {
+    /// The format a report path's extension implies, if any.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()?.to_str()?.parse().ok()
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "markdown" | "md" => Ok(ReportFormat::Markdown),
+            "html" | "htm" => Ok(ReportFormat::Html),
+            "junit" | "xml" => Ok(ReportFormat::Junit),
+            "csv" => Ok(ReportFormat::Csv),
+            other => Err(format!(
+                "unknown report format `{other}` (expected markdown, html, junit or csv)"
+            )),
+        }
+    }
+}
+
+/// Columns of [`WorkflowStatusReport::to_csv`].
+const CSV_COLUMNS: [&str; 11] = [
+    "id",
+    "summary",
+    "status",
+    "started_at",
+    "finished_at",
+    "duration_seconds",
+    "attempts",
+    "model",
+    "note",
+    "worker_log",
+    "review_log",
+];
+
+/// Lines of a failed ticket's latest session log quoted in its JUnit
+/// failure.
+const JUNIT_LOG_EXCERPT_LINES: usize = 20;
+
+/// `42s`, `3m07s`, or `2h05m`.
+pub fn format_duration(elapsed: TimeDelta) -> String {
+    let seconds = elapsed.num_seconds().max(0);
+    match seconds {
+        0..60 => format!("{seconds}s"),
+        60..3600 => format!("{}m{:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h{:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+impl WorkflowStatusReport {
+    /// A Markdown document for pasting into a PR description: the run's
+    /// duration, a status table, a section per ticket with its review
+    /// summary and links to its logs and diff, and a rollup by status. Links
+    /// are relative to `link_base`, normally the report's directory.
+    pub fn to_markdown(&self, link_base: &Path) -> String {
+        let mut out = format!("# Workflow report: {}\n\n", self.workflow_name);
+        let started = self
+            .tickets
+            .iter()
+            .filter_map(|ticket| ticket.started_at)
+            .min();
+        let finished = self
+            .tickets
+            .iter()
+            .filter_map(|ticket| ticket.finished_at)
+            .max();
+        if let (Some(started), Some(finished)) = (started, finished) {
+            out.push_str(&format!(
+                "Ran from {} to {} ({}).\n\n",
+                started.format("%Y-%m-%d %H:%M:%S UTC"),
+                finished.format("%Y-%m-%d %H:%M:%S UTC"),
+                format_duration(finished - started)
+            ));
+        }
+        for warning in &self.warnings {
+            out.push_str(&format!("> **Warning:** {warning}\n\n"));
+        }
+
+        out.push_str("| Ticket | Status | Duration | Note |\n| --- | --- | --- | --- |\n");
+        let now = Utc::now();
+        for ticket in &self.tickets {
+            let duration = ticket
+                .elapsed(now)
+                .map(format_duration)
+                .unwrap_or_else(|| "-".to_string());
+            let note = ticket.note.as_deref().unwrap_or("");
+            out.push_str(&format!(
+                "| {} | {} | {duration} | {} |\n",
+                table_cell(&ticket.ticket_id),
+                ticket.status.label(),
+                table_cell(note.lines().next().unwrap_or(""))
+            ));
+        }
+
+        for ticket in &self.tickets {
+            out.push_str(&format!(
+                "\n## {}: {}\n\n",
+                ticket.ticket_id,
+                ticket.status.label()
+            ));
+            if let Some(note) = &ticket.note {
+                out.push_str(&format!("{}\n\n", note.trim()));
+            }
+            if let Some(summary) = &ticket.review_summary {
+                out.push_str("Review summary:\n\n");
+                for line in summary.lines() {
+                    match line.trim_end() {
+                        "" => out.push_str(">\n"),
+                        line => out.push_str(&format!("> {line}\n")),
+                    }
+                }
+                out.push('\n');
+            }
+            let links = ticket_links(ticket, link_base);
+            for (label, link) in &links {
+                out.push_str(&format!("- {label}: {link}\n"));
+            }
+            if !links.is_empty() {
+                out.push('\n');
+            }
+        }
+
+        out.push_str("\n## Rollup\n\n");
+        let statuses = [
+            TicketStatus::Complete,
+            TicketStatus::Failed,
+            TicketStatus::Blocked,
+            TicketStatus::Skipped,
+            TicketStatus::RunningWorker,
+            TicketStatus::NeedsReview,
+            TicketStatus::RunningReview,
+            TicketStatus::Pending,
+        ];
+        for status in statuses {
+            let ids: Vec<&str> = self
+                .tickets
+                .iter()
+                .filter(|ticket| ticket.status == status)
+                .map(|ticket| ticket.ticket_id.as_str())
+                .collect();
+            if !ids.is_empty() {
+                out.push_str(&format!(
+                    "- {}: {} ({})\n",
+                    status.label(),
+                    ids.len(),
+                    ids.join(", ")
+                ));
+            }
+        }
+        let failed = self.failed_tickets().len();
+        out.push_str(&format!(
+            "\n{} of {} ticket(s) complete; {failed} failed or blocked.\n",
+            self.tickets
+                .iter()
+                .filter(|ticket| ticket.status == TicketStatus::Complete)
+                .count(),
+            self.tickets.len()
+        ));
+        out
+    }
+
+    /// [`WorkflowStatusReport::to_markdown`] as a standalone HTML page
+    /// with inline styles.
+    pub fn to_html(&self, link_base: &Path) -> String {
+        let markdown = self.to_markdown(link_base);
+        // Ids, summaries and notes come from manifests and sessions; any HTML
+        // in them is shown as text rather than rendered.
+        let parser = Parser::new_ext(&markdown, Options::ENABLE_TABLES).map(|event| match event {
+            Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+            event => event,
+        });
+        let mut body = String::new();
+        html::push_html(&mut body, parser);
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+            <title>Workflow report: {}</title>\n<style>{HTML_STYLE}</style>\n</head>\n\
+            <body>\n{body}</body>\n</html>\n",
+            html_escape(&self.workflow_name)
+        )
+    }
+
+    /// A JUnit XML document with the workflow as the test suite and a test
+    /// case per ticket. Failed and blocked tickets fail with their note and
+    /// the end of their latest session log; skipped and unfinished tickets,
+    /// and allowed failures, are skipped.
+    pub fn to_junit(&self) -> String {
+        let now = Utc::now();
+        let name = xml_escape(&self.workflow_name);
+        let mut failures = 0;
+        let mut skipped = 0;
+        let mut cases = String::new();
+        for ticket in &self.tickets {
+            let time = ticket.elapsed(now).map(seconds).unwrap_or(0.0);
+            cases.push_str(&format!(
+                "    <testcase name=\"{}\" classname=\"{name}\" time=\"{time:.3}\"",
+                xml_escape(&ticket.ticket_id)
+            ));
+            let note = ticket.note.as_deref().unwrap_or("").trim();
+            let allowed = self.allowed_failures.contains(&ticket.ticket_id);
+            let skip_message = match &ticket.status {
+                TicketStatus::Complete => {
+                    cases.push_str("/>\n");
+                    continue;
+                }
+                TicketStatus::Failed | TicketStatus::Blocked if !allowed => {
+                    failures += 1;
+                    let mut details = note.to_string();
+                    if let Some(excerpt) = log_excerpt(ticket) {
+                        details.push_str(&format!("\n\n{excerpt}"));
+                    }
+                    cases.push_str(&format!(
+                        ">\n      <failure message=\"{}\" type=\"{}\">{}</failure>\n    \
+                        </testcase>\n",
+                        xml_escape(note.lines().next().unwrap_or("")),
+                        ticket.status.label(),
+                        xml_escape(details.trim())
+                    ));
+                    continue;
+                }
+                TicketStatus::Failed | TicketStatus::Blocked => {
+                    format!("{} (failure allowed): {note}", ticket.status.label())
+                }
+                TicketStatus::Skipped => note.to_string(),
+                status => format!("Not finished: {}", status.label()),
+            };
+            skipped += 1;
+            cases.push_str(&format!(
+                ">\n      <skipped message=\"{}\"/>\n    </testcase>\n",
+                xml_escape(skip_message.lines().next().unwrap_or(""))
+            ));
+        }
+
+        let started = self
+            .tickets
+            .iter()
+            .filter_map(|ticket| ticket.started_at)
+            .min();
+        let finished = self
+            .tickets
+            .iter()
+            .filter_map(|ticket| ticket.finished_at)
+            .max();
+        let time = match (started, finished) {
+            (Some(started), Some(finished)) => seconds(finished - started),
+            _ => 0.0,
+        };
+        let timestamp = started.unwrap_or(now).format("%Y-%m-%dT%H:%M:%S");
+        let counts = format!(
+            "tests=\"{}\" failures=\"{failures}\" errors=\"0\" skipped=\"{skipped}\" \
+            time=\"{time:.3}\"",
+            self.tickets.len()
+        );
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+            <testsuites name=\"{name}\" {counts}>\n  \
+            <testsuite name=\"{name}\" {counts} timestamp=\"{timestamp}\">\n\
+            {cases}  </testsuite>\n</testsuites>\n"
+        )
+    }
+
+    /// CSV with a header row and a row per ticket: its summary, status,
+    /// start and finish as RFC 3339 UTC timestamps, duration in seconds,
+    /// sessions launched, worker model, note, and log paths relative to
+    /// `link_base`. Fields are quoted as RFC 4180 requires, so notes may hold
+    /// commas, quotes and newlines.
+    pub fn to_csv(&self, link_base: &Path) -> String {
+        let now = Utc::now();
+        let timestamp = |at: Option<DateTime<Utc>>| {
+            at.map(|at| at.to_rfc3339_opts(SecondsFormat::Secs, true))
+                .unwrap_or_default()
+        };
+        let log = |path: &Option<PathBuf>| {
+            path.as_deref()
+                .map(|path| relative_path(path, link_base))
+                .unwrap_or_default()
+        };
+        let mut out = csv_row(CSV_COLUMNS.iter().map(ToString::to_string));
+        for ticket in &self.tickets {
+            out.push_str(&csv_row([
+                ticket.ticket_id.clone(),
+                self.summaries
+                    .get(&ticket.ticket_id)
+                    .cloned()
+                    .unwrap_or_default(),
+                ticket.status.label().to_string(),
+                timestamp(ticket.started_at),
+                timestamp(ticket.finished_at),
+                ticket
+                    .elapsed(now)
+                    .map(|elapsed| format!("{:.3}", seconds(elapsed)))
+                    .unwrap_or_default(),
+                ticket.attempts.len().to_string(),
+                ticket.worker_model.clone().unwrap_or_default(),
+                ticket.note.as_deref().unwrap_or("").trim().to_string(),
+                log(&ticket.worker_log),
+                log(&ticket.review_log),
+            ]));
+        }
+        out
+    }
+
+    /// The report in `format`.
+    pub fn render(&self, format: ReportFormat, link_base: &Path) -> String {
+        match format {
+            ReportFormat::Markdown => self.to_markdown(link_base),
+            ReportFormat::Html => self.to_html(link_base),
+            ReportFormat::Junit => self.to_junit(),
+            ReportFormat::Csv => self.to_csv(link_base),
+        }
+    }
+}
+
+const HTML_STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:60em;margin:2em auto;\
+padding:0 1em;line-height:1.5}table{border-collapse:collapse}th,td{border:1px solid #ccc;\
+padding:.3em .6em;text-align:left}blockquote{margin-left:0;padding-left:1em;\
+border-left:3px solid #ccc;color:#555}";
+
+/// Markdown links to a ticket's logs, outputs, and diff.
+fn ticket_links(ticket: &TicketRunState, link_base: &Path) -> Vec<(String, String)> {
+    let mut links = Vec::new();
+    let paths = [
+        ("Worker log", &ticket.worker_log),
+        ("Worker output", &ticket.worker_output),
+        ("Review log", &ticket.review_log),
+        ("Review output", &ticket.review_output),
+    ];
+    for (label, path) in paths {
+        let Some(path) = path else {
+            continue;
+        };
+        links.push((label.to_string(), markdown_link(path, link_base)));
+    }
+    if let Some(diff) = &ticket.worker_diff {
+        let mut link = markdown_link(diff, link_base);
+        if let Some(changed) = ticket.changed_files {
+            link.push_str(&format!(" ({changed} file(s) changed)"));
+        }
+        links.push(("Diff".to_string(), link));
+    }
+    links
+}
+
+fn markdown_link(path: &Path, link_base: &Path) -> String {
+    let target = relative_path(path, link_base);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| target.clone());
+    format!("[{name}](<{target}>)")
+}
+
+/// `path` relative to `base` with forward slashes, or as is if it has no
+/// relative form.
+fn relative_path(path: &Path, base: &Path) -> String {
+    let absolute = |path: &Path| std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let relative: PathBuf =
+        pathdiff::diff_paths(absolute(path), absolute(base)).unwrap_or_else(|| path.to_path_buf());
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The last lines of the ticket's latest session log, without ANSI escapes.
+fn log_excerpt(ticket: &TicketRunState) -> Option<String> {
+    let path = ticket
+        .attempts
+        .last()
+        .map(|attempt| &attempt.log_path)
+        .or(ticket.review_log.as_ref())
+        .or(ticket.worker_log.as_ref())?;
+    let log = strip_ansi(&String::from_utf8_lossy(&std::fs::read(path).ok()?));
+    let lines: Vec<&str> = log.trim_end().lines().collect();
+    if lines.is_empty() {
+        return None;
+    }
+    let tail = &lines[lines.len().saturating_sub(JUNIT_LOG_EXCERPT_LINES)..];
+    Some(format!(
+        "Last lines of {}:\n{}",
+        path.display(),
+        tail.join("\n")
+    ))
+}
+
+fn seconds(elapsed: TimeDelta) -> f64 {
+    elapsed.num_milliseconds().max(0) as f64 / 1000.0
+}
+
+/// A CSV line, quoting fields that hold a comma, quote or line break.
+fn csv_row(fields: impl IntoIterator<Item = String>) -> String {
+    let fields: Vec<String> = fields
+        .into_iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field
+            }
+        })
+        .collect();
+    format!("{}\r\n", fields.join(","))
+}
+
+fn table_cell(text: &str) -> String {
+    text.replace('|', "\\|")
+}
+
+/// Escapes markup characters and drops characters XML 1.0 does not allow,
+/// such as most control characters.
+fn xml_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if c.is_control() || c == '\u{fffe}' || c == '\u{ffff}' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::TicketSpec;
+    use crate::manifest::WorkflowManifest;
+    use crate::state::SessionPhase;
+    use crate::state::WorkflowState;
+    use std::collections::BTreeMap;
+
+    fn report(dir: &Path) -> WorkflowStatusReport {
+        let manifest = WorkflowManifest {
+            name: Some("demo".to_string()),
+            tickets: ["T1", "T2"]
+                .into_iter()
+                .map(|id| TicketSpec {
+                    id: id.to_string(),
+                    summary: id.to_string(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let mut state = WorkflowState::initialize(&manifest);
+        let ticket = state.ticket_mut("T1").expect("T1");
+        ticket.mark_running(TicketStatus::RunningWorker);
+        ticket.started_at = ticket.started_at.map(|at| at - TimeDelta::seconds(65));
+        ticket.worker_log = Some(dir.join("runs/1/ticket-T1/worker.1.md"));
+        ticket.review_summary = Some("All good.\n\nShips | today".to_string());
+        ticket.mark_finished(TicketStatus::Complete, Some("Review passed".to_string()));
+        WorkflowStatusReport::from_state(state, dir.join("state.json"))
+    }
+
+    #[test]
+    fn markdown_has_table_sections_relative_links_and_rollup() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let markdown = report(dir.path()).to_markdown(dir.path());
+
+        assert!(markdown.starts_with("# Workflow report: demo\n"));
+        assert!(markdown.contains("| T1 | Complete | 1m05s | Review passed |\n"));
+        assert!(markdown.contains("| T2 | Pending | - |  |\n"));
+        assert!(markdown.contains("> All good.\n>\n> Ships | today\n"));
+        assert!(markdown.contains("- Worker log: [worker.1.md](<runs/1/ticket-T1/worker.1.md>)\n"));
+        assert!(markdown.contains("- Complete: 1 (T1)\n- Pending: 1 (T2)\n"));
+    }
+
+    #[test]
+    fn html_is_a_standalone_page() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let html = report(dir.path()).render(ReportFormat::Html, dir.path());
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<title>Workflow report: demo</title>"));
+        assert!(html.contains("<table>"));
+        assert!(html.contains("href=\"runs/1/ticket-T1/worker.1.md\""));
+    }
+
+    #[test]
+    fn html_escapes_ids_summaries_and_notes() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let mut report = report(dir.path());
+        report.tickets[1].ticket_id = "<script>alert(1)</script>".to_string();
+        report.summaries =
+            BTreeMap::from([("T1".to_string(), "<img src=x onerror=alert(1)>".to_string())]);
+        report.tickets[0].note = Some("<div>\n<iframe src=x></iframe>\n</div>".to_string());
+        let html = report.render(ReportFormat::Html, dir.path());
+
+        for tag in ["<script", "<img", "<iframe", "<div"] {
+            assert!(!html.contains(tag), "{tag} in {html}");
+        }
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+    }
+
+    #[test]
+    fn csv_has_a_row_per_ticket_with_quoted_fields() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let mut report = report(dir.path());
+        report.summaries = BTreeMap::from([
+            ("T1".to_string(), "Add \"login\", then logout".to_string()),
+            ("T2".to_string(), "Second".to_string()),
+        ]);
+        let t1 = &mut report.tickets[0];
+        t1.worker_model = Some("gpt-5".to_string());
+        t1.note = Some("Review passed, mostly\nsee log".to_string());
+        t1.start_attempt(
+            SessionPhase::Worker,
+            1,
+            dir.path().join("runs/1/ticket-T1/worker.1.md"),
+            None,
+            None,
+        );
+        let started = t1.started_at.expect("started");
+        let finished = t1.finished_at.expect("finished");
+
+        let csv = report.render(ReportFormat::Csv, dir.path());
+        let rows: Vec<&str> = csv.split("\r\n").collect();
+        assert_eq!(
+            rows[0],
+            "id,summary,status,started_at,finished_at,duration_seconds,attempts,model,note,\
+            worker_log,review_log"
+        );
+        assert_eq!(
+            rows[1],
+            format!(
+                "T1,\"Add \"\"login\"\", then logout\",Complete,{},{},65.000,1,gpt-5,\
+                \"Review passed, mostly\nsee log\",runs/1/ticket-T1/worker.1.md,",
+                started.to_rfc3339_opts(SecondsFormat::Secs, true),
+                finished.to_rfc3339_opts(SecondsFormat::Secs, true)
+            )
+        );
+        assert!(rows[1].contains("Z,"), "{}", rows[1]);
+        assert_eq!(rows[2], "T2,Second,Pending,,,,0,,,,");
+        assert_eq!(rows[3], "");
+    }
+
+    #[test]
+    fn junit_follows_the_junit_schema() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let mut report = report(dir.path());
+        let log = dir.path().join("worker.1.md");
+        std::fs::write(&log, "setup\n\u{1b}[31merror: <boom> & \"bust\"\u{1b}[0m\n").expect("log");
+        let mut failed = TicketRunState::new("T3");
+        failed.start_attempt(SessionPhase::Worker, 1, log, None, None);
+        failed.mark_finished(
+            TicketStatus::Failed,
+            Some("Worker failed with status Some(1)\n  it's <broken>\u{7}".to_string()),
+        );
+        let mut allowed = TicketRunState::new("T4");
+        allowed.mark_finished(
+            TicketStatus::Blocked,
+            Some("Dependency T3 failed".to_string()),
+        );
+        report.tickets.extend([failed, allowed]);
+        report.allowed_failures = vec!["T4".to_string()];
+
+        let xml = report.render(ReportFormat::Junit, dir.path());
+        let doc = roxmltree::Document::parse(&xml).expect("well-formed XML");
+        let root = doc.root_element();
+        assert_eq!(root.tag_name().name(), "testsuites");
+        let suites: Vec<_> = root.children().filter(|node| node.is_element()).collect();
+        assert_eq!(suites.len(), 1);
+        let suite = suites[0];
+        assert_eq!(suite.tag_name().name(), "testsuite");
+        for attribute in [
+            "name",
+            "tests",
+            "failures",
+            "errors",
+            "skipped",
+            "time",
+            "timestamp",
+        ] {
+            assert!(
+                suite.has_attribute(attribute),
+                "testsuite lacks {attribute}"
+            );
+        }
+        assert_eq!(suite.attribute("name"), Some("demo"));
+        assert_eq!(suite.attribute("tests"), Some("4"));
+        assert_eq!(suite.attribute("failures"), Some("1"));
+        assert_eq!(suite.attribute("skipped"), Some("2"));
+        let timestamp = suite.attribute("timestamp").unwrap_or_default();
+        assert!(
+            chrono::NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%S").is_ok(),
+            "{timestamp}"
+        );
+
+        let cases: Vec<_> = suite.children().filter(|node| node.is_element()).collect();
+        assert_eq!(cases.len(), 4);
+        for case in &cases {
+            assert_eq!(case.tag_name().name(), "testcase");
+            assert_eq!(case.attribute("classname"), Some("demo"));
+            let time = case.attribute("time").unwrap_or_default();
+            assert!(time.parse::<f64>().is_ok_and(|time| time >= 0.0), "{time}");
+        }
+        assert_eq!(cases[0].attribute("time"), Some("65.000"));
+        assert!(!cases[0].has_children());
+        let child = |index: usize| {
+            cases[index]
+                .children()
+                .find(|node| node.is_element())
+                .expect("child element")
+        };
+        assert_eq!(child(1).tag_name().name(), "skipped");
+        assert_eq!(child(1).attribute("message"), Some("Not finished: Pending"));
+        let failure = child(2);
+        assert_eq!(failure.tag_name().name(), "failure");
+        assert_eq!(
+            failure.attribute("message"),
+            Some("Worker failed with status Some(1)")
+        );
+        assert_eq!(failure.attribute("type"), Some("Failed"));
+        let details = failure.text().unwrap_or_default();
+        assert!(details.contains("  it's <broken>\n"), "{details}");
+        assert!(
+            details.ends_with("setup\nerror: <boom> & \"bust\""),
+            "{details}"
+        );
+        assert_eq!(
+            child(3).attribute("message"),
+            Some("Blocked (failure allowed): Dependency T3 failed")
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use crate::state::RequirementOutcome;
+use crate::state::ReviewReport;
+use std::collections::BTreeMap;
+
+/// Extracts per-requirement verdicts from a reviewer's message.
+///
+/// Reviewers are asked to report one requirement per line, e.g.
+/// `- [R1] satisfied` or `R2: unsatisfied - missing tests`. Only ids in
+/// `requirement_ids` are recognized; later lines win if an id repeats.
+pub(crate) fn parse_requirement_verdicts(
+    message: &str,
+    requirement_ids: &[&str],
+) -> BTreeMap<String, RequirementOutcome> {
+    let mut verdicts = BTreeMap::new();
+    for line in message.lines() {
+        let line = line.trim().trim_start_matches(['-', '*']).trim_start();
+        for id in requirement_ids {
+            let Some(rest) = strip_requirement_id(line, id) else {
+                continue;
+            };
+            let rest = rest
+                .trim_start_matches([':', '-', ' ', '\t'])
+                .to_ascii_lowercase();
+            let outcome = if rest.starts_with("unsatisfied") || rest.starts_with("not satisfied") {
+                RequirementOutcome::Unsatisfied
+            } else if rest.starts_with("satisfied") {
+                RequirementOutcome::Satisfied
+            } else {
+                continue;
+            };
+            verdicts.insert((*id).to_string(), outcome);
+        }
+    }
+    verdicts
+}
+
+/// Parses the last fenced `json` code block of a reviewer's message
+/// that holds a [`ReviewReport`]. Blocks that do not parse are ignored.
+pub(crate) fn parse_review_report(message: &str) -> Option<ReviewReport> {
+    let mut report = None;
+    let mut block: Option<String> = None;
+    for line in message.lines() {
+        let trimmed = line.trim();
+        match block.as_mut() {
+            None if trimmed.eq_ignore_ascii_case("```json") => block = Some(String::new()),
+            None => {}
+            Some(_) if trimmed == "```" => {
+                if let Some(parsed) = block
+                    .take()
+                    .and_then(|json| serde_json::from_str(&json).ok())
+                {
+                    report = Some(parsed);
+                }
+            }
+            Some(json) => {
+                json.push_str(line);
+                json.push('\n');
+            }
+        }
+    }
+    report
+}
+
+fn strip_requirement_id<'a>(line: &'a str, id: &str) -> Option<&'a str> {
+    if let Some(rest) = line.strip_prefix('[') {
+        return rest.strip_prefix(id)?.strip_prefix(']');
+    }
+    let rest = line.strip_prefix(id)?;
+    // Avoid matching `R1` against a line about `R10`.
+    match rest.chars().next() {
+        Some(c) if c.is_alphanumeric() || c == '_' => None,
+        _ => Some(rest),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::ReviewVerdict;
+
+    #[test]
+    fn parses_requirement_lines() {
+        let message = "\
+Overall the change looks good.
+
+- [R1] satisfied
+- R2: unsatisfied - no migration was added
+* R10: satisfied
+R3 not satisfied
+R4: unclear
+";
+        let verdicts = parse_requirement_verdicts(message, &["R1", "R2", "R3", "R4", "R10"]);
+        assert_eq!(
+            verdicts,
+            BTreeMap::from([
+                ("R1".to_string(), RequirementOutcome::Satisfied),
+                ("R10".to_string(), RequirementOutcome::Satisfied),
+                ("R2".to_string(), RequirementOutcome::Unsatisfied),
+                ("R3".to_string(), RequirementOutcome::Unsatisfied),
+            ])
+        );
+    }
+
+    #[test]
+    fn parses_last_review_report_block() {
+        let message = r#"Needs work.
+
+```json
+{"verdict": "approved"}
+```
+
+```json
+{
+  "verdict": "changes_requested",
+  "blocking_issues": ["No tests for the parser"],
+  "suggestions": ["Rename foo"]
+}
+```
+
+```json
+not json
+```
+"#;
+        assert_eq!(
+            parse_review_report(message),
+            Some(ReviewReport {
+                verdict: ReviewVerdict::ChangesRequested,
+                blocking_issues: vec!["No tests for the parser".to_string()],
+                suggestions: vec!["Rename foo".to_string()],
+            })
+        );
+        assert_eq!(parse_review_report("Looks good."), None);
+    }
+}
@@ -0,0 +1,125 @@
+use anyhow::Context;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Loads a dotenv-style secrets file (`KEY=VALUE` per line). Blank lines and
+/// `#` comments are ignored, a leading `export ` is accepted, and values may be
+/// wrapped in single or double quotes.
+pub fn load_secrets_file(path: &Path) -> anyhow::Result<BTreeMap<String, String>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("failed to read secrets file {}", path.display()))?;
+    parse_secrets(&contents).with_context(|| format!("failed to parse {}", path.display()))
+}
+
+fn parse_secrets(contents: &str) -> anyhow::Result<BTreeMap<String, String>> {
+    let mut secrets = BTreeMap::new();
+    for (idx, raw_line) in contents.lines().enumerate() {
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            anyhow::bail!("line {}: expected KEY=VALUE", idx + 1);
+        };
+        let key = key.trim();
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            anyhow::bail!("line {}: invalid variable name", idx + 1);
+        }
+        secrets.insert(key.to_string(), unquote(value.trim()).to_string());
+    }
+    Ok(secrets)
+}
+
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote) {
+            return &value[1..value.len() - 1];
+        }
+    }
+    value
+}
+
+/// Replaces every occurrence of a secret value in `text` with a placeholder.
+pub(crate) fn redact(text: &str, secret_values: &[String]) -> String {
+    let mut redacted = text.to_string();
+    for value in secret_values {
+        if !value.is_empty() {
+            redacted = redacted.replace(value.as_str(), REDACTED);
+        }
+    }
+    redacted
+}
+
+/// Key fragments that mark an environment variable as holding a secret.
+const SECRET_KEY_PARTS: [&str; 4] = ["TOKEN", "KEY", "SECRET", "PASSWORD"];
+
+/// An environment value as it may be shown: fully redacted when its key looks
+/// like it names a secret, otherwise with known secret values redacted.
+pub(crate) fn redact_env_value(key: &str, value: &str, secret_values: &[String]) -> String {
+    let key = key.to_ascii_uppercase();
+    if !value.is_empty() && SECRET_KEY_PARTS.iter().any(|part| key.contains(part)) {
+        REDACTED.to_string()
+    } else {
+        redact(value, secret_values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dotenv_lines() {
+        let secrets = parse_secrets(
+            r#"
+# API credentials
+OPENAI_API_KEY=sk-test
+export TOKEN="quoted value"
+SINGLE='abc'
+EMPTY=
+"#,
+        )
+        .expect("parse");
+        assert_eq!(
+            secrets.get("OPENAI_API_KEY").map(String::as_str),
+            Some("sk-test")
+        );
+        assert_eq!(
+            secrets.get("TOKEN").map(String::as_str),
+            Some("quoted value")
+        );
+        assert_eq!(secrets.get("SINGLE").map(String::as_str), Some("abc"));
+        assert_eq!(secrets.get("EMPTY").map(String::as_str), Some(""));
+    }
+
+    #[test]
+    fn rejects_lines_without_assignment() {
+        let err = parse_secrets("JUST_A_NAME\n").expect_err("should fail");
+        assert!(err.to_string().contains("line 1"));
+    }
+
+    #[test]
+    fn redacts_secret_values() {
+        let values = vec!["sk-test".to_string(), String::new()];
+        assert_eq!(
+            redact("key=sk-test and sk-test", &values),
+            "key=[REDACTED] and [REDACTED]"
+        );
+    }
+
+    #[test]
+    fn redacts_env_values_with_secret_like_keys() {
+        let secrets = vec!["sk-test".to_string()];
+        assert_eq!(redact_env_value("github_token", "ghp", &secrets), REDACTED);
+        assert_eq!(redact_env_value("DB_PASSWORD", "x", &secrets), REDACTED);
+        assert_eq!(
+            redact_env_value("URL", "https://x?k=sk-test", &secrets),
+            "https://x?k=[REDACTED]"
+        );
+        assert_eq!(redact_env_value("RUST_LOG", "debug", &secrets), "debug");
+    }
+}
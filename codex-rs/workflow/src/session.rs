@@ -1,81 +1,884 @@
+use crate::capture::BoundedCapture;
+use crate::capture::CapturedOutput;
+use crate::capture::DEFAULT_MAX_LOG_BYTES;
+use crate::capture::LiveLog;
+use crate::capture::LogStream;
+use crate::error::WorkflowError;
+use crate::log_format::LiveSessionLog;
+use crate::log_format::LogFormat;
+use crate::log_format::LogStyle;
+use crate::log_format::SessionLog;
+use crate::log_format::render_live_preamble;
+use crate::log_format::write_session_log;
+use crate::process::ProcessTreeGuard;
+use crate::secrets::redact;
+use crate::secrets::redact_env_value;
 use anyhow::Context;
+use async_trait::async_trait;
+use chrono::Utc;
+use futures::FutureExt;
+use futures::StreamExt;
+use futures::future;
+use futures::stream;
+use futures::stream::BoxStream;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
 use std::process::Stdio;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+use tokio::io::AsyncRead;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
 use tokio::process::Command;
+use tokio::sync::mpsc;
+
+/// Chunks a [`SessionLauncher::run_streaming`] consumer may fall behind by
+/// before reading the session's output waits for it.
+const STREAM_CHANNEL_CAPACITY: usize = 256;
+
+/// Executes a single codex session. [`SessionLauncher`] spawns real codex
+/// processes; embedders and tests can substitute their own implementation.
+#[async_trait]
+pub trait SessionRunner: Send + Sync {
+    async fn run(&self, request: SessionRequest) -> anyhow::Result<SessionResult>;
+
+    /// Fails if `program`, or the runner's own binary when `None`, cannot be
+    /// found. Checked before a run starts any session; runners that start no
+    /// processes of their own can keep the default.
+    fn check_program(&self, _program: Option<&Path>) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Like `run`, but yields the session's output line by line as it
+    /// arrives and ends with [`SessionChunk::Finished`]. The default yields
+    /// only that last chunk.
+    fn run_streaming(&self, request: SessionRequest) -> BoxStream<'_, SessionChunk> {
+        stream::once(async move { SessionChunk::Finished(self.run(request).await) }).boxed()
+    }
+}
+
+/// A piece of a session's progress, from [`SessionRunner::run_streaming`].
+#[derive(Debug)]
+pub enum SessionChunk {
+    /// A complete line of stdout, without its newline and with secret values
+    /// redacted.
+    Stdout(String),
+    /// A complete line of stderr, like `Stdout`.
+    Stderr(String),
+    /// The session is being rerun, e.g. as a fresh session after a resume
+    /// failed; the lines sent so far belong to the abandoned run.
+    Restarted,
+    /// The session is over and its log written; always the last chunk.
+    Finished(anyhow::Result<SessionResult>),
+}
+
+/// What [`SessionLauncher`] prints to stderr before starting each codex
+/// process, for `--print-command`. Secret values are redacted either way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrintCommand {
+    /// The command line with the prompt elided.
+    Redacted,
+    /// The command line followed by the prompt.
+    Full,
+}
+
+impl FromStr for PrintCommand {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().as_str() {
+            "redacted" => Ok(PrintCommand::Redacted),
+            "full" => Ok(PrintCommand::Full),
+            other => Err(format!(
+                "unknown print mode `{other}` (expected redacted or full)"
+            )),
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct SessionLauncher {
     codex_bin: PathBuf,
+    /// Arguments placed before all others, `exec` by default.
+    subcommand: Vec<String>,
     config_overrides: Vec<String>,
+    prompt_via_argv: bool,
+    secrets: BTreeMap<String, String>,
+    /// Cleared once a codex binary rejects `--output-last-message`, so the
+    /// flag is only probed once per run.
+    last_message_supported: Arc<AtomicBool>,
+    log_style: LogStyle,
+    max_log_bytes: i64,
+    print_command: Option<PrintCommand>,
+    echo_output: bool,
 }
 
 impl SessionLauncher {
     pub fn new(codex_bin: PathBuf, config_overrides: Vec<String>) -> Self {
         Self {
             codex_bin,
+            subcommand: vec!["exec".to_string()],
             config_overrides,
+            prompt_via_argv: false,
+            secrets: BTreeMap::new(),
+            last_message_supported: Arc::new(AtomicBool::new(true)),
+            log_style: LogStyle::default(),
+            max_log_bytes: DEFAULT_MAX_LOG_BYTES,
+            print_command: None,
+            echo_output: false,
         }
     }
 
+    /// Replace the leading `exec` with these arguments, e.g. for a wrapper
+    /// or front-end that takes codex exec's flags under another subcommand.
+    /// May be empty.
+    pub fn with_subcommand(mut self, subcommand: Vec<String>) -> Self {
+        self.subcommand = subcommand;
+        self
+    }
+
+    /// Pass the prompt as the final positional argument instead of over stdin.
+    /// Only needed for codex binaries that predate reading the prompt from `-`.
+    pub fn with_prompt_via_argv(mut self, prompt_via_argv: bool) -> Self {
+        self.prompt_via_argv = prompt_via_argv;
+        self
+    }
+
+    /// Environment variables applied to every session. Per-request `env`
+    /// entries take precedence, and secret values are redacted from logs.
+    pub fn with_secrets(mut self, secrets: BTreeMap<String, String>) -> Self {
+        self.secrets = secrets;
+        self
+    }
+
+    /// Keep ANSI escape sequences in session logs instead of stripping them.
+    pub fn with_log_ansi(mut self, ansi: bool) -> Self {
+        self.log_style.ansi = ansi;
+        self
+    }
+
+    /// Prefix each logged output line with the time elapsed since the session
+    /// started (`[00:12:34]`). On by default; the prompt is never timestamped.
+    pub fn with_log_timestamps(mut self, timestamps: bool) -> Self {
+        self.log_style.timestamps = timestamps;
+        self
+    }
+
+    /// Caps the captured output of a session, and with it the log size. Past
+    /// the cap the middle of the output is dropped and the end is kept.
+    pub fn with_max_log_bytes(mut self, max_log_bytes: i64) -> Self {
+        self.max_log_bytes = max_log_bytes;
+        self
+    }
+
+    /// Print every codex invocation to stderr just before it starts.
+    pub fn with_print_command(mut self, print_command: Option<PrintCommand>) -> Self {
+        self.print_command = print_command;
+        self
+    }
+
+    /// Copy each line of session output to stderr as it arrives, labelled
+    /// with the session's log (`ticket-T4/worker.1 | ...`). Secret values are
+    /// redacted as in the log.
+    pub fn with_echo_output(mut self, echo_output: bool) -> Self {
+        self.echo_output = echo_output;
+        self
+    }
+
     pub async fn run(&self, request: SessionRequest) -> anyhow::Result<SessionResult> {
-        let mut cmd = Command::new(&self.codex_bin);
-        cmd.arg("exec");
-        for override_flag in &self.config_overrides {
-            cmd.arg("-c");
-            cmd.arg(override_flag);
+        self.run_session(request, None).await
+    }
+
+    /// [`SessionLauncher::run`], yielding each line of output as it arrives.
+    /// Dropping the stream before its `Finished` chunk kills the session.
+    pub fn run_streaming(&self, request: SessionRequest) -> BoxStream<'_, SessionChunk> {
+        let (sender, receiver) = mpsc::channel(STREAM_CHANNEL_CAPACITY);
+        let run = async move {
+            let result = self.run_session(request, Some(&sender)).await;
+            let _ = sender.send(SessionChunk::Finished(result)).await;
+        };
+        // The result is sent after the last line, and dropping the sender
+        // with it ends `chunks`; `run` itself yields nothing.
+        let chunks = stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|chunk| (chunk, receiver))
+        });
+        let run = run.into_stream().filter_map(|()| future::ready(None));
+        stream::select(run, chunks).boxed()
+    }
+
+    /// Runs a session, also sending its output lines to `chunks` if given.
+    async fn run_session(
+        &self,
+        request: SessionRequest,
+        chunks: Option<&mpsc::Sender<SessionChunk>>,
+    ) -> anyhow::Result<SessionResult> {
+        let started_at = Utc::now();
+        let last_message_tmp = request
+            .last_message_path
+            .as_ref()
+            .filter(|_| self.last_message_supported.load(Ordering::Relaxed))
+            .map(|_| last_message_tmp_path());
+        let last_message_arg = || {
+            last_message_tmp
+                .as_deref()
+                .filter(|_| self.last_message_supported.load(Ordering::Relaxed))
+        };
+        let resume_session = request.resume_session.as_deref();
+        let mut warnings = Vec::new();
+
+        let mut command_line = self.command_line(&request, resume_session, last_message_arg());
+        let mut execution = self
+            .execute(&request, resume_session, last_message_arg(), chunks)
+            .await?;
+        if last_message_tmp.is_some() && rejected_last_message_flag(&execution.output) {
+            // Older codex binaries do not know `--output-last-message`; remember
+            // that for the remaining sessions and rerun without it.
+            self.last_message_supported.store(false, Ordering::Relaxed);
+            command_line = self.command_line(&request, resume_session, None);
+            send_restarted(chunks).await;
+            execution = self.execute(&request, resume_session, None, chunks).await?;
+        }
+        let mut session_id = parse_session_id(&execution.output);
+        if let Some(requested) = resume_session {
+            // Other failures, e.g. a crash or a rejected prompt, would hit a
+            // fresh session too; they are returned as they are.
+            if !execution.timed_out && resumed_session_not_found(&execution.output, requested) {
+                warnings.push(format!(
+                    "Could not resume session {requested} (exit status {:?}); \
+                    started a fresh session instead.",
+                    execution.output.status.code()
+                ));
+                command_line = self.command_line(&request, None, last_message_arg());
+                send_restarted(chunks).await;
+                execution = self
+                    .execute(&request, None, last_message_arg(), chunks)
+                    .await?;
+                session_id = parse_session_id(&execution.output);
+            } else if session_id.as_deref().is_some_and(|id| id != requested) {
+                warnings.push(format!(
+                    "Session {requested} was not found; codex started a fresh session."
+                ));
+            }
+        }
+        let Execution {
+            output,
+            timed_out,
+            stdout_times,
+            stderr_times,
+            truncated_bytes,
+        } = execution;
+        if truncated_bytes > 0 {
+            warnings.push(format!(
+                "Output exceeded the {} byte log cap; {truncated_bytes} bytes were \
+                truncated from the middle.",
+                self.max_log_bytes
+            ));
+        }
+        if timed_out && let Some(timeout) = request.timeout {
+            warnings.push(format!(
+                "Session timed out after {}s; its process group was terminated.",
+                timeout.as_secs_f64()
+            ));
+        }
+        let last_message = match (last_message_arg(), &request.last_message_path) {
+            (Some(tmp), Some(dest)) => collect_last_message(tmp, dest)?,
+            _ => None,
+        };
+
+        let secret_values: Vec<String> = self.secrets.values().cloned().collect();
+        let result = SessionResult {
+            success: output.status.success() && !timed_out,
+            timed_out,
+            truncated_bytes,
+            status_code: output.status.code(),
+            stdout: redact(&String::from_utf8_lossy(&output.stdout), &secret_values),
+            stderr: redact(&String::from_utf8_lossy(&output.stderr), &secret_values),
+            last_message: last_message.map(|message| redact(&message, &secret_values)),
+            session_id,
+            warnings,
+            command_line: redact(&command_line, &secret_values),
+        };
+        write_session_log(
+            &request.log_path,
+            request.log_format,
+            &SessionLog {
+                prompt: &redact(&request.prompt, &secret_values),
+                result: &result,
+                started_at,
+                finished_at: Utc::now(),
+                timeout: request.timeout,
+                profile: request.profile.as_deref(),
+                codex_bin: self.program(&request),
+                attempt: request.attempt,
+                model: request.model.as_deref(),
+                working_dir: &request.working_dir,
+                env: self.logged_env(&request),
+                stdout_times: &stdout_times,
+                stderr_times: &stderr_times,
+                style: self.log_style,
+            },
+        )?;
+        Ok(result)
+    }
+
+    /// The environment a session gets on top of the inherited one, redacted
+    /// for its log.
+    fn logged_env(&self, request: &SessionRequest) -> BTreeMap<String, String> {
+        let secret_values: Vec<String> = self.secrets.values().cloned().collect();
+        // Request entries win over secrets, as in `execute`.
+        self.secrets
+            .iter()
+            .chain(&request.env)
+            .map(|(key, value)| (key.clone(), redact_env_value(key, value, &secret_values)))
+            .collect()
+    }
+
+    /// Starts the log of one codex invocation, filled in while it runs.
+    fn create_live_log(
+        &self,
+        request: &SessionRequest,
+        resume_session: Option<&str>,
+        last_message_path: Option<&Path>,
+    ) -> anyhow::Result<LiveLog> {
+        let secret_values: Vec<String> = self.secrets.values().cloned().collect();
+        let preamble = render_live_preamble(&LiveSessionLog {
+            prompt: &redact(&request.prompt, &secret_values),
+            command_line: &redact(
+                &self.command_line(request, resume_session, last_message_path),
+                &secret_values,
+            ),
+            started_at: Utc::now(),
+            timeout: request.timeout,
+            profile: request.profile.as_deref(),
+            codex_bin: self.program(request),
+            attempt: request.attempt,
+            model: request.model.as_deref(),
+            working_dir: &request.working_dir,
+            env: &self.logged_env(request),
+        });
+        LiveLog::create(&request.log_path, &preamble, secret_values)
+    }
+
+    /// The binary `request` runs: its own `codex_bin`, else the launcher's.
+    fn program<'a>(&'a self, request: &'a SessionRequest) -> &'a Path {
+        request.codex_bin.as_deref().unwrap_or(&self.codex_bin)
+    }
+
+    /// Arguments passed to the codex binary, excluding the trailing prompt.
+    fn build_args(
+        &self,
+        request: &SessionRequest,
+        resume_session: Option<&str>,
+        last_message_path: Option<&Path>,
+    ) -> Vec<OsString> {
+        let mut args: Vec<OsString> = self.subcommand.iter().map(OsString::from).collect();
+        for override_flag in request
+            .config_overrides
+            .iter()
+            .chain(&self.config_overrides)
+        {
+            args.push("-c".into());
+            args.push(override_flag.into());
+        }
+        if request.skip_git_repo_check {
+            args.push("--skip-git-repo-check".into());
         }
-        cmd.arg("--skip-git-repo-check");
         if let Some(model) = &request.model {
-            cmd.arg("-m");
-            cmd.arg(model);
+            args.push("-m".into());
+            args.push(model.into());
+        }
+        if let Some(profile) = &request.profile {
+            args.push("--profile".into());
+            args.push(profile.into());
+        }
+        args.push("-C".into());
+        args.push(request.working_dir.clone().into());
+        if let Some(path) = last_message_path {
+            args.push("--output-last-message".into());
+            args.push(path.into());
+        }
+        // Passed through verbatim (no shell involved), after the built-in flags
+        // so they can override them.
+        args.extend(request.extra_args.iter().map(OsString::from));
+        if let Some(session_id) = resume_session {
+            args.push("resume".into());
+            args.push(session_id.into());
+        }
+        args
+    }
+
+    /// Human-readable command line for logs, with the prompt elided.
+    pub(crate) fn command_line(
+        &self,
+        request: &SessionRequest,
+        resume_session: Option<&str>,
+        last_message_path: Option<&Path>,
+    ) -> String {
+        let prompt_placeholder = if self.prompt_via_argv {
+            "<prompt>"
+        } else {
+            "-"
+        };
+        std::iter::once(self.program(request).as_os_str().to_os_string())
+            .chain(self.build_args(request, resume_session, last_message_path))
+            .map(|arg| {
+                let arg = arg.to_string_lossy().into_owned();
+                shlex::try_quote(&arg).map_or(arg.clone(), |quoted| quoted.into_owned())
+            })
+            .chain(std::iter::once(prompt_placeholder.to_string()))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The `--print-command` output for one invocation, if enabled.
+    fn printed_command(
+        &self,
+        request: &SessionRequest,
+        resume_session: Option<&str>,
+        last_message_path: Option<&Path>,
+    ) -> Option<String> {
+        let mode = self.print_command?;
+        let secret_values: Vec<String> = self.secrets.values().cloned().collect();
+        let command_line = self.command_line(request, resume_session, last_message_path);
+        let mut out = format!(
+            "codex session for {}:\n  {command_line}\n",
+            request.log_path.display()
+        );
+        if mode == PrintCommand::Full {
+            let via = if self.prompt_via_argv {
+                "argv"
+            } else {
+                "stdin"
+            };
+            out.push_str(&format!(
+                "  prompt ({via}):\n{}\n",
+                request.prompt.trim_end()
+            ));
+        }
+        Some(redact(&out, &secret_values))
+    }
+
+    async fn execute(
+        &self,
+        request: &SessionRequest,
+        resume_session: Option<&str>,
+        last_message_path: Option<&Path>,
+        chunks: Option<&mpsc::Sender<SessionChunk>>,
+    ) -> anyhow::Result<Execution> {
+        if let Some(printed) = self.printed_command(request, resume_session, last_message_path) {
+            eprint!("{printed}");
+        }
+        let program = self.program(request);
+        let mut cmd = Command::new(program);
+        cmd.args(self.build_args(request, resume_session, last_message_path));
+        if self.prompt_via_argv {
+            cmd.arg(&request.prompt);
+            cmd.stdin(Stdio::null());
+        } else {
+            // Large prompts can exceed the argv length limit and would otherwise
+            // be visible in `ps`, so hand them to codex over stdin.
+            cmd.arg("-");
+            cmd.stdin(Stdio::piped());
         }
-        cmd.arg("-C");
-        cmd.arg(&request.working_dir);
-        cmd.arg(&request.prompt);
         cmd.stdout(Stdio::piped());
         cmd.stderr(Stdio::piped());
+        cmd.envs(&self.secrets);
+        cmd.envs(&request.env);
 
-        let output = cmd
-            .output()
-            .await
-            .with_context(|| format!("failed to run {}", self.codex_bin.display()))?;
+        // Run codex as the leader of its own process group so a timeout or a
+        // cancelled run also reaps the commands it started.
+        #[cfg(unix)]
+        cmd.process_group(0);
+        cmd.kill_on_drop(true);
+
+        let mut child = cmd.spawn().map_err(|source| WorkflowError::LauncherSpawn {
+            program: program.to_path_buf(),
+            source,
+        })?;
+        // Split the log budget between the two streams.
+        let stream_limit = usize::try_from(self.max_log_bytes / 2).unwrap_or(0);
+        let started = Instant::now();
+        let mut guard = ProcessTreeGuard::new(child.id());
+        let live_log = self.create_live_log(request, resume_session, last_message_path)?;
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let write_prompt = async {
+            let Some(mut stdin) = stdin else {
+                return Ok(());
+            };
+            match stdin.write_all(request.prompt.as_bytes()).await {
+                // The child may exit without draining stdin; its exit status
+                // is what gets reported in that case.
+                Err(err) if err.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+                other => other,
+            }
+        };
+        let wait = async {
+            let Some(timeout) = request.timeout else {
+                return child.wait().await.map(|status| (status, false));
+            };
+            match tokio::time::timeout(timeout, child.wait()).await {
+                Ok(status) => status.map(|status| (status, false)),
+                Err(_) => {
+                    guard.terminate();
+                    child.wait().await.map(|status| (status, true))
+                }
+            }
+        };
+        let echo = || {
+            self.echo_output.then(|| {
+                LineEcho::new(
+                    echo_label(&request.log_path),
+                    self.secrets.values().cloned().collect(),
+                )
+            })
+        };
+        let forward = |chunk: fn(String) -> SessionChunk| {
+            chunks.map(|sender| {
+                LineForward::new(
+                    sender.clone(),
+                    chunk,
+                    self.secrets.values().cloned().collect(),
+                )
+            })
+        };
+        let (write_result, stdout, stderr, status) = tokio::join!(
+            write_prompt,
+            read_stream(
+                stdout,
+                started,
+                stream_limit,
+                (&live_log, LogStream::Stdout),
+                echo(),
+                forward(SessionChunk::Stdout)
+            ),
+            read_stream(
+                stderr,
+                started,
+                stream_limit,
+                (&live_log, LogStream::Stderr),
+                echo(),
+                forward(SessionChunk::Stderr)
+            ),
+            wait
+        );
+        guard.disarm();
+        let run_context = || format!("failed to run {}", program.display());
+        let (status, timed_out) = status.with_context(run_context)?;
+        let stdout = stdout.with_context(run_context)?;
+        let stderr = stderr.with_context(run_context)?;
+        write_result.context("failed to write prompt to codex stdin")?;
+        Ok(Execution {
+            output: std::process::Output {
+                status,
+                stdout: stdout.bytes,
+                stderr: stderr.bytes,
+            },
+            timed_out,
+            stdout_times: stdout.line_times,
+            stderr_times: stderr.line_times,
+            truncated_bytes: stdout.truncated_bytes + stderr.truncated_bytes,
+        })
+    }
+}
+
+/// Output of one codex process. `timed_out` is set when the session exceeded
+/// `SessionRequest::timeout` and its process group was terminated.
+struct Execution {
+    output: std::process::Output,
+    timed_out: bool,
+    /// Arrival time of each stdout line, relative to the spawn.
+    stdout_times: Vec<Duration>,
+    stderr_times: Vec<Duration>,
+    /// Output dropped from the middle of the streams to respect the log cap.
+    truncated_bytes: u64,
+}
+
+/// Reads a pipe to the end, noting how long after `started` each line arrived
+/// and keeping at most `limit` bytes, of which the head goes to `live_log` as
+/// it arrives. Complete lines also go to `echo` and `forward`.
+async fn read_stream(
+    stream: Option<impl AsyncRead + Unpin>,
+    started: Instant,
+    limit: usize,
+    (live_log, log_stream): (&LiveLog, LogStream),
+    mut echo: Option<LineEcho>,
+    mut forward: Option<LineForward>,
+) -> std::io::Result<CapturedOutput> {
+    let mut capture = BoundedCapture::new(limit);
+    if let Some(mut stream) = stream {
+        let mut chunk = [0u8; 8192];
+        loop {
+            let read = stream.read(&mut chunk).await?;
+            if read == 0 {
+                break;
+            }
+            let elapsed = started.elapsed();
+            let head_done = capture.head_done();
+            live_log.push(log_stream, capture.push(&chunk[..read], elapsed), elapsed)?;
+            if capture.head_done() && !head_done {
+                live_log.close(log_stream, elapsed)?;
+            }
+            if let Some(echo) = &mut echo {
+                echo.push(&chunk[..read], &mut std::io::stderr().lock());
+            }
+            if let Some(forward) = &mut forward {
+                forward.push(&chunk[..read]).await;
+            }
+        }
+    }
+    if let Some(echo) = echo {
+        echo.finish(&mut std::io::stderr().lock());
+    }
+    if let Some(forward) = forward {
+        forward.finish().await;
+    }
+    let elapsed = started.elapsed();
+    live_log.close(log_stream, elapsed)?;
+    Ok(capture.finish(live_log.head(log_stream)?, elapsed))
+}
+
+/// Tells a [`SessionLauncher::run_streaming`] consumer that the session is
+/// being rerun.
+async fn send_restarted(chunks: Option<&mpsc::Sender<SessionChunk>>) {
+    if let Some(sender) = chunks {
+        let _ = sender.send(SessionChunk::Restarted).await;
+    }
+}
+
+/// Label for echoed output: the log's directory and file stem, e.g.
+/// `ticket-T4/worker.1`.
+fn echo_label(log_path: &Path) -> String {
+    let stem = log_path.file_stem().unwrap_or_default().to_string_lossy();
+    match log_path.parent().and_then(Path::file_name) {
+        Some(dir) => format!("{}/{stem}", dir.to_string_lossy()),
+        None => stem.into_owned(),
+    }
+}
+
+/// Writes a stream out line by line behind a label, holding back a partial
+/// line until it is complete so concurrent sessions do not interleave
+/// mid-line.
+struct LineEcho {
+    label: String,
+    secret_values: Vec<String>,
+    lines: LineBuffer,
+}
+
+impl LineEcho {
+    fn new(label: String, secret_values: Vec<String>) -> Self {
+        Self {
+            label,
+            secret_values,
+            lines: LineBuffer::default(),
+        }
+    }
+
+    fn push(&mut self, chunk: &[u8], out: &mut impl Write) {
+        for line in self.lines.push(chunk) {
+            self.write_line(&line, out);
+        }
+    }
+
+    fn finish(mut self, out: &mut impl Write) {
+        if let Some(line) = self.lines.finish() {
+            self.write_line(&line, out);
+        }
+    }
+
+    fn write_line(&self, line: &str, out: &mut impl Write) {
+        let line = redact(line, &self.secret_values);
+        // Echoing is best effort; a closed stderr must not fail the session.
+        let _ = writeln!(out, "{} | {line}", self.label);
+    }
+}
+
+/// Sends a stream's complete lines to a [`SessionLauncher::run_streaming`]
+/// consumer, wrapped by `chunk`.
+struct LineForward {
+    sender: mpsc::Sender<SessionChunk>,
+    chunk: fn(String) -> SessionChunk,
+    secret_values: Vec<String>,
+    lines: LineBuffer,
+}
+
+impl LineForward {
+    fn new(
+        sender: mpsc::Sender<SessionChunk>,
+        chunk: fn(String) -> SessionChunk,
+        secret_values: Vec<String>,
+    ) -> Self {
+        Self {
+            sender,
+            chunk,
+            secret_values,
+            lines: LineBuffer::default(),
+        }
+    }
+
+    async fn push(&mut self, chunk: &[u8]) {
+        for line in self.lines.push(chunk) {
+            self.send(&line).await;
+        }
+    }
+
+    async fn finish(mut self) {
+        if let Some(line) = self.lines.finish() {
+            self.send(&line).await;
+        }
+    }
+
+    /// Waits while the consumer is [`STREAM_CHANNEL_CAPACITY`] chunks behind.
+    async fn send(&self, line: &str) {
+        // A consumer that stopped listening does not stop the session.
+        let _ = self
+            .sender
+            .send((self.chunk)(redact(line, &self.secret_values)))
+            .await;
+    }
+}
+
+/// Splits output into lines, holding back a trailing partial line.
+#[derive(Default)]
+struct LineBuffer {
+    pending: Vec<u8>,
+}
+
+impl LineBuffer {
+    /// The lines `chunk` completes.
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.pending.extend_from_slice(chunk);
+        let Some(end) = self.pending.iter().rposition(|byte| *byte == b'\n') else {
+            return Vec::new();
+        };
+        let lines: Vec<u8> = self.pending.drain(..=end).collect();
+        String::from_utf8_lossy(&lines)
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// The partial line left at the end of the stream, if any.
+    fn finish(&mut self) -> Option<String> {
+        let rest = std::mem::take(&mut self.pending);
+        (!rest.is_empty()).then(|| String::from_utf8_lossy(&rest).into_owned())
+    }
+}
 
-        write_log(&request.log_path, &request.prompt, &output)?;
+#[async_trait]
+impl SessionRunner for SessionLauncher {
+    async fn run(&self, request: SessionRequest) -> anyhow::Result<SessionResult> {
+        SessionLauncher::run(self, request).await
+    }
+
+    fn run_streaming(&self, request: SessionRequest) -> BoxStream<'_, SessionChunk> {
+        SessionLauncher::run_streaming(self, request)
+    }
 
-        let status_code = output.status.code();
-        Ok(SessionResult {
-            success: output.status.success(),
-            status_code,
-            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
-            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+    fn check_program(&self, program: Option<&Path>) -> anyhow::Result<()> {
+        let program = program.unwrap_or(&self.codex_bin);
+        if find_program(program).is_none() {
+            return Err(WorkflowError::LauncherSpawn {
+                program: program.to_path_buf(),
+                source: std::io::ErrorKind::NotFound.into(),
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Where `program` would be run from: the path itself if it has a directory
+/// part, else the first match on `PATH`.
+fn find_program(program: &Path) -> Option<PathBuf> {
+    if program.components().count() > 1 {
+        return program.is_file().then(|| program.to_path_buf());
+    }
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
+
+fn last_message_tmp_path() -> PathBuf {
+    static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!(
+        "codex-workflow-{}-{id}.last-message.md",
+        std::process::id()
+    ))
+}
+
+/// Extracts the `session id: <uuid>` line codex exec prints in its header.
+fn parse_session_id(output: &std::process::Output) -> Option<String> {
+    [&output.stderr, &output.stdout]
+        .into_iter()
+        .find_map(|stream| {
+            String::from_utf8_lossy(stream).lines().find_map(|line| {
+                let (_, rest) = line.split_once("session id:")?;
+                let id = rest
+                    .trim()
+                    .trim_start_matches(|c: char| !c.is_ascii_alphanumeric())
+                    .split_whitespace()
+                    .next()?;
+                Some(id.to_string())
+            })
         })
+}
+
+fn rejected_last_message_flag(output: &std::process::Output) -> bool {
+    if output.status.success() {
+        return false;
     }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    stderr.contains("--output-last-message")
+        && (stderr.contains("unexpected argument") || stderr.contains("unrecognized"))
 }
 
-fn write_log(log_path: &Path, prompt: &str, output: &std::process::Output) -> anyhow::Result<()> {
-    if let Some(parent) = log_path.parent() {
+/// Whether a failed `codex exec resume <requested>` reported that it has no
+/// such session, as opposed to failing for another reason.
+fn resumed_session_not_found(output: &std::process::Output, requested: &str) -> bool {
+    if output.status.success() {
+        return false;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    stderr.lines().chain(stdout.lines()).any(|line| {
+        let line = line.to_lowercase();
+        (line.contains("session") || line.contains(&requested.to_lowercase()))
+            && ["not found", "no such", "does not exist", "no rollout"]
+                .iter()
+                .any(|phrase| line.contains(phrase))
+    })
+}
+
+/// Moves the last-message file written by codex into its final location and
+/// returns its contents. A session that produced no final message yields `None`.
+fn collect_last_message(tmp: &Path, dest: &Path) -> anyhow::Result<Option<String>> {
+    let message = match std::fs::read_to_string(tmp) {
+        Ok(message) => message,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => {
+            return Err(err).with_context(|| format!("failed to read {}", tmp.display()));
+        }
+    };
+    if let Some(parent) = dest.parent() {
         std::fs::create_dir_all(parent)
             .with_context(|| format!("failed to create {}", parent.display()))?;
     }
-    let mut file = std::fs::File::create(log_path)
-        .with_context(|| format!("failed to create {}", log_path.display()))?;
-    use std::io::Write;
-    writeln!(file, "# Prompt")?;
-    writeln!(file, "{prompt}")?;
-    writeln!(file)?;
-    writeln!(file, "# Exit Status: {:?}", output.status.code())?;
-    writeln!(file)?;
-    writeln!(file, "## STDOUT")?;
-    file.write_all(&output.stdout)?;
-    if !output.stdout.ends_with(b"\n") {
-        writeln!(file)?;
-    }
-    writeln!(file)?;
-    writeln!(file, "## STDERR")?;
-    file.write_all(&output.stderr)?;
-    writeln!(file)?;
-    Ok(())
+    // The temp dir may live on another filesystem, so copy instead of rename.
+    std::fs::write(dest, &message)
+        .with_context(|| format!("failed to write {}", dest.display()))?;
+    let _ = std::fs::remove_file(tmp);
+    Ok(Some(message))
 }
 
 #[derive(Debug, Clone)]
@@ -83,16 +886,759 @@ pub struct SessionRequest {
     pub prompt: String,
     pub working_dir: PathBuf,
     pub log_path: PathBuf,
+    /// Attempt number shown in the session log.
+    pub attempt: Option<i32>,
     pub model: Option<String>,
+    /// codex config profile to run with (`--profile`).
+    pub profile: Option<String>,
+    /// Binary to run in place of the launcher's, e.g. a wrapper script for
+    /// review sessions.
+    pub codex_bin: Option<PathBuf>,
+    pub env: BTreeMap<String, String>,
+    /// Where to store the agent's final message (`--output-last-message`).
+    pub last_message_path: Option<PathBuf>,
+    /// Continue an earlier codex session (`codex exec resume <id>`) instead of
+    /// starting a fresh one. Falls back to a fresh session if resuming fails.
+    pub resume_session: Option<String>,
+    /// `-c` overrides passed before the launcher's own, which win over them.
+    pub config_overrides: Vec<String>,
+    /// Additional arguments appended after the built-in codex exec flags.
+    pub extra_args: Vec<String>,
+    /// Terminate the session (and every process it started) after this long.
+    pub timeout: Option<Duration>,
+    /// Format of the log written to `log_path`.
+    pub log_format: LogFormat,
+    /// Pass `--skip-git-repo-check`, so codex also runs outside a git
+    /// repository.
+    pub skip_git_repo_check: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct SessionResult {
-    #[allow(dead_code)]
     pub success: bool,
+    /// The session hit `SessionRequest::timeout` and was terminated.
+    pub timed_out: bool,
+    /// Bytes dropped from the middle of stdout/stderr (and the log) to stay
+    /// under the log size cap.
+    pub truncated_bytes: u64,
     pub status_code: Option<i32>,
-    #[allow(dead_code)]
     pub stdout: String,
-    #[allow(dead_code)]
     pub stderr: String,
+    pub last_message: Option<String>,
+    /// Session id reported by codex exec, usable with `resume`.
+    pub session_id: Option<String>,
+    /// Problems worth surfacing that did not fail the session.
+    pub warnings: Vec<String>,
+    /// The codex invocation that produced this result, with the prompt elided.
+    pub command_line: String,
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn request(dir: &Path) -> SessionRequest {
+        SessionRequest {
+            prompt: "Fix it".to_string(),
+            working_dir: dir.to_path_buf(),
+            log_path: dir.join("worker.1.md"),
+            attempt: None,
+            model: None,
+            profile: None,
+            codex_bin: None,
+            env: BTreeMap::new(),
+            last_message_path: None,
+            resume_session: None,
+            config_overrides: Vec::new(),
+            extra_args: Vec::new(),
+            timeout: None,
+            log_format: LogFormat::Markdown,
+            skip_git_repo_check: true,
+        }
+    }
+
+    fn fake_codex(dir: &Path, script: &str) -> PathBuf {
+        let path = dir.join("fake-codex");
+        std::fs::write(&path, format!("#!/bin/sh\n{script}\n")).expect("write script");
+        let mut perms = std::fs::metadata(&path).expect("metadata").permissions();
+        perms.set_mode(0o755);
+        std::fs::set_permissions(&path, perms).expect("chmod");
+        path
+    }
+
+    #[tokio::test]
+    async fn spawn_failures_carry_the_binary_and_error_kind() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let not_executable = dir.path().join("codex.txt");
+        std::fs::write(&not_executable, "not a program").expect("write file");
+        for (codex_bin, kind) in [
+            (
+                dir.path().join("missing-codex"),
+                std::io::ErrorKind::NotFound,
+            ),
+            (not_executable, std::io::ErrorKind::PermissionDenied),
+        ] {
+            let err = SessionLauncher::new(codex_bin.clone(), Vec::new())
+                .run(request(dir.path()))
+                .await
+                .expect_err("spawn fails");
+            match err.downcast_ref::<WorkflowError>() {
+                Some(WorkflowError::LauncherSpawn { program, source }) => {
+                    assert_eq!(program, &codex_bin);
+                    assert_eq!(source.kind(), kind);
+                }
+                other => panic!("expected a spawn error, got {other:?}"),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn log_headers_describe_the_session() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let codex_bin = fake_codex(dir.path(), "cat >/dev/null; echo done");
+        let launcher = SessionLauncher::new(codex_bin.clone(), Vec::new()).with_secrets(
+            BTreeMap::from([("API_TOKEN".to_string(), "sk-test".to_string())]),
+        );
+        let log_path = dir.path().join("ticket-T1/worker.3.log");
+        launcher
+            .run(SessionRequest {
+                log_path: log_path.clone(),
+                attempt: Some(3),
+                model: Some("gpt-test".to_string()),
+                env: BTreeMap::from([
+                    ("URL".to_string(), "https://x?key=sk-test".to_string()),
+                    ("DB_PASSWORD".to_string(), "hunter2".to_string()),
+                ]),
+                log_format: LogFormat::Plain,
+                ..request(dir.path())
+            })
+            .await
+            .expect("run session");
+
+        let log = std::fs::read_to_string(&log_path).expect("read log");
+        assert!(log.starts_with("Codex session\n\nStarted: "), "{log}");
+        for line in [
+            "Attempt: 3".to_string(),
+            "Model: gpt-test".to_string(),
+            format!("Binary: {}", codex_bin.display()),
+            format!("Working dir: {}", dir.path().display()),
+            "Exit status: 0".to_string(),
+        ] {
+            assert!(
+                log.contains(&format!("\n{line}\n")),
+                "{line} missing from {log}"
+            );
+        }
+        assert!(log.contains(
+            "\n== Environment ==\nAPI_TOKEN=[REDACTED]\nDB_PASSWORD=[REDACTED]\n\
+                 URL=https://x?key=[REDACTED]\n"
+        ));
+        assert!(log.contains("\n== Prompt ==\nFix it\n"));
+        assert!(!log.contains("sk-test"));
+        assert!(!log.contains("hunter2"));
+    }
+
+    #[tokio::test]
+    async fn requests_can_run_another_binary() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let launcher = SessionLauncher::new(dir.path().join("missing-codex"), Vec::new());
+        let reviewer_bin = fake_codex(dir.path(), "echo reviewed");
+        assert!(launcher.check_program(None).is_err());
+        launcher
+            .check_program(Some(&reviewer_bin))
+            .expect("reviewer binary exists");
+
+        let log_path = dir.path().join("review.1.md");
+        let result = launcher
+            .run(SessionRequest {
+                prompt: "Review it".to_string(),
+                log_path: log_path.clone(),
+                codex_bin: Some(reviewer_bin.clone()),
+                ..request(dir.path())
+            })
+            .await
+            .expect("run session");
+
+        assert!(result.success);
+        assert_eq!(result.stdout.trim(), "reviewed");
+        let log = std::fs::read_to_string(&log_path).expect("read log");
+        assert!(log.contains(&format!("- Binary: {}\n", reviewer_bin.display())));
+    }
+
+    #[tokio::test]
+    async fn passes_large_prompt_over_stdin() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let codex_bin = fake_codex(dir.path(), "wc -c");
+        let launcher = SessionLauncher::new(codex_bin, Vec::new());
+        // Well beyond the per-argument limit, so this could not be spawned via argv.
+        let prompt = "x".repeat(4 * 1024 * 1024);
+        let log_path = dir.path().join("worker.log");
+        let result = launcher
+            .run(SessionRequest {
+                prompt: prompt.clone(),
+                log_path: log_path.clone(),
+                ..request(dir.path())
+            })
+            .await
+            .expect("run session");
+
+        assert!(result.success);
+        assert_eq!(result.stdout.trim(), prompt.len().to_string());
+        let log = std::fs::read_to_string(&log_path).expect("read log");
+        assert!(log.contains(&prompt));
+    }
+
+    #[tokio::test]
+    async fn streams_output_lines_then_the_result() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let codex_bin = fake_codex(
+            dir.path(),
+            "cat >/dev/null; echo one; echo \"token $API_TOKEN\" >&2; printf two",
+        );
+        let launcher =
+            SessionLauncher::new(codex_bin, Vec::new()).with_secrets(BTreeMap::from([(
+                "API_TOKEN".to_string(),
+                "sk-test".to_string(),
+            )]));
+        let log_path = dir.path().join("worker.1.md");
+        let chunks: Vec<SessionChunk> = launcher
+            .run_streaming(SessionRequest {
+                log_path: log_path.clone(),
+                ..request(dir.path())
+            })
+            .collect()
+            .await;
+
+        let (last, lines) = chunks.split_last().expect("chunks");
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        for chunk in lines {
+            match chunk {
+                SessionChunk::Stdout(line) => stdout.push(line.as_str()),
+                SessionChunk::Stderr(line) => stderr.push(line.as_str()),
+                SessionChunk::Restarted => panic!("unexpected restart"),
+                SessionChunk::Finished(_) => panic!("result before the last chunk"),
+            }
+        }
+        assert_eq!(stdout, ["one", "two"]);
+        assert_eq!(stderr, ["token [REDACTED]"]);
+        let SessionChunk::Finished(result) = last else {
+            panic!("expected the result last, got {last:?}");
+        };
+        let result = result.as_ref().expect("session result");
+        assert!(result.success);
+        assert_eq!(result.stdout, "one\ntwo");
+        assert!(log_path.exists());
+    }
+
+    #[tokio::test]
+    async fn marks_where_a_streamed_session_restarts() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let codex_bin = fake_codex(
+            dir.path(),
+            r#"cat >/dev/null
+for arg; do
+  if [ "$arg" = "resume" ]; then
+    echo "error: session expired-session not found"
+    exit 1
+  fi
+done
+echo fresh"#,
+        );
+        let launcher = SessionLauncher::new(codex_bin, Vec::new());
+        let chunks: Vec<SessionChunk> = launcher
+            .run_streaming(SessionRequest {
+                resume_session: Some("expired-session".to_string()),
+                ..request(dir.path())
+            })
+            .collect()
+            .await;
+
+        let labels: Vec<String> = chunks
+            .iter()
+            .map(|chunk| match chunk {
+                SessionChunk::Stdout(line) => line.clone(),
+                SessionChunk::Stderr(line) => format!("stderr: {line}"),
+                SessionChunk::Restarted => "restarted".to_string(),
+                SessionChunk::Finished(_) => "finished".to_string(),
+            })
+            .collect();
+        assert_eq!(
+            labels,
+            [
+                "error: session expired-session not found",
+                "restarted",
+                "fresh",
+                "finished"
+            ]
+        );
+    }
+
+    #[test]
+    fn echoes_complete_lines_with_a_label() {
+        assert_eq!(
+            echo_label(Path::new("/run/ticket-T4/worker.1.md")),
+            "ticket-T4/worker.1"
+        );
+        let mut echo = LineEcho::new(
+            "ticket-T4/worker.1".to_string(),
+            vec!["sk-test".to_string()],
+        );
+        let mut out = Vec::new();
+        echo.push(b"one\ntw", &mut out);
+        echo.push(b"o sk-test\nthr", &mut out);
+        assert_eq!(
+            String::from_utf8_lossy(&out),
+            "ticket-T4/worker.1 | one\nticket-T4/worker.1 | two [REDACTED]\n"
+        );
+        echo.finish(&mut out);
+        assert!(String::from_utf8_lossy(&out).ends_with("ticket-T4/worker.1 | thr\n"));
+    }
+
+    #[test]
+    fn printed_command_redacts_secrets_and_optionally_the_prompt() {
+        let launcher = SessionLauncher::new(PathBuf::from("codex"), Vec::new()).with_secrets(
+            BTreeMap::from([("TOKEN".to_string(), "sk-test".to_string())]),
+        );
+        let request = SessionRequest {
+            prompt: "Use sk-test to fix it".to_string(),
+            log_path: PathBuf::from("/logs/worker.1.md"),
+            model: Some("gpt-test".to_string()),
+            profile: Some("fast".to_string()),
+            ..request(Path::new("/work"))
+        };
+        assert_eq!(launcher.printed_command(&request, None, None), None);
+
+        let redacted = launcher
+            .clone()
+            .with_print_command(Some(PrintCommand::Redacted))
+            .printed_command(&request, None, None)
+            .expect("printed");
+        assert_eq!(
+            redacted,
+            "codex session for /logs/worker.1.md:\n  codex exec --skip-git-repo-check -m gpt-test \
+            --profile fast -C /work -\n"
+        );
+        let full = launcher
+            .with_print_command(Some(PrintCommand::Full))
+            .printed_command(&request, None, None)
+            .expect("printed");
+        assert!(full.ends_with("  prompt (stdin):\nUse [REDACTED] to fix it\n"));
+    }
+
+    #[tokio::test]
+    async fn argv_fallback_passes_prompt_as_last_argument() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let codex_bin = fake_codex(dir.path(), r#"for arg; do last="$arg"; done; echo "$last""#);
+        let launcher = SessionLauncher::new(codex_bin, Vec::new()).with_prompt_via_argv(true);
+        let result = launcher
+            .run(SessionRequest {
+                prompt: "hello".to_string(),
+                log_path: dir.path().join("worker.log"),
+                ..request(dir.path())
+            })
+            .await
+            .expect("run session");
+
+        assert_eq!(result.stdout.trim(), "hello");
+    }
+
+    #[test]
+    fn subcommand_replaces_exec() {
+        let request = SessionRequest {
+            prompt: "work".to_string(),
+            log_path: PathBuf::from("/logs/worker.1.md"),
+            ..request(Path::new("/work"))
+        };
+        let launcher = SessionLauncher::new(PathBuf::from("wrapper"), Vec::new());
+        assert_eq!(
+            launcher
+                .clone()
+                .with_subcommand(vec!["agent".to_string(), "exec".to_string()])
+                .command_line(&request, None, None),
+            "wrapper agent exec --skip-git-repo-check -C /work -"
+        );
+        assert_eq!(
+            launcher
+                .clone()
+                .with_subcommand(Vec::new())
+                .command_line(&request, None, None),
+            "wrapper --skip-git-repo-check -C /work -"
+        );
+        let enforced = SessionRequest {
+            skip_git_repo_check: false,
+            ..request
+        };
+        assert_eq!(
+            launcher.command_line(&enforced, None, None),
+            "wrapper exec -C /work -"
+        );
+    }
+
+    #[tokio::test]
+    async fn extra_args_are_passed_verbatim_and_logged() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let codex_bin = fake_codex(dir.path(), r#"cat >/dev/null; printf '%s\n' "$@""#);
+        let launcher = SessionLauncher::new(codex_bin, vec!["model=\"o3\"".to_string()]);
+        let log_path = dir.path().join("worker.log");
+        let result = launcher
+            .run(SessionRequest {
+                prompt: "work".to_string(),
+                log_path: log_path.clone(),
+                extra_args: vec!["--profile".to_string(), "fast $HOME".to_string()],
+                ..request(dir.path())
+            })
+            .await
+            .expect("run session");
+
+        let args: Vec<&str> = result.stdout.lines().collect();
+        assert_eq!(&args[args.len() - 3..], ["--profile", "fast $HOME", "-"]);
+        let log = std::fs::read_to_string(&log_path).expect("read log");
+        assert!(log.contains("--profile 'fast $HOME' -\n"));
+        assert!(!log.lines().next().unwrap_or_default().contains("work"));
+    }
+
+    #[tokio::test]
+    async fn secrets_reach_the_child_but_not_the_log() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let codex_bin = fake_codex(dir.path(), r#"echo "token=$API_TOKEN region=$REGION""#);
+        let secrets = BTreeMap::from([
+            ("API_TOKEN".to_string(), "s3cr3t-value".to_string()),
+            ("REGION".to_string(), "secret-region".to_string()),
+        ]);
+        let launcher = SessionLauncher::new(codex_bin, Vec::new()).with_secrets(secrets);
+        let log_path = dir.path().join("worker.log");
+        let result = launcher
+            .run(SessionRequest {
+                prompt: "use s3cr3t-value carefully".to_string(),
+                log_path: log_path.clone(),
+                env: BTreeMap::from([("REGION".to_string(), "eu-west-1".to_string())]),
+                ..request(dir.path())
+            })
+            .await
+            .expect("run session");
+
+        assert_eq!(result.stdout.trim(), "token=[REDACTED] region=eu-west-1");
+        let log = std::fs::read_to_string(&log_path).expect("read log");
+        assert!(!log.contains("s3cr3t-value"));
+        assert!(log.contains("use [REDACTED] carefully"));
+    }
+
+    #[tokio::test]
+    async fn captures_last_message_into_destination() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let codex_bin = fake_codex(
+            dir.path(),
+            r#"cat >/dev/null
+while [ $# -gt 0 ]; do
+  if [ "$1" = "--output-last-message" ]; then printf 'All done.\n' > "$2"; fi
+  shift
+done"#,
+        );
+        let launcher = SessionLauncher::new(codex_bin, Vec::new());
+        let dest = dir.path().join("ticket").join("worker.output.md");
+        let result = launcher
+            .run(SessionRequest {
+                prompt: "work".to_string(),
+                log_path: dir.path().join("worker.log"),
+                last_message_path: Some(dest.clone()),
+                ..request(dir.path())
+            })
+            .await
+            .expect("run session");
+
+        assert_eq!(result.last_message.as_deref(), Some("All done.\n"));
+        assert_eq!(std::fs::read_to_string(&dest).expect("read"), "All done.\n");
+    }
+
+    #[tokio::test]
+    async fn retries_without_last_message_flag_on_older_binaries() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let codex_bin = fake_codex(
+            dir.path(),
+            r#"cat >/dev/null
+for arg; do
+  if [ "$arg" = "--output-last-message" ]; then
+    echo "error: unexpected argument '--output-last-message' found" >&2
+    exit 2
+  fi
+done
+echo ok"#,
+        );
+        let launcher = SessionLauncher::new(codex_bin, Vec::new());
+        let dest = dir.path().join("review.output.md");
+        let request = SessionRequest {
+            prompt: "review".to_string(),
+            log_path: dir.path().join("review.log"),
+            last_message_path: Some(dest.clone()),
+            ..request(dir.path())
+        };
+        let result = launcher.run(request.clone()).await.expect("run session");
+        assert!(result.success);
+        assert_eq!(result.last_message, None);
+        assert!(!dest.exists());
+        assert!(!launcher.last_message_supported.load(Ordering::Relaxed));
+
+        let second = launcher.run(request).await.expect("run session");
+        assert!(second.success);
+    }
+
+    #[tokio::test]
+    async fn resumes_session_and_falls_back_to_fresh_session() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let codex_bin = fake_codex(
+            dir.path(),
+            r#"cat >/dev/null
+prev=""
+for arg; do
+  if [ "$prev" = "resume" ]; then
+    if [ "$arg" = "known-session" ]; then
+      echo "session id: known-session" >&2
+      exit 0
+    fi
+    if [ "$arg" = "overloaded-session" ]; then
+      echo "error: model is overloaded" >&2
+      exit 2
+    fi
+    echo "error: session $arg not found" >&2
+    exit 1
+  fi
+  prev="$arg"
+done
+echo "session id: fresh-session" >&2"#,
+        );
+        let launcher = SessionLauncher::new(codex_bin, Vec::new());
+        let request = SessionRequest {
+            prompt: "review".to_string(),
+            log_path: dir.path().join("review.log"),
+            resume_session: Some("known-session".to_string()),
+            ..request(dir.path())
+        };
+        let resumed = launcher.run(request.clone()).await.expect("run session");
+        assert_eq!(resumed.session_id.as_deref(), Some("known-session"));
+        assert!(resumed.warnings.is_empty());
+
+        let failed = launcher
+            .run(SessionRequest {
+                resume_session: Some("overloaded-session".to_string()),
+                ..request.clone()
+            })
+            .await
+            .expect("run session");
+        assert!(!failed.success);
+        assert_eq!(failed.status_code, Some(2));
+        assert!(failed.warnings.is_empty());
+        assert!(failed.stderr.contains("overloaded"));
+
+        let fallback = launcher
+            .run(SessionRequest {
+                resume_session: Some("expired-session".to_string()),
+                ..request
+            })
+            .await
+            .expect("run session");
+        assert!(fallback.success);
+        assert_eq!(fallback.session_id.as_deref(), Some("fresh-session"));
+        assert_eq!(fallback.warnings.len(), 1);
+        let log = std::fs::read_to_string(dir.path().join("review.log")).expect("read log");
+        assert!(log.contains("Could not resume session expired-session"));
+    }
+
+    fn process_alive(pid: i32) -> bool {
+        match std::fs::read_to_string(format!("/proc/{pid}/stat")) {
+            // Zombies linger until reaped but no longer run anything.
+            Ok(stat) => !stat
+                .rsplit(')')
+                .next()
+                .is_some_and(|rest| rest.trim_start().starts_with('Z')),
+            // SAFETY: signal 0 only checks whether the pid exists.
+            Err(_) if !Path::new("/proc").exists() => unsafe { libc::kill(pid, 0) == 0 },
+            Err(_) => false,
+        }
+    }
+
+    async fn wait_for_grandchild_exit(pid_file: &Path) {
+        let pid: i32 = std::fs::read_to_string(pid_file)
+            .expect("read pid file")
+            .trim()
+            .parse()
+            .expect("parse pid");
+        for _ in 0..50 {
+            if !process_alive(pid) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("process {pid} spawned by the session is still running");
+    }
+
+    fn sleeping_session(
+        dir: &Path,
+        timeout: Option<Duration>,
+    ) -> (SessionLauncher, SessionRequest) {
+        let pid_file = dir.join("grandchild.pid");
+        let codex_bin = fake_codex(
+            dir,
+            &format!("sleep 30 &\necho $! > '{}'\nwait", pid_file.display()),
+        );
+        let request = SessionRequest {
+            prompt: "hello".to_string(),
+            log_path: dir.join("worker.log"),
+            timeout,
+            ..request(dir)
+        };
+        (SessionLauncher::new(codex_bin, Vec::new()), request)
+    }
+
+    #[tokio::test]
+    async fn timeout_terminates_the_process_group() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let (launcher, request) = sleeping_session(dir.path(), Some(Duration::from_millis(500)));
+        let result = launcher.run(request).await.expect("run session");
+
+        assert!(result.timed_out);
+        assert!(!result.success);
+        let log = std::fs::read_to_string(dir.path().join("worker.log")).expect("read log");
+        assert!(log.contains("> **Warning:** Session timed out after 0.5s"));
+        assert!(log.contains("- Exit status: timed out\n"));
+        wait_for_grandchild_exit(&dir.path().join("grandchild.pid")).await;
+    }
+
+    #[tokio::test]
+    async fn cancelling_the_session_terminates_the_process_group() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let (launcher, request) = sleeping_session(dir.path(), None);
+        let pid_file = dir.path().join("grandchild.pid");
+        let mut run = Box::pin(launcher.run(request));
+        for _ in 0..50 {
+            tokio::select! {
+                _ = &mut run => panic!("session should still be running"),
+                _ = tokio::time::sleep(Duration::from_millis(100)) => {}
+            }
+            if std::fs::read_to_string(&pid_file).is_ok_and(|pid| pid.ends_with('\n')) {
+                break;
+            }
+        }
+        drop(run);
+        wait_for_grandchild_exit(&pid_file).await;
+    }
+
+    #[tokio::test]
+    async fn log_strips_ansi_and_timestamps_output_lines() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let codex_bin = fake_codex(dir.path(), r"printf '\033[32mok\033[0m\nnext\n'");
+        let launcher = SessionLauncher::new(codex_bin, Vec::new());
+        let result = launcher
+            .run(SessionRequest {
+                prompt: "first line\nsecond line".to_string(),
+                log_path: dir.path().join("worker.log"),
+                ..request(dir.path())
+            })
+            .await
+            .expect("run session");
+
+        assert_eq!(result.stdout, "\u{1b}[32mok\u{1b}[0m\nnext\n");
+        let log = std::fs::read_to_string(dir.path().join("worker.log")).expect("read log");
+        assert!(log.contains("## Prompt\n\n```text\nfirst line\nsecond line\n```\n"));
+        assert!(log.contains("## Stdout\n\n```text\n[00:00:00] ok\n[00:00:00] next\n```\n"));
+        assert!(!log.contains('\u{1b}'));
+    }
+
+    #[tokio::test]
+    async fn caps_captured_output_and_keeps_the_tail() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let codex_bin = fake_codex(
+            dir.path(),
+            "i=0\nwhile [ $i -lt 2000 ]; do echo \"noise $i\"; i=$((i+1)); done\necho fatal: boom",
+        );
+        let launcher = SessionLauncher::new(codex_bin, Vec::new()).with_max_log_bytes(2048);
+        let result = launcher
+            .run(SessionRequest {
+                prompt: "hello".to_string(),
+                log_path: dir.path().join("worker.log"),
+                ..request(dir.path())
+            })
+            .await
+            .expect("run session");
+
+        assert!(result.truncated_bytes > 0);
+        assert!(result.stdout.len() < 1100);
+        assert!(result.stdout.starts_with("noise 0\n"));
+        assert!(result.stdout.ends_with("fatal: boom\n"));
+        let log = std::fs::read_to_string(dir.path().join("worker.log")).expect("read log");
+        assert!(log.contains(&format!(
+            "[... {} bytes truncated ...]",
+            result.truncated_bytes
+        )));
+        assert!(log.contains("log cap"));
+    }
+
+    #[tokio::test]
+    async fn writes_the_log_while_the_session_runs() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let release = dir.path().join("release");
+        let codex_bin = fake_codex(
+            dir.path(),
+            &format!(
+                "cat >/dev/null; echo working\nwhile [ ! -e '{}' ]; do sleep 0.05; done\necho done",
+                release.display()
+            ),
+        );
+        let launcher = SessionLauncher::new(codex_bin, Vec::new());
+        let log_path = dir.path().join("worker.1.md");
+        let mut run = Box::pin(launcher.run(request(dir.path())));
+        let mut live = String::new();
+        for _ in 0..50 {
+            tokio::select! {
+                _ = &mut run => panic!("session should still be running"),
+                _ = tokio::time::sleep(Duration::from_millis(100)) => {}
+            }
+            live = std::fs::read_to_string(&log_path).unwrap_or_default();
+            if live.contains("stdout | working\n") {
+                break;
+            }
+        }
+        assert!(live.starts_with("Codex session (running)\n"), "{live}");
+        assert!(
+            live.contains("\n== Prompt ==\nFix it\n\n== Output ==\n"),
+            "{live}"
+        );
+        assert!(live.contains("] stdout | working\n"), "{live}");
+
+        std::fs::write(&release, "").expect("write release file");
+        let result = run.await.expect("run session");
+        assert_eq!(result.stdout, "working\ndone\n");
+        let log = std::fs::read_to_string(&log_path).expect("read log");
+        assert!(log.starts_with("# Codex session\n"), "{log}");
+        assert!(log.contains("] working\n[00:00:"), "{log}");
+    }
+
+    #[tokio::test]
+    async fn writes_json_logs() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let codex_bin = fake_codex(dir.path(), "echo done; exit 3");
+        let launcher = SessionLauncher::new(codex_bin, Vec::new());
+        let log_path = dir.path().join("worker.1.json");
+        launcher
+            .run(SessionRequest {
+                prompt: "hello".to_string(),
+                log_path: log_path.clone(),
+                log_format: LogFormat::Json,
+                ..request(dir.path())
+            })
+            .await
+            .expect("run session");
+
+        let log: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&log_path).expect("read log"))
+                .expect("parse log");
+        assert_eq!(log["prompt"], "hello");
+        assert_eq!(log["exit_code"], 3);
+        assert_eq!(log["stdout"], "done\n");
+        assert_eq!(
+            log["stdout_line_elapsed_ms"].as_array().map(Vec::len),
+            Some(1)
+        );
+    }
 }
@@ -1,5 +1,5 @@
+use crate::error::WorkflowError;
 use crate::manifest::WorkflowManifest;
-use anyhow::Context;
 use chrono::DateTime;
 use chrono::Utc;
 use serde::Deserialize;
@@ -8,10 +8,15 @@ use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WorkflowState {
     pub workflow_name: String,
+    /// Run directory of the most recent run; unset for the flat layout.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_dir: Option<PathBuf>,
     pub tickets: BTreeMap<String, TicketRunState>,
 }
 
@@ -20,64 +25,61 @@ impl WorkflowState {
         let tickets = manifest
             .tickets
             .iter()
-            .map(|ticket| {
-                (
-                    ticket.id.clone(),
-                    TicketRunState {
-                        ticket_id: ticket.id.clone(),
-                        status: TicketStatus::Pending,
-                        worker_log: None,
-                        review_log: None,
-                        note: None,
-                        started_at: None,
-                        finished_at: None,
-                    },
-                )
-            })
+            .map(|ticket| (ticket.id.clone(), TicketRunState::new(&ticket.id)))
             .collect();
 
         Self {
             workflow_name: manifest.workflow_name(),
+            run_dir: None,
             tickets,
         }
     }
 
     pub fn sync_with_manifest(&mut self, manifest: &WorkflowManifest) {
         for ticket in &manifest.tickets {
-            self
-                .tickets
+            self.tickets
                 .entry(ticket.id.clone())
-                .or_insert_with(|| TicketRunState {
-                    ticket_id: ticket.id.clone(),
-                    status: TicketStatus::Pending,
-                    worker_log: None,
-                    review_log: None,
-                    note: None,
-                    started_at: None,
-                    finished_at: None,
-                });
+                .or_insert_with(|| TicketRunState::new(&ticket.id));
+        }
+    }
+
+    /// Ids of recorded tickets that no longer appear in the manifest.
+    pub fn orphaned_tickets(&self, manifest: &WorkflowManifest) -> Vec<String> {
+        self.tickets
+            .keys()
+            .filter(|id| !manifest.tickets.iter().any(|ticket| &ticket.id == *id))
+            .cloned()
+            .collect()
+    }
+
+    pub fn remove_tickets(&mut self, ticket_ids: &[String]) {
+        for ticket_id in ticket_ids {
+            self.tickets.remove(ticket_id);
         }
     }
 
-    pub fn load(path: &Path) -> anyhow::Result<Self> {
-        let data = fs::read_to_string(path)
-            .with_context(|| format!("failed to read workflow state {}", path.display()))?;
-        let state: WorkflowState =
-            serde_json::from_str(&data).context("parse workflow state json")?;
-        Ok(state)
+    pub fn load(path: &Path) -> Result<Self, WorkflowError> {
+        let load_error =
+            |source: Box<dyn std::error::Error + Send + Sync>| WorkflowError::StateLoad {
+                path: path.to_path_buf(),
+                source,
+            };
+        let data = fs::read_to_string(path).map_err(|err| load_error(err.into()))?;
+        serde_json::from_str(&data).map_err(|err| load_error(err.into()))
     }
 
-    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+    pub fn save(&self, path: &Path) -> Result<(), WorkflowError> {
+        let persist_error = |source| WorkflowError::StatePersist {
+            path: path.to_path_buf(),
+            source,
+        };
         if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent)
-                .with_context(|| format!("failed to create {}", parent.display()))?;
+            fs::create_dir_all(parent).map_err(persist_error)?;
         }
         let tmp_path = tmp_path(path);
-        let data = serde_json::to_vec_pretty(self)?;
-        fs::write(&tmp_path, data)
-            .with_context(|| format!("failed to write {}", tmp_path.display()))?;
-        fs::rename(&tmp_path, path)
-            .with_context(|| format!("failed to persist {}", path.display()))?;
+        let data = serde_json::to_vec_pretty(self).map_err(|err| persist_error(err.into()))?;
+        fs::write(&tmp_path, data).map_err(persist_error)?;
+        fs::rename(&tmp_path, path).map_err(persist_error)?;
         Ok(())
     }
 
@@ -88,6 +90,105 @@ impl WorkflowState {
     pub fn ticket_mut(&mut self, ticket_id: &str) -> Option<&mut TicketRunState> {
         self.tickets.get_mut(ticket_id)
     }
+
+    /// The number of tickets in a final status, and of all tickets.
+    pub fn progress(&self) -> (usize, usize) {
+        let done = self
+            .tickets
+            .values()
+            .filter(|ticket| ticket.status.is_final())
+            .count();
+        (done, self.tickets.len())
+    }
+
+    /// Moves a `Blocked` ticket back to `Pending` so the next run retries it.
+    pub fn unblock(&mut self, ticket_id: &str) -> anyhow::Result<()> {
+        let Some(ticket) = self.tickets.get_mut(ticket_id) else {
+            anyhow::bail!("unknown ticket id {ticket_id}");
+        };
+        if ticket.status != TicketStatus::Blocked {
+            anyhow::bail!(
+                "ticket {ticket_id} is not blocked (status: {:?})",
+                ticket.status
+            );
+        }
+        ticket.reset(Some("Unblocked; pending retry".to_string()));
+        Ok(())
+    }
+
+    /// Marks a ticket `Complete` for work done outside the workflow.
+    pub fn complete_manually(
+        &mut self,
+        ticket_id: &str,
+        note: Option<String>,
+    ) -> anyhow::Result<()> {
+        let Some(ticket) = self.tickets.get_mut(ticket_id) else {
+            anyhow::bail!("unknown ticket id {ticket_id}");
+        };
+        if ticket.status == TicketStatus::Complete {
+            anyhow::bail!("ticket {ticket_id} is already complete");
+        }
+        if ticket.started_at.is_none() {
+            ticket.started_at = Some(Utc::now());
+        }
+        let note = note.unwrap_or_else(|| "Marked complete by hand".to_string());
+        ticket.mark_finished(TicketStatus::Complete, Some(note));
+        Ok(())
+    }
+
+    /// For `--resume-from`: marks the `earlier` tickets complete, leaving
+    /// complete ones as they are, and sends `ticket_id` back to pending if it
+    /// had finished. Returns the ids it marked complete.
+    pub fn resume_from(
+        &mut self,
+        ticket_id: &str,
+        earlier: &[&str],
+    ) -> anyhow::Result<Vec<String>> {
+        let Some(ticket) = self.tickets.get_mut(ticket_id) else {
+            anyhow::bail!("unknown ticket id {ticket_id}");
+        };
+        if ticket.status.is_final() {
+            ticket.reset(Some(format!("Reset by --resume-from {ticket_id}")));
+        }
+        let mut completed = Vec::new();
+        for id in earlier {
+            if let Some(ticket) = self.tickets.get_mut(*id)
+                && ticket.status != TicketStatus::Complete
+            {
+                ticket.mark_finished(
+                    TicketStatus::Complete,
+                    Some(format!("Marked complete by --resume-from {ticket_id}")),
+                );
+                completed.push(id.to_string());
+            }
+        }
+        Ok(completed)
+    }
+
+    /// Unblocks every `Blocked` ticket and returns their ids.
+    pub fn unblock_all(&mut self) -> Vec<String> {
+        let mut unblocked = Vec::new();
+        for ticket in self.tickets.values_mut() {
+            if ticket.status == TicketStatus::Blocked {
+                ticket.reset(Some("Unblocked; pending retry".to_string()));
+                unblocked.push(ticket.ticket_id.clone());
+            }
+        }
+        unblocked
+    }
+
+    /// For `--only-failed`: moves every `Failed` and `Blocked` ticket back to
+    /// `Pending` and returns their ids.
+    pub fn reset_failed(&mut self) -> Vec<String> {
+        let mut reset = Vec::new();
+        for ticket in self.tickets.values_mut() {
+            if matches!(ticket.status, TicketStatus::Failed | TicketStatus::Blocked) {
+                ticket.reset(Some("Reset by --only-failed; pending retry".to_string()));
+                reset.push(ticket.ticket_id.clone());
+            }
+        }
+        reset
+    }
 }
 
 fn tmp_path(path: &Path) -> PathBuf {
@@ -107,12 +208,133 @@ pub struct TicketRunState {
     pub status: TicketStatus,
     pub worker_log: Option<PathBuf>,
     pub review_log: Option<PathBuf>,
+    /// Final agent message of the worker session (`worker.output.md`).
+    pub worker_output: Option<PathBuf>,
+    /// Final agent message of the review session (`review.output.md`).
+    pub review_output: Option<PathBuf>,
+    pub review_summary: Option<String>,
+    /// codex session id of the latest worker session.
+    pub worker_session_id: Option<String>,
+    /// codex session id of the latest review session.
+    pub review_session_id: Option<String>,
+    /// Prompt file of the latest worker session (`worker.prompt.txt`).
+    pub worker_prompt: Option<PathBuf>,
+    /// Prompt file of the latest review session (`review.prompt.txt`).
+    pub review_prompt: Option<PathBuf>,
+    /// codex invocation of the latest worker session, prompt elided.
+    pub worker_command: Option<String>,
+    /// codex invocation of the latest review session, prompt elided.
+    pub review_command: Option<String>,
+    /// `--model` of the latest worker session, if one was given.
+    #[serde(default)]
+    pub worker_model: Option<String>,
+    /// Directory the latest review session ran in.
+    #[serde(default)]
+    pub review_working_dir: Option<PathBuf>,
+    /// `git diff` of the working directory after the latest successful
+    /// worker session (`patches/worker.diff`); unset outside git work trees.
+    #[serde(default)]
+    pub worker_diff: Option<PathBuf>,
+    /// Number of changed and untracked files when `worker_diff` was captured.
+    #[serde(default)]
+    pub changed_files: Option<usize>,
+    /// Commit checked out before the ticket's first worker session, which
+    /// `worker_diff` is taken against with `review_against_diff`.
+    #[serde(default)]
+    pub diff_base: Option<String>,
+    /// Reviewer verdicts for requirements that carry an id.
+    #[serde(default)]
+    pub requirement_results: BTreeMap<String, RequirementOutcome>,
+    /// The latest review's structured verdict, if the reviewer gave one.
+    #[serde(default)]
+    pub review_report: Option<ReviewReport>,
+    /// Times a review requesting changes sent the ticket back to the worker.
+    #[serde(default)]
+    pub reworks: i32,
+    /// Hash of the ticket's spec and inputs when it completed; see
+    /// `--skip-unchanged`.
+    #[serde(default)]
+    pub fingerprint: Option<String>,
+    /// The ticket's git branch with `git.branch_per_ticket`, or with
+    /// `git.use_worktrees` and `git.autocommit`.
+    #[serde(default)]
+    pub branch: Option<String>,
+    /// What `branch` was created from, and what `git.return_to_base` checks
+    /// out again.
+    #[serde(default)]
+    pub base_branch: Option<String>,
+    /// URL of the pull request `git.open_pr` opened or updated for the
+    /// ticket's branch.
+    #[serde(default)]
+    pub pull_request: Option<String>,
+    /// Commit of the ticket's changes made by `git.autocommit`; unset when
+    /// there was nothing to commit.
+    #[serde(default)]
+    pub commit: Option<String>,
+    /// Paths with uncommitted changes before the ticket's first worker
+    /// session, which `git.autocommit` leaves out of the ticket's commit.
+    #[serde(default)]
+    pub preexisting_changes: Option<Vec<String>>,
+    /// The ticket's checkout with `git.use_worktrees`, until it is removed.
+    #[serde(default)]
+    pub worktree: Option<TicketWorktree>,
     pub note: Option<String>,
     pub started_at: Option<DateTime<Utc>>,
     pub finished_at: Option<DateTime<Utc>>,
+    /// Every worker/review session launched for this ticket, oldest first.
+    #[serde(default)]
+    pub attempts: Vec<AttemptRecord>,
 }
 
 impl TicketRunState {
+    pub fn new(ticket_id: &str) -> Self {
+        Self {
+            ticket_id: ticket_id.to_string(),
+            status: TicketStatus::Pending,
+            worker_log: None,
+            review_log: None,
+            worker_output: None,
+            review_output: None,
+            review_summary: None,
+            worker_session_id: None,
+            review_session_id: None,
+            worker_prompt: None,
+            review_prompt: None,
+            worker_command: None,
+            review_command: None,
+            worker_model: None,
+            review_working_dir: None,
+            worker_diff: None,
+            changed_files: None,
+            diff_base: None,
+            requirement_results: BTreeMap::new(),
+            review_report: None,
+            reworks: 0,
+            fingerprint: None,
+            branch: None,
+            base_branch: None,
+            pull_request: None,
+            commit: None,
+            preexisting_changes: None,
+            worktree: None,
+            note: None,
+            started_at: None,
+            finished_at: None,
+            attempts: Vec::new(),
+        }
+    }
+
+    /// Time from the first session's start to the ticket finishing, or to
+    /// `now` while it is still running.
+    pub fn elapsed(&self, now: DateTime<Utc>) -> Option<chrono::TimeDelta> {
+        let started = self.started_at?;
+        let end = match (&self.status, self.finished_at) {
+            (TicketStatus::RunningWorker | TicketStatus::RunningReview, _) | (_, None) => now,
+            (_, Some(finished)) => finished,
+        };
+        Some((end - started).max(chrono::TimeDelta::zero()))
+    }
+
     pub fn mark_running(&mut self, status: TicketStatus) {
         self.status = status;
         if self.started_at.is_none() {
@@ -127,6 +349,81 @@ impl TicketRunState {
         self.finished_at = Some(Utc::now());
     }
 
+    fn reset(&mut self, note: Option<String>) {
+        self.status = TicketStatus::Pending;
+        self.note = note;
+        self.started_at = None;
+        self.finished_at = None;
+    }
+
+    /// Number of sessions already recorded for `phase`.
+    pub fn attempt_count(&self, phase: SessionPhase) -> i32 {
+        let count = self
+            .attempts
+            .iter()
+            .filter(|attempt| attempt.phase == phase)
+            .count();
+        i32::try_from(count).unwrap_or(i32::MAX)
+    }
+
+    /// Records the start of a session attempt; `finish_attempt` fills in the outcome.
+    pub fn start_attempt(
+        &mut self,
+        phase: SessionPhase,
+        attempt: i32,
+        log_path: PathBuf,
+        timeout: Option<Duration>,
+        profile: Option<String>,
+    ) {
+        match phase {
+            SessionPhase::Worker => self.set_worker_log(log_path.clone()),
+            SessionPhase::Review => self.set_review_log(log_path.clone()),
+        }
+        self.attempts.push(AttemptRecord {
+            phase,
+            attempt,
+            log_path,
+            started_at: Utc::now(),
+            finished_at: None,
+            status_code: None,
+            truncated_bytes: 0,
+            timeout_secs: timeout.map(|timeout| timeout.as_secs_f64()),
+            profile,
+            log_pruned: false,
+        });
+    }
+
+    pub fn finish_attempt(
+        &mut self,
+        phase: SessionPhase,
+        status_code: Option<i32>,
+        truncated_bytes: u64,
+    ) {
+        if let Some(record) = self
+            .attempts
+            .iter_mut()
+            .rev()
+            .find(|attempt| attempt.phase == phase)
+        {
+            record.finished_at = Some(Utc::now());
+            record.status_code = status_code;
+            record.truncated_bytes = truncated_bytes;
+        }
+    }
+
+    /// Drops the latest `phase` attempt if it never finished, for a session
+    /// that could not be started at all.
+    pub fn discard_attempt(&mut self, phase: SessionPhase) {
+        if let Some(index) = self
+            .attempts
+            .iter()
+            .rposition(|attempt| attempt.phase == phase)
+            && self.attempts[index].finished_at.is_none()
+        {
+            self.attempts.remove(index);
+        }
+    }
+
     pub fn set_worker_log(&mut self, log_path: PathBuf) {
         self.worker_log = Some(log_path);
     }
@@ -148,25 +445,19 @@ mod tests {
         let manifest = WorkflowManifest {
             source_path: PathBuf::from("workflow.yaml"),
             name: Some("demo".into()),
-            overview: None,
             tickets: vec![
                 TicketSpec {
                     id: "A".into(),
                     summary: "Ticket A".into(),
-                    requirements: vec![],
-                    working_dir: None,
-                    prompt: None,
-                    review_prompt: None,
+                    ..Default::default()
                 },
                 TicketSpec {
                     id: "B".into(),
                     summary: "Ticket B".into(),
-                    requirements: vec![],
-                    working_dir: None,
-                    prompt: None,
-                    review_prompt: None,
+                    ..Default::default()
                 },
             ],
+            ..Default::default()
         };
 
         let state = WorkflowState::initialize(&manifest);
@@ -178,6 +469,88 @@ mod tests {
                 .all(|ticket| ticket.status == TicketStatus::Pending)
         );
     }
+
+    #[test]
+    fn elapsed_runs_until_now_while_a_session_is_running() {
+        let mut ticket = TicketRunState::new("T1");
+        let now = Utc::now();
+        assert_eq!(ticket.elapsed(now), None);
+
+        let started = now - chrono::TimeDelta::seconds(90);
+        ticket.started_at = Some(started);
+        ticket.finished_at = Some(started + chrono::TimeDelta::seconds(30));
+        ticket.status = TicketStatus::Failed;
+        assert_eq!(ticket.elapsed(now), Some(chrono::TimeDelta::seconds(30)));
+        // A rerun keeps the earlier finish time until it finishes again.
+        ticket.status = TicketStatus::RunningWorker;
+        assert_eq!(ticket.elapsed(now), Some(chrono::TimeDelta::seconds(90)));
+    }
+
+    #[test]
+    fn parses_the_status_names_state_files_use() {
+        for status in [
+            TicketStatus::Pending,
+            TicketStatus::RunningWorker,
+            TicketStatus::NeedsReview,
+            TicketStatus::RunningReview,
+            TicketStatus::Complete,
+            TicketStatus::Failed,
+            TicketStatus::Blocked,
+            TicketStatus::Skipped,
+        ] {
+            let name = serde_json::to_value(&status).expect("serialize");
+            let name = name.as_str().expect("string");
+            assert_eq!(name.parse::<TicketStatus>(), Ok(status));
+        }
+        assert_eq!("Needs-Review".parse(), Ok(TicketStatus::NeedsReview));
+        let err = "done".parse::<TicketStatus>().expect_err("unknown status");
+        assert!(err.contains("expected pending"), "{err}");
+    }
+
+    #[test]
+    fn unblock_only_applies_to_blocked_tickets() {
+        let manifest = WorkflowManifest {
+            source_path: PathBuf::from("workflow.yaml"),
+            name: Some("demo".into()),
+            tickets: vec![
+                TicketSpec {
+                    id: "A".into(),
+                    summary: "Ticket A".into(),
+                    ..Default::default()
+                },
+                TicketSpec {
+                    id: "B".into(),
+                    summary: "Ticket B".into(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let mut state = WorkflowState::initialize(&manifest);
+        for ticket in state.tickets.values_mut() {
+            ticket.mark_finished(TicketStatus::Blocked, Some("setup failed".into()));
+        }
+        state
+            .ticket_mut("B")
+            .expect("ticket B")
+            .mark_finished(TicketStatus::Failed, None);
+
+        state.unblock("A").expect("unblock A");
+        let ticket = state.ticket("A").expect("ticket A");
+        assert_eq!(ticket.status, TicketStatus::Pending);
+        assert_eq!(ticket.finished_at, None);
+        assert!(state.unblock("B").is_err());
+        assert!(state.unblock("missing").is_err());
+        assert!(state.unblock_all().is_empty());
+
+        let mut reset = state.reset_failed();
+        reset.sort();
+        assert_eq!(reset, ["B"]);
+        assert_eq!(
+            state.ticket("B").expect("ticket B").status,
+            TicketStatus::Pending
+        );
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -188,6 +561,139 @@ pub enum TicketStatus {
     NeedsReview,
     RunningReview,
     Complete,
+    /// A session ran and did not succeed. Failed tickets are final and are not
+    /// retried by a later `--resume` unless it passes `--only-failed`.
     Failed,
+    /// The ticket could not run because something outside of it was not ready
+    /// (for example a setup problem). Unlike `Failed`, no session verdict was
+    /// reached, so `--unblock` can move it back to `Pending` once the
+    /// underlying issue is fixed.
     Blocked,
+    /// Passed over on request during an interactive run. Final; tickets
+    /// depending on it proceed as if it had completed.
+    Skipped,
+}
+
+impl TicketStatus {
+    /// Human-readable name, e.g. `Running review`.
+    pub fn label(&self) -> &'static str {
+        match self {
+            TicketStatus::Pending => "Pending",
+            TicketStatus::RunningWorker => "Running worker",
+            TicketStatus::NeedsReview => "Needs review",
+            TicketStatus::RunningReview => "Running review",
+            TicketStatus::Complete => "Complete",
+            TicketStatus::Failed => "Failed",
+            TicketStatus::Blocked => "Blocked",
+            TicketStatus::Skipped => "Skipped",
+        }
+    }
+
+    /// Whether a run has nothing left to do for a ticket in this status.
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            TicketStatus::Complete
+                | TicketStatus::Failed
+                | TicketStatus::Blocked
+                | TicketStatus::Skipped
+        )
+    }
+}
+
+impl FromStr for TicketStatus {
+    type Err = String;
+
+    /// The names `state.json` uses, e.g. `needs_review`; `-` may stand in
+    /// for `_`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_lowercase().replace('-', "_").as_str() {
+            "pending" => Ok(TicketStatus::Pending),
+            "running_worker" => Ok(TicketStatus::RunningWorker),
+            "needs_review" => Ok(TicketStatus::NeedsReview),
+            "running_review" => Ok(TicketStatus::RunningReview),
+            "complete" => Ok(TicketStatus::Complete),
+            "failed" => Ok(TicketStatus::Failed),
+            "blocked" => Ok(TicketStatus::Blocked),
+            "skipped" => Ok(TicketStatus::Skipped),
+            _ => Err(format!(
+                "unknown ticket status `{value}` (expected pending, running_worker, \
+                 needs_review, running_review, complete, failed, blocked or skipped)"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionPhase {
+    Worker,
+    Review,
+}
+
+impl SessionPhase {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SessionPhase::Worker => "worker",
+            SessionPhase::Review => "review",
+        }
+    }
+}
+
+/// A single session launched for a ticket.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttemptRecord {
+    pub phase: SessionPhase,
+    pub attempt: i32,
+    pub log_path: PathBuf,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub status_code: Option<i32>,
+    /// Output dropped from the middle of the log to respect the size cap; a
+    /// non-zero value means the log is incomplete.
+    #[serde(default)]
+    pub truncated_bytes: u64,
+    /// The session's time limit, which grows across retries with
+    /// `timeout_backoff`.
+    #[serde(default)]
+    pub timeout_secs: Option<f64>,
+    /// codex config profile the session ran with, if it was given one.
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// `log_path` was deleted to keep within `keep_log_attempts`.
+    #[serde(default)]
+    pub log_pruned: bool,
+}
+
+/// A ticket's `git worktree`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TicketWorktree {
+    pub path: PathBuf,
+    /// Where the ticket's sessions run: the worktree's counterpart of the
+    /// ticket's working directory.
+    pub working_dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RequirementOutcome {
+    Satisfied,
+    Unsatisfied,
+}
+
+/// Structured verdict a reviewer reports in a fenced `json` block.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReviewReport {
+    pub verdict: ReviewVerdict,
+    #[serde(default)]
+    pub blocking_issues: Vec<String>,
+    #[serde(default)]
+    pub suggestions: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewVerdict {
+    Approved,
+    ChangesRequested,
 }
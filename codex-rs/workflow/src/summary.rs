@@ -0,0 +1,160 @@
+use crate::error::WorkflowError;
+use crate::list::status_counts;
+use crate::orchestrator::WorkflowStatusReport;
+use crate::state::TicketRunState;
+use anyhow::Context;
+use chrono::DateTime;
+use chrono::Utc;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// Writes the end-of-run `summary.md`. With `append`, each invocation adds a
+/// timestamped section to the existing file so the file keeps a run history;
+/// otherwise the file only describes the latest run.
+pub(crate) fn write_run_summary(
+    path: &Path,
+    workflow_name: &str,
+    tickets: &[TicketRunState],
+    started_at: DateTime<Utc>,
+    finished_at: DateTime<Utc>,
+    append: bool,
+) -> anyhow::Result<()> {
+    let section = render_run_section(tickets, started_at, finished_at);
+    let existing = append && path.exists();
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(existing)
+        .truncate(!existing)
+        .open(path)
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    if !existing {
+        writeln!(file, "# Workflow summary: {workflow_name}")?;
+    }
+    writeln!(file)?;
+    file.write_all(section.as_bytes())
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Writes `result.json` for CI and other callers: the run's ticket counts
+/// per status, each ticket's outcome, and the error that stopped the run, if
+/// one did.
+pub(crate) fn write_run_result(
+    path: &Path,
+    report: &WorkflowStatusReport,
+    error: Option<&WorkflowError>,
+) -> anyhow::Result<()> {
+    let tickets: Vec<serde_json::Value> = report
+        .tickets
+        .iter()
+        .map(|ticket| {
+            serde_json::json!({
+                "id": ticket.ticket_id,
+                "status": ticket.status,
+                "note": ticket.note,
+                "allow_failure": report.allowed_failures.contains(&ticket.ticket_id),
+            })
+        })
+        .collect();
+    let failed: Vec<&str> = report
+        .failed_tickets()
+        .iter()
+        .map(|ticket| ticket.ticket_id.as_str())
+        .collect();
+    let result = serde_json::json!({
+        "workflow_name": report.workflow_name,
+        "finished_at": Utc::now(),
+        "success": error.is_none() && failed.is_empty(),
+        "error": error.map(error_chain),
+        "counts": status_counts(report.tickets.iter().map(|ticket| &ticket.status)),
+        "failed": failed,
+        "tickets": tickets,
+    });
+    fs::write(path, serde_json::to_vec_pretty(&result)?)
+        .with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// `err` and its causes on one line, as `{:#}` renders an anyhow error.
+fn error_chain(err: &WorkflowError) -> String {
+    let mut message = err.to_string();
+    let mut source = std::error::Error::source(err);
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+fn render_run_section(
+    tickets: &[TicketRunState],
+    started_at: DateTime<Utc>,
+    finished_at: DateTime<Utc>,
+) -> String {
+    let mut out = format!("## Run {}\n\n", started_at.to_rfc3339());
+    let elapsed = (finished_at - started_at).num_seconds().max(0);
+    out.push_str(&format!(
+        "Finished {} ({elapsed}s).\n\n",
+        finished_at.to_rfc3339()
+    ));
+    out.push_str("| Ticket | Status | Note |\n| --- | --- | --- |\n");
+    for ticket in tickets {
+        let note = ticket.note.as_deref().unwrap_or("").replace('|', "\\|");
+        let note = note.lines().next().unwrap_or("");
+        out.push_str(&format!(
+            "| {} | {:?} | {note} |\n",
+            ticket.ticket_id, ticket.status
+        ));
+    }
+    out
+}
+
+/// `SUMMARY.md` of a workflow archive: one row per ticket with its status,
+/// how long it took, and its note.
+pub(crate) fn render_archive_summary(workflow_name: &str, tickets: &[TicketRunState]) -> String {
+    let mut out = format!("# Workflow archive: {workflow_name}\n\n");
+    out.push_str(&format!("Archived {}.\n\n", Utc::now().to_rfc3339()));
+    out.push_str("| Ticket | Status | Duration | Note |\n| --- | --- | --- | --- |\n");
+    for ticket in tickets {
+        let duration = match (ticket.started_at, ticket.finished_at) {
+            (Some(started), Some(finished)) => {
+                format!("{}s", (finished - started).num_seconds().max(0))
+            }
+            _ => "-".to_string(),
+        };
+        let note = ticket.note.as_deref().unwrap_or("").replace('|', "\\|");
+        let note = note.lines().next().unwrap_or("");
+        out.push_str(&format!(
+            "| {} | {:?} | {duration} | {note} |\n",
+            ticket.ticket_id, ticket.status
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::TicketStatus;
+
+    #[test]
+    fn appends_one_section_per_run() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let path = dir.path().join("summary.md");
+        let mut ticket = TicketRunState::new("T1");
+        ticket.mark_finished(TicketStatus::Complete, Some("Review passed".into()));
+        let now = Utc::now();
+
+        write_run_summary(&path, "demo", &[ticket.clone()], now, now, true).expect("first");
+        write_run_summary(&path, "demo", &[ticket.clone()], now, now, true).expect("second");
+        let contents = fs::read_to_string(&path).expect("read");
+        assert_eq!(contents.matches("# Workflow summary: demo").count(), 1);
+        assert_eq!(contents.matches("## Run ").count(), 2);
+        assert!(contents.contains("| T1 | Complete | Review passed |"));
+
+        write_run_summary(&path, "demo", &[ticket], now, now, false).expect("overwrite");
+        let contents = fs::read_to_string(&path).expect("read");
+        assert_eq!(contents.matches("## Run ").count(), 1);
+    }
+}
@@ -0,0 +1,67 @@
+//! `{{ name }}` placeholders in manifest text.
+
+use std::collections::BTreeMap;
+
+/// Replaces every `{{ name }}` whose name is in `vars`. Unknown placeholders
+/// are left untouched so that text meant for codex survives verbatim.
+pub(crate) fn render(template: &str, vars: &BTreeMap<String, String>) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + 2 + len + 2;
+        rendered.push_str(&rest[..start]);
+        match vars.get(rest[start + 2..end - 2].trim()) {
+            Some(value) => rendered.push_str(value),
+            None => rendered.push_str(&rest[start..end]),
+        }
+        rest = &rest[end..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Byte offsets of the `{{` in `text` that are not closed by a `}}` before
+/// the next `{{`.
+pub(crate) fn unclosed_placeholders(text: &str) -> Vec<usize> {
+    let starts: Vec<usize> = text.match_indices("{{").map(|(start, _)| start).collect();
+    starts
+        .iter()
+        .enumerate()
+        .filter(|(index, start)| {
+            let end = starts.get(index + 1).copied().unwrap_or(text.len());
+            !text[**start + 2..end].contains("}}")
+        })
+        .map(|(_, start)| *start)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitutes_known_placeholders_only() {
+        let vars = BTreeMap::from([("service".to_string(), "billing".to_string())]);
+        assert_eq!(
+            render(
+                "Fix {{service}} and {{ service }}; keep {{other}} {{",
+                &vars
+            ),
+            "Fix billing and billing; keep {{other}} {{"
+        );
+    }
+
+    #[test]
+    fn finds_every_unclosed_placeholder() {
+        assert_eq!(
+            unclosed_placeholders("{{ a }} and {{ b }}"),
+            Vec::<usize>::new()
+        );
+        assert_eq!(unclosed_placeholders("{{ a }} and {{ b"), vec![12]);
+        assert_eq!(unclosed_placeholders("{{ a and {{ b }}"), vec![0]);
+        assert_eq!(unclosed_placeholders("{{ a {{ b"), vec![0, 5]);
+    }
+}
@@ -0,0 +1,96 @@
+//! Helpers for exercising the orchestrator without spawning codex.
+
+use crate::session::SessionRequest;
+use crate::session::SessionResult;
+use crate::session::SessionRunner;
+use anyhow::Context;
+use async_trait::async_trait;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// A [`SessionRunner`] that records every request and replies with scripted
+/// results in order, falling back to [`successful_session`] once the script
+/// runs out. Each call writes a stub log at the request's `log_path` and, when
+/// the result carries one, the last message at `last_message_path`.
+#[derive(Default)]
+pub struct MockSessionRunner {
+    requests: Mutex<Vec<SessionRequest>>,
+    responses: Mutex<VecDeque<SessionResult>>,
+}
+
+impl MockSessionRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_responses(responses: impl IntoIterator<Item = SessionResult>) -> Self {
+        Self {
+            requests: Mutex::default(),
+            responses: Mutex::new(responses.into_iter().collect()),
+        }
+    }
+
+    pub fn push_response(&self, result: SessionResult) {
+        self.responses
+            .lock()
+            .expect("responses poisoned")
+            .push_back(result);
+    }
+
+    /// Requests received so far, in call order.
+    pub fn requests(&self) -> Vec<SessionRequest> {
+        self.requests.lock().expect("requests poisoned").clone()
+    }
+}
+
+#[async_trait]
+impl SessionRunner for MockSessionRunner {
+    async fn run(&self, request: SessionRequest) -> anyhow::Result<SessionResult> {
+        let result = self
+            .responses
+            .lock()
+            .expect("responses poisoned")
+            .pop_front()
+            .unwrap_or_else(successful_session);
+        if let Some(parent) = request.log_path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("failed to create {}", parent.display()))?;
+        }
+        std::fs::write(&request.log_path, "# Mock session\n")
+            .with_context(|| format!("failed to write {}", request.log_path.display()))?;
+        if let (Some(path), Some(message)) = (&request.last_message_path, &result.last_message) {
+            std::fs::write(path, message)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+        }
+        self.requests
+            .lock()
+            .expect("requests poisoned")
+            .push(request);
+        Ok(result)
+    }
+}
+
+/// A session that exited with status 0.
+pub fn successful_session() -> SessionResult {
+    SessionResult {
+        success: true,
+        timed_out: false,
+        truncated_bytes: 0,
+        status_code: Some(0),
+        stdout: String::new(),
+        stderr: String::new(),
+        last_message: None,
+        session_id: None,
+        warnings: Vec::new(),
+        command_line: "codex exec -".to_string(),
+    }
+}
+
+/// A session that exited with the given non-zero status.
+pub fn failed_session(code: i32) -> SessionResult {
+    SessionResult {
+        success: false,
+        status_code: Some(code),
+        ..successful_session()
+    }
+}
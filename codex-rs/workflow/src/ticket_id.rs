@@ -0,0 +1,102 @@
+//! Lenient matching of ticket ids typed on the command line.
+
+use crate::manifest::TicketSpec;
+use crate::manifest::WorkflowManifest;
+use anyhow::Result;
+use anyhow::bail;
+
+/// The id in `ids` that `input` names: the id itself, else the only id equal
+/// to it ignoring case, else the only id it is a case-insensitive prefix of.
+/// Fails with the candidates when several ids match, or with every id when
+/// none does.
+pub fn resolve_ticket_id<'a>(
+    ids: impl IntoIterator<Item = &'a str>,
+    input: &str,
+) -> Result<&'a str> {
+    let ids: Vec<&str> = ids.into_iter().collect();
+    if let Some(id) = ids.iter().find(|id| **id == input) {
+        return Ok(*id);
+    }
+    let lowered = input.to_lowercase();
+    let ignoring_case: Vec<&str> = ids
+        .iter()
+        .copied()
+        .filter(|id| id.to_lowercase() == lowered)
+        .collect();
+    let candidates: Vec<&str> = if ignoring_case.is_empty() {
+        ids.iter()
+            .copied()
+            .filter(|id| id.to_lowercase().starts_with(&lowered))
+            .collect()
+    } else {
+        ignoring_case
+    };
+    match candidates.as_slice() {
+        [id] => Ok(*id),
+        [] => bail!(
+            "unknown ticket {input}; the manifest has: {}",
+            ids.join(", ")
+        ),
+        _ => bail!(
+            "ticket {input} is ambiguous; it matches: {}",
+            candidates.join(", ")
+        ),
+    }
+}
+
+impl WorkflowManifest {
+    /// The ticket `input` names; see [`resolve_ticket_id`].
+    pub fn resolve_ticket(&self, input: &str) -> Result<&TicketSpec> {
+        let id = resolve_ticket_id(self.tickets.iter().map(|ticket| ticket.id.as_str()), input)?;
+        self.ticket(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IDS: [&str; 4] = ["api-auth", "api-billing", "UI", "ui-theme"];
+
+    #[test]
+    fn exact_and_case_insensitive_ids_resolve() {
+        assert_eq!(
+            resolve_ticket_id(IDS, "api-auth").expect("exact"),
+            "api-auth"
+        );
+        assert_eq!(
+            resolve_ticket_id(IDS, "API-AUTH").expect("case"),
+            "api-auth"
+        );
+        // An exact match wins over the longer id it prefixes.
+        assert_eq!(resolve_ticket_id(IDS, "UI").expect("exact"), "UI");
+        assert_eq!(resolve_ticket_id(IDS, "ui").expect("case"), "UI");
+    }
+
+    #[test]
+    fn unambiguous_prefixes_resolve() {
+        assert_eq!(
+            resolve_ticket_id(IDS, "api-b").expect("prefix"),
+            "api-billing"
+        );
+        assert_eq!(resolve_ticket_id(IDS, "UI-T").expect("prefix"), "ui-theme");
+    }
+
+    #[test]
+    fn ambiguous_prefix_lists_candidates() {
+        let err = resolve_ticket_id(IDS, "api").expect_err("ambiguous");
+        assert_eq!(
+            err.to_string(),
+            "ticket api is ambiguous; it matches: api-auth, api-billing"
+        );
+    }
+
+    #[test]
+    fn no_match_lists_every_id() {
+        let err = resolve_ticket_id(IDS, "db").expect_err("unknown");
+        assert_eq!(
+            err.to_string(),
+            "unknown ticket db; the manifest has: api-auth, api-billing, UI, ui-theme"
+        );
+    }
+}
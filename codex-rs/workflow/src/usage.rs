@@ -0,0 +1,159 @@
+//! Disk usage of a workflow's artifacts, for `workflow status --du`.
+
+use crate::layout::WorkflowLayout;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::fs;
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Bytes taken by a set of artifacts, split by kind.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ArtifactSizes {
+    /// Worker and review session logs.
+    pub logs: u64,
+    /// Everything under `patches/`.
+    pub patches: u64,
+    /// Prompts, outputs, state, summaries, and anything else.
+    pub other: u64,
+}
+
+impl ArtifactSizes {
+    pub fn total(&self) -> u64 {
+        self.logs + self.patches + self.other
+    }
+
+    fn add(&mut self, sizes: ArtifactSizes) {
+        self.logs += sizes.logs;
+        self.patches += sizes.patches;
+        self.other += sizes.other;
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DiskUsage {
+    /// Per ticket, summed over its flat directory and its directory in every
+    /// run.
+    pub tickets: BTreeMap<String, ArtifactSizes>,
+    /// The whole artifacts root, including files outside ticket directories.
+    pub total: ArtifactSizes,
+}
+
+/// Measures the artifacts under `layout`'s root, walking the directories of
+/// `ticket_ids` on a few threads. Symlinks are counted as empty rather than
+/// followed, and entries that cannot be read are skipped.
+pub fn measure_disk_usage(layout: &WorkflowLayout, ticket_ids: &[String]) -> DiskUsage {
+    let workers = std::thread::available_parallelism()
+        .map_or(1, NonZeroUsize::get)
+        .min(ticket_ids.len())
+        .max(1);
+    let chunk_len = ticket_ids.len().div_ceil(workers).max(1);
+    let tickets: BTreeMap<String, ArtifactSizes> = std::thread::scope(|scope| {
+        let handles: Vec<_> = ticket_ids
+            .chunks(chunk_len)
+            .map(|ids| {
+                scope.spawn(move || {
+                    ids.iter()
+                        .map(|id| (id.clone(), ticket_sizes(layout, id)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap_or_default())
+            .collect()
+    });
+
+    let measured: HashSet<PathBuf> = ticket_ids
+        .iter()
+        .flat_map(|id| layout.all_ticket_dirs(id))
+        .collect();
+    let mut total = ArtifactSizes {
+        other: tree_size(layout.root(), &measured),
+        ..Default::default()
+    };
+    for sizes in tickets.values() {
+        total.add(*sizes);
+    }
+    DiskUsage { tickets, total }
+}
+
+fn ticket_sizes(layout: &WorkflowLayout, ticket_id: &str) -> ArtifactSizes {
+    let mut sizes = ArtifactSizes::default();
+    for dir in layout.all_ticket_dirs(ticket_id) {
+        let logs = WorkflowLayout::session_log_files(&dir);
+        let patch_dir = dir.join("patches");
+        sizes.logs += logs.iter().map(|log| disk_usage(log)).sum::<u64>();
+        sizes.patches += disk_usage(&patch_dir);
+        let skip: HashSet<PathBuf> = logs.into_iter().chain([patch_dir]).collect();
+        sizes.other += tree_size(&dir, &skip);
+    }
+    sizes
+}
+
+/// Total size of the files under `path`, without following symlinks.
+pub(crate) fn disk_usage(path: &Path) -> u64 {
+    tree_size(path, &HashSet::new())
+}
+
+/// Like [`disk_usage`], leaving out the paths in `skip` and everything below
+/// them.
+fn tree_size(path: &Path, skip: &HashSet<PathBuf>) -> u64 {
+    if skip.contains(path) {
+        return 0;
+    }
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if metadata.is_symlink() {
+        return 0;
+    }
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| tree_size(&entry.path(), skip))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_ticket_sizes_by_kind() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let layout = WorkflowLayout::new(dir.path().join("artifacts"));
+        fs::create_dir_all(layout.root()).expect("root");
+        fs::write(layout.state_file(), "12").expect("write state");
+        let run = layout.clone().start_run("20260101T000000Z").expect("run");
+        for ticket_layout in [&layout, &run] {
+            ticket_layout.ensure_ticket_dir("T1").expect("ticket dir");
+            fs::write(ticket_layout.worker_log_path("T1", 1), "12345").expect("write log");
+            fs::write(ticket_layout.patch_dir("T1").join("a.diff"), "123").expect("write patch");
+            fs::write(ticket_layout.worker_output_path("T1"), "1").expect("write output");
+        }
+
+        let usage = measure_disk_usage(&layout, &["T1".to_string(), "T2".to_string()]);
+        let expected = ArtifactSizes {
+            logs: 10,
+            patches: 6,
+            other: 2,
+        };
+        assert_eq!(usage.tickets["T1"], expected);
+        assert_eq!(usage.tickets["T2"], ArtifactSizes::default());
+        assert_eq!(usage.total.logs, expected.logs);
+        assert_eq!(usage.total.patches, expected.patches);
+        // The state file; `latest` is a symlink, which counts as empty.
+        #[cfg(unix)]
+        assert_eq!(usage.total.other, expected.other + 2);
+    }
+}
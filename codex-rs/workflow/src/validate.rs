@@ -0,0 +1,558 @@
+//! Whole-manifest validation for `workflow validate` and run pre-flight.
+
+use crate::manifest::Requirement;
+use crate::manifest::WorkflowManifest;
+use crate::template;
+use std::collections::BTreeMap;
+use std::collections::HashSet;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum IssueSeverity {
+    Warning,
+    Error,
+}
+
+/// A problem found in a manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestIssue {
+    pub severity: IssueSeverity,
+    /// Ticket the problem belongs to, if any.
+    pub ticket_id: Option<String>,
+    /// Where in the manifest to look, e.g. `T1.depends_on`.
+    pub location: Option<String>,
+    pub message: String,
+}
+
+impl ManifestIssue {
+    pub(crate) fn error(ticket_id: Option<&str>, field: Option<&str>, message: String) -> Self {
+        Self::new(IssueSeverity::Error, ticket_id, field, message)
+    }
+
+    fn warning(ticket_id: Option<&str>, field: Option<&str>, message: String) -> Self {
+        Self::new(IssueSeverity::Warning, ticket_id, field, message)
+    }
+
+    fn new(
+        severity: IssueSeverity,
+        ticket_id: Option<&str>,
+        field: Option<&str>,
+        message: String,
+    ) -> Self {
+        let location = match (ticket_id, field) {
+            (Some(id), Some(field)) => Some(format!("{id}.{field}")),
+            (None, Some(field)) => Some(field.to_string()),
+            _ => None,
+        };
+        Self {
+            severity,
+            ticket_id: ticket_id.map(str::to_string),
+            location,
+            message,
+        }
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == IssueSeverity::Error
+    }
+}
+
+impl fmt::Display for ManifestIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            IssueSeverity::Warning => "warning",
+            IssueSeverity::Error => "error",
+        };
+        write!(f, "{severity}: {}", self.message)?;
+        if let Some(location) = &self.location {
+            write!(f, " (at {location})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Top-level manifest keys. Keep in sync with [`WorkflowManifest`].
+const MANIFEST_FIELDS: &[&str] = &[
+    "name",
+    "overview",
+    "tickets",
+    "defaults",
+    "review_resumes_worker_session",
+    "worker_resumes_session",
+    "review_against_diff",
+    "max_retries",
+    "max_reworks",
+    "log_format",
+    "retry_on_exit_codes",
+    "timeout_backoff",
+    "worker_timeout",
+    "review_timeout",
+    "config_profile",
+    "config_overrides",
+    "skip_git_repo_check",
+    "prompt_token_budget",
+    "notifications",
+    "git",
+];
+
+/// Ticket keys. Keep in sync with [`crate::TicketSpec`].
+const TICKET_FIELDS: &[&str] = &[
+    "id",
+    "summary",
+    "description",
+    "requirements",
+    "working_dir",
+    "review_working_dir",
+    "profile",
+    "reviewer_bin",
+    "log_dir",
+    "prompt",
+    "review_prompt",
+    "prompt_file",
+    "review_prompt_file",
+    "requirements_file",
+    "env",
+    "extra_args",
+    "depends_on",
+    "expected_artifacts",
+    "inputs",
+    "allow_failure",
+    "on_review_failure",
+    "matrix",
+];
+
+const DEFAULTS_FIELDS: &[&str] = &[
+    "working_dir",
+    "review_working_dir",
+    "log_dir",
+    "prompt",
+    "review_prompt",
+    "env",
+    "extra_args",
+    "expected_artifacts",
+    "inputs",
+    "allow_failure",
+    "on_review_failure",
+];
+
+/// Keys of a parsed manifest that serde silently ignores, as locations such
+/// as `T1.retries`, `tickets[2].retries` for a ticket without an id, or
+/// `defaults.retries`.
+pub(crate) fn unknown_fields(raw: &serde_json::Value) -> Vec<String> {
+    let Some(top) = raw.as_object() else {
+        return Vec::new();
+    };
+    let mut unknown: Vec<String> = top
+        .keys()
+        .filter(|key| !MANIFEST_FIELDS.contains(&key.as_str()))
+        .cloned()
+        .collect();
+    if let Some(defaults) = top
+        .get("defaults")
+        .and_then(|defaults| defaults.as_object())
+    {
+        unknown.extend(
+            defaults
+                .keys()
+                .filter(|key| !DEFAULTS_FIELDS.contains(&key.as_str()))
+                .map(|key| format!("defaults.{key}")),
+        );
+    }
+    let tickets = top.get("tickets").and_then(|tickets| tickets.as_array());
+    for (index, ticket) in tickets.into_iter().flatten().enumerate() {
+        let Some(fields) = ticket.as_object() else {
+            continue;
+        };
+        let prefix = match fields.get("id").and_then(|id| id.as_str()) {
+            Some(id) => id.to_string(),
+            None => format!("tickets[{index}]"),
+        };
+        unknown.extend(
+            fields
+                .keys()
+                .filter(|key| !TICKET_FIELDS.contains(&key.as_str()))
+                .map(|key| format!("{prefix}.{key}")),
+        );
+    }
+    unknown
+}
+
+impl WorkflowManifest {
+    /// Every problem in the manifest: the structural checks `load` enforces,
+    /// plus working directories and prompt or requirements files that do
+    /// not exist, unclosed `{{` placeholders, and, as warnings, unknown
+    /// fields and tickets that can never run.
+    pub fn validate_full(&self) -> Vec<ManifestIssue> {
+        let mut issues = self.structural_issues();
+        for field in &self.unknown_fields {
+            issues.push(ManifestIssue::warning(
+                None,
+                Some(field),
+                format!("unknown field {field} is ignored"),
+            ));
+        }
+        if self.git.open_pr && !self.git.branch_per_ticket {
+            issues.push(ManifestIssue::warning(
+                None,
+                Some("git.open_pr"),
+                "git.open_pr has no effect without git.branch_per_ticket".to_string(),
+            ));
+        }
+        let base_dir = self.base_dir();
+        let manifest_dir = self.manifest_dir();
+        for ticket in &self.tickets {
+            let id = Some(ticket.id.as_str());
+            if ticket.working_dir.is_some() {
+                let dir = ticket.resolved_working_dir(&base_dir);
+                if !dir.is_dir() {
+                    issues.push(ManifestIssue::error(
+                        id,
+                        Some("working_dir"),
+                        format!(
+                            "working directory {} of ticket {} does not exist",
+                            dir.display(),
+                            ticket.id
+                        ),
+                    ));
+                }
+            }
+            for input in &ticket.inputs {
+                if let Err(err) = glob::Pattern::new(&input.to_string_lossy()) {
+                    issues.push(ManifestIssue::error(
+                        id,
+                        Some("inputs"),
+                        format!(
+                            "invalid input glob {} of ticket {}: {err}",
+                            input.display(),
+                            ticket.id
+                        ),
+                    ));
+                }
+            }
+            let files = [
+                ("prompt_file", &ticket.prompt_file),
+                ("review_prompt_file", &ticket.review_prompt_file),
+                ("requirements_file", &ticket.requirements_file),
+            ];
+            for (field, path) in files {
+                let Some(path) = path else {
+                    continue;
+                };
+                let path = manifest_dir.join(path);
+                if !path.is_file() {
+                    issues.push(ManifestIssue::error(
+                        id,
+                        Some(field),
+                        format!(
+                            "{field} {} of ticket {} does not exist",
+                            path.display(),
+                            ticket.id
+                        ),
+                    ));
+                }
+            }
+            let texts = [
+                ("summary", Some(ticket.summary.as_str())),
+                ("description", ticket.description.as_deref()),
+                ("prompt", ticket.prompt.as_deref()),
+                ("review_prompt", ticket.review_prompt.as_deref()),
+            ]
+            .into_iter()
+            .chain(
+                ticket
+                    .requirements
+                    .iter()
+                    .map(|requirement| ("requirements", Some(requirement.text()))),
+            );
+            for (field, text) in texts {
+                for offset in template::unclosed_placeholders(text.unwrap_or_default()) {
+                    issues.push(ManifestIssue::error(
+                        id,
+                        Some(field),
+                        format!(
+                            "unclosed {{{{ placeholder at byte {offset} in {field} of ticket {}",
+                            ticket.id
+                        ),
+                    ));
+                }
+            }
+        }
+        issues
+    }
+
+    /// Problems that make the manifest unusable, followed by tickets that
+    /// can never run because of them.
+    pub(crate) fn structural_issues(&self) -> Vec<ManifestIssue> {
+        let mut issues = Vec::new();
+        if self.tickets.is_empty() {
+            issues.push(ManifestIssue::error(
+                None,
+                Some("tickets"),
+                "workflow manifest must contain at least one ticket".to_string(),
+            ));
+        }
+        if let Some(backoff) = self.timeout_backoff
+            && !(backoff >= 1.0 && backoff.is_finite())
+        {
+            issues.push(ManifestIssue::error(
+                None,
+                Some("timeout_backoff"),
+                format!("timeout_backoff must be a number of at least 1.0, got {backoff}"),
+            ));
+        }
+        for (field, count) in [
+            ("max_retries", self.max_retries),
+            ("max_reworks", self.max_reworks),
+        ] {
+            if count < 0 {
+                issues.push(ManifestIssue::error(
+                    None,
+                    Some(field),
+                    format!("{field} must not be negative, got {count}"),
+                ));
+            }
+        }
+        if let Some(budget) = self.prompt_token_budget
+            && budget < 0
+        {
+            issues.push(ManifestIssue::error(
+                None,
+                Some("prompt_token_budget"),
+                format!("prompt_token_budget must not be negative, got {budget}"),
+            ));
+        }
+        for config_override in &self.config_overrides {
+            if config_override
+                .split_once('=')
+                .is_none_or(|(key, _)| key.trim().is_empty())
+            {
+                issues.push(ManifestIssue::error(
+                    None,
+                    Some("config_overrides"),
+                    format!("config override {config_override:?} is not of the form key=value"),
+                ));
+            }
+        }
+        let mut seen: HashSet<&str> = HashSet::new();
+        for ticket in &self.tickets {
+            let id = Some(ticket.id.as_str());
+            if !seen.insert(ticket.id.as_str()) {
+                issues.push(ManifestIssue::error(
+                    id,
+                    Some("id"),
+                    format!("duplicate ticket id {}", ticket.id),
+                ));
+            }
+            let mut requirement_ids: HashSet<&str> = HashSet::new();
+            for requirement_id in ticket.requirements.iter().filter_map(Requirement::id) {
+                if !requirement_ids.insert(requirement_id) {
+                    issues.push(ManifestIssue::error(
+                        id,
+                        Some("requirements"),
+                        format!(
+                            "duplicate requirement id {requirement_id} in ticket {}",
+                            ticket.id
+                        ),
+                    ));
+                }
+            }
+        }
+        let mut broken: HashSet<&str> = HashSet::new();
+        for ticket in &self.tickets {
+            for dependency in &ticket.depends_on {
+                if !seen.contains(dependency.as_str()) {
+                    broken.insert(ticket.id.as_str());
+                    issues.push(ManifestIssue::error(
+                        Some(&ticket.id),
+                        Some("depends_on"),
+                        format!(
+                            "ticket {} depends on unknown ticket {dependency}",
+                            ticket.id
+                        ),
+                    ));
+                }
+            }
+        }
+
+        let deps: BTreeMap<&str, &[String]> = self
+            .tickets
+            .iter()
+            .map(|ticket| (ticket.id.as_str(), ticket.depends_on.as_slice()))
+            .collect();
+        // Repeatedly retire tickets whose dependencies have all been retired;
+        // whatever is left over sits on a cycle or waits for one.
+        let mut resolved: HashSet<&str> = HashSet::new();
+        loop {
+            let ready: Vec<&str> = deps
+                .iter()
+                .filter(|(id, ticket_deps)| {
+                    !resolved.contains(*id)
+                        && ticket_deps
+                            .iter()
+                            .all(|dep| resolved.contains(dep.as_str()))
+                })
+                .map(|(id, _)| *id)
+                .collect();
+            if ready.is_empty() {
+                break;
+            }
+            resolved.extend(ready);
+        }
+        for id in deps.keys().filter(|id| !resolved.contains(*id)) {
+            if reaches(&deps, id, id) {
+                issues.push(ManifestIssue::error(
+                    Some(id),
+                    Some("depends_on"),
+                    format!("dependency cycle involving ticket {id}"),
+                ));
+            } else if !broken.contains(id) {
+                issues.push(ManifestIssue::warning(
+                    Some(id),
+                    Some("depends_on"),
+                    format!("ticket {id} can never run: one of its dependencies cannot"),
+                ));
+            }
+        }
+        issues
+    }
+}
+
+/// Whether `target` is reachable from `from` through one or more
+/// dependency edges.
+fn reaches(deps: &BTreeMap<&str, &[String]>, from: &str, target: &str) -> bool {
+    let mut visited: HashSet<&str> = HashSet::new();
+    let mut stack: Vec<&str> = deps
+        .get(from)
+        .into_iter()
+        .flat_map(|ticket_deps| ticket_deps.iter().map(String::as_str))
+        .collect();
+    while let Some(id) = stack.pop() {
+        if id == target {
+            return true;
+        }
+        if visited.insert(id) {
+            stack.extend(
+                deps.get(id)
+                    .into_iter()
+                    .flat_map(|ticket_deps| ticket_deps.iter().map(String::as_str)),
+            );
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn known_fields_match_the_schema() {
+        let schema = crate::WorkflowManifest::json_schema();
+        let keys = |properties: &serde_json::Value| {
+            let mut keys: Vec<String> = properties
+                .as_object()
+                .expect("properties")
+                .keys()
+                .cloned()
+                .collect();
+            keys.sort();
+            keys
+        };
+        let sorted = |fields: &[&str]| {
+            let mut fields: Vec<String> = fields.iter().map(ToString::to_string).collect();
+            fields.sort();
+            fields
+        };
+        assert_eq!(keys(&schema["properties"]), sorted(MANIFEST_FIELDS));
+        assert_eq!(
+            keys(&schema["definitions"]["TicketSpec"]["properties"]),
+            sorted(TICKET_FIELDS)
+        );
+        assert_eq!(
+            keys(&schema["definitions"]["TicketDefaults"]["properties"]),
+            sorted(DEFAULTS_FIELDS)
+        );
+    }
+
+    #[test]
+    fn reports_every_problem_with_locations() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest_path = dir.path().join("demo.yaml");
+        let contents = r#"
+retries: 3
+tickets:
+  - id: T1
+    summary: Fix {{ service
+    working_dir: missing
+    prompt_file: prompts/missing.md
+    colour: blue
+  - id: T2
+    summary: Two
+    depends_on: [T3]
+  - id: T3
+    summary: Three
+    depends_on: [T2]
+  - id: T4
+    summary: Waits on the cycle
+    depends_on: [T3]
+"#;
+        fs::write(&manifest_path, contents).expect("write manifest");
+        let err = WorkflowManifest::load(&manifest_path).expect_err("cycle");
+        assert!(err.to_string().contains("dependency cycle"));
+
+        let manifest = WorkflowManifest::parse(&manifest_path).expect("parse");
+        let rendered: Vec<String> = manifest
+            .validate_full()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "error: dependency cycle involving ticket T2 (at T2.depends_on)".to_string(),
+                "error: dependency cycle involving ticket T3 (at T3.depends_on)".to_string(),
+                "warning: ticket T4 can never run: one of its dependencies cannot \
+                (at T4.depends_on)"
+                    .to_string(),
+                "warning: unknown field retries is ignored (at retries)".to_string(),
+                "warning: unknown field T1.colour is ignored (at T1.colour)".to_string(),
+                format!(
+                    "error: working directory {} of ticket T1 does not exist \
+                    (at T1.working_dir)",
+                    dir.path().join("missing").display()
+                ),
+                format!(
+                    "error: prompt_file {} of ticket T1 does not exist (at T1.prompt_file)",
+                    dir.path().join("prompts/missing.md").display()
+                ),
+                "error: unclosed {{ placeholder at byte 4 in summary of ticket T1 \
+                (at T1.summary)"
+                    .to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_negative_counts() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let manifest_path = dir.path().join("demo.yaml");
+        let contents = "max_retries: -1\nmax_reworks: -2\nprompt_token_budget: -3\n\
+            tickets:\n  - id: T1\n    summary: One\n";
+        fs::write(&manifest_path, contents).expect("write manifest");
+        let err = WorkflowManifest::load(&manifest_path).expect_err("negative counts");
+        let err = err.to_string();
+        assert!(
+            err.contains("max_retries must not be negative, got -1"),
+            "{err}"
+        );
+        assert!(
+            err.contains("max_reworks must not be negative, got -2"),
+            "{err}"
+        );
+        assert!(
+            err.contains("prompt_token_budget must not be negative, got -3"),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,173 @@
+//! Delivers run events to a manifest's `notifications.webhook_url`.
+//!
+//! Each notification is a POST whose body is the event as it appears in
+//! `events.jsonl`. Deliveries run in the background so a slow or dead
+//! endpoint never holds up the run.
+
+use anyhow::Context;
+use anyhow::Result;
+use reqwest::Url;
+use reqwest::header::CONTENT_TYPE;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Limit on each delivery attempt, connecting included.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Attempts per notification. Only `5xx` responses are retried.
+const WEBHOOK_ATTEMPTS: u32 = 3;
+
+/// Wait before the first retry; later retries wait proportionally longer.
+const WEBHOOK_RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// How long the end of a run waits for deliveries still in flight.
+const WEBHOOK_FLUSH_TIMEOUT: Duration = Duration::from_secs(15);
+
+pub(crate) struct Webhook {
+    client: reqwest::Client,
+    url: Url,
+    deliveries: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl Webhook {
+    pub(crate) fn new(url: &str) -> Result<Self> {
+        let url = Url::parse(url).with_context(|| format!("invalid webhook URL {url}"))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            anyhow::bail!("webhook URL must use http or https, not {}", url.scheme());
+        }
+        let client = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .context("failed to set up the webhook client")?;
+        Ok(Webhook {
+            client,
+            url,
+            deliveries: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Posts `body` in the background. `on_failure` receives the reason once
+    /// every attempt failed.
+    pub(crate) fn send(&self, body: Vec<u8>, on_failure: impl FnOnce(String) + Send + 'static) {
+        let client = self.client.clone();
+        let url = self.url.clone();
+        let delivery = tokio::spawn(async move {
+            if let Err(err) = post(&client, url, body).await {
+                on_failure(err);
+            }
+        });
+        self.deliveries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(delivery);
+    }
+
+    /// Gives deliveries in flight a bounded time to finish.
+    pub(crate) async fn close(self) {
+        let deliveries = self
+            .deliveries
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        let _ = tokio::time::timeout(WEBHOOK_FLUSH_TIMEOUT, async {
+            for delivery in deliveries {
+                let _ = delivery.await;
+            }
+        })
+        .await;
+    }
+}
+
+async fn post(client: &reqwest::Client, url: Url, body: Vec<u8>) -> Result<(), String> {
+    let mut attempt = 1;
+    loop {
+        let response = client
+            .post(url.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.clone())
+            .send()
+            .await;
+        let status = match response {
+            Ok(response) => response.status(),
+            // The URL often embeds a token, so it stays out of the error.
+            Err(err) if err.is_timeout() => {
+                return Err(format!("no response within {}s", WEBHOOK_TIMEOUT.as_secs()));
+            }
+            Err(err) => return Err(err.without_url().to_string()),
+        };
+        if status.is_success() {
+            return Ok(());
+        }
+        if !status.is_server_error() || attempt == WEBHOOK_ATTEMPTS {
+            let attempts = if attempt == 1 {
+                String::new()
+            } else {
+                format!(" after {attempt} attempts")
+            };
+            return Err(format!("webhook responded with {status}{attempts}"));
+        }
+        tokio::time::sleep(WEBHOOK_RETRY_DELAY * attempt).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+    use wiremock::matchers::method;
+
+    async fn deliver(server: &MockServer) -> Option<String> {
+        let webhook = Webhook::new(&server.uri()).expect("webhook");
+        let failure = Arc::new(Mutex::new(None));
+        let reported = Arc::clone(&failure);
+        webhook.send(b"{}\n".to_vec(), move |err| {
+            *reported.lock().expect("lock") = Some(err);
+        });
+        webhook.close().await;
+        failure.lock().expect("lock").take()
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        assert_eq!(deliver(&server).await, None);
+    }
+
+    #[tokio::test]
+    async fn reports_client_errors_without_retrying() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        assert_eq!(
+            deliver(&server).await.as_deref(),
+            Some("webhook responded with 404 Not Found")
+        );
+    }
+
+    #[test]
+    fn rejects_urls_that_are_not_http() {
+        assert!(Webhook::new("ftp://example.com/hook").is_err());
+        assert!(Webhook::new("not a url").is_err());
+    }
+}